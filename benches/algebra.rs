@@ -1,5 +1,5 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
-use gzset::ScoreSet;
+use gzset::{Aggregate, ScoreSet};
 use rustc_hash::FxHashMap as FastHashMap;
 
 #[cfg(feature = "bench-borrowed")]
@@ -8,6 +8,7 @@ use hashbrown::HashSet;
 mod support;
 
 const SET_SIZE: usize = 120_000;
+const TINY_SET_SIZE: usize = 64;
 
 fn bench_algebra(c: &mut Criterion) {
     let overlap_cases = [
@@ -35,6 +36,14 @@ fn bench_algebra(c: &mut Criterion) {
                 black_box(cardinality);
             });
         });
+        // The k-way merge against the map above; `union_overlap_is_low`
+        // should pick the merge only where it wins.
+        group.bench_function(format!("union/2sets/{label}/merged"), |b| {
+            b.iter(|| {
+                let merged = ScoreSet::union_merged(&[set_a, set_b], None, Aggregate::Sum);
+                black_box(merged.len());
+            });
+        });
         group.bench_function(format!("inter/2sets/{label}"), |b| {
             b.iter(|| {
                 let cardinality = inter_two(set_a, set_b);
@@ -79,6 +88,25 @@ fn bench_algebra(c: &mut Criterion) {
             black_box(cardinality);
         });
     });
+    group.bench_function("union/multikey/6sets_merged", |b| {
+        b.iter(|| {
+            let merged = ScoreSet::union_merged(&multi_sets, None, Aggregate::Sum);
+            black_box(merged.len());
+        });
+    });
+    group.bench_function("unionstore/multikey/6sets_map", |b| {
+        b.iter(|| {
+            let stored = unionstore_via_map(&multi_sets);
+            black_box(stored.len());
+        });
+    });
+    group.bench_function("unionstore/multikey/6sets_union_into", |b| {
+        let mut dst = ScoreSet::default();
+        b.iter(|| {
+            let cardinality = ScoreSet::union_into(&mut dst, &multi_sets, None, Aggregate::Sum);
+            black_box(cardinality);
+        });
+    });
     group.bench_function("inter/multikey/6sets", |b| {
         b.iter(|| {
             let cardinality = inter_multi(&multi_sets);
@@ -86,6 +114,29 @@ fn bench_algebra(c: &mut Criterion) {
         });
     });
 
+    // One tiny set against several huge ones: the cost should track the tiny
+    // set (plus the LIMIT early exit), not the huge sets' size.
+    let tiny = Box::leak(Box::new(support::build_set(&support::unique_increasing(
+        TINY_SET_SIZE,
+    ))));
+    for huge in [100_000usize, 1_000_000] {
+        let mut sets: Vec<&ScoreSet> = vec![&*tiny];
+        for shift in 0..3 {
+            let entries: Vec<(f64, String)> = support::unique_increasing(huge)
+                .into_iter()
+                .map(|(score, member)| (score + shift as f64, member))
+                .collect();
+            sets.push(Box::leak(Box::new(support::build_set(&entries))));
+        }
+        group.throughput(Throughput::Elements(TINY_SET_SIZE as u64));
+        group.bench_function(format!("intercard/tiny_vs_huge/{huge}"), |b| {
+            b.iter(|| black_box(ScoreSet::intersection_card(&sets, 0)));
+        });
+        group.bench_function(format!("intercard/tiny_vs_huge/{huge}/limit"), |b| {
+            b.iter(|| black_box(ScoreSet::intersection_card(&sets, TINY_SET_SIZE / 4)));
+        });
+    }
+
     group.finish();
 }
 
@@ -185,6 +236,23 @@ fn intercard_two(a: &ScoreSet, b: &ScoreSet, limit: Option<usize>) -> usize {
     count
 }
 
+/// Store path before `ScoreSet::union_into`: aggregate into an owned-key map,
+/// then insert every entry into a fresh set.
+fn unionstore_via_map(sets: &[&ScoreSet]) -> ScoreSet {
+    let mut agg: FastHashMap<String, f64> = FastHashMap::default();
+    for set in sets {
+        agg.reserve(set.len());
+        for (member, score) in set.iter_all() {
+            agg.entry(member.to_owned())
+                .and_modify(|v| *v += score)
+                .or_insert(score);
+        }
+    }
+    let mut out = ScoreSet::default();
+    out.insert_many(agg.iter().map(|(member, score)| (*score, member.as_str())));
+    out
+}
+
 fn union_multi(sets: &[&ScoreSet]) -> usize {
     let mut agg: FastHashMap<String, f64> = FastHashMap::default();
     let total: usize = sets.iter().map(|set| set.len()).sum();
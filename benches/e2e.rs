@@ -0,0 +1,225 @@
+//! End-to-end command throughput: `GZ*` against native `Z*` on a live Valkey,
+//! so the command and reply layer is measured along with the data structure.
+//! Needs `valkey-server` in `PATH`; run with
+//! `cargo bench --features bench --bench e2e`.
+
+use std::time::Instant;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use redis::Connection;
+
+mod support;
+
+/// Commands pipelined per measured batch.
+const BATCH: usize = 100;
+/// Members per `ZADD`/`GZADD` while loading a dataset.
+const LOAD_CHUNK: usize = 1000;
+/// Members returned by each `ZRANGE`/`GZRANGE`.
+const RANGE_WIDTH: usize = 100;
+const FAMILIES: [&str; 2] = ["Z", "GZ"];
+const OPS: [Op; 4] = [Op::Add, Op::Range, Op::Rank, Op::RankMiss];
+
+#[derive(Clone, Copy)]
+enum Op {
+    Add,
+    Range,
+    Rank,
+    /// `RANK` of members that are not in the set, as in "is this user
+    /// ranked?" checks.
+    RankMiss,
+}
+
+impl Op {
+    fn name(self) -> &'static str {
+        match self {
+            Op::Add => "ADD",
+            Op::Range => "RANGE",
+            Op::Rank => "RANK",
+            Op::RankMiss => "RANKMISS",
+        }
+    }
+
+    /// Command suffix sent for this op.
+    fn command(self) -> &'static str {
+        match self {
+            Op::RankMiss => "RANK",
+            op => op.name(),
+        }
+    }
+}
+
+fn key(prefix: &str, dataset: &str) -> String {
+    format!("{}:{dataset}", prefix.to_ascii_lowercase())
+}
+
+fn load(con: &mut Connection, prefix: &str, key: &str, entries: &[(f64, String)]) {
+    redis::cmd("DEL")
+        .arg(key)
+        .query::<()>(con)
+        .expect("DEL failed");
+    let add = format!("{prefix}ADD");
+    let mut pipe = redis::pipe();
+    for chunk in entries.chunks(LOAD_CHUNK) {
+        let cmd = pipe.cmd(&add).arg(key);
+        for (score, member) in chunk {
+            cmd.arg(*score).arg(member);
+        }
+        cmd.ignore();
+    }
+    pipe.query::<()>(con).expect("failed to load dataset");
+}
+
+/// Pipeline `BATCH` calls of `op` against `key`. `ADD` rescores existing
+/// members so the set keeps its size across iterations.
+fn run_batch(
+    con: &mut Connection,
+    prefix: &str,
+    op: Op,
+    key: &str,
+    entries: &[(f64, String)],
+    rng: &mut StdRng,
+) {
+    let name = format!("{prefix}{}", op.command());
+    let mut pipe = redis::pipe();
+    for _ in 0..BATCH {
+        let (score, member) = &entries[rng.gen_range(0..entries.len())];
+        let cmd = pipe.cmd(&name).arg(key);
+        match op {
+            Op::Add => cmd.arg(score + rng.gen_range(-1.0..1.0)).arg(member),
+            Op::Range => {
+                let start = rng.gen_range(0..entries.len());
+                cmd.arg(start).arg(start + RANGE_WIDTH - 1)
+            }
+            Op::Rank => cmd.arg(member),
+            Op::RankMiss => cmd.arg(format!("missing:{member}")),
+        };
+        cmd.ignore();
+    }
+    pipe.query::<()>(con).expect("pipeline failed");
+}
+
+/// Print `GZ*` throughput relative to `Z*` for every op and dataset, timed
+/// over a fixed number of batches with identical request streams.
+fn report_relative(con: &mut Connection, datasets: &[(&str, Vec<(f64, String)>)]) {
+    let rounds = support::usize_env("GZSET_BENCH_E2E_ROUNDS", 200);
+    println!(
+        "\n{:<10} {:<6} {:>12} {:>12} {:>7}",
+        "dataset", "op", "Z* ops/s", "GZ* ops/s", "GZ/Z"
+    );
+    for (name, entries) in datasets {
+        for op in OPS {
+            let [native, module] = FAMILIES.map(|prefix| {
+                let key = key(prefix, name);
+                let mut rng = StdRng::seed_from_u64(0xe2e);
+                let start = Instant::now();
+                for _ in 0..rounds {
+                    run_batch(con, prefix, op, &key, entries, &mut rng);
+                }
+                (rounds * BATCH) as f64 / start.elapsed().as_secs_f64()
+            });
+            println!(
+                "{name:<10} {:<6} {native:>12.0} {module:>12.0} {:>6.2}x",
+                op.name(),
+                module / native
+            );
+        }
+    }
+}
+
+fn bench_e2e(c: &mut Criterion) {
+    if which::which("valkey-server").is_err() {
+        eprintln!("valkey-server not found in PATH; skipping e2e benches");
+        return;
+    }
+    let size = support::usize_env("GZSET_BENCH_E2E_SIZE", 100_000);
+    let datasets = [
+        ("uniform", support::uniform_random(size, size as f64)),
+        ("clustered", support::clustered(size, 16, 1.0)),
+        ("zipf", support::zipf_like(size, 1.2)),
+    ];
+
+    let server = support::server::Server::start().expect("failed to start valkey-server");
+    let mut con = server.connection().expect("failed to connect");
+    for (name, entries) in &datasets {
+        for prefix in FAMILIES {
+            load(&mut con, prefix, &key(prefix, name), entries);
+        }
+    }
+
+    let mut group = c.benchmark_group("e2e");
+    let measurement = support::duration_env("GZSET_BENCH_MEASUREMENT_SECS", 10.0);
+    let warmup = support::duration_env("GZSET_BENCH_WARMUP_SECS", 3.0);
+    let sample_size = support::usize_env("GZSET_BENCH_SAMPLE_SIZE", 10);
+    group.measurement_time(measurement);
+    group.warm_up_time(warmup);
+    group.sample_size(sample_size);
+    group.throughput(Throughput::Elements(BATCH as u64));
+
+    for (name, entries) in &datasets {
+        for op in OPS {
+            for prefix in FAMILIES {
+                let key = key(prefix, name);
+                let mut rng = StdRng::seed_from_u64(0xe2e);
+                let id = BenchmarkId::new(format!("{prefix}{}", op.name()), name);
+                group.bench_function(id, |b| {
+                    b.iter(|| run_batch(&mut con, prefix, op, &key, entries, &mut rng));
+                });
+            }
+        }
+    }
+    group.finish();
+
+    report_relative(&mut con, &datasets);
+}
+
+/// One `ZRANGE`/`GZRANGE key 0 -1` over a whole `GZSET_BENCH_WIDE_SIZE`
+/// member set, with and without scores. Each member is its own reply call,
+/// so this is the reply path at its widest: the call count scales with the
+/// set while the lookup work stays a single walk.
+fn bench_wide_range(c: &mut Criterion) {
+    if which::which("valkey-server").is_err() {
+        eprintln!("valkey-server not found in PATH; skipping e2e benches");
+        return;
+    }
+    let size = support::usize_env("GZSET_BENCH_WIDE_SIZE", 100_000);
+    let entries = support::uniform_random(size, size as f64);
+
+    let server = support::server::Server::start().expect("failed to start valkey-server");
+    let mut con = server.connection().expect("failed to connect");
+    for prefix in FAMILIES {
+        load(&mut con, prefix, &key(prefix, "wide"), &entries);
+    }
+
+    let mut group = c.benchmark_group("e2e_wide_range");
+    let measurement = support::duration_env("GZSET_BENCH_MEASUREMENT_SECS", 10.0);
+    let warmup = support::duration_env("GZSET_BENCH_WARMUP_SECS", 3.0);
+    let sample_size = support::usize_env("GZSET_BENCH_SAMPLE_SIZE", 10);
+    group.measurement_time(measurement);
+    group.warm_up_time(warmup);
+    group.sample_size(sample_size);
+    group.throughput(Throughput::Elements(size as u64));
+
+    for with_scores in [false, true] {
+        let variant = if with_scores { "withscores" } else { "members" };
+        for prefix in FAMILIES {
+            let key = key(prefix, "wide");
+            let name = format!("{prefix}RANGE");
+            group.bench_function(BenchmarkId::new(name.as_str(), variant), |b| {
+                b.iter(|| {
+                    let mut cmd = redis::cmd(&name);
+                    cmd.arg(&key).arg(0).arg(-1);
+                    if with_scores {
+                        cmd.arg("WITHSCORES");
+                    }
+                    let reply: Vec<String> = cmd.query(&mut con).expect("range failed");
+                    assert_eq!(reply.len(), size * (1 + with_scores as usize));
+                });
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_e2e, bench_wide_range);
+criterion_main!(benches);
@@ -0,0 +1,102 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use gzset::ScoreSet;
+
+mod support;
+
+fn bench_insert_many(c: &mut Criterion) {
+    let insert_size = support::usize_env("GZSET_BENCH_INSERT_SIZE", 200_000);
+    let unique_entries = support::unique_increasing(insert_size);
+    let uniform_entries = support::uniform_random(insert_size, insert_size as f64);
+    let clustered_entries = support::clustered(insert_size, 64, 4.0);
+
+    let mut group = c.benchmark_group("insert_many");
+    let measurement = support::duration_env("GZSET_BENCH_MEASUREMENT_SECS", 10.0);
+    let warmup = support::duration_env("GZSET_BENCH_WARMUP_SECS", 3.0);
+    let sample_size = support::usize_env("GZSET_BENCH_SAMPLE_SIZE", 10);
+    group.measurement_time(measurement);
+    group.warm_up_time(warmup);
+    group.sample_size(sample_size);
+    for (name, entries) in [
+        ("unique_increasing", &unique_entries),
+        ("uniform_random", &uniform_entries),
+        ("clustered", &clustered_entries),
+    ] {
+        group.throughput(Throughput::Elements(entries.len() as u64));
+        group.bench_with_input(BenchmarkId::new("insert_loop", name), entries, |b, data| {
+            b.iter(|| {
+                let mut set = ScoreSet::default();
+                for (score, member) in data {
+                    set.insert(*score, member);
+                }
+                black_box(set.len());
+            });
+        });
+        group.bench_with_input(BenchmarkId::new("insert_many", name), entries, |b, data| {
+            b.iter(|| {
+                let mut set = ScoreSet::default();
+                let added = set.insert_many(data.iter().map(|(s, m)| (*s, m.as_str())));
+                black_box(added);
+            });
+        });
+        let mut built = ScoreSet::default();
+        built.insert_many(entries.iter().map(|(s, m)| (*s, m.as_str())));
+        let mem = support::mem_usage_bytes(&built);
+        support::record_mem(format!("insert_many/{name}"), mem);
+
+        // Score-sorted input, as an RDB load or a STORE over another set's
+        // iteration would produce it.
+        let mut sorted = entries.clone();
+        sorted.sort_by(|a, b| a.0.total_cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+        group.bench_with_input(
+            BenchmarkId::new("insert_many_sorted", name),
+            &sorted,
+            |b, data| {
+                b.iter(|| {
+                    let mut set = ScoreSet::default();
+                    let added = set.insert_many(data.iter().map(|(s, m)| (*s, m.as_str())));
+                    black_box(added);
+                });
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("extend_from_sorted", name),
+            &sorted,
+            |b, data| {
+                b.iter(|| {
+                    let mut set = ScoreSet::default();
+                    let added = set.extend_from_sorted(data.iter().map(|(s, m)| (*s, m.as_str())));
+                    black_box(added);
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Reload of one large key in unsorted order, as the RDB decoder does it when
+/// a payload is not sorted.
+fn bench_reload(c: &mut Criterion) {
+    let reload_size = support::usize_env("GZSET_BENCH_RELOAD_SIZE", 1_000_000);
+    let entries = support::uniform_random(reload_size, reload_size as f64);
+
+    let mut group = c.benchmark_group("reload");
+    group.measurement_time(support::duration_env("GZSET_BENCH_MEASUREMENT_SECS", 10.0));
+    group.warm_up_time(support::duration_env("GZSET_BENCH_WARMUP_SECS", 3.0));
+    group.sample_size(support::usize_env("GZSET_BENCH_SAMPLE_SIZE", 10));
+    group.throughput(Throughput::Elements(entries.len() as u64));
+    group.bench_with_input(
+        BenchmarkId::new("insert_many", reload_size),
+        &entries,
+        |b, data| {
+            b.iter(|| {
+                let mut set = ScoreSet::default();
+                let added = set.insert_many(data.iter().map(|(s, m)| (*s, m.as_str())));
+                black_box(added);
+            });
+        },
+    );
+    group.finish();
+}
+
+criterion_group!(benches, bench_insert_many, bench_reload);
+criterion_main!(benches);
@@ -31,5 +31,39 @@ fn bench_insert_many_ties(c: &mut Criterion) {
     support::record_mem("insert_many_ties", mem);
 }
 
-criterion_group!(benches, bench_insert_many_ties);
+fn bench_insert_same_score_names(c: &mut Criterion) {
+    let size = support::usize_env("GZSET_BENCH_TIED_SIZE", 50_000);
+    let mut short_names: Vec<String> = (0..size).map(|i| format!("{i:x}")).collect();
+    let mut long_names: Vec<String> = (0..size)
+        .map(|i| format!("tenant:0042:leaderboard:season:7:player:{i:010}"))
+        .collect();
+    support::shuffle_members(&mut short_names);
+    support::shuffle_members(&mut long_names);
+
+    let mut group = c.benchmark_group("insert_same_score");
+    let measurement = support::duration_env("GZSET_BENCH_MEASUREMENT_SECS", 10.0);
+    let warmup = support::duration_env("GZSET_BENCH_WARMUP_SECS", 3.0);
+    let sample_size = support::usize_env("GZSET_BENCH_SAMPLE_SIZE", 10);
+    group.measurement_time(measurement);
+    group.warm_up_time(warmup);
+    group.sample_size(sample_size);
+    for (name, members) in [("short_names", &short_names), ("long_prefix", &long_names)] {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let mut set = ScoreSet::default();
+                for member in members {
+                    set.insert(0.0, member);
+                }
+                black_box(set.len())
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_insert_many_ties,
+    bench_insert_same_score_names
+);
 criterion_main!(benches);
@@ -0,0 +1,133 @@
+use criterion::{
+    black_box, criterion_group, criterion_main, BatchSize, Criterion, SamplingMode, Throughput,
+};
+use gzset::ScoreSet;
+use rand::Rng;
+
+mod support;
+
+/// Leaderboard-style traffic: point reads (`GZSCORE`/`GZRANK`) interleaved
+/// with writes (`GZADD`/`GZREM`) on a zipf-shaped set. The read share comes
+/// from `GZSET_MIXED_READ_PCT`; criterion's element throughput is ops/sec.
+fn bench_mixed(c: &mut Criterion) {
+    let base_size = support::usize_env("GZSET_MIXED_BASE", 100_000);
+    let script_len = support::usize_env("GZSET_MIXED_OPS", 50_000);
+    let read_pct = support::usize_env("GZSET_MIXED_READ_PCT", 80).min(100);
+    let measurement = support::duration_env("GZSET_BENCH_MEASUREMENT_SECS", 10.0);
+    let warmup = support::duration_env("GZSET_BENCH_WARMUP_SECS", 3.0);
+    let sample_size = support::usize_env("GZSET_BENCH_SAMPLE_SIZE", 10);
+
+    let base_entries = support::zipf_like(base_size, 1.2);
+    let script = build_script(&base_entries, script_len, read_pct);
+
+    let mut group = c.benchmark_group("mixed");
+    group.measurement_time(measurement);
+    group.warm_up_time(warmup);
+    group.sample_size(sample_size);
+    group.sampling_mode(SamplingMode::Flat);
+    group.throughput(Throughput::Elements(script.len() as u64));
+
+    let id = format!("read{read_pct}_write{}", 100 - read_pct);
+    group.bench_function(id, |b| {
+        b.iter_batched(
+            || support::build_set(&base_entries),
+            |mut set| {
+                black_box(apply_script(&mut set, &script));
+            },
+            BatchSize::LargeInput,
+        );
+    });
+
+    group.finish();
+}
+
+enum Operation {
+    Score { member: String },
+    Rank { member: String },
+    Add { member: String, score: f64 },
+    Remove { member: String },
+}
+
+/// Reads split evenly between score and rank lookups. Writes are half score
+/// bumps of existing members and a quarter each inserts and removes, so the
+/// set keeps its size. Every lookup and remove targets a live member.
+fn build_script(
+    base_entries: &[(f64, String)],
+    script_len: usize,
+    read_pct: usize,
+) -> Vec<Operation> {
+    let mut rng = support::seeded_rng();
+    let mut existing: Vec<(String, f64)> = base_entries
+        .iter()
+        .map(|(score, member)| (member.clone(), *score))
+        .collect();
+    let top_score = base_entries
+        .iter()
+        .map(|&(score, _)| score)
+        .fold(0.0, f64::max);
+    let mut next_insert_id = 0usize;
+
+    let mut script = Vec::with_capacity(script_len);
+    for _ in 0..script_len {
+        if existing.is_empty() {
+            break;
+        }
+        let idx = rng.gen_range(0..existing.len());
+        let op = if rng.gen_range(0..100) < read_pct {
+            let member = existing[idx].0.clone();
+            if rng.gen_bool(0.5) {
+                Operation::Score { member }
+            } else {
+                Operation::Rank { member }
+            }
+        } else {
+            match rng.gen_range(0..4) {
+                0 | 1 => {
+                    let (member, score) = &mut existing[idx];
+                    *score += rng.gen_range(1.0..=100.0);
+                    Operation::Add {
+                        member: member.clone(),
+                        score: *score,
+                    }
+                }
+                2 => {
+                    let member = format!("new:{next_insert_id}");
+                    next_insert_id += 1;
+                    let score = rng.gen_range(0.0..=top_score);
+                    existing.push((member.clone(), score));
+                    Operation::Add { member, score }
+                }
+                _ => {
+                    let (member, _) = existing.swap_remove(idx);
+                    Operation::Remove { member }
+                }
+            }
+        };
+        script.push(op);
+    }
+    script
+}
+
+fn apply_script(set: &mut ScoreSet, script: &[Operation]) -> usize {
+    let mut hits = 0usize;
+    for op in script {
+        match op {
+            Operation::Score { member } => {
+                hits += usize::from(set.score(member).is_some());
+            }
+            Operation::Rank { member } => {
+                hits += usize::from(set.rank(member).is_some());
+            }
+            Operation::Add { member, score } => {
+                set.insert(*score, member);
+            }
+            Operation::Remove { member } => {
+                set.remove(member);
+            }
+        }
+    }
+    hits
+}
+
+criterion_group!(benches, bench_mixed);
+criterion_main!(benches);
@@ -5,6 +5,8 @@ mod support;
 
 const DEFAULT_ENTRY_COUNT: usize = 100_000;
 const DEFAULT_REPEAT_POPS: usize = 50;
+const DEFAULT_HOT_BUCKET_LEN: usize = 48;
+const DEFAULT_HOT_BUCKET_CYCLES: usize = 10_000;
 
 fn entry_count() -> usize {
     std::env::var("BENCH_ENTRY_COUNT")
@@ -137,9 +139,56 @@ fn bench_pop(c: &mut Criterion) {
         })
     });
 
+    let hot_len = support::usize_env("GZSET_HOT_BUCKET_LEN", DEFAULT_HOT_BUCKET_LEN);
+    let hot_cycles = support::usize_env("GZSET_HOT_BUCKET_CYCLES", DEFAULT_HOT_BUCKET_CYCLES);
+    let hot_members: Vec<String> = (0..hot_len + hot_cycles)
+        .map(|i| format!("member:{i:08}"))
+        .collect();
+    let (hot_set, changes) = hot_bucket_cycle(&hot_members, hot_len);
+    // The bucket's capacity settles after a few changes however long the run;
+    // without hysteresis it would shrink and regrow on every cycle.
+    assert!(
+        changes <= 3,
+        "hot bucket capacity changed {changes} times in {hot_cycles} cycles"
+    );
+    support::record_structural_mem("pop_min_insert_hot_bucket", hot_set.mem_bytes());
+
+    group.throughput(Throughput::Elements(hot_cycles as u64));
+    group.bench_function("pop_min_insert_hot_bucket", |b| {
+        b.iter(|| black_box(hot_bucket_cycle(&hot_members, hot_len).1))
+    });
+
     group.finish();
 }
 
+/// Fill one score with `live` members, then alternate a min pop with an insert
+/// into the same bucket for the rest of `members`. Returns the final set and
+/// how often the bucket's capacity changed along the way.
+fn hot_bucket_cycle(members: &[String], live: usize) -> (ScoreSet, usize) {
+    let mut set = ScoreSet::default();
+    for member in &members[..live] {
+        set.insert(0.0, member);
+    }
+    let mut changes = 0;
+    let mut capacity = set.bucket_capacity_for_test(0.0);
+    // Sample after both halves of the cycle: a shrink on pop followed by a
+    // regrow on insert leaves the capacity unchanged across the full cycle.
+    let mut observe = |set: &ScoreSet| {
+        let now = set.bucket_capacity_for_test(0.0);
+        if now != capacity {
+            changes += 1;
+            capacity = now;
+        }
+    };
+    for member in &members[live..] {
+        black_box(set.pop_one(true));
+        observe(&set);
+        set.insert(0.0, member);
+        observe(&set);
+    }
+    (set, changes)
+}
+
 fn pop_all_members(set: &mut ScoreSet, min: bool) -> Vec<String> {
     set.pop_n(min, set.len())
         .into_iter()
@@ -103,6 +103,42 @@ fn bench_randmember(c: &mut Criterion) {
         });
     });
 
+    // GZRANDMEMBER's path for counts above len/3: distinct sampled ranks
+    // resolved one by one, versus one in-order scan filling sorted ranks.
+    let count_half = len / 2;
+    group.throughput(Throughput::Elements(count_half as u64));
+    group.bench_function("count_pos_half/select", |b| {
+        let mut rng = support::seeded_rng();
+        b.iter(|| {
+            for rank in sample(&mut rng, len, count_half) {
+                black_box(set.select_by_rank(rank));
+            }
+        });
+    });
+    group.bench_function("count_pos_half/sorted_scan", |b| {
+        let mut rng = support::seeded_rng();
+        b.iter(|| {
+            let mut ranks: Vec<(usize, usize)> = sample(&mut rng, len, count_half)
+                .into_iter()
+                .enumerate()
+                .map(|(slot, rank)| (rank, slot))
+                .collect();
+            ranks.sort_unstable();
+            let mut selected: Vec<Option<(&str, f64)>> = vec![None; count_half];
+            let mut wanted = ranks.iter().peekable();
+            for (i, item) in set.iter_all().enumerate() {
+                let Some(&&(rank, slot)) = wanted.peek() else {
+                    break;
+                };
+                if rank == i {
+                    selected[slot] = Some(item);
+                    wanted.next();
+                }
+            }
+            black_box(selected);
+        });
+    });
+
     group.throughput(Throughput::Elements(count_large as u64));
     group.bench_function("count_neg_with_replacement", |b| {
         let mut rng = support::seeded_rng();
@@ -97,6 +97,14 @@ fn add_range_benches(
             }
         });
     });
+
+    group.bench_function(BenchmarkId::new("members/whole_set", name), |b| {
+        b.iter(|| {
+            for member in set.members_in_range(0, len - 1) {
+                black_box(member);
+            }
+        });
+    });
 }
 
 fn add_score_range_benches(
@@ -22,18 +22,25 @@ fn bench_scan(c: &mut Criterion) {
     group.warm_up_time(warmup);
     group.sample_size(sample_size);
     group.sampling_mode(criterion::SamplingMode::Flat);
+    // `count_N` follows the command, which replies members straight from the
+    // arena; `count_N_owned` keeps the earlier path that copied each member
+    // and score into an owned `String` first.
+    let variants: [(&str, fn(&ScoreSet, &str, usize) -> String); 2] =
+        [("", simulate_scan), ("_owned", simulate_scan_owned)];
     for &count in &[10usize, 100, 1024] {
         group.throughput(Throughput::Elements(count as u64));
-        group.bench_function(format!("count_{count}"), |b| {
-            let index = RefCell::new(0usize);
-            b.iter(|| {
-                let mut guard = index.borrow_mut();
-                let cursor = &cursors[*guard];
-                let next = simulate_scan(set, cursor, count);
-                *guard = (*guard + 1) % cursors.len();
-                black_box(next);
+        for (suffix, scan) in variants {
+            group.bench_function(format!("count_{count}{suffix}"), |b| {
+                let index = RefCell::new(0usize);
+                b.iter(|| {
+                    let mut guard = index.borrow_mut();
+                    let cursor = &cursors[*guard];
+                    let next = scan(set, cursor, count);
+                    *guard = (*guard + 1) % cursors.len();
+                    black_box(next);
+                });
             });
-        });
+        }
     }
     group.finish();
 }
@@ -65,18 +72,22 @@ fn build_cursors(set: &ScoreSet, samples: usize) -> Vec<String> {
 }
 
 fn simulate_scan(set: &ScoreSet, cursor: &str, count: usize) -> String {
-    let parsed = if cursor == "0" {
-        None
-    } else {
-        Some(decode_cursor(cursor).expect("valid cursor"))
-    };
-    let mut iter = match parsed {
-        None => set
-            .iter_from(OrderedFloat(f64::NEG_INFINITY), "", true)
-            .peekable(),
-        Some((score, ref member)) => set.iter_from(OrderedFloat(score), member, true).peekable(),
-    };
+    let parsed = parse_cursor(cursor);
+    let mut iter = scan_iter(set, &parsed);
+    let batch: Vec<(&str, f64)> = iter.by_ref().take(count).collect();
+    for &(member, score) in &batch {
+        black_box(member.as_bytes());
+        with_fmt_buf(|b| black_box(fmt_f64(b, score).as_bytes()).len());
+    }
+    match batch.last() {
+        Some(&(m, sc)) if iter.peek().is_some() => encode_cursor(sc, m),
+        _ => "0".to_string(),
+    }
+}
 
+fn simulate_scan_owned(set: &ScoreSet, cursor: &str, count: usize) -> String {
+    let parsed = parse_cursor(cursor);
+    let mut iter = scan_iter(set, &parsed);
     let mut arr = Vec::with_capacity(count * 2);
     let mut last = None;
     for _ in 0..count {
@@ -95,6 +106,26 @@ fn simulate_scan(set: &ScoreSet, cursor: &str, count: usize) -> String {
     }
 }
 
+fn parse_cursor(cursor: &str) -> Option<(f64, String)> {
+    if cursor == "0" {
+        None
+    } else {
+        Some(decode_cursor(cursor).expect("valid cursor"))
+    }
+}
+
+fn scan_iter<'a>(
+    set: &'a ScoreSet,
+    parsed: &'a Option<(f64, String)>,
+) -> std::iter::Peekable<impl Iterator<Item = (&'a str, f64)> + 'a> {
+    match parsed {
+        None => set
+            .iter_from(OrderedFloat(f64::NEG_INFINITY), "", true)
+            .peekable(),
+        Some((score, member)) => set.iter_from(OrderedFloat(*score), member, true).peekable(),
+    }
+}
+
 fn encode_cursor(score: f64, member: &str) -> String {
     with_fmt_buf(|b| {
         let score_s = fmt_f64(b, score);
@@ -37,6 +37,24 @@ fn bench_lookup(c: &mut Criterion) {
         });
     });
 
+    group.bench_function("score_then_rank/existing_random", |b| {
+        b.iter(|| {
+            for member in rank_existing {
+                let score = set.score(black_box(member.as_str()));
+                let rank = set.rank(black_box(member.as_str()));
+                black_box((score, rank));
+            }
+        });
+    });
+    group.bench_function("score_and_rank/existing_random", |b| {
+        b.iter(|| {
+            for member in rank_existing {
+                let res = set.score_and_rank(black_box(member.as_str()));
+                black_box(res);
+            }
+        });
+    });
+
     #[cfg(feature = "bench-internals")]
     let existing_handles: Vec<_> = rank_existing
         .iter()
@@ -79,6 +97,14 @@ fn bench_lookup(c: &mut Criterion) {
             }
         });
     });
+    group.bench_function("score_and_rank/missing_random", |b| {
+        b.iter(|| {
+            for member in &missing {
+                let res = set.score_and_rank(black_box(member.as_str()));
+                black_box(res);
+            }
+        });
+    });
     group.throughput(Throughput::Elements(existing.len() as u64));
     group.bench_function("score/existing_random", |b| {
         b.iter(|| {
@@ -1,15 +1,72 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering::Relaxed},
+};
+
 use criterion::{criterion_group, criterion_main, Criterion};
 
 mod support;
 
+/// System allocator that tracks live heap bytes and their high-water mark, so
+/// [`peak_during`] can report what one call allocated at its worst.
+struct PeakAlloc;
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+fn grow(bytes: usize) {
+    let live = LIVE.fetch_add(bytes, Relaxed) + bytes;
+    PEAK.fetch_max(live, Relaxed);
+}
+
+unsafe impl GlobalAlloc for PeakAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        LIVE.fetch_sub(layout.size(), Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new = System.realloc(ptr, layout, new_size);
+        if !new.is_null() {
+            if new_size > layout.size() {
+                grow(new_size - layout.size());
+            } else {
+                LIVE.fetch_sub(layout.size() - new_size, Relaxed);
+            }
+        }
+        new
+    }
+}
+
+#[global_allocator]
+static ALLOC: PeakAlloc = PeakAlloc;
+
+/// Run `f` and return its result with the most heap bytes held above the
+/// starting level at any point during the call.
+fn peak_during<R>(f: impl FnOnce() -> R) -> (R, usize) {
+    let base = LIVE.load(Relaxed);
+    PEAK.store(base, Relaxed);
+    let out = f();
+    (out, PEAK.load(Relaxed) - base)
+}
+
 type DatasetGenerator = fn(usize) -> Vec<(f64, String)>;
 
 fn bench_memory(_: &mut Criterion) {
     const SIZES: [usize; 5] = [10_000, 50_000, 100_000, 500_000, 1_000_000];
-    let datasets: [(&str, DatasetGenerator); 3] = [
+    let datasets: [(&str, DatasetGenerator); 4] = [
         ("unique_increasing", support::unique_increasing),
         ("same_score", same_score_dataset),
         ("uniform_random", uniform_random_dataset),
+        ("small_ties", small_ties_dataset),
     ];
 
     for &(name, generator) in &datasets {
@@ -24,6 +81,65 @@ fn bench_memory(_: &mut Criterion) {
     }
 }
 
+/// Memory held by a `copy_range` slice (the `GZRANGESTORE` destination), and
+/// the peak heap allocated while building it. The copy is built from the
+/// range iterator, so both should track the slice size and not the source it
+/// was cut from. Sources are the `GZSET_COPY_RANGE_SOURCE` size and two
+/// tenfold smaller ones.
+fn bench_copy_range(_: &mut Criterion) {
+    let largest = support::usize_env("GZSET_COPY_RANGE_SOURCE", 10_000_000);
+    let slice_len = support::usize_env("GZSET_COPY_RANGE_SLICE", 100_000);
+
+    for n in [largest / 100, largest / 10, largest] {
+        let source = support::build_set(&support::unique_increasing(n));
+        let start = (n / 2) as isize;
+        let (copy, peak) = peak_during(|| source.copy_range(start, start + slice_len as isize - 1));
+        let bench_id = format!("memory/copy_range/{n}/{}", copy.len());
+        support::record_mem(bench_id.as_str(), support::mem_usage_bytes(&copy));
+        support::record_structural_mem(bench_id.as_str(), copy.mem_bytes());
+        support::record_mem(format!("memory/copy_range_peak/{n}/{}", copy.len()), peak);
+    }
+}
+
+/// Memory of one key under adversarial churn: each step adds a never-seen
+/// member and removes the oldest, so the live count stays fixed while the
+/// string arena sees only distinct names. Usage is recorded every tenth of
+/// the run. Removed names stay in the arena until the pool is rebuilt, so
+/// usage still grows with the operation count; setting
+/// `GZSET_CHURN_GROWTH_ASSERT=1` turns the samples into a check that it
+/// stays bounded, which fails until the arena reclaims them on its own.
+fn bench_churn_growth(_: &mut Criterion) {
+    let live = support::usize_env("GZSET_CHURN_GROWTH_LIVE", 1_000);
+    let ops = support::usize_env("GZSET_CHURN_GROWTH_OPS", 1_000_000);
+    let checkpoint = (ops / 10).max(1);
+
+    let mut set = gzset::ScoreSet::default();
+    let mut samples = Vec::new();
+    for i in 0..ops {
+        set.insert(i as f64, &format!("churn:{i}"));
+        if i >= live {
+            set.remove(&format!("churn:{}", i - live));
+        }
+        if (i + 1) % checkpoint == 0 {
+            let bytes = support::mem_usage_bytes(&set);
+            let bench_id = format!("memory/churn_growth/{live}/{}", i + 1);
+            support::record_mem(bench_id.as_str(), bytes);
+            support::record_structural_mem(bench_id.as_str(), set.mem_bytes());
+            samples.push(bytes);
+        }
+    }
+
+    if support::usize_env("GZSET_CHURN_GROWTH_ASSERT", 0) == 0 {
+        return;
+    }
+    if let (Some(&first), Some(&last)) = (samples.first(), samples.last()) {
+        assert!(
+            last <= first * 2,
+            "usage grew from {first} to {last} bytes over {ops} churn ops with {live} live members"
+        );
+    }
+}
+
 fn same_score_dataset(n: usize) -> Vec<(f64, String)> {
     support::same_score(n, 42.0)
 }
@@ -32,5 +148,9 @@ fn uniform_random_dataset(n: usize) -> Vec<(f64, String)> {
     support::uniform_random(n, n as f64)
 }
 
-criterion_group!(benches, bench_memory);
+fn small_ties_dataset(n: usize) -> Vec<(f64, String)> {
+    support::small_ties(n, 4)
+}
+
+criterion_group!(benches, bench_memory, bench_copy_range, bench_churn_growth);
 criterion_main!(benches);
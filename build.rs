@@ -1 +1,14 @@
-fn main() {}
+use std::env;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    // `gzset_mem_breakdown` gates the running `MemBreakdown` copy in
+    // `ScoreSet`. Code pairs it with `test`, which build scripts cannot see.
+    println!("cargo:rustc-check-cfg=cfg(gzset_mem_breakdown)");
+    if env::var_os("CARGO_CFG_DEBUG_ASSERTIONS").is_some()
+        || env::var_os("CARGO_FEATURE_MEM_CHECK").is_some()
+        || env::var_os("CARGO_FEATURE_MEMORY_INTROSPECTION").is_some()
+    {
+        println!("cargo:rustc-cfg=gzset_mem_breakdown");
+    }
+}
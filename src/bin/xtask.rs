@@ -1,7 +1,6 @@
 use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
 use std::{
-    env,
     fs::{self, File},
     io::ErrorKind,
     mem,
@@ -11,7 +10,12 @@ use std::{
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
+use gzset::dev_support as support;
+
 const DEFAULT_PORT: u16 = 6379;
+const SEED_KEY: &str = "seed";
+/// Members per `GZADD` when seeding, to keep individual commands small.
+const SEED_CHUNK: usize = 1000;
 
 fn occupant_info(port: u16) -> Option<(u32, String)> {
     #[cfg(target_os = "linux")]
@@ -64,6 +68,12 @@ enum Cmd {
         /// Kill any existing valkey on port 6379 before starting
         #[arg(long)]
         force_kill: bool,
+        /// Preload this many generated members into the `seed` key
+        #[arg(long)]
+        seed_members: Option<usize>,
+        /// Score layout of the seeded members (see benches/support)
+        #[arg(long, default_value = "increasing")]
+        seed_shape: SeedShape,
         /// Extra arguments forwarded verbatim to valkey-server
         #[arg(trailing_var_arg = true)]
         args: Vec<String>,
@@ -89,6 +99,18 @@ enum Cmd {
         #[arg(trailing_var_arg = true)]
         args: Vec<String>,
     },
+    /// Build gzset, start valkey-server, and diff Z* against GZ* replies.
+    Parity {
+        /// debug (default) or release
+        #[arg(long, default_value = "debug")]
+        profile: Profile,
+        /// Optional fixed port. If omitted an unused one is picked automatically.
+        #[arg(long)]
+        port: Option<u16>,
+        /// Only run cases whose name contains this string (case-insensitive)
+        #[arg(long)]
+        filter: Option<String>,
+    },
 }
 
 #[derive(clap::ValueEnum, Clone, Copy)]
@@ -97,6 +119,34 @@ enum Profile {
     Release,
 }
 
+/// Dataset generators shared with the benches. All are deterministic; the
+/// random ones honour `GZSET_BENCH_SEED`.
+#[derive(clap::ValueEnum, Clone, Copy)]
+enum SeedShape {
+    /// Scores 0..N, one member each (`unique_increasing`)
+    Increasing,
+    /// Uniform random scores in 0..N (`uniform_random`)
+    Uniform,
+    /// Every member at score 0 (`same_score`)
+    Same,
+    /// 16 tight clusters of scores (`clustered`)
+    Clustered,
+    /// Power-law spaced scores (`zipf_like`)
+    Zipf,
+}
+
+impl SeedShape {
+    fn generate(self, n: usize) -> Vec<(f64, String)> {
+        match self {
+            SeedShape::Increasing => support::unique_increasing(n),
+            SeedShape::Uniform => support::uniform_random(n, n.max(1) as f64),
+            SeedShape::Same => support::same_score(n, 0.0),
+            SeedShape::Clustered => support::clustered(n, 16, 1.0),
+            SeedShape::Zipf => support::zipf_like(n, 1.2),
+        }
+    }
+}
+
 fn main() -> Result<()> {
     let cli = Cli::parse();
     match cli.cmd {
@@ -104,8 +154,16 @@ fn main() -> Result<()> {
             profile,
             port,
             force_kill,
+            seed_members,
+            seed_shape,
             args,
-        } => start_valkey(profile, port, force_kill, &args),
+        } => start_valkey(
+            profile,
+            port,
+            force_kill,
+            seed_members.map(|n| (n, seed_shape)),
+            &args,
+        ),
         Cmd::Flame {
             profile,
             port,
@@ -114,51 +172,16 @@ fn main() -> Result<()> {
             shutdown,
             args,
         } => flame_valkey(profile, port, duration, out_dir, shutdown, &args),
-    }
-}
-
-impl Profile {
-    fn artifact_dir(self) -> &'static str {
-        match self {
-            Profile::Debug => "debug",
-            Profile::Release => "release",
-        }
+        Cmd::Parity {
+            profile,
+            port,
+            filter,
+        } => parity(profile, port, filter.as_deref()),
     }
 }
 
 fn build_module(profile: Profile, extra_rustflags: Option<&str>) -> Result<()> {
-    let mut build = Command::new("cargo");
-    build.arg("build").arg("--package=gzset");
-    if matches!(profile, Profile::Release) {
-        build.arg("--release");
-    }
-    build.arg("--features").arg("redis-module");
-
-    if let Some(flags) = extra_rustflags {
-        let mut rustflags = env::var("RUSTFLAGS").unwrap_or_default();
-        if !rustflags.is_empty() {
-            rustflags.push(' ');
-        }
-        rustflags.push_str(flags);
-        build.env("RUSTFLAGS", rustflags);
-    }
-
-    anyhow::ensure!(build.status()?.success(), "cargo build failed");
-    Ok(())
-}
-
-fn resolve_module_path(profile: Profile) -> Result<PathBuf> {
-    let so_name = format!(
-        "{}gzset{}",
-        env::consts::DLL_PREFIX,
-        env::consts::DLL_SUFFIX
-    );
-    let so_path = Path::new(env!("CARGO_MANIFEST_DIR"))
-        .join("target")
-        .join(profile.artifact_dir())
-        .join(&so_name);
-    anyhow::ensure!(so_path.exists(), "module not found at {so_path:?}");
-    Ok(so_path)
+    support::server::build_module(matches!(profile, Profile::Release), extra_rustflags)
 }
 
 fn spawn_valkey(
@@ -202,43 +225,16 @@ fn spawn_valkey(
         fallback
     };
 
-    let so_path = resolve_module_path(profile)?;
-
-    let mut cmd = Command::new("valkey-server");
-    cmd.arg("--port")
-        .arg(port.to_string())
-        .arg("--loadmodule")
-        .arg(&so_path)
-        .arg("--save")
-        .arg("") // disable RDB; keep gzset non-persistent in production until an RDB format exists
-        .arg("--daemonize")
-        .arg("no")
-        .stdin(Stdio::null())
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit());
-    cmd.args(extra_args);
-
-    let mut child = cmd.spawn().context("failed to start valkey-server")?;
-
-    for _ in 0..50u8 {
-        if redis::Client::open(format!("redis://127.0.0.1:{port}"))
-            .and_then(|c| c.get_connection())
-            .and_then(|mut con| redis::cmd("PING").query::<String>(&mut con))
-            .map(|p| p == "PONG")
-            .unwrap_or(false)
-        {
-            return Ok((child, port, so_path));
-        }
-        thread::sleep(Duration::from_millis(100));
-    }
-    let _ = child.kill();
-    anyhow::bail!("valkey-server failed to start");
+    let so_path = support::server::module_path(matches!(profile, Profile::Release))?;
+    let child = support::server::launch(&so_path, port, extra_args)?;
+    Ok((child, port, so_path))
 }
 
 fn start_valkey(
     profile: Profile,
     port_opt: Option<u16>,
     force_kill: bool,
+    seed: Option<(usize, SeedShape)>,
     extra_args: &[String],
 ) -> Result<()> {
     build_module(profile, None)?;
@@ -247,12 +243,41 @@ fn start_valkey(
     println!("=> launching valkey-server on port {port}");
     println!("=> module path         {}", so_path.display());
     println!("=> redis url           redis://127.0.0.1:{port}");
+    if let Some((members, shape)) = seed {
+        if let Err(err) = seed_valkey(port, members, shape) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(err);
+        }
+        println!("=> seeded key          {SEED_KEY} ({members} members)");
+    }
     println!("⇧ press Ctrl-C to stop");
 
     let status = child.wait()?;
     anyhow::bail!("valkey-server exited with status {status}");
 }
 
+/// Replace `SEED_KEY` with `members` entries from `shape`, pipelining the
+/// `GZADD`s in chunks of `SEED_CHUNK`.
+fn seed_valkey(port: u16, members: usize, shape: SeedShape) -> Result<()> {
+    let mut con = redis::Client::open(format!("redis://127.0.0.1:{port}"))?
+        .get_connection()
+        .context("failed to connect to valkey-server")?;
+    redis::cmd("DEL").arg(SEED_KEY).query::<()>(&mut con)?;
+    let entries = shape.generate(members);
+    let mut pipe = redis::pipe();
+    for chunk in entries.chunks(SEED_CHUNK) {
+        let cmd = pipe.cmd("GZADD").arg(SEED_KEY);
+        for (score, member) in chunk {
+            cmd.arg(*score).arg(member);
+        }
+        cmd.ignore();
+    }
+    pipe.query::<()>(&mut con)
+        .context("failed to seed members")?;
+    Ok(())
+}
+
 fn flame_valkey(
     profile: Profile,
     port_opt: Option<u16>,
@@ -631,3 +656,329 @@ fn finish_flame(mut child: Child, port: u16, pid: u32, shutdown: bool) -> Result
 
     Ok(())
 }
+
+/// One row of the parity matrix. Every step is sent once per family with its
+/// first token prefixed by `Z` or `GZ`; tokens starting with `$` are keys and
+/// are namespaced per family so the two runs never see each other's data.
+/// All replies, errors included, must match.
+struct ParityCase {
+    name: &'static str,
+    steps: &'static [&'static [&'static str]],
+}
+
+const PARITY_CASES: &[ParityCase] = &[
+    ParityCase {
+        name: "ADD",
+        steps: &[&["ADD", "$k", "1", "a", "2", "b"], &["ADD", "$k", "3", "a"]],
+    },
+    ParityCase {
+        name: "ADD NX",
+        steps: &[
+            &["ADD", "$k", "1", "a"],
+            &["ADD", "$k", "NX", "5", "a", "2", "b"],
+        ],
+    },
+    ParityCase {
+        name: "ADD XX CH",
+        steps: &[
+            &["ADD", "$k", "1", "a"],
+            &["ADD", "$k", "XX", "CH", "5", "a", "2", "b"],
+        ],
+    },
+    ParityCase {
+        name: "ADD GT/LT",
+        steps: &[
+            &["ADD", "$k", "5", "a", "5", "b"],
+            &["ADD", "$k", "GT", "CH", "4", "a", "6", "b"],
+            &["ADD", "$k", "LT", "CH", "1", "a", "9", "b"],
+            &["RANGE", "$k", "0", "-1", "WITHSCORES"],
+        ],
+    },
+    ParityCase {
+        name: "ADD INCR",
+        steps: &[&["ADD", "$k", "1", "a"], &["ADD", "$k", "INCR", "2.5", "a"]],
+    },
+    ParityCase {
+        name: "ADD errors",
+        steps: &[
+            &["ADD", "$k", "XX", "NX", "1", "a"],
+            &["ADD", "$k", "1"],
+            &["ADD", "$k", "nan", "a"],
+            &["ADD", "$k", "INCR", "1", "a", "2", "b"],
+        ],
+    },
+    ParityCase {
+        name: "CARD",
+        steps: &[
+            &["CARD", "$k"],
+            &["ADD", "$k", "1", "a", "2", "b"],
+            &["CARD", "$k"],
+        ],
+    },
+    ParityCase {
+        name: "SCORE",
+        steps: &[
+            &["SCORE", "$k", "a"],
+            &["ADD", "$k", "1.5", "a", "-2.25", "b"],
+            &["SCORE", "$k", "a"],
+            &["SCORE", "$k", "b"],
+            &["SCORE", "$k", "c"],
+        ],
+    },
+    ParityCase {
+        name: "MSCORE",
+        steps: &[
+            &["MSCORE", "$k", "a", "b"],
+            &["ADD", "$k", "1", "a"],
+            &["MSCORE", "$k", "a", "b"],
+        ],
+    },
+    ParityCase {
+        name: "RANK",
+        steps: &[
+            &["RANK", "$k", "a"],
+            &["ADD", "$k", "1", "a", "2", "b", "2", "c"],
+            &["RANK", "$k", "a"],
+            &["RANK", "$k", "c"],
+            &["RANK", "$k", "d"],
+            &["RANK", "$k", "b", "WITHSCORE"],
+        ],
+    },
+    ParityCase {
+        name: "RANGE",
+        steps: &[
+            &["ADD", "$k", "1", "a", "2", "b", "3", "c", "4", "d"],
+            &["RANGE", "$k", "0", "-1"],
+            &["RANGE", "$k", "1", "2", "WITHSCORES"],
+            &["RANGE", "$k", "-2", "-1"],
+            &["RANGE", "$k", "3", "1"],
+            &["RANGE", "$k", "10", "20"],
+            &["RANGE", "$missing", "0", "-1"],
+        ],
+    },
+    ParityCase {
+        name: "REVRANGE",
+        steps: &[
+            &["ADD", "$k", "1", "a", "2", "b", "2", "c", "4", "d"],
+            &["REVRANGE", "$k", "0", "-1"],
+            &["REVRANGE", "$k", "1", "2", "WITHSCORES"],
+            &["REVRANGE", "$k", "-2", "-1"],
+            &["REVRANGE", "$k", "3", "1"],
+            &["REVRANGE", "$missing", "0", "-1"],
+        ],
+    },
+    ParityCase {
+        name: "RANGEBYSCORE",
+        steps: &[
+            &["ADD", "$k", "1", "a", "2", "b", "3", "c", "4", "d"],
+            &["RANGEBYSCORE", "$k", "-inf", "+inf"],
+            &["RANGEBYSCORE", "$k", "(1", "3", "WITHSCORES"],
+            &["RANGEBYSCORE", "$k", "2", "(4"],
+            &["RANGEBYSCORE", "$k", "-inf", "+inf", "LIMIT", "1", "2"],
+            &["RANGEBYSCORE", "$k", "5", "10"],
+            &["RANGEBYSCORE", "$k", "x", "10"],
+        ],
+    },
+    ParityCase {
+        name: "REM",
+        steps: &[
+            &["ADD", "$k", "1", "a", "2", "b", "3", "c"],
+            &["REM", "$k", "a", "x"],
+            &["REM", "$k", "b", "c"],
+            &["CARD", "$k"],
+            &["REM", "$k", "a"],
+        ],
+    },
+    ParityCase {
+        name: "POPMIN",
+        steps: &[
+            &["POPMIN", "$k"],
+            &["ADD", "$k", "1", "a", "2", "b", "3", "c"],
+            &["POPMIN", "$k"],
+            &["POPMIN", "$k", "5"],
+            &["CARD", "$k"],
+        ],
+    },
+    ParityCase {
+        name: "POPMAX",
+        steps: &[
+            &["POPMAX", "$k"],
+            &["ADD", "$k", "1", "a", "2", "b", "3", "c"],
+            &["POPMAX", "$k"],
+            &["POPMAX", "$k", "5"],
+            &["CARD", "$k"],
+        ],
+    },
+    ParityCase {
+        name: "RANDMEMBER",
+        steps: &[
+            &["RANDMEMBER", "$k"],
+            &["ADD", "$k", "1", "a"],
+            &["RANDMEMBER", "$k"],
+            &["RANDMEMBER", "$k", "3", "WITHSCORES"],
+            &["RANDMEMBER", "$k", "0"],
+        ],
+    },
+    ParityCase {
+        name: "UNION",
+        steps: &[
+            &["ADD", "$a", "1", "x", "2", "y"],
+            &["ADD", "$b", "3", "y", "4", "z"],
+            &["UNION", "2", "$a", "$b"],
+            &["UNION", "2", "$a", "$b", "WITHSCORES"],
+            &["UNION", "0", "$a"],
+        ],
+    },
+    ParityCase {
+        name: "UNIONSTORE",
+        steps: &[
+            &["ADD", "$a", "1", "x", "2", "y"],
+            &["ADD", "$b", "3", "y", "4", "z"],
+            &["UNIONSTORE", "$d", "2", "$a", "$b", "WEIGHTS", "2", "1"],
+            &["RANGE", "$d", "0", "-1", "WITHSCORES"],
+            &["UNIONSTORE", "$d", "2", "$a", "$b", "AGGREGATE", "MAX"],
+            &["RANGE", "$d", "0", "-1", "WITHSCORES"],
+            &["UNIONSTORE", "$d", "1", "$missing"],
+            &["CARD", "$d"],
+        ],
+    },
+    ParityCase {
+        name: "INTER",
+        steps: &[
+            &["ADD", "$a", "1", "x", "2", "y"],
+            &["ADD", "$b", "3", "y", "4", "z"],
+            &["INTER", "2", "$a", "$b"],
+            &["INTER", "2", "$a", "$b", "WITHSCORES"],
+            &["INTER", "2", "$a", "$missing"],
+        ],
+    },
+    ParityCase {
+        name: "INTERCARD",
+        steps: &[
+            &["ADD", "$a", "1", "x", "2", "y", "3", "z"],
+            &["ADD", "$b", "2", "y", "3", "z"],
+            &["INTERCARD", "2", "$a", "$b"],
+            &["INTERCARD", "2", "$a", "$b", "LIMIT", "1"],
+        ],
+    },
+    ParityCase {
+        name: "DIFF",
+        steps: &[
+            &["ADD", "$a", "1", "x", "2", "y", "3", "z"],
+            &["ADD", "$b", "2", "y"],
+            &["DIFF", "2", "$a", "$b"],
+            &["DIFF", "2", "$a", "$b", "WITHSCORES"],
+            &["DIFF", "1", "$missing"],
+        ],
+    },
+];
+
+/// Expand one step for `family`, returning the command line as sent.
+fn parity_args(family: &str, case: usize, step: &[&str]) -> Vec<String> {
+    let mut out = Vec::with_capacity(step.len());
+    for (i, tok) in step.iter().enumerate() {
+        if i == 0 {
+            out.push(format!("{family}{tok}"));
+        } else if let Some(key) = tok.strip_prefix('$') {
+            out.push(format!("parity:{}:{case}:{key}", family.to_lowercase()));
+        } else {
+            out.push((*tok).to_string());
+        }
+    }
+    out
+}
+
+/// Render a reply in a `valkey-cli`-like form so mismatches are readable.
+fn fmt_reply(reply: &redis::RedisResult<redis::Value>) -> String {
+    fn value(v: &redis::Value) -> String {
+        match v {
+            redis::Value::Nil => "(nil)".to_string(),
+            redis::Value::Int(n) => format!("(integer) {n}"),
+            redis::Value::Data(bytes) => format!("{:?}", String::from_utf8_lossy(bytes)),
+            redis::Value::Bulk(items) => {
+                let items: Vec<String> = items.iter().map(value).collect();
+                format!("[{}]", items.join(", "))
+            }
+            redis::Value::Status(s) => s.clone(),
+            redis::Value::Okay => "OK".to_string(),
+        }
+    }
+    match reply {
+        Ok(v) => value(v),
+        Err(err) => format!(
+            "(error) {} {}",
+            err.code().unwrap_or("ERR"),
+            err.detail().unwrap_or_default()
+        ),
+    }
+}
+
+fn parity(profile: Profile, port_opt: Option<u16>, filter: Option<&str>) -> Result<()> {
+    build_module(profile, None)?;
+    let quiet = ["--loglevel".to_string(), "warning".to_string()];
+    let (mut child, port, so_path) = spawn_valkey(profile, port_opt, false, &quiet)?;
+    println!("=> module path         {}", so_path.display());
+    println!("=> redis url           redis://127.0.0.1:{port}");
+
+    let result = run_parity(port, filter);
+    let _ = child.kill();
+    let _ = child.wait();
+    let failed = result?;
+    anyhow::ensure!(failed == 0, "{failed} parity case(s) failed");
+    Ok(())
+}
+
+/// Run every selected case, print mismatches and a summary table, and return
+/// the number of failing cases.
+fn run_parity(port: u16, filter: Option<&str>) -> Result<usize> {
+    let mut con = redis::Client::open(format!("redis://127.0.0.1:{port}"))?
+        .get_connection()
+        .context("failed to connect to valkey-server")?;
+    let filter = filter.map(str::to_lowercase);
+
+    let mut rows = Vec::new();
+    for (idx, case) in PARITY_CASES.iter().enumerate() {
+        if let Some(f) = &filter {
+            if !case.name.to_lowercase().contains(f.as_str()) {
+                continue;
+            }
+        }
+        let mut mismatches = 0usize;
+        for step in case.steps {
+            let mut replies = Vec::with_capacity(2);
+            for family in ["Z", "GZ"] {
+                let args = parity_args(family, idx, step);
+                let mut cmd = redis::cmd(&args[0]);
+                for arg in &args[1..] {
+                    cmd.arg(arg);
+                }
+                replies.push(fmt_reply(&cmd.query::<redis::Value>(&mut con)));
+            }
+            if replies[0] != replies[1] {
+                mismatches += 1;
+                println!("MISMATCH [{}] {}", case.name, step.join(" "));
+                println!("    Z : {}", replies[0]);
+                println!("    GZ: {}", replies[1]);
+            }
+        }
+        rows.push((case.name, case.steps.len(), mismatches));
+    }
+    anyhow::ensure!(!rows.is_empty(), "no parity cases matched the filter");
+
+    let width = rows.iter().map(|(name, ..)| name.len()).max().unwrap_or(0);
+    println!();
+    println!("{:<width$}  steps  result", "case");
+    println!("{}", "-".repeat(width + 15));
+    for (name, steps, mismatches) in &rows {
+        let result = if *mismatches == 0 {
+            "pass".to_string()
+        } else {
+            format!("FAIL ({mismatches})")
+        };
+        println!("{name:<width$}  {steps:>5}  {result}");
+    }
+    let failed = rows.iter().filter(|(.., m)| *m > 0).count();
+    println!("{}", "-".repeat(width + 15));
+    println!("{} passed, {failed} failed", rows.len() - failed);
+    Ok(failed)
+}
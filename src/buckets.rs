@@ -1,28 +1,149 @@
-use std::{convert::TryFrom, mem::size_of};
+use std::{cmp::Ordering, convert::TryFrom, mem::size_of};
 
 use crate::pool::MemberId;
 
 pub type BucketId = u32;
 
+/// Most members a score keeps inline in the score map before spilling to a
+/// [`BucketStore`] bucket. One by default, which keeps each score map value
+/// at eight bytes. The `inline-ties` feature raises it to three: every value
+/// widens to sixteen bytes, which pays off only for sets with many small ties.
+#[cfg(not(feature = "inline-ties"))]
+pub const INLINE_MEMBERS_MAX: usize = 1;
+#[cfg(feature = "inline-ties")]
+pub const INLINE_MEMBERS_MAX: usize = 3;
+
+const _: () = assert!(INLINE_MEMBERS_MAX >= 1 && INLINE_MEMBERS_MAX <= u8::MAX as usize);
+const _: () = assert!(INLINE_MEMBERS_MAX > 1 || size_of::<BucketRef>() == 8);
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum BucketRef {
     /// Exactly one member, stored inline in the score map.
     Inline1(MemberId),
+    /// Two to [`INLINE_MEMBERS_MAX`] members in name order, stored inline
+    /// in the score map. Slots past `len` are unused.
+    InlineN {
+        len: u8,
+        ids: [MemberId; INLINE_MEMBERS_MAX],
+    },
     /// Index into [`BucketStore`].
     Handle(BucketId),
 }
 
+impl BucketRef {
+    /// Inline entry for `ids`, which must be in name order, or `None` if
+    /// there are none or more than [`INLINE_MEMBERS_MAX`].
+    pub fn inline(ids: &[MemberId]) -> Option<BucketRef> {
+        match *ids {
+            [] => None,
+            [only] => Some(BucketRef::Inline1(only)),
+            _ if ids.len() <= INLINE_MEMBERS_MAX => {
+                let mut slots = [0; INLINE_MEMBERS_MAX];
+                slots[..ids.len()].copy_from_slice(ids);
+                Some(BucketRef::InlineN {
+                    len: ids.len() as u8,
+                    ids: slots,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Members of an inline entry. Panics on a handle.
+    pub fn inline_ids(&self) -> &[MemberId] {
+        match self {
+            BucketRef::Inline1(id) => std::slice::from_ref(id),
+            BucketRef::InlineN { len, ids } => &ids[..usize::from(*len)],
+            BucketRef::Handle(_) => panic!("handle has no inline members"),
+        }
+    }
+
+    /// This inline entry with `member` inserted at `pos` of its name order,
+    /// or `None` if the entry is already full.
+    pub fn inline_inserted(&self, pos: usize, member: MemberId) -> Option<BucketRef> {
+        let ids = self.inline_ids();
+        if ids.len() == INLINE_MEMBERS_MAX {
+            return None;
+        }
+        let mut slots = [0; INLINE_MEMBERS_MAX];
+        slots[..pos].copy_from_slice(&ids[..pos]);
+        slots[pos] = member;
+        slots[pos + 1..=ids.len()].copy_from_slice(&ids[pos..]);
+        BucketRef::inline(&slots[..=ids.len()])
+    }
+
+    /// This inline entry without `member`, or `None` if nothing remains.
+    pub fn inline_removed(&self, member: MemberId) -> Option<BucketRef> {
+        let ids = self.inline_ids();
+        debug_assert!(ids.contains(&member), "inline bucket must contain member");
+        let mut slots = [0; INLINE_MEMBERS_MAX];
+        let mut len = 0;
+        for &id in ids.iter().filter(|&&id| id != member) {
+            slots[len] = id;
+            len += 1;
+        }
+        BucketRef::inline(&slots[..len])
+    }
+
+    /// The score's members in name order.
+    #[inline]
+    pub fn ids<'a>(&'a self, store: &'a BucketStore) -> &'a [MemberId] {
+        match self {
+            BucketRef::Handle(bucket_id) => store.slice(*bucket_id),
+            inline => inline.inline_ids(),
+        }
+    }
+
+    /// Number of members at the score.
+    #[inline]
+    pub fn len(&self, store: &BucketStore) -> usize {
+        match self {
+            BucketRef::Inline1(_) => 1,
+            BucketRef::InlineN { len, .. } => usize::from(*len),
+            BucketRef::Handle(bucket_id) => store.len(*bucket_id),
+        }
+    }
+}
+
+/// Heap bytes held per bucket slot: the member id plus its cached sort key.
+pub const BUCKET_SLOT_BYTES: usize = size_of::<MemberId>() + size_of::<u64>();
+
+/// Big-endian packing of the first eight bytes of `name`, zero padded.
+///
+/// Comparing two keys orders names the same way as comparing the names
+/// themselves, except that equal keys (shared 8-byte prefix, or trailing NUL
+/// bytes) must fall back to the full comparison.
+#[inline]
+pub(crate) fn sort_key(name: &str) -> u64 {
+    let bytes = name.as_bytes();
+    let mut buf = [0u8; 8];
+    let n = bytes.len().min(8);
+    buf[..n].copy_from_slice(&bytes[..n]);
+    u64::from_be_bytes(buf)
+}
+
+/// Members sorted by name. `keys` runs parallel to `data` and caches each
+/// member's [`sort_key`] so binary searches rarely touch the string arena.
+///
+/// `dropped` counts removals since the bucket was last compacted. Draining the
+/// head waits until that count reaches half the live length, so a hot bucket
+/// that keeps losing and regaining members pays for one drain per `len / 2`
+/// removals instead of one per removal.
 #[derive(Debug, Default)]
 pub struct Bucket {
     data: Vec<MemberId>,
+    keys: Vec<u64>,
     head: usize,
+    dropped: usize,
 }
 
 impl Bucket {
     fn with_capacity(min_cap: usize) -> Self {
         Self {
             data: Vec::with_capacity(min_cap),
+            keys: Vec::with_capacity(min_cap),
             head: 0,
+            dropped: 0,
         }
     }
 
@@ -38,17 +159,57 @@ impl Bucket {
         self.data.capacity()
     }
 
+    fn heap_bytes(&self) -> usize {
+        self.data.capacity() * size_of::<MemberId>() + self.keys.capacity() * size_of::<u64>()
+    }
+
     fn as_slice(&self) -> &[MemberId] {
         debug_assert!(self.head <= self.data.len(), "bucket head beyond buffer");
         &self.data[self.head..]
     }
 
-    fn insert_at(&mut self, pos: usize, member: MemberId) {
+    fn key_slice(&self) -> &[u64] {
+        debug_assert_eq!(self.keys.len(), self.data.len(), "bucket keys out of sync");
+        &self.keys[self.head..]
+    }
+
+    fn clear(&mut self) {
+        self.data.clear();
+        self.keys.clear();
+        self.head = 0;
+        self.dropped = 0;
+    }
+
+    /// Binary search by `(sort key, full name)`. The name is only resolved
+    /// when the cached keys tie.
+    fn search<'a, F>(&self, key: u64, name: &str, cmp_name: &F) -> Result<usize, usize>
+    where
+        F: Fn(MemberId) -> &'a str,
+    {
+        let ids = self.as_slice();
+        let keys = self.key_slice();
+        let (mut lo, mut hi) = (0usize, ids.len());
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let ord = keys[mid]
+                .cmp(&key)
+                .then_with(|| cmp_name(ids[mid]).cmp(name));
+            match ord {
+                Ordering::Less => lo = mid + 1,
+                Ordering::Greater => hi = mid,
+                Ordering::Equal => return Ok(mid),
+            }
+        }
+        Err(lo)
+    }
+
+    fn insert_at(&mut self, pos: usize, member: MemberId, key: u64) {
         let len = self.len();
         debug_assert!(pos <= len, "insert position out of bounds");
         if pos == 0 && self.head > 0 {
             let new_head = self.head - 1;
             self.data[new_head] = member;
+            self.keys[new_head] = key;
             self.head = new_head;
             return;
         }
@@ -56,21 +217,23 @@ impl Bucket {
         let idx = self.head + pos;
         if idx == self.data.len() {
             self.data.push(member);
+            self.keys.push(key);
         } else {
             self.data.insert(idx, member);
+            self.keys.insert(idx, key);
         }
     }
 
     fn remove_at(&mut self, pos: usize) -> MemberId {
         let len = self.len();
         debug_assert!(pos < len, "remove position out of bounds");
+        self.dropped += 1;
         if pos == 0 {
             let idx = self.head;
             let value = self.data[idx];
             self.head += 1;
             if self.head >= self.data.len() {
-                self.data.clear();
-                self.head = 0;
+                self.clear();
             }
             return value;
         }
@@ -82,12 +245,13 @@ impl Bucket {
                 .data
                 .pop()
                 .expect("bucket len tracked pop requires element");
+            self.keys.pop();
             if self.head >= self.data.len() {
-                self.data.clear();
-                self.head = 0;
+                self.clear();
             }
             value
         } else {
+            self.keys.remove(idx);
             self.data.remove(idx)
         }
     }
@@ -98,10 +262,10 @@ impl Bucket {
         if take == 0 {
             return 0;
         }
+        self.dropped += take;
         self.head += take;
         if self.head >= self.data.len() {
-            self.data.clear();
-            self.head = 0;
+            self.clear();
         }
         take
     }
@@ -112,11 +276,12 @@ impl Bucket {
         if take == 0 {
             return 0;
         }
+        self.dropped += take;
         let new_len = self.data.len() - take;
         self.data.truncate(new_len);
+        self.keys.truncate(new_len);
         if self.head >= self.data.len() {
-            self.data.clear();
-            self.head = 0;
+            self.clear();
         }
         take
     }
@@ -125,55 +290,62 @@ impl Bucket {
         if self.head > 0 {
             debug_assert!(self.head <= self.data.len(), "bucket head beyond data len");
             self.data.drain(..self.head);
+            self.keys.drain(..self.head);
             self.head = 0;
         }
     }
 
-    /// Decide whether draining the head would reclaim meaningful space.
+    /// Whether spare capacity is worth a reallocation: the live tail is at
+    /// most `shrink_threshold` and fills less than half the buffer.
     ///
-    /// Triggers when the live tail is small (<= `shrink_threshold`), when the
-    /// skipped prefix has grown large (>= `shrink_threshold`), or when the head
-    /// accounts for more than half of the buffer.
-    fn should_compact(&self, shrink_threshold: usize) -> bool {
-        if self.head == 0 {
-            return false;
-        }
-
-        let total_len = self.data.len();
-        debug_assert!(self.head <= total_len, "bucket head beyond buffer");
+    /// The strict ratio leaves room for the doubling a push just caused, so a
+    /// bucket oscillating around one length never shrinks and regrows.
+    fn has_excess_capacity(&self, shrink_threshold: usize) -> bool {
         let len = self.len();
+        len <= shrink_threshold && self.capacity() > len.saturating_mul(2)
+    }
 
-        len <= shrink_threshold || self.head >= shrink_threshold || self.head > total_len / 2
+    /// Whether the removals since the last compaction reach half the live
+    /// length.
+    fn has_excess_waste(&self) -> bool {
+        self.dropped > 0 && self.dropped.saturating_mul(2) >= self.len()
     }
 
-    fn maybe_compact(&mut self, shrink_threshold: usize) -> isize {
+    /// Drain the consumed head and, if `shrink` is set, release spare
+    /// capacity. Returns the (non-positive) change in heap bytes.
+    fn compact(&mut self, shrink: bool) -> isize {
         if self.is_empty() {
-            self.data.clear();
-            self.head = 0;
+            self.clear();
             return 0;
         }
 
-        let cap_before = self.data.capacity();
-        let total_len = self.data.len();
-        debug_assert!(self.head <= total_len, "bucket head beyond buffer");
-
-        if self.should_compact(shrink_threshold) {
-            self.compact_head();
-        }
-
-        let len_after = self.len();
-        if len_after <= shrink_threshold {
+        let bytes_before = self.heap_bytes();
+        self.compact_head();
+        if shrink {
             self.data.shrink_to_fit();
+            self.keys.shrink_to_fit();
         }
+        self.dropped = 0;
 
-        let cap_after = self.data.capacity();
-        if cap_after < cap_before {
-            let bytes = (cap_before - cap_after) * size_of::<MemberId>();
-            -isize::try_from(bytes).expect("bucket shrink delta overflow")
+        let bytes_after = self.heap_bytes();
+        if bytes_after < bytes_before {
+            -isize::try_from(bytes_before - bytes_after).expect("bucket shrink delta overflow")
         } else {
             0
         }
     }
+
+    fn maybe_compact(&mut self, shrink_threshold: usize) -> isize {
+        if self.is_empty() {
+            self.clear();
+            return 0;
+        }
+        let shrink = self.has_excess_capacity(shrink_threshold);
+        if !shrink && !self.has_excess_waste() {
+            return 0;
+        }
+        self.compact(shrink)
+    }
 }
 
 #[derive(Default, Debug)]
@@ -230,6 +402,21 @@ impl BucketStore {
         self.alloc_inner(min_cap)
     }
 
+    /// Drop consumed heads and spare capacity from every live bucket and from
+    /// the store's own vectors. Returns the change in bucket heap bytes; the
+    /// store vectors are not part of that figure.
+    pub fn shrink_to_fit(&mut self) -> isize {
+        let delta = self
+            .buckets
+            .iter_mut()
+            .flatten()
+            .map(|bucket| bucket.compact(true))
+            .sum();
+        self.buckets.shrink_to_fit();
+        self.free.shrink_to_fit();
+        delta
+    }
+
     pub fn free_if_empty(&mut self, id: BucketId) -> (bool, isize) {
         let buckets_len = self.buckets.len();
         let is_last = (id as usize) + 1 == buckets_len;
@@ -239,7 +426,7 @@ impl BucketStore {
             .expect("invalid bucket id");
         if let Some(bucket) = slot {
             if bucket.is_empty() {
-                let spilled_bytes = bucket.capacity() * size_of::<MemberId>();
+                let spilled_bytes = bucket.heap_bytes();
                 *slot = None;
                 self.free.push(id);
                 if is_last {
@@ -270,28 +457,53 @@ impl BucketStore {
         F: Fn(MemberId) -> &'a str,
     {
         let bucket = self.bucket_mut(id);
-        let cap_before = bucket.capacity();
-        let spilled_before = cap_before > 0;
+        let bytes_before = bucket.heap_bytes();
+        let spilled_before = bucket.capacity() > 0;
         let member_name = cmp_name(member);
-        match bucket
-            .as_slice()
-            .binary_search_by(|&m| cmp_name(m).cmp(member_name))
-        {
+        let key = sort_key(member_name);
+        match bucket.search(key, member_name, &cmp_name) {
             Ok(pos) => (false, 0, spilled_before, bucket.capacity() > 0, pos),
             Err(pos) => {
-                bucket.insert_at(pos, member);
-                let cap_after = bucket.capacity();
-                let delta = if cap_after > cap_before {
-                    let bytes = (cap_after - cap_before) * size_of::<MemberId>();
-                    isize::try_from(bytes).expect("bucket spill delta overflow")
+                bucket.insert_at(pos, member, key);
+                let bytes_after = bucket.heap_bytes();
+                let delta = if bytes_after > bytes_before {
+                    isize::try_from(bytes_after - bytes_before)
+                        .expect("bucket spill delta overflow")
                 } else {
                     0
                 };
-                (true, delta, spilled_before, cap_after > 0, pos)
+                (true, delta, spilled_before, bucket.capacity() > 0, pos)
             }
         }
     }
 
+    /// Append `members`, which must be in ascending name order and sort after
+    /// everything already in the bucket, without searching for their slots.
+    /// Returns the change in heap bytes.
+    pub fn extend_sorted<'a, F>(&mut self, id: BucketId, members: &[MemberId], name_of: F) -> isize
+    where
+        F: Fn(MemberId) -> &'a str,
+    {
+        let bucket = self.bucket_mut(id);
+        let bytes_before = bucket.heap_bytes();
+        bucket.data.reserve(members.len());
+        bucket.keys.reserve(members.len());
+        for &member in members {
+            debug_assert!(
+                bucket
+                    .data
+                    .last()
+                    .is_none_or(|&last| name_of(last) < name_of(member)),
+                "extend_sorted input out of name order"
+            );
+            bucket.data.push(member);
+            bucket.keys.push(sort_key(name_of(member)));
+        }
+        let bytes_after = bucket.heap_bytes();
+        isize::try_from(bytes_after).expect("bucket size overflow")
+            - isize::try_from(bytes_before).expect("bucket size overflow")
+    }
+
     pub fn remove_by_name<'a, F>(
         &mut self,
         id: BucketId,
@@ -302,10 +514,7 @@ impl BucketStore {
         F: Fn(MemberId) -> &'a str,
     {
         let bucket = self.bucket_mut(id);
-        match bucket
-            .as_slice()
-            .binary_search_by(|&m| cmp_name(m).cmp(name))
-        {
+        match bucket.search(sort_key(name), name, &cmp_name) {
             Ok(pos) => {
                 bucket.remove_at(pos);
                 (true, 0, bucket.is_empty())
@@ -314,6 +523,27 @@ impl BucketStore {
         }
     }
 
+    /// Position of `name` within the bucket, if present.
+    pub fn position<'a, F>(&self, id: BucketId, name: &str, cmp_name: F) -> Option<usize>
+    where
+        F: Fn(MemberId) -> &'a str,
+    {
+        self.bucket(id).search(sort_key(name), name, &cmp_name).ok()
+    }
+
+    /// Rewrite every member id in bucket `id` through `f`, front to back.
+    /// Names (and therefore order and sort keys) are unchanged.
+    pub fn remap_members<F>(&mut self, id: BucketId, mut f: F)
+    where
+        F: FnMut(MemberId) -> MemberId,
+    {
+        let bucket = self.bucket_mut(id);
+        let head = bucket.head;
+        for member in &mut bucket.data[head..] {
+            *member = f(*member);
+        }
+    }
+
     pub fn take_singleton(&mut self, id: BucketId) -> (MemberId, isize) {
         let is_last = (id as usize) + 1 == self.buckets.len();
         let slot = self
@@ -323,7 +553,7 @@ impl BucketStore {
         let bucket = slot.take().expect("bucket must exist");
         debug_assert_eq!(bucket.len(), 1, "take_singleton requires len == 1");
         let member = bucket.as_slice()[0];
-        let spilled_bytes = bucket.capacity() * size_of::<MemberId>();
+        let spilled_bytes = bucket.heap_bytes();
         let delta = if spilled_bytes == 0 {
             0
         } else {
@@ -358,7 +588,13 @@ impl BucketStore {
     }
 
     pub fn capacity_bytes(&self, id: BucketId) -> usize {
-        self.bucket(id).capacity() * size_of::<MemberId>()
+        self.bucket(id).heap_bytes()
+    }
+
+    /// Number of allocated buckets. Freed slots are always on the free list,
+    /// so this is O(1).
+    pub fn live_count(&self) -> usize {
+        self.buckets.len() - self.free.len()
     }
 
     pub fn len(&self, id: BucketId) -> usize {
@@ -378,9 +614,6 @@ impl BucketStore {
             if take == 0 {
                 return (false, 0);
             }
-            if !bucket.is_empty() && bucket.should_compact(shrink_threshold) {
-                bucket.compact_head();
-            }
             remaining = bucket.len();
         }
 
@@ -391,9 +624,6 @@ impl BucketStore {
         } else if remaining == 1 {
             (false, 0)
         } else {
-            // `maybe_shrink` still runs here so buckets shrink their capacity once
-            // the tail falls under the threshold, even if we already compacted
-            // above.
             let delta = self.maybe_shrink(id, shrink_threshold);
             (false, delta)
         }
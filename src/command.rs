@@ -1,22 +1,59 @@
 use crate::format::{fmt_f64, with_fmt_buf};
-use crate::{score_set::ScoreSet, FastHashMap};
+use crate::{
+    memory::{self, Footprint},
+    pool::{
+        ARENA_CHUNK_SIZE, ARENA_CHUNK_SIZE_DEFAULT, ARENA_CHUNK_SIZE_MAX, ARENA_CHUNK_SIZE_MIN,
+    },
+    range::parse_score_bound,
+    score_set::{
+        Aggregate, IncrError, ScoreOp, ScoreSet, UpdateCond, UpsertOutcome,
+        BUCKET_SHRINK_THRESHOLD, BUCKET_SHRINK_THRESHOLD_DEFAULT, BUCKET_SHRINK_THRESHOLD_MAX,
+        BUCKET_SHRINK_THRESHOLD_MIN,
+    },
+    FastHashMap,
+};
 use ordered_float::OrderedFloat;
 use redis_module::raw::{
-    RedisModule_ReplyWithArray, RedisModule_ReplyWithDouble, RedisModule_ReplyWithNull,
-    RedisModule_ReplyWithStringBuffer,
+    RedisModule_ReplyWithArray, RedisModule_ReplyWithDouble, RedisModule_ReplyWithLongLong,
+    RedisModule_ReplyWithNull, RedisModule_ReplyWithStringBuffer,
+};
+use redis_module::{
+    self as rm,
+    key::{RedisKey, RedisKeyWritable},
+    raw, Context, ContextFlags, KeyType, RedisError, RedisResult, RedisString, RedisValue,
 };
-use redis_module::{self as rm, raw, Context, RedisError, RedisResult, RedisString, RedisValue};
-use std::convert::TryFrom;
+use std::convert::{Infallible, TryFrom};
 use std::ffi::CString;
 use std::os::raw::{c_char, c_int, c_long, c_void};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::Duration;
 
 pub type Result<T = RedisValue> = RedisResult<T>;
 
+/// Reply when a key has no member id left for a new member. Pairs applied
+/// earlier in the same command are kept.
+const ERR_TOO_MANY_MEMBERS: &str = "ERR too many members in sorted set";
+
 const REDISMODULE_API_VERSION: c_int = raw::REDISMODULE_APIVER_1 as c_int;
 
+/// Default for the `gzset.scan-default-count` module config.
+const SCAN_DEFAULT_COUNT_DEFAULT: i64 = 10;
+/// Default for the `gzset.scan-max-count` module config.
+const SCAN_MAX_COUNT_DEFAULT: i64 = 1024;
+/// Lower bound for both scan count configs.
+const SCAN_COUNT_MIN: i64 = 1;
+/// Upper bound for both scan count configs.
+const SCAN_COUNT_MAX: i64 = 1 << 20;
+/// Pairs per `GZSCAN` reply when no `COUNT` is given. Backs the
+/// `gzset.scan-default-count` module config.
+static SCAN_DEFAULT_COUNT: AtomicI64 = AtomicI64::new(SCAN_DEFAULT_COUNT_DEFAULT);
+/// Largest `COUNT` a `GZSCAN` call may request. Backs the
+/// `gzset.scan-max-count` module config.
+static SCAN_MAX_COUNT: AtomicI64 = AtomicI64::new(SCAN_MAX_COUNT_DEFAULT);
+
 pub static GZSET_TYPE: rm::native_types::RedisType = rm::native_types::RedisType::new(
-    "gzsetmod1",
-    0,
+    crate::persist::TYPE_NAME,
+    crate::persist::ENCODING_VERSION,
     raw::RedisModuleTypeMethods {
         version: raw::REDISMODULE_TYPE_METHOD_VERSION as u64,
         rdb_load: None,
@@ -40,39 +77,116 @@ pub static GZSET_TYPE: rm::native_types::RedisType = rm::native_types::RedisType
     },
 );
 
+/// `get_value` only fails when the key holds another type, and then with a
+/// bare "Existing key has wrong Redis type" string; map it to the standard
+/// `WRONGTYPE` reply.
+fn wrong_type(_: RedisError) -> RedisError {
+    RedisError::WrongType
+}
+
 fn with_set_write<F, R>(ctx: &Context, key: &RedisString, f: F) -> rm::RedisResult<R>
 where
     F: FnOnce(&mut ScoreSet) -> R,
+{
+    let (res, _, _) = with_set_write_created(ctx, key, None, |s| Ok::<_, Infallible>(f(s)))?;
+    Ok(res.unwrap_or_else(|never| match never {}))
+}
+
+/// [`with_set_write`], also reporting whether the write created the key: it
+/// was missing and `f` left members behind, so a new value was stored. When
+/// `expire` is given, `f` succeeded and the key holds members afterwards, its
+/// TTL is set on the same handle, and the absolute expiry the server stored
+/// (Unix ms) is returned third. A failed `f` must leave the set as it found it.
+fn with_set_write_created<F, T, E>(
+    ctx: &Context,
+    key: &RedisString,
+    expire: Option<Duration>,
+    f: F,
+) -> rm::RedisResult<(std::result::Result<T, E>, bool, Option<i64>)>
+where
+    F: FnOnce(&mut ScoreSet) -> std::result::Result<T, E>,
 {
     let rkey = ctx.open_key_writable(key);
-    let cached = rkey.get_value::<ScoreSet>(&GZSET_TYPE)?;
+    let cached = rkey
+        .get_value::<ScoreSet>(&GZSET_TYPE)
+        .map_err(wrong_type)?;
     let was_missing = cached.is_none();
+    let before = cached.as_deref().map(Footprint::of);
     let mut inserted = if was_missing {
         Some(ScoreSet::default())
     } else {
         None
     };
 
-    let (res, empty) = {
+    let (res, empty, after) = {
         let set = match cached {
             Some(set) => set,
             None => inserted.as_mut().expect("score set must exist"),
         };
         let r = f(set);
-        (r, set.is_empty())
+        (r, set.is_empty(), Footprint::of(set))
+    };
+
+    // A fresh set is counted by `finish_write` once it is stored.
+    if let Some(before) = before {
+        memory::track(before, after);
+    }
+    finish_write(&rkey, inserted, empty)?;
+    let expire_at = match expire {
+        Some(ttl) if res.is_ok() && !empty => {
+            rkey.set_expire(ttl)?;
+            stored_expire_at(ctx, key)
+        }
+        _ => None,
     };
+    Ok((res, was_missing && !empty, expire_at))
+}
+
+/// The absolute expiry, in Unix milliseconds, the server stored for `key`,
+/// or `None` when it has no TTL. Propagating this value, rather than a
+/// deadline computed from a separate clock read, makes replicas expire the
+/// key at exactly the primary's moment.
+fn stored_expire_at(ctx: &Context, key: &RedisString) -> Option<i64> {
+    // A write handle looks the key up without touching keyspace hit stats.
+    let k = raw::open_key(ctx.ctx, key.inner, raw::KeyMode::WRITE);
+    let at = unsafe { raw::RedisModule_GetAbsExpire.unwrap()(k) };
+    raw::close_key(k);
+    (at != raw::REDISMODULE_NO_EXPIRE as i64).then_some(at)
+}
 
-    if was_missing && !empty {
-        if let Some(set) = inserted {
+/// Settle `rkey` after a write: `fresh` is a set the key did not hold yet and
+/// is stored unless empty, while a stored set that `empty` reports drained is
+/// deleted. No write leaves an empty set behind, so `EXISTS` reports 0 as it
+/// does for core sorted sets.
+fn finish_write(rkey: &RedisKeyWritable, fresh: Option<ScoreSet>, empty: bool) -> Result<()> {
+    match fresh {
+        Some(set) if !empty => {
+            let footprint = Footprint::of(&set);
             rkey.set_value(&GZSET_TYPE, set)?;
+            memory::track(Footprint::default(), footprint);
+        }
+        Some(_) => {}
+        None if empty => {
+            rkey.delete()?;
         }
+        None => {}
     }
+    Ok(())
+}
 
-    if empty {
-        rkey.delete()?;
+/// Propagate a pop as `GZREM key member [member ...]` instead of the pop
+/// itself, so replicas and the AOF drop exactly the members this call
+/// returned. It is emitted within the popping command, so a `WAIT` issued
+/// after the pop also covers the removal.
+fn replicate_removals<'a, I>(ctx: &Context, key: &'a RedisString, members: I)
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut args: Vec<&[u8]> = vec![key.as_slice()];
+    args.extend(members.into_iter().map(str::as_bytes));
+    if args.len() > 1 {
+        ctx.replicate("GZREM", args.as_slice());
     }
-
-    Ok(res)
 }
 
 fn with_set_read<F, R>(ctx: &Context, key: &RedisString, f: F) -> rm::RedisResult<R>
@@ -80,7 +194,10 @@ where
     F: FnOnce(&ScoreSet) -> R,
 {
     let rkey = ctx.open_key(key);
-    if let Some(set) = rkey.get_value::<ScoreSet>(&GZSET_TYPE)? {
+    if let Some(set) = rkey
+        .get_value::<ScoreSet>(&GZSET_TYPE)
+        .map_err(wrong_type)?
+    {
         Ok(f(set))
     } else {
         let tmp = ScoreSet::default();
@@ -102,6 +219,36 @@ unsafe fn reply_with_score(raw: *mut raw::RedisModuleCtx, score: f64) {
     }
 }
 
+/// Reply with `s` as a bulk string, copied from the borrowed bytes.
+///
+/// Each call only appends to the client's output buffer; the server writes
+/// that buffer to the socket once the command returns, so a wide range costs
+/// one reply call per element but not one syscall. The module API has no way
+/// to append several pre-encoded elements at once, so there is nothing to
+/// batch on this side.
+#[inline]
+unsafe fn reply_with_str(raw: *mut raw::RedisModuleCtx, s: &str) {
+    RedisModule_ReplyWithStringBuffer.unwrap()(raw, s.as_ptr().cast(), s.len());
+}
+
+/// Reply with a flat array of bulk strings: each member, followed by its
+/// formatted score when `with_scores` is set. Members borrowed from a set are
+/// copied into the reply straight from its arena.
+unsafe fn reply_flat_pairs(
+    raw: *mut raw::RedisModuleCtx,
+    items: &[(&str, f64)],
+    with_scores: bool,
+) {
+    let per_item = if with_scores { 2 } else { 1 };
+    RedisModule_ReplyWithArray.unwrap()(raw, (items.len() * per_item) as c_long);
+    for &(member, score) in items {
+        reply_with_str(raw, member);
+        if with_scores {
+            with_fmt_buf(|b| unsafe { reply_with_str(raw, fmt_f64(b, score)) });
+        }
+    }
+}
+
 macro_rules! redis_command {
     (
         $ctx:expr,
@@ -110,7 +257,9 @@ macro_rules! redis_command {
         $command_flags:expr,
         $firstkey:expr,
         $lastkey:expr,
-        $keystep:expr
+        $keystep:expr,
+        $arity:expr,
+        $summary:expr
     ) => {{
         let name = CString::new($command_name).unwrap();
         let flags = CString::new($command_flags).unwrap();
@@ -140,107 +289,532 @@ macro_rules! redis_command {
         if status == raw::Status::Err as c_int {
             Err(rm::RedisError::Str("command registration failed"))
         } else {
-            Ok(())
+            set_acl_categories($ctx, &name, $command_flags)
+                .and_then(|()| set_command_info($ctx, &name, $arity, $summary))
         }
     }};
 }
 
-fn gzadd(_ctx: &Context, args: Vec<RedisString>) -> Result {
+/// ACL categories for a command, derived from its flags the way the server
+/// derives them for its own `Z*` commands.
+fn acl_categories(flags: &str) -> String {
+    let mut cats = vec!["sortedset"];
+    for flag in flags.split_whitespace() {
+        match flag {
+            "readonly" => cats.push("read"),
+            "write" => cats.push("write"),
+            _ => {}
+        }
+    }
+    cats.push(if flags.split_whitespace().any(|f| f == "fast") {
+        "fast"
+    } else {
+        "slow"
+    });
+    cats.join(" ")
+}
+
+/// Attach [`acl_categories`] to a registered command so `+@read`,
+/// `+@sortedset` and friends cover it. Servers without
+/// `RedisModule_SetCommandACLCategories` keep the commands uncategorized.
+unsafe fn set_acl_categories(
+    ctx: *mut raw::RedisModuleCtx,
+    name: &CString,
+    flags: &str,
+) -> rm::RedisResult<()> {
+    let (Some(get_command), Some(set_categories)) = (
+        raw::RedisModule_GetCommand,
+        raw::RedisModule_SetCommandACLCategories,
+    ) else {
+        return Ok(());
+    };
+    let command = get_command(ctx, name.as_ptr());
+    if command.is_null() {
+        return Err(rm::RedisError::Str("command registration failed"));
+    }
+    let cats = CString::new(acl_categories(flags)).unwrap();
+    if set_categories(command, cats.as_ptr()) == raw::Status::Err as c_int {
+        return Err(rm::RedisError::Str("setting command ACL categories failed"));
+    }
+    Ok(())
+}
+
+/// Layout of the command-info structs this module was built against.
+static COMMAND_INFO_VERSION: raw::RedisModuleCommandInfoVersion =
+    raw::RedisModuleCommandInfoVersion {
+        version: 1,
+        sizeof_historyentry: std::mem::size_of::<raw::RedisModuleCommandHistoryEntry>(),
+        sizeof_keyspec: std::mem::size_of::<raw::RedisModuleCommandKeySpec>(),
+        sizeof_arg: std::mem::size_of::<raw::RedisModuleCommandArg>(),
+    };
+
+/// Publish `arity` (as in `COMMAND INFO`: negative means "at least") and a
+/// one-line `summary` for `COMMAND DOCS`. Key specs are left to the server,
+/// which derives them from the firstkey/lastkey/keystep given at
+/// registration. Servers without `RedisModule_SetCommandInfo` report the
+/// commands with arity -1.
+unsafe fn set_command_info(
+    ctx: *mut raw::RedisModuleCtx,
+    name: &CString,
+    arity: c_int,
+    summary: &str,
+) -> rm::RedisResult<()> {
+    let (Some(get_command), Some(set_info)) =
+        (raw::RedisModule_GetCommand, raw::RedisModule_SetCommandInfo)
+    else {
+        return Ok(());
+    };
+    let command = get_command(ctx, name.as_ptr());
+    if command.is_null() {
+        return Err(rm::RedisError::Str("command registration failed"));
+    }
+    // The server copies the strings it keeps.
+    let summary = CString::new(summary).unwrap();
+    let info = raw::RedisModuleCommandInfo {
+        version: &COMMAND_INFO_VERSION,
+        summary: summary.as_ptr(),
+        complexity: std::ptr::null(),
+        since: std::ptr::null(),
+        history: std::ptr::null_mut(),
+        tips: std::ptr::null(),
+        arity,
+        key_specs: std::ptr::null_mut(),
+        args: std::ptr::null_mut(),
+    };
+    if set_info(command, &info) == raw::Status::Err as c_int {
+        return Err(rm::RedisError::Str("setting command info failed"));
+    }
+    Ok(())
+}
+
+/// Options accepted between the key and the score/member pairs of `GZADD`.
+#[derive(Default)]
+struct GzaddOpts {
+    cond: UpdateCond,
+    ch: bool,
+    incr: bool,
+    /// gzset extension: reply with the member's stored score instead of the
+    /// added count.
+    get_score: bool,
+    /// gzset extension: reply `[reply, created]`, where `created` is 1 when
+    /// this call created the key.
+    get_created: bool,
+    /// gzset extension: `EX seconds`, as `(index of EX, TTL)`. The key's TTL
+    /// is set once the write is done.
+    ex: Option<(usize, Duration)>,
+}
+
+/// Parse leading `GZADD` options, returning them with the index of the first
+/// non-option argument.
+fn parse_gzadd_opts(args: &[RedisString]) -> Result<(GzaddOpts, usize)> {
+    let mut opts = GzaddOpts::default();
+    let mut idx = 2;
+    while idx < args.len() {
+        let tok = args[idx].to_string_lossy();
+        if tok.eq_ignore_ascii_case("nx") {
+            opts.cond.nx = true;
+        } else if tok.eq_ignore_ascii_case("xx") {
+            opts.cond.xx = true;
+        } else if tok.eq_ignore_ascii_case("gt") {
+            opts.cond.gt = true;
+        } else if tok.eq_ignore_ascii_case("lt") {
+            opts.cond.lt = true;
+        } else if tok.eq_ignore_ascii_case("ch") {
+            opts.ch = true;
+        } else if tok.eq_ignore_ascii_case("incr") {
+            opts.incr = true;
+        } else if tok.eq_ignore_ascii_case("getscore") {
+            opts.get_score = true;
+        } else if tok.eq_ignore_ascii_case("getcreated") {
+            opts.get_created = true;
+        } else if tok.eq_ignore_ascii_case("ex") {
+            if opts.ex.is_some() {
+                return Err(RedisError::Str("ERR syntax error"));
+            }
+            let seconds = args
+                .get(idx + 1)
+                .ok_or(RedisError::Str("ERR syntax error"))?
+                .parse_integer()?;
+            if seconds <= 0 || seconds > i64::MAX / 1000 {
+                return Err(RedisError::Str(
+                    "ERR invalid expire time in 'gzadd' command",
+                ));
+            }
+            opts.ex = Some((idx, Duration::from_secs(seconds as u64)));
+            idx += 1;
+        } else {
+            break;
+        }
+        idx += 1;
+    }
+    Ok((opts, idx))
+}
+
+/// Propagate a `GZADD` call. Without `EX` the command goes out verbatim.
+/// With it, replicas and the AOF get the `GZADD` without `EX`, followed by a
+/// `PEXPIREAT` to `expire_at` when the write left the key with a TTL, as core
+/// Redis sends `SET EX`, so every copy expires at the same moment.
+fn replicate_gzadd(
+    ctx: &Context,
+    args: &[RedisString],
+    ex_idx: Option<usize>,
+    expire_at: Option<i64>,
+) {
+    let Some(ex_idx) = ex_idx else {
+        ctx.replicate_verbatim();
+        return;
+    };
+    let rest: Vec<&[u8]> = args[1..]
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| i + 1 != ex_idx && i + 1 != ex_idx + 1)
+        .map(|(_, arg)| arg.as_slice())
+        .collect();
+    ctx.replicate("GZADD", rest.as_slice());
+    if let Some(at) = expire_at {
+        let at = at.to_string();
+        ctx.replicate("PEXPIREAT", &[args[1].as_slice(), at.as_bytes()][..]);
+    }
+}
+
+/// Map a refused score write to the reply Redis gives for the same case.
+fn incr_error(err: IncrError) -> RedisError {
+    match err {
+        IncrError::NotFinite => RedisError::Str("ERR resulting score is not a finite number"),
+        IncrError::NotANumber => RedisError::Str("ERR resulting score is not a number (NaN)"),
+        IncrError::IdsExhausted => RedisError::Str(ERR_TOO_MANY_MEMBERS),
+    }
+}
+
+/// `GZADD key [NX|XX] [GT|LT] [CH] [INCR] [GETSCORE] [GETCREATED] [EX seconds]
+/// score member [score member ...]`
+fn gzadd(ctx: &Context, args: Vec<RedisString>) -> Result {
+    if args.len() < 4 {
+        return Err(RedisError::WrongArity);
+    }
+    let key = &args[1];
+    let _ = key.try_as_str()?;
+    let (opts, idx) = parse_gzadd_opts(&args)?;
+    let pairs = &args[idx..];
+    if pairs.is_empty() || pairs.len() % 2 == 1 {
+        return Err(RedisError::Str("ERR syntax error"));
+    }
+    let elements = pairs.len() / 2;
+    if opts.incr && elements > 1 {
+        return Err(RedisError::Str(
+            "ERR INCR option supports a single increment-element pair",
+        ));
+    }
+    if opts.get_score && elements > 1 {
+        return Err(RedisError::Str(
+            "ERR GETSCORE option supports a single score-element pair",
+        ));
+    }
+    // Both pick the reply but disagree on a blocked write: INCR replies nil,
+    // GETSCORE the score left in place.
+    if opts.incr && opts.get_score {
+        return Err(RedisError::Str(
+            "ERR INCR and GETSCORE options at the same time are not compatible",
+        ));
+    }
+
+    let cond = opts.cond;
+    if cond.nx && cond.xx {
+        return Err(RedisError::Str(
+            "ERR XX and NX options at the same time are not compatible",
+        ));
+    }
+    if (cond.nx && (cond.gt || cond.lt)) || (cond.gt && cond.lt) {
+        return Err(RedisError::Str(
+            "ERR GT, LT, and/or NX options at the same time are not compatible",
+        ));
+    }
+    // Validate every pair before touching the set so errors leave it intact.
+    let mut items = Vec::with_capacity(elements);
+    for pair in pairs.chunks_exact(2) {
+        let score: f64 = pair[0].parse_float()?;
+        if !score.is_finite() {
+            return Err(RedisError::Str("ERR score is not a finite number"));
+        }
+        items.push((score, pair[1].try_as_str()?));
+    }
+
+    let (ex_idx, ttl) = opts.ex.unzip();
+    if opts.incr || opts.get_score {
+        let (value, member) = items[0];
+        let op = ScoreOp::from_flags(value, opts.incr, cond);
+        let (outcome, created, expire_at) =
+            with_set_write_created(ctx, key, ttl, |s| s.upsert(member, op))?;
+        let outcome = outcome.map_err(incr_error)?;
+        replicate_gzadd(ctx, &args, ex_idx, expire_at);
+        // INCR replies with the new score, or nil when a flag blocked the
+        // write; GETSCORE always replies with whatever score is stored.
+        let reply = if opts.incr {
+            outcome.written()
+        } else {
+            outcome.score()
+        };
+        let raw = ctx.get_raw();
+        unsafe {
+            if opts.get_created {
+                RedisModule_ReplyWithArray.unwrap()(raw, 2);
+            }
+            match reply {
+                Some(score) => reply_with_score(raw, score),
+                None => {
+                    RedisModule_ReplyWithNull.unwrap()(raw);
+                }
+            }
+            if opts.get_created {
+                RedisModule_ReplyWithLongLong.unwrap()(raw, created.into());
+            }
+        }
+        return Ok(RedisValue::NoReply);
+    }
+
+    let (counts, created, expire_at) = with_set_write_created(ctx, key, ttl, |s| {
+        // Refuse up front rather than fail partway, which would leave the
+        // earlier pairs applied but never propagated.
+        if !cond.xx && items.len() > s.spare_ids() {
+            let fresh: rustc_hash::FxHashSet<&str> = items
+                .iter()
+                .map(|&(_, member)| member)
+                .filter(|member| !s.contains(member))
+                .collect();
+            if fresh.len() > s.spare_ids() {
+                return Err(IncrError::IdsExhausted);
+            }
+        }
+        let (mut added, mut updated) = (0i64, 0i64);
+        for &(score, member) in &items {
+            match s.upsert(member, ScoreOp::from_flags(score, false, cond))? {
+                UpsertOutcome::Added(_) => added += 1,
+                UpsertOutcome::Updated { .. } => updated += 1,
+                UpsertOutcome::Unchanged(_) | UpsertOutcome::Skipped(_) => {}
+            }
+        }
+        Ok((added, updated))
+    })?;
+    let (added, updated) = counts.map_err(incr_error)?;
+    replicate_gzadd(ctx, &args, ex_idx, expire_at);
+    let count = if opts.ch { added + updated } else { added };
+    if opts.get_created {
+        return Ok(RedisValue::Array(vec![
+            count.into(),
+            i64::from(created).into(),
+        ]));
+    }
+    Ok(count.into())
+}
+
+/// `GZINCRBY key increment member`: add `increment` to `member`'s score,
+/// adding it at `increment` if absent, and reply with the new score. As with
+/// `GZADD`, the increment must be finite, so every stored score stays finite.
+fn gzincrby(ctx: &Context, args: Vec<RedisString>) -> Result {
     if args.len() != 4 {
         return Err(RedisError::WrongArity);
     }
     let key = &args[1];
     let _ = key.try_as_str()?;
-    let score: f64 = args[2].parse_float()?;
-    if !score.is_finite() {
+    let delta = args[2].parse_float()?;
+    if !delta.is_finite() {
         return Err(RedisError::Str("ERR score is not a finite number"));
     }
     let member = args[3].try_as_str()?;
-
-    let added = with_set_write(_ctx, key, |s| s.insert(score, member))?;
-    Ok((added as i64).into())
+    let score = with_set_write(ctx, key, |s| {
+        s.incr_by(member, delta, UpdateCond::default())
+    })?
+    .map_err(incr_error)?
+    .expect("no condition to skip the write");
+    ctx.replicate_verbatim();
+    unsafe {
+        reply_with_score(ctx.get_raw(), score);
+    }
+    Ok(RedisValue::NoReply)
 }
 
-fn gzrank(_ctx: &Context, args: Vec<RedisString>) -> Result {
-    if args.len() != 3 {
+/// `GZRANK key member [WITHSCORE]`. With `WITHSCORE` the reply is
+/// `[rank, score]`, both taken from a single lookup.
+fn gzrank(ctx: &Context, args: Vec<RedisString>) -> Result {
+    if args.len() < 3 || args.len() > 4 {
         return Err(RedisError::WrongArity);
     }
     let key = &args[1];
     let _ = key.try_as_str()?;
     let member = args[2].try_as_str()?;
-    if let Some(rank) = with_set_read(_ctx, key, |s| s.rank(member))? {
-        return Ok((rank as i64).into());
+    let with_score = match args.get(3) {
+        None => false,
+        Some(tok) if tok.to_string_lossy().eq_ignore_ascii_case("withscore") => true,
+        Some(_) => return Err(RedisError::Str("ERR syntax error")),
+    };
+    if !with_score {
+        return match with_set_read(ctx, key, |s| s.rank(member))? {
+            Some(rank) => Ok((rank as i64).into()),
+            None => Ok(RedisValue::Null),
+        };
     }
-    Ok(RedisValue::Null)
+    let Some((score, rank)) = with_set_read(ctx, key, |s| s.score_and_rank(member))? else {
+        return Ok(RedisValue::Null);
+    };
+    let raw = ctx.get_raw();
+    unsafe {
+        RedisModule_ReplyWithArray.unwrap()(raw, 2);
+        raw::RedisModule_ReplyWithLongLong.unwrap()(raw, rank as i64);
+        reply_with_score(raw, score);
+    }
+    Ok(RedisValue::NoReply)
+}
+
+fn parse_index(arg: &RedisString) -> Result<isize> {
+    let x: i64 = arg.parse_integer()?;
+    isize::try_from(x).map_err(|_| RedisError::Str("ERR index is out of range"))
+}
+
+/// Trailing options of `GZRANGE`/`GZREVRANGE`: `WITHSCORES`, possibly
+/// repeated. Anything else is a syntax error, as in `ZRANGE`, rather than an
+/// arity error.
+fn parse_withscores(opts: &[RedisString]) -> Result<bool> {
+    for opt in opts {
+        if !opt.to_string_lossy().eq_ignore_ascii_case("withscores") {
+            return Err(RedisError::Str("ERR syntax error"));
+        }
+    }
+    Ok(!opts.is_empty())
 }
 
 fn gzrange(ctx: &Context, args: Vec<RedisString>) -> Result {
-    if args.len() < 4 || args.len() > 5 {
+    if args.len() < 4 {
         return Err(RedisError::WrongArity);
     }
     let key = &args[1];
     let _ = key.try_as_str()?;
-    let mut with_scores = false;
-    if args.len() == 5 {
-        with_scores = args[4].to_string_lossy().eq_ignore_ascii_case("withscores");
+    let with_scores = parse_withscores(&args[4..])?;
+    let start = parse_index(&args[2])?;
+    let stop = parse_index(&args[3])?;
+    with_set_read(ctx, key, |s| unsafe {
+        let raw = ctx.get_raw();
+        let reply_member = |m: &str| {
+            raw::RedisModule_ReplyWithStringBuffer.unwrap()(raw, m.as_ptr().cast(), m.len());
+        };
         if !with_scores {
-            return Err(RedisError::WrongArity);
+            let members = s.members_in_range(start, stop);
+            raw::RedisModule_ReplyWithArray.unwrap()(raw, members.len() as c_long);
+            members.for_each(reply_member);
+            return;
+        }
+        let reply_pair = |(m, score): (&str, f64)| {
+            reply_member(m);
+            with_fmt_buf(|b| reply_member(fmt_f64(b, score)));
+        };
+        // The whole set, in order: stream it without resolving ranks. On an
+        // empty set this replies `[]` like the range path would.
+        if start == 0 && (stop == -1 || (stop >= 0 && stop as usize + 1 == s.len())) {
+            raw::RedisModule_ReplyWithArray.unwrap()(raw, (s.len() * 2) as c_long);
+            s.iter_all().for_each(reply_pair);
+        } else {
+            let it = s.iter_range_fwd(start, stop);
+            raw::RedisModule_ReplyWithArray.unwrap()(raw, (it.len() * 2) as c_long);
+            it.for_each(reply_pair);
         }
+    })?;
+    Ok(RedisValue::NoReply)
+}
+
+/// `GZREVRANGE key start stop [WITHSCORES]`: like `GZRANGE`, but ranks count
+/// from the highest score and members are returned highest first.
+fn gzrevrange(ctx: &Context, args: Vec<RedisString>) -> Result {
+    if args.len() < 4 {
+        return Err(RedisError::WrongArity);
     }
-    let parse_index = |arg: &RedisString| -> Result<isize> {
-        let x: i64 = arg.parse_integer()?;
-        isize::try_from(x).map_err(|_| RedisError::Str("ERR index is out of range"))
-    };
+    let key = &args[1];
+    let _ = key.try_as_str()?;
+    let with_scores = parse_withscores(&args[4..])?;
     let start = parse_index(&args[2])?;
     let stop = parse_index(&args[3])?;
-    with_set_read(ctx, key, |s| {
-        let len = s.len();
-        if len > 0 && start == 0 && (stop == -1 || (stop >= 0 && stop as usize == len - 1)) {
-            unsafe {
-                let raw = ctx.get_raw();
-                let reply_len = if with_scores { len * 2 } else { len };
-                raw::RedisModule_ReplyWithArray.unwrap()(raw, reply_len as c_long);
-                for (m, score) in s.iter_all() {
-                    raw::RedisModule_ReplyWithStringBuffer.unwrap()(
-                        raw,
-                        m.as_ptr().cast(),
-                        m.len(),
-                    );
-                    if with_scores {
-                        with_fmt_buf(|b| {
-                            let s = fmt_f64(b, score);
-                            raw::RedisModule_ReplyWithStringBuffer.unwrap()(
-                                raw,
-                                s.as_ptr().cast(),
-                                s.len(),
-                            );
-                        });
-                    }
-                }
+    with_set_read(ctx, key, |s| unsafe {
+        let raw = ctx.get_raw();
+        let reply_member = |m: &str| {
+            raw::RedisModule_ReplyWithStringBuffer.unwrap()(raw, m.as_ptr().cast(), m.len());
+        };
+        let it = s.iter_range_rev(start, stop);
+        let per_item = if with_scores { 2 } else { 1 };
+        raw::RedisModule_ReplyWithArray.unwrap()(raw, (it.len() * per_item) as c_long);
+        for (m, score) in it {
+            reply_member(m);
+            if with_scores {
+                with_fmt_buf(|b| reply_member(fmt_f64(b, score)));
             }
+        }
+    })?;
+    Ok(RedisValue::NoReply)
+}
+
+/// Parse the `offset count` pair following `LIMIT` into how many matches to
+/// skip and how many to return. As in Redis a negative count returns
+/// everything after `offset`, while a negative offset selects nothing and
+/// yields `None`.
+fn parse_limit(offset: &RedisString, count: &RedisString) -> Result<Option<(usize, usize)>> {
+    let offset: i64 = offset.parse_integer()?;
+    let count: i64 = count.parse_integer()?;
+    let Ok(skip) = usize::try_from(offset) else {
+        return Ok(None);
+    };
+    Ok(Some((skip, usize::try_from(count).unwrap_or(usize::MAX))))
+}
+
+/// `GZRANGEBYSCORE key min max [WITHSCORES] [LIMIT offset count]`
+fn gzrangebyscore(ctx: &Context, args: Vec<RedisString>) -> Result {
+    if args.len() < 4 {
+        return Err(RedisError::WrongArity);
+    }
+    let key = &args[1];
+    let _ = key.try_as_str()?;
+    let (min, min_excl) = parse_score_bound(args[2].try_as_str()?)?;
+    let (max, max_excl) = parse_score_bound(args[3].try_as_str()?)?;
+    let mut with_scores = false;
+    let mut window = Some((0, usize::MAX));
+    let mut idx = 4;
+    while idx < args.len() {
+        let tok = args[idx].to_string_lossy();
+        if tok.eq_ignore_ascii_case("withscores") {
+            with_scores = true;
+            idx += 1;
+        } else if tok.eq_ignore_ascii_case("limit") && idx + 2 < args.len() {
+            window = parse_limit(&args[idx + 1], &args[idx + 2])?;
+            idx += 3;
         } else {
-            let mut it = s.iter_range_fwd(start, stop);
-            unsafe {
-                let raw = ctx.get_raw();
-                let (lower, _) = it.size_hint();
-                let reply_len = if with_scores { lower * 2 } else { lower };
-                raw::RedisModule_ReplyWithArray.unwrap()(raw, reply_len as c_long);
-                for (m, score) in &mut it {
-                    raw::RedisModule_ReplyWithStringBuffer.unwrap()(
-                        raw,
-                        m.as_ptr().cast(),
-                        m.len(),
-                    );
-                    if with_scores {
-                        with_fmt_buf(|b| {
-                            let s = fmt_f64(b, score);
-                            raw::RedisModule_ReplyWithStringBuffer.unwrap()(
-                                raw,
-                                s.as_ptr().cast(),
-                                s.len(),
-                            );
-                        });
-                    }
+            return Err(RedisError::Str("ERR syntax error"));
+        }
+    }
+    let Some((skip, take)) = window else {
+        return Ok(RedisValue::Array(Vec::new()));
+    };
+
+    with_set_read(ctx, key, |s| {
+        let hits: Vec<(&str, f64)> = s
+            .iter_by_score_range(min, max, min_excl, max_excl)
+            .skip(skip)
+            .take(take)
+            .collect();
+        unsafe {
+            let raw = ctx.get_raw();
+            let reply_len = if with_scores {
+                hits.len() * 2
+            } else {
+                hits.len()
+            };
+            raw::RedisModule_ReplyWithArray.unwrap()(raw, reply_len as c_long);
+            for (m, score) in hits {
+                raw::RedisModule_ReplyWithStringBuffer.unwrap()(raw, m.as_ptr().cast(), m.len());
+                if with_scores {
+                    with_fmt_buf(|b| {
+                        let s = fmt_f64(b, score);
+                        raw::RedisModule_ReplyWithStringBuffer.unwrap()(
+                            raw,
+                            s.as_ptr().cast(),
+                            s.len(),
+                        );
+                    });
                 }
             }
         }
@@ -248,14 +822,22 @@ fn gzrange(ctx: &Context, args: Vec<RedisString>) -> Result {
     Ok(RedisValue::NoReply)
 }
 
-fn gzrem(_ctx: &Context, args: Vec<RedisString>) -> Result {
-    if args.len() != 3 {
+/// `GZREM key member [member ...]`: remove members and reply with how many
+/// were present.
+fn gzrem(ctx: &Context, args: Vec<RedisString>) -> Result {
+    if args.len() < 3 {
         return Err(RedisError::WrongArity);
     }
     let key = &args[1];
     let _ = key.try_as_str()?;
-    let member = args[2].try_as_str()?;
-    let removed = with_set_write(_ctx, key, |s| s.remove(member))?;
+    let members = args[2..]
+        .iter()
+        .map(|m| m.try_as_str())
+        .collect::<rm::RedisResult<Vec<_>>>()?;
+    let removed = with_set_write(ctx, key, |s| {
+        members.iter().filter(|member| s.remove(member)).count()
+    })?;
+    ctx.replicate_verbatim();
     Ok((removed as i64).into())
 }
 
@@ -283,28 +865,42 @@ fn gzcard(_ctx: &Context, args: Vec<RedisString>) -> Result {
 }
 
 fn gzpop_generic(ctx: &Context, args: Vec<RedisString>, min: bool) -> Result {
-    if args.len() > 3 || args.len() < 2 {
+    if args.len() < 2 {
         return Err(RedisError::WrongArity);
     }
+    // Arguments past the count are a syntax error in ZPOPMIN, not an arity one.
+    if args.len() > 3 {
+        return Err(RedisError::Str("ERR syntax error"));
+    }
     let key = &args[1];
     let _ = key.try_as_str()?;
+    let count_given = args.len() == 3;
     let mut count = 1usize;
-    if args.len() == 3 {
+    if count_given {
         let c: i64 = args[2].parse_integer()?;
         if c < 0 {
-            return Err(RedisError::Str("ERR count must be positive"));
+            return Err(RedisError::Str(
+                "ERR value is out of range, must be positive",
+            ));
         }
         if c == 0 {
             return Ok(RedisValue::Array(Vec::new()));
         }
         count = c as usize;
     }
+    // Like ZPOPMIN, RESP3 clients that pass a count get one [member, score]
+    // pair per element; otherwise the reply is a flat member/score array.
+    let nested = count_given && ctx.get_flags().contains(ContextFlags::FLAGS_RESP3);
+    let raw = ctx.get_raw();
     if count == 1 {
-        let raw = ctx.get_raw();
         let mut replied = false;
+        let mut popped_name = None;
         let popped = with_set_write(ctx, key, |set| {
             set.pop_one_visit(min, |name, score| {
                 unsafe {
+                    if nested {
+                        RedisModule_ReplyWithArray.unwrap()(raw, 1);
+                    }
                     RedisModule_ReplyWithArray.unwrap()(raw, 2);
                     RedisModule_ReplyWithStringBuffer.unwrap()(
                         raw,
@@ -314,45 +910,53 @@ fn gzpop_generic(ctx: &Context, args: Vec<RedisString>, min: bool) -> Result {
                     reply_with_score(raw, score);
                 }
                 replied = true;
+                popped_name = Some(name.to_owned());
             })
         })?;
+        replicate_removals(ctx, key, popped_name.as_deref());
         return if popped {
             debug_assert!(replied);
             Ok(RedisValue::NoReply)
         } else {
             debug_assert!(!replied);
-            Ok(RedisValue::Null)
+            Ok(RedisValue::Array(Vec::new()))
         };
     }
-    let raw = ctx.get_raw();
+    let mut popped_names = Vec::new();
     let emitted = with_set_write(ctx, key, |set| {
         let pairs_to_emit = set.peek_pop_count(min, count);
         if pairs_to_emit == 0 {
-            return None;
+            return 0;
         }
+        let reply_len = if nested {
+            pairs_to_emit
+        } else {
+            pairs_to_emit * 2
+        };
         unsafe {
-            RedisModule_ReplyWithArray.unwrap()(raw, (pairs_to_emit * 2) as c_long);
+            RedisModule_ReplyWithArray.unwrap()(raw, reply_len as c_long);
         }
         let mut pairs = 0usize;
+        popped_names.reserve_exact(pairs_to_emit);
         set.pop_n_visit(min, count, |name, score| {
             unsafe {
+                if nested {
+                    RedisModule_ReplyWithArray.unwrap()(raw, 2);
+                }
                 RedisModule_ReplyWithStringBuffer.unwrap()(raw, name.as_ptr().cast(), name.len());
                 reply_with_score(raw, score);
             }
             pairs += 1;
+            popped_names.push(name.to_owned());
         });
         debug_assert_eq!(pairs, pairs_to_emit);
-        Some(pairs)
+        pairs
     })?;
-    match emitted {
-        Some(_) => Ok(RedisValue::NoReply),
-        None => {
-            if count == 1 {
-                Ok(RedisValue::Null)
-            } else {
-                Ok(RedisValue::Array(Vec::new()))
-            }
-        }
+    replicate_removals(ctx, key, popped_names.iter().map(String::as_str));
+    if emitted == 0 {
+        Ok(RedisValue::Array(Vec::new()))
+    } else {
+        Ok(RedisValue::NoReply)
     }
 }
 
@@ -364,253 +968,811 @@ fn gzpopmax(ctx: &Context, args: Vec<RedisString>) -> Result {
     gzpop_generic(ctx, args, false)
 }
 
-fn gzrandmember(ctx: &Context, args: Vec<RedisString>) -> Result {
-    if args.len() < 2 || args.len() > 4 {
+/// `GZPOPRANGEBYSCORE key min max`: remove and return every member in the
+/// score range as a flat member/score array, lowest first.
+fn gzpoprangebyscore(ctx: &Context, args: Vec<RedisString>) -> Result {
+    if args.len() != 4 {
         return Err(RedisError::WrongArity);
     }
     let key = &args[1];
     let _ = key.try_as_str()?;
-    let mut idx = 2usize;
-    let mut count: Option<i64> = None;
-    let mut with_scores = false;
-    while idx < args.len() {
-        let token = args[idx].to_string_lossy();
-        if token.eq_ignore_ascii_case("withscores") {
-            if with_scores {
-                return Err(RedisError::WrongArity);
-            }
-            with_scores = true;
-            idx += 1;
-            continue;
-        }
-        if count.is_some() {
-            return Err(RedisError::WrongArity);
+    let (min, min_excl) = parse_score_bound(args[2].try_as_str()?)?;
+    let (max, max_excl) = parse_score_bound(args[3].try_as_str()?)?;
+    let popped = with_set_write(ctx, key, |s| {
+        s.pop_range_by_score(min, max, min_excl, max_excl)
+    })?;
+    replicate_removals(ctx, key, popped.iter().map(|(name, _)| name.as_str()));
+    let raw = ctx.get_raw();
+    unsafe {
+        RedisModule_ReplyWithArray.unwrap()(raw, (popped.len() * 2) as c_long);
+        for (name, score) in &popped {
+            RedisModule_ReplyWithStringBuffer.unwrap()(raw, name.as_ptr().cast(), name.len());
+            reply_with_score(raw, *score);
         }
-        count = Some(args[idx].parse_integer()?);
-        idx += 1;
     }
-    if idx != args.len() {
+    Ok(RedisValue::NoReply)
+}
+
+/// `GZREMRANGEBYRANK key start stop`: remove the members ranked
+/// `start..=stop` and reply with how many were removed.
+fn gzremrangebyrank(ctx: &Context, args: Vec<RedisString>) -> Result {
+    if args.len() != 4 {
         return Err(RedisError::WrongArity);
     }
-
-    let result = with_set_read(ctx, key, |s| -> rm::RedisResult<RedisValue> {
-        if s.is_empty() {
-            return Ok(if count.is_some() {
-                RedisValue::Array(Vec::new())
-            } else {
-                RedisValue::Null
-            });
-        }
-        use rand::{
-            seq::{index::sample, SliceRandom},
-            thread_rng, Rng,
-        };
-        use rustc_hash::FxHashSet;
-        let len = s.len();
-        let mut rng = thread_rng();
-        match count {
-            None => {
-                let idx = rng.gen_range(0..len);
-                let (m, sc) = s.select_by_rank(idx);
-                if with_scores {
-                    Ok(RedisValue::Array(vec![
-                        m.to_owned().into(),
-                        with_fmt_buf(|b| fmt_f64(b, sc).to_owned()).into(),
-                    ]))
-                } else {
-                    Ok(m.to_owned().into())
-                }
-            }
-            Some(c) => {
-                if c == 0 {
-                    return Ok(RedisValue::Array(Vec::new()));
-                }
-                let mut out = Vec::new();
-                if c < 0 {
-                    let cnt = (-c) as usize;
-                    for _ in 0..cnt {
-                        let idx = rng.gen_range(0..len);
-                        let (m, sc) = s.select_by_rank(idx);
-                        out.push(m.to_owned().into());
-                        if with_scores {
-                            with_fmt_buf(|b| out.push(fmt_f64(b, sc).to_owned().into()));
-                        }
-                    }
-                } else {
-                    let cnt = c as usize;
-                    if cnt >= len {
-                        let mut items: Vec<_> =
-                            s.iter_all().map(|(m, sc)| (m.to_owned(), sc)).collect();
-                        items.shuffle(&mut rng);
-                        for (m, sc) in items {
-                            out.push(m.into());
-                            if with_scores {
-                                with_fmt_buf(|b| out.push(fmt_f64(b, sc).to_owned().into()));
-                            }
-                        }
-                    } else if cnt <= 64 || cnt * 3 <= len {
-                        let mut seen: FxHashSet<usize> = FxHashSet::default();
-                        while out.len() < cnt {
-                            let idx = rng.gen_range(0..len);
-                            if seen.insert(idx) {
-                                let (m, sc) = s.select_by_rank(idx);
-                                out.push(m.to_owned().into());
-                                if with_scores {
-                                    with_fmt_buf(|b| out.push(fmt_f64(b, sc).to_owned().into()));
-                                }
-                            }
-                        }
-                    } else {
-                        let idxs = sample(&mut rng, len, cnt).into_vec();
-                        let mut positions: FastHashMap<usize, usize> = FastHashMap::default();
-                        positions.reserve(cnt);
-                        for (pos, idx) in idxs.iter().enumerate() {
-                            positions.insert(*idx, pos);
-                        }
-                        let mut selected: Vec<Option<(String, f64)>> = vec![None; cnt];
-                        let mut remaining = cnt;
-                        for (i, (m, sc)) in s.iter_all().enumerate() {
-                            if let Some(pos) = positions.get(&i) {
-                                selected[*pos] = Some((m.to_owned(), sc));
-                                remaining -= 1;
-                                if remaining == 0 {
-                                    break;
-                                }
-                            }
-                        }
-                        for (m, sc) in selected.into_iter().flatten() {
-                            out.push(m.into());
-                            if with_scores {
-                                with_fmt_buf(|b| out.push(fmt_f64(b, sc).to_owned().into()));
-                            }
-                        }
-                    }
-                }
-                Ok(RedisValue::Array(out))
-            }
-        }
-    })??;
-    Ok(result)
+    let key = &args[1];
+    let _ = key.try_as_str()?;
+    let start = parse_index(&args[2])?;
+    let stop = parse_index(&args[3])?;
+    let removed = with_set_write(ctx, key, |s| s.remove_range_by_rank(start, stop))?;
+    ctx.replicate_verbatim();
+    Ok(RedisValue::Integer(removed as i64))
 }
 
-fn gzmscore(ctx: &Context, args: Vec<RedisString>) -> Result {
-    if args.len() < 3 {
+/// `GZREMRANGEBYSCORE key min max`: remove every member in the score range
+/// and reply with how many were removed.
+fn gzremrangebyscore(ctx: &Context, args: Vec<RedisString>) -> Result {
+    if args.len() != 4 {
         return Err(RedisError::WrongArity);
     }
     let key = &args[1];
     let _ = key.try_as_str()?;
-    let members: Vec<_> = args[2..].iter().map(|m| m.try_as_str().unwrap()).collect();
-    let raw = ctx.get_raw();
-    unsafe { RedisModule_ReplyWithArray.unwrap()(raw, members.len() as c_long) };
-    with_set_read(ctx, key, |set| {
-        for member in &members {
-            unsafe {
-                if let Some(score) = set.score(member) {
-                    RedisModule_ReplyWithDouble.unwrap()(raw, score);
-                } else {
-                    RedisModule_ReplyWithNull.unwrap()(raw);
-                }
+    let (min, min_excl) = parse_score_bound(args[2].try_as_str()?)?;
+    let (max, max_excl) = parse_score_bound(args[3].try_as_str()?)?;
+    let removed = with_set_write(ctx, key, |s| {
+        s.remove_range_by_score(min, max, min_excl, max_excl)
+    })?;
+    ctx.replicate_verbatim();
+    Ok(RedisValue::Integer(removed as i64))
+}
+
+/// `GZRANGESTORE dst src start stop`: store the members of `src` ranked
+/// `start..=stop` (indices as in `GZRANGE`) in `dst` and reply with how many
+/// were stored. As with `ZRANGESTORE`, `dst` is replaced whatever it held and
+/// deleted when the range is empty. The slice is streamed into the new set
+/// (see [`ScoreSet::copy_range`]), so a small slice of a huge `src` stays
+/// cheap.
+fn gzrangestore(ctx: &Context, args: Vec<RedisString>) -> Result {
+    if args.len() != 5 {
+        return Err(RedisError::WrongArity);
+    }
+    let dst = &args[1];
+    let _ = dst.try_as_str()?;
+    let src = &args[2];
+    let _ = src.try_as_str()?;
+    let start = parse_index(&args[3])?;
+    let stop = parse_index(&args[4])?;
+    let slice = with_set_read(ctx, src, |s| s.copy_range(start, stop))?;
+
+    let stored = slice.len();
+    let rkey = ctx.open_key_writable(dst);
+    rkey.delete()?;
+    finish_write(&rkey, Some(slice), stored == 0)?;
+    ctx.replicate_verbatim();
+    Ok(RedisValue::Integer(stored as i64))
+}
+
+/// `GZSPLIT src dst score` (gzset extension): move every member of `src`
+/// scoring at least `score` (above it for `(score`) into `dst` and reply with
+/// how many moved. Like a `*STORE` command, `dst` is replaced whatever it held
+/// and deleted when nothing moves; with `src` as `dst` the key ends up holding
+/// only the moved members.
+fn gzsplit(ctx: &Context, args: Vec<RedisString>) -> Result {
+    if args.len() != 4 {
+        return Err(RedisError::WrongArity);
+    }
+    let src = &args[1];
+    let _ = src.try_as_str()?;
+    let dst = &args[2];
+    let _ = dst.try_as_str()?;
+    let (min, excl) = parse_score_bound(args[3].try_as_str()?)?;
+    let upper = with_set_write(ctx, src, |s| s.split_off_by_score(min, excl))?;
+
+    let moved = upper.len();
+    let rkey = ctx.open_key_writable(dst);
+    rkey.delete()?;
+    finish_write(&rkey, Some(upper), moved == 0)?;
+    ctx.replicate_verbatim();
+    Ok(RedisValue::Integer(moved as i64))
+}
+
+/// `GZMOVE src dst member` (gzset extension): move `member` with its score
+/// from `src` to `dst`, replacing any score it held there, and reply 1, or 0
+/// when `src` lacks it. As with `SMOVE`, naming the same key twice changes
+/// nothing. `dst` is written before `src`, so an error leaves both keys as
+/// they were; `src` is deleted once empty.
+fn gzmove(ctx: &Context, args: Vec<RedisString>) -> Result {
+    if args.len() != 4 {
+        return Err(RedisError::WrongArity);
+    }
+    let src = &args[1];
+    let _ = src.try_as_str()?;
+    let dst = &args[2];
+    let _ = dst.try_as_str()?;
+    let member = args[3].try_as_str()?;
+    let score = with_set_read(ctx, src, |s| s.score(member))?;
+    with_set_read(ctx, dst, |_| ())?;
+    let Some(score) = score else {
+        return Ok(RedisValue::Integer(0));
+    };
+    if src.as_slice() == dst.as_slice() {
+        return Ok(RedisValue::Integer(1));
+    }
+
+    with_set_write(ctx, dst, |s| s.try_insert(score, member))?
+        .map_err(|_| RedisError::Str(ERR_TOO_MANY_MEMBERS))?;
+    with_set_write(ctx, src, |s| s.remove(member))?;
+    ctx.replicate_verbatim();
+    Ok(RedisValue::Integer(1))
+}
+
+fn gzpeek_generic(ctx: &Context, args: Vec<RedisString>, min: bool) -> Result {
+    if args.len() != 2 {
+        return Err(RedisError::WrongArity);
+    }
+    let key = &args[1];
+    let _ = key.try_as_str()?;
+    let raw = ctx.get_raw();
+    let found = with_set_read(ctx, key, |set| {
+        let entry = if min { set.first() } else { set.last() };
+        if let Some((name, score)) = entry {
+            unsafe {
+                RedisModule_ReplyWithArray.unwrap()(raw, 2);
+                RedisModule_ReplyWithStringBuffer.unwrap()(raw, name.as_ptr().cast(), name.len());
+                reply_with_score(raw, score);
             }
+            true
+        } else {
+            false
         }
     })?;
-    Ok(RedisValue::NoReply)
+    if found {
+        Ok(RedisValue::NoReply)
+    } else {
+        Ok(RedisValue::Null)
+    }
 }
 
-fn gzunion(ctx: &Context, args: Vec<RedisString>) -> Result {
-    if args.len() < 3 {
+fn gzmin(ctx: &Context, args: Vec<RedisString>) -> Result {
+    gzpeek_generic(ctx, args, true)
+}
+
+fn gzmax(ctx: &Context, args: Vec<RedisString>) -> Result {
+    gzpeek_generic(ctx, args, false)
+}
+
+/// `GZTRIM key` releases capacity left behind by deletes (see
+/// [`ScoreSet::shrink_to_fit`]). Replies `OK`, also for a missing key.
+fn gztrim(ctx: &Context, args: Vec<RedisString>) -> Result {
+    if args.len() != 2 {
         return Err(RedisError::WrongArity);
     }
-    let num: i64 = args[1].parse_integer()?;
-    if num <= 0 {
-        return Err(RedisError::Str("ERR numkeys must be > 0"));
+    let key = &args[1];
+    let _ = key.try_as_str()?;
+    with_set_write(ctx, key, ScoreSet::shrink_to_fit)?;
+    ctx.replicate_verbatim();
+    Ok(RedisValue::SimpleStringStatic("OK"))
+}
+
+/// `GZSCORECARD key score` replies how many members hold exactly `score`,
+/// for spotting hot scores that grow large buckets.
+fn gzscorecard(ctx: &Context, args: Vec<RedisString>) -> Result {
+    if args.len() != 3 {
+        return Err(RedisError::WrongArity);
     }
-    let num = num as usize;
-    if args.len() != num + 2 {
+    let key = &args[1];
+    let _ = key.try_as_str()?;
+    let score = args[2].parse_float()?;
+    let count = with_set_read(ctx, key, |s| s.count_at_score(score))?;
+    Ok((count as i64).into())
+}
+
+/// `GZSCORERANK key score`: how many members score strictly below `score`.
+fn gzscorerank(ctx: &Context, args: Vec<RedisString>) -> Result {
+    if args.len() != 3 {
         return Err(RedisError::WrongArity);
     }
-    let keys: Vec<&RedisString> = args[2..].iter().collect();
-    for key in &keys {
-        let _ = key.try_as_str()?;
+    let key = &args[1];
+    let _ = key.try_as_str()?;
+    let score = args[2].parse_float()?;
+    if score.is_nan() {
+        return Err(RedisError::Str("ERR value is not a valid float"));
     }
-    let mut agg: FastHashMap<String, f64> = FastHashMap::default();
-    for key in keys {
-        with_set_read(ctx, key, |set| {
-            agg.reserve(set.len());
-            for (member, score) in set.iter_all() {
-                if let Some(v) = agg.get_mut(member) {
-                    *v += score;
+    let rank = with_set_read(ctx, key, |s| s.rank_of_score(score))?;
+    Ok((rank as i64).into())
+}
+
+/// `GZPERCENTILE key p`: the score at percentile `p` (clamped to 0..100), by
+/// the nearest-rank method. Null for a missing key.
+fn gzpercentile(ctx: &Context, args: Vec<RedisString>) -> Result {
+    if args.len() != 3 {
+        return Err(RedisError::WrongArity);
+    }
+    let key = &args[1];
+    let _ = key.try_as_str()?;
+    let p = args[2].parse_float()?;
+    if p.is_nan() {
+        return Err(RedisError::Str("ERR value is not a valid float"));
+    }
+    match with_set_read(ctx, key, |s| s.score_at_percentile(p))? {
+        Some(score) => Ok(score.into()),
+        None => Ok(RedisValue::Null),
+    }
+}
+
+/// `GZDEBUG ENCODING key`: report how the key's score buckets are stored as
+/// `[inline, n, spilled, m]`. gzset spills per score rather than per key, so
+/// there is no single encoding to report as `OBJECT ENCODING` does.
+///
+/// `GZDEBUG MEMCHECK key` (debug builds or the `mem-check` feature): recompute
+/// the memory breakdown from scratch and reply `OK` when it matches both the
+/// running `mem_bytes` and the running per-component counts, otherwise the
+/// running total, the recomputed one, each recomputed component and the
+/// recomputed total including member names.
+fn gzdebug(ctx: &Context, args: Vec<RedisString>) -> Result {
+    if args.len() < 2 {
+        return Err(RedisError::WrongArity);
+    }
+    let sub = args[1].to_string_lossy();
+    #[cfg(any(debug_assertions, feature = "mem-check"))]
+    if sub.eq_ignore_ascii_case("memcheck") {
+        return gzdebug_memcheck(ctx, &args);
+    }
+    if sub.eq_ignore_ascii_case("topbuckets") {
+        return gzdebug_topbuckets(ctx, &args);
+    }
+    if !sub.eq_ignore_ascii_case("encoding") {
+        return Err(RedisError::Str("ERR unknown GZDEBUG subcommand"));
+    }
+    if args.len() != 3 {
+        return Err(RedisError::WrongArity);
+    }
+    let key = &args[2];
+    let _ = key.try_as_str()?;
+    let (buckets, spilled) = with_set_read(ctx, key, |s| {
+        (s.distinct_scores(), s.spilled_bucket_count())
+    })?;
+    Ok(RedisValue::Array(vec![
+        "inline".into(),
+        ((buckets - spilled) as i64).into(),
+        "spilled".into(),
+        (spilled as i64).into(),
+    ]))
+}
+
+/// `GZDEBUG TOPBUCKETS key n`: the `n` scores with the most members, as a
+/// flat score/count list, largest first.
+fn gzdebug_topbuckets(ctx: &Context, args: &[RedisString]) -> Result {
+    if args.len() != 4 {
+        return Err(RedisError::WrongArity);
+    }
+    let key = &args[2];
+    let _ = key.try_as_str()?;
+    let n = args[3].parse_integer()?;
+    if n < 0 {
+        return Err(RedisError::Str(
+            "ERR value is out of range, must be positive",
+        ));
+    }
+    let buckets = with_set_read(ctx, key, |s| s.largest_buckets(n as usize))?;
+    let mut out = Vec::with_capacity(buckets.len() * 2);
+    for (score, members) in buckets {
+        with_fmt_buf(|b| out.push(fmt_f64(b, score).to_owned().into()));
+        out.push((members as i64).into());
+    }
+    Ok(RedisValue::Array(out))
+}
+
+#[cfg(any(debug_assertions, feature = "mem-check"))]
+fn gzdebug_memcheck(ctx: &Context, args: &[RedisString]) -> Result {
+    if args.len() != 3 {
+        return Err(RedisError::WrongArity);
+    }
+    let key = &args[2];
+    let _ = key.try_as_str()?;
+    let (running, components, fresh) = with_set_read(ctx, key, |s| {
+        (
+            s.mem_bytes(),
+            s.debug_mem_breakdown(),
+            s.recompute_mem_breakdown(),
+        )
+    })?;
+    if running == fresh.structural() && components == fresh {
+        return Ok(RedisValue::SimpleStringStatic("OK"));
+    }
+    Ok(RedisValue::Array(vec![
+        "mem_bytes".into(),
+        (running as i64).into(),
+        "recomputed".into(),
+        (fresh.structural() as i64).into(),
+        "score_map".into(),
+        (fresh.score_map as i64).into(),
+        "buckets".into(),
+        (fresh.buckets as i64).into(),
+        "member_table".into(),
+        (fresh.member_table as i64).into(),
+        "strings".into(),
+        (fresh.strings as i64).into(),
+        "total".into(),
+        (fresh.total() as i64).into(),
+    ]))
+}
+
+/// `GZMEMESTIMATE members avg_len`: predicted memory, in bytes, of a key
+/// holding `members` distinct-score members with names of `avg_len` bytes.
+/// Touches no key; see [`ScoreSet::estimate_memory`].
+fn gzmemestimate(_ctx: &Context, args: Vec<RedisString>) -> Result {
+    if args.len() != 3 {
+        return Err(RedisError::WrongArity);
+    }
+    let members: i64 = args[1].parse_integer()?;
+    let avg_len: i64 = args[2].parse_integer()?;
+    let (Ok(members), Ok(avg_len)) = (usize::try_from(members), usize::try_from(avg_len)) else {
+        return Err(RedisError::Str(
+            "ERR value is out of range, must not be negative",
+        ));
+    };
+    // Past the pool's id space, or a size no integer reply can carry.
+    ScoreSet::estimate_memory(members, avg_len)
+        .and_then(|bytes| i64::try_from(bytes).ok())
+        .map(RedisValue::Integer)
+        .ok_or(RedisError::Str("ERR value is out of range"))
+}
+
+fn gzrandmember(ctx: &Context, args: Vec<RedisString>) -> Result {
+    if args.len() < 2 {
+        return Err(RedisError::WrongArity);
+    }
+    let key = &args[1];
+    let _ = key.try_as_str()?;
+    // As in ZRANDMEMBER: `count` comes first and is parsed before anything
+    // else, so a lone `WITHSCORES` is a bad count, and any trailing argument
+    // other than a single `WITHSCORES` is a syntax error.
+    let count = match args.get(2) {
+        Some(arg) => Some(arg.parse_integer()?),
+        None => None,
+    };
+    let with_scores = match &args[2..] {
+        [] | [_] => false,
+        [_, opt] if opt.to_string_lossy().eq_ignore_ascii_case("withscores") => true,
+        _ => return Err(RedisError::Str("ERR syntax error")),
+    };
+
+    let raw = ctx.get_raw();
+    with_set_read(ctx, key, |s| {
+        if s.is_empty() {
+            unsafe {
+                if count.is_some() {
+                    RedisModule_ReplyWithArray.unwrap()(raw, 0);
                 } else {
-                    agg.insert(member.to_owned(), score);
+                    RedisModule_ReplyWithNull.unwrap()(raw);
                 }
             }
-        })?;
+            return;
+        }
+        use rand::{
+            seq::{index::sample, SliceRandom},
+            thread_rng, Rng,
+        };
+        use rustc_hash::FxHashSet;
+        let len = s.len();
+        let mut rng = thread_rng();
+        let Some(c) = count else {
+            let (m, sc) = s.select_by_rank(rng.gen_range(0..len));
+            unsafe {
+                if with_scores {
+                    reply_flat_pairs(raw, &[(m, sc)], true);
+                } else {
+                    reply_with_str(raw, m);
+                }
+            }
+            return;
+        };
+        // Members are borrowed from the set and copied into the reply
+        // straight from its arena.
+        let mut out: Vec<(&str, f64)> = Vec::new();
+        if c < 0 {
+            let cnt = (-c) as usize;
+            for _ in 0..cnt {
+                out.push(s.select_by_rank(rng.gen_range(0..len)));
+            }
+        } else {
+            let cnt = c as usize;
+            if cnt >= len {
+                out.extend(s.iter_all());
+                out.shuffle(&mut rng);
+            } else if cnt <= 64 || cnt * 3 <= len {
+                let mut seen: FxHashSet<usize> = FxHashSet::default();
+                while out.len() < cnt {
+                    let idx = rng.gen_range(0..len);
+                    if seen.insert(idx) {
+                        out.push(s.select_by_rank(idx));
+                    }
+                }
+            } else {
+                // Sort the sampled ranks, remembering their random
+                // output slots, and resolve them in one forward pass
+                // with `select_by_ranks`.
+                let mut ranks: Vec<(usize, usize)> = sample(&mut rng, len, cnt)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(slot, rank)| (rank, slot))
+                    .collect();
+                ranks.sort_unstable();
+                let sorted: Vec<usize> = ranks.iter().map(|&(rank, _)| rank).collect();
+                let mut selected: Vec<Option<(&str, f64)>> = vec![None; cnt];
+                for (&(_, slot), item) in ranks.iter().zip(s.select_by_ranks(&sorted)) {
+                    selected[slot] = Some(item);
+                }
+                out.extend(selected.into_iter().flatten());
+            }
+        }
+        unsafe { reply_flat_pairs(raw, &out, with_scores) };
+    })?;
+    Ok(RedisValue::NoReply)
+}
+
+fn gzmscore(ctx: &Context, args: Vec<RedisString>) -> Result {
+    if args.len() < 3 {
+        return Err(RedisError::WrongArity);
     }
-    let mut items: Vec<_> = agg.into_iter().collect();
-    items.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap().then_with(|| a.0.cmp(&b.0)));
+    let key = &args[1];
+    let _ = key.try_as_str()?;
+    let members: Vec<_> = args[2..].iter().map(|m| m.try_as_str().unwrap()).collect();
     let raw = ctx.get_raw();
-    unsafe { RedisModule_ReplyWithArray.unwrap()(raw, (items.len() * 2) as c_long) };
-    for (member, score) in items {
-        unsafe {
-            RedisModule_ReplyWithStringBuffer.unwrap()(raw, member.as_ptr().cast(), member.len());
-            RedisModule_ReplyWithDouble.unwrap()(raw, score);
+    unsafe { RedisModule_ReplyWithArray.unwrap()(raw, members.len() as c_long) };
+    with_set_read(ctx, key, |set| {
+        for member in &members {
+            unsafe {
+                if let Some(score) = set.score(member) {
+                    RedisModule_ReplyWithDouble.unwrap()(raw, score);
+                } else {
+                    RedisModule_ReplyWithNull.unwrap()(raw);
+                }
+            }
         }
-    }
+    })?;
     Ok(RedisValue::NoReply)
 }
 
-fn gzinter(ctx: &Context, args: Vec<RedisString>) -> Result {
-    if args.len() < 3 {
+/// Read-only input to the set-algebra commands. Plain SET keys act like a
+/// sorted set whose members all score 1, as they do for ZUNION and friends.
+enum Operand<'a> {
+    Gz(&'a ScoreSet),
+    Owned(Box<ScoreSet>),
+}
+
+impl Operand<'_> {
+    fn set(&self) -> &ScoreSet {
+        match self {
+            Operand::Gz(set) => set,
+            Operand::Owned(set) => set,
+        }
+    }
+}
+
+fn open_operand<'a>(ctx: &Context, key: &RedisString, rkey: &'a RedisKey) -> Result<Operand<'a>> {
+    if rkey.key_type() == KeyType::Set {
+        let members = match ctx.call("SMEMBERS", &[key][..])? {
+            RedisValue::Array(items) => items,
+            _ => return Err(RedisError::Str("ERR unexpected SMEMBERS reply")),
+        };
+        let mut names = Vec::with_capacity(members.len());
+        for item in members {
+            let name = match item {
+                RedisValue::SimpleString(s) | RedisValue::BulkString(s) => s,
+                RedisValue::StringBuffer(bytes) => String::from_utf8(bytes)
+                    .map_err(|_| RedisError::Str("ERR set member is not valid UTF-8"))?,
+                RedisValue::BulkRedisString(s) => s.try_as_str()?.to_owned(),
+                _ => return Err(RedisError::Str("ERR unexpected SMEMBERS reply")),
+            };
+            names.push(name);
+        }
+        let mut set = ScoreSet::default();
+        set.insert_many(names.iter().map(|name| (1.0, name.as_str())));
+        return Ok(Operand::Owned(Box::new(set)));
+    }
+    match rkey
+        .get_value::<ScoreSet>(&GZSET_TYPE)
+        .map_err(wrong_type)?
+    {
+        Some(set) => Ok(Operand::Gz(set)),
+        None => Ok(Operand::Owned(Box::default())),
+    }
+}
+
+/// Answer a `getkeys-api` key position request for a command taking
+/// `numkeys key [key ...]` with `numkeys` at `args[at]`, plus a destination at
+/// `args[1]` when `dest` is set. Returns whether this call was such a request.
+///
+/// A fixed firstkey/lastkey span cannot describe these commands: it would
+/// count `numkeys` and trailing options as keys, so cluster slot checks would
+/// hash the wrong arguments. Malformed calls declare what they can; the
+/// command itself rejects them when it runs.
+fn declare_numkeys_keys(ctx: &Context, args: &[RedisString], at: usize, dest: bool) -> bool {
+    if !ctx.is_keys_position_request() {
+        return false;
+    }
+    if dest && args.len() > 1 {
+        ctx.key_at_pos(1);
+    }
+    if let Some(num) = args.get(at).and_then(|arg| arg.parse_integer().ok()) {
+        let last = (at as i64).saturating_add(num).min(args.len() as i64 - 1);
+        for pos in at as i64 + 1..=last {
+            ctx.key_at_pos(pos as i32);
+        }
+    }
+    true
+}
+
+/// Split `numkeys key [key ...] ...` with `numkeys` at `args[at]` into the keys
+/// and the trailing arguments.
+fn split_numkeys(args: &[RedisString], at: usize) -> Result<(&[RedisString], &[RedisString])> {
+    if args.len() < at + 2 {
         return Err(RedisError::WrongArity);
     }
-    let num: i64 = args[1].parse_integer()?;
+    let num: i64 = args[at].parse_integer()?;
     if num <= 0 {
         return Err(RedisError::Str("ERR numkeys must be > 0"));
     }
     let num = num as usize;
-    if args.len() != num + 2 {
-        return Err(RedisError::WrongArity);
+    // More keys announced than given is a syntax error in ZUNION and friends.
+    if args.len() < num + at + 1 {
+        return Err(RedisError::Str("ERR syntax error"));
     }
-    let keys: Vec<&RedisString> = args[2..].iter().collect();
-    for key in &keys {
+    let (keys, tail) = args[at + 1..].split_at(num);
+    for key in keys {
         let _ = key.try_as_str()?;
     }
-    let mut keys_vec: Vec<&RedisString> = keys.clone();
-    keys_vec.sort_by_key(|k| with_set_read(ctx, k, |s| s.len()).unwrap());
-    let mut agg: FastHashMap<String, f64> = FastHashMap::default();
-    with_set_read(ctx, keys_vec[0], |s| -> rm::RedisResult<()> {
-        agg.reserve(s.len());
-        for (m, sc) in s.iter_all() {
-            let mut sum = sc;
-            let mut present = true;
-            for &k in keys_vec.iter().skip(1) {
-                match with_set_read(ctx, k, |set| set.score(m))? {
-                    Some(other_sc) => sum += other_sc,
-                    None => {
-                        present = false;
-                        break;
-                    }
-                }
-            }
-            if present {
-                agg.insert(m.to_owned(), sum);
-            }
+    Ok((keys, tail))
+}
+
+/// Split `numkeys key [key ...] [WITHSCORES]` into the keys and whether
+/// `WITHSCORES` was given.
+fn parse_numkeys(args: &[RedisString]) -> Result<(&[RedisString], bool)> {
+    let (keys, tail) = split_numkeys(args, 1)?;
+    let with_scores = match tail {
+        [] => false,
+        [tok] if tok.to_string_lossy().eq_ignore_ascii_case("withscores") => true,
+        _ => return Err(RedisError::Str("ERR syntax error")),
+    };
+    Ok((keys, with_scores))
+}
+
+/// Options accepted after the keys of `GZUNION`, `GZINTER` and
+/// `GZUNIONSTORE`.
+struct AlgebraOpts {
+    weights: Option<Vec<f64>>,
+    agg: Aggregate,
+    with_scores: bool,
+}
+
+impl AlgebraOpts {
+    /// Score that source `i` contributes for a member it holds at `score`.
+    /// Like Redis, `0 * inf` counts as 0 rather than NaN.
+    fn weighted(&self, i: usize, score: f64) -> f64 {
+        let value = score * self.weights.as_ref().map_or(1.0, |w| w[i]);
+        if value.is_nan() {
+            0.0
+        } else {
+            value
         }
-        Ok(())
-    })??;
+    }
+}
+
+/// Parse the `[WEIGHTS weight ...] [AGGREGATE SUM|MIN|MAX] [WITHSCORES]` tail
+/// over `numkeys` sources; store commands pass `allow_withscores = false`.
+/// Errors follow Redis: a weight that is not a float is reported as such,
+/// while a short weight list, an unknown or missing `AGGREGATE` mode and any
+/// other stray token are `ERR syntax error`.
+fn parse_algebra_opts(
+    tail: &[RedisString],
+    numkeys: usize,
+    allow_withscores: bool,
+) -> Result<AlgebraOpts> {
+    let mut opts = AlgebraOpts {
+        weights: None,
+        agg: Aggregate::Sum,
+        with_scores: false,
+    };
+    let mut idx = 0usize;
+    while idx < tail.len() {
+        let token = tail[idx].to_string_lossy();
+        let remaining = tail.len() - idx;
+        if token.eq_ignore_ascii_case("weights") && remaining > numkeys {
+            let parsed = tail[idx + 1..=idx + numkeys]
+                .iter()
+                .map(|w| match w.parse_float() {
+                    Ok(v) if !v.is_nan() => Ok(v),
+                    _ => Err(RedisError::Str("ERR weight value is not a float")),
+                })
+                .collect::<Result<Vec<f64>>>()?;
+            opts.weights = Some(parsed);
+            idx += numkeys + 1;
+        } else if token.eq_ignore_ascii_case("aggregate") && remaining > 1 {
+            let mode = tail[idx + 1].to_string_lossy();
+            opts.agg = if mode.eq_ignore_ascii_case("sum") {
+                Aggregate::Sum
+            } else if mode.eq_ignore_ascii_case("min") {
+                Aggregate::Min
+            } else if mode.eq_ignore_ascii_case("max") {
+                Aggregate::Max
+            } else {
+                return Err(RedisError::Str("ERR syntax error"));
+            };
+            idx += 2;
+        } else if allow_withscores && token.eq_ignore_ascii_case("withscores") {
+            opts.with_scores = true;
+            idx += 1;
+        } else {
+            return Err(RedisError::Str("ERR syntax error"));
+        }
+    }
+    Ok(opts)
+}
+
+fn reply_sorted_pairs(ctx: &Context, agg: FastHashMap<String, f64>, with_scores: bool) -> Result {
     let mut items: Vec<_> = agg.into_iter().collect();
     items.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap().then_with(|| a.0.cmp(&b.0)));
+    reply_pairs(ctx, &items, with_scores)
+}
+
+/// Reply with `items` in the given order, as members only or member/score
+/// pairs.
+fn reply_pairs<S: AsRef<str>>(ctx: &Context, items: &[(S, f64)], with_scores: bool) -> Result {
     let raw = ctx.get_raw();
-    unsafe { RedisModule_ReplyWithArray.unwrap()(raw, (items.len() * 2) as c_long) };
+    let reply_len = if with_scores {
+        items.len() * 2
+    } else {
+        items.len()
+    };
+    unsafe { RedisModule_ReplyWithArray.unwrap()(raw, reply_len as c_long) };
     for (member, score) in items {
+        let member = member.as_ref();
         unsafe {
             RedisModule_ReplyWithStringBuffer.unwrap()(raw, member.as_ptr().cast(), member.len());
-            RedisModule_ReplyWithDouble.unwrap()(raw, score);
+            if with_scores {
+                reply_with_score(raw, *score);
+            }
         }
     }
     Ok(RedisValue::NoReply)
 }
 
+/// `GZUNION numkeys key [key ...] [WEIGHTS weight ...] [AGGREGATE SUM|MIN|MAX]
+/// [WITHSCORES]`. Mostly disjoint sources are k-way merged in score order;
+/// otherwise scores are aggregated in a map and sorted.
+fn gzunion(ctx: &Context, args: Vec<RedisString>) -> Result {
+    if declare_numkeys_keys(ctx, &args, 1, false) {
+        return Ok(RedisValue::NoReply);
+    }
+    let (keys, tail) = split_numkeys(&args, 1)?;
+    let opts = parse_algebra_opts(tail, keys.len(), true)?;
+    let rkeys: Vec<RedisKey> = keys.iter().map(|k| ctx.open_key(k)).collect();
+    let operands = keys
+        .iter()
+        .zip(&rkeys)
+        .map(|(key, rkey)| open_operand(ctx, key, rkey))
+        .collect::<Result<Vec<_>>>()?;
+    let sources: Vec<&ScoreSet> = operands.iter().map(Operand::set).collect();
+    if ScoreSet::union_overlap_is_low(&sources) {
+        let merged = ScoreSet::union_merged(&sources, opts.weights.as_deref(), opts.agg);
+        return reply_pairs(ctx, &merged, opts.with_scores);
+    }
+    let mut agg: FastHashMap<String, f64> = FastHashMap::default();
+    for (i, set) in sources.iter().enumerate() {
+        agg.reserve(set.len());
+        for (member, score) in set.iter_all() {
+            let value = opts.weighted(i, score);
+            if let Some(v) = agg.get_mut(member) {
+                *v = opts.agg.combine(*v, value);
+            } else {
+                agg.insert(member.to_owned(), value);
+            }
+        }
+    }
+    reply_sorted_pairs(ctx, agg, opts.with_scores)
+}
+
+/// `GZUNIONSTORE destination numkeys key [key ...] [WEIGHTS weight ...]
+/// [AGGREGATE SUM|MIN|MAX]` replaces `destination` with the union and replies
+/// with its cardinality. An empty union deletes `destination`. Unlike
+/// `GZUNION` it never takes the k-way merge: [`ScoreSet::union_into`] already
+/// skips the map of owned names by interning straight into the destination,
+/// and it matches or beats merging and then appending at every overlap.
+fn gzunionstore(ctx: &Context, args: Vec<RedisString>) -> Result {
+    if declare_numkeys_keys(ctx, &args, 2, true) {
+        return Ok(RedisValue::NoReply);
+    }
+    let (keys, tail) = split_numkeys(&args, 2)?;
+    let opts = parse_algebra_opts(tail, keys.len(), false)?;
+    let dst_key = &args[1];
+    let _ = dst_key.try_as_str()?;
+
+    let mut out = ScoreSet::default();
+    {
+        let rkeys: Vec<RedisKey> = keys.iter().map(|k| ctx.open_key(k)).collect();
+        let operands = keys
+            .iter()
+            .zip(&rkeys)
+            .map(|(key, rkey)| open_operand(ctx, key, rkey))
+            .collect::<Result<Vec<_>>>()?;
+        let sources: Vec<&ScoreSet> = operands.iter().map(Operand::set).collect();
+        ScoreSet::union_into(&mut out, &sources, opts.weights.as_deref(), opts.agg);
+    }
+    let overflowed = |entry: Option<(&str, f64)>| entry.is_some_and(|(_, sc)| !sc.is_finite());
+    if overflowed(out.first()) || overflowed(out.last()) {
+        return Err(RedisError::Str(
+            "ERR resulting score is not a finite number",
+        ));
+    }
+
+    let len = out.len();
+    let rkey = ctx.open_key_writable(dst_key);
+    rkey.delete()?;
+    finish_write(&rkey, Some(out), len == 0)?;
+    ctx.replicate_verbatim();
+    Ok(RedisValue::Integer(len as i64))
+}
+
+fn gzinter(ctx: &Context, args: Vec<RedisString>) -> Result {
+    if declare_numkeys_keys(ctx, &args, 1, false) {
+        return Ok(RedisValue::NoReply);
+    }
+    let (keys, tail) = split_numkeys(&args, 1)?;
+    let opts = parse_algebra_opts(tail, keys.len(), true)?;
+    let rkeys: Vec<RedisKey> = keys.iter().map(|k| ctx.open_key(k)).collect();
+    // Keep each operand's key position so its weight follows it through the
+    // smallest-first sort.
+    let mut operands = keys
+        .iter()
+        .zip(&rkeys)
+        .map(|(key, rkey)| open_operand(ctx, key, rkey))
+        .enumerate()
+        .map(|(i, op)| op.map(|op| (i, op)))
+        .collect::<Result<Vec<_>>>()?;
+    operands.sort_by_key(|(_, op)| op.set().len());
+    let mut agg: FastHashMap<String, f64> = FastHashMap::default();
+    let ((first_idx, first), rest) = operands.split_first().expect("numkeys must be > 0");
+    agg.reserve(first.set().len());
+    'members: for (m, sc) in first.set().iter_all() {
+        let mut acc = opts.weighted(*first_idx, sc);
+        for (i, other) in rest {
+            match other.set().score(m) {
+                Some(other_sc) => acc = opts.agg.combine(acc, opts.weighted(*i, other_sc)),
+                None => continue 'members,
+            }
+        }
+        agg.insert(m.to_owned(), acc);
+    }
+    reply_sorted_pairs(ctx, agg, opts.with_scores)
+}
+
 fn gzdiff(ctx: &Context, args: Vec<RedisString>) -> Result {
+    if declare_numkeys_keys(ctx, &args, 1, false) {
+        return Ok(RedisValue::NoReply);
+    }
+    let (keys, with_scores) = parse_numkeys(&args)?;
+    let rkeys: Vec<RedisKey> = keys.iter().map(|k| ctx.open_key(k)).collect();
+    let operands = keys
+        .iter()
+        .zip(&rkeys)
+        .map(|(key, rkey)| open_operand(ctx, key, rkey))
+        .collect::<Result<Vec<_>>>()?;
+    let (first, rest) = operands.split_first().expect("numkeys must be > 0");
+    let mut diff: FastHashMap<String, f64> = FastHashMap::default();
+    diff.reserve(first.set().len());
+    for (m, sc) in first.set().iter_all() {
+        if !rest.iter().any(|other| other.set().contains(m)) {
+            diff.insert(m.to_owned(), sc);
+        }
+    }
+    reply_sorted_pairs(ctx, diff, with_scores)
+}
+
+fn gzintercard(ctx: &Context, args: Vec<RedisString>) -> Result {
+    if declare_numkeys_keys(ctx, &args, 1, false) {
+        return Ok(RedisValue::NoReply);
+    }
     if args.len() < 3 {
         return Err(RedisError::WrongArity);
     }
@@ -619,85 +1781,49 @@ fn gzdiff(ctx: &Context, args: Vec<RedisString>) -> Result {
         return Err(RedisError::Str("ERR numkeys must be > 0"));
     }
     let num = num as usize;
-    if args.len() != num + 2 {
-        return Err(RedisError::WrongArity);
+    if args.len() < num + 2 {
+        return Err(RedisError::Str(
+            "ERR Number of keys can't be greater than number of args",
+        ));
     }
-    let keys: Vec<&RedisString> = args[2..].iter().collect();
-    for key in &keys {
+    let keys = &args[2..num + 2];
+    for key in keys {
         let _ = key.try_as_str()?;
     }
-    let mut diff: FastHashMap<String, f64> = FastHashMap::default();
-    with_set_read(ctx, keys[0], |s| -> rm::RedisResult<()> {
-        diff.reserve(s.len());
-        for (m, sc) in s.iter_all() {
-            let mut found = false;
-            for &k in keys.iter().skip(1) {
-                if with_set_read(ctx, k, |set| set.contains(m))? {
-                    found = true;
-                    break;
-                }
-            }
-            if !found {
-                diff.insert(m.to_owned(), sc);
+    let mut limit = 0usize;
+    let tail = &args[num + 2..];
+    match tail {
+        [] => {}
+        [opt, value] if opt.to_string_lossy().eq_ignore_ascii_case("LIMIT") => {
+            let l: i64 = value.parse_integer()?;
+            if l < 0 {
+                return Err(RedisError::Str("ERR LIMIT can't be negative"));
             }
+            limit = l as usize;
         }
-        Ok(())
-    })??;
-    let mut items: Vec<_> = diff.into_iter().collect();
-    items.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap().then_with(|| a.0.cmp(&b.0)));
-    let raw = ctx.get_raw();
-    unsafe { RedisModule_ReplyWithArray.unwrap()(raw, (items.len() * 2) as c_long) };
-    for (member, score) in items {
-        unsafe {
-            RedisModule_ReplyWithStringBuffer.unwrap()(raw, member.as_ptr().cast(), member.len());
-            RedisModule_ReplyWithDouble.unwrap()(raw, score);
-        }
+        _ => return Err(RedisError::Str("ERR syntax error")),
     }
-    Ok(RedisValue::NoReply)
-}
 
-fn gzintercard(_ctx: &Context, args: Vec<RedisString>) -> Result {
-    if args.len() < 3 || args.len() > 4 {
-        return Err(RedisError::WrongArity);
-    }
-    let key1 = &args[1];
-    let _ = key1.try_as_str()?;
-    let key2 = &args[2];
-    let _ = key2.try_as_str()?;
-    let limit = if args.len() == 4 {
-        Some(args[3].parse_integer()?)
-    } else {
-        None
-    };
-    let len1 = with_set_read(_ctx, key1, |s| s.len())?;
-    let len2 = with_set_read(_ctx, key2, |s| s.len())?;
-    if len1 == 0 || len2 == 0 {
-        return Ok(0i64.into());
-    }
-    let (small_key, big_key) = if len1 <= len2 {
-        (key1, key2)
-    } else {
-        (key2, key1)
-    };
-    let count = with_set_read(_ctx, small_key, |s| -> rm::RedisResult<i64> {
-        let mut count = 0i64;
-        for (m, _) in s.iter_all() {
-            let present = with_set_read(_ctx, big_key, |set| set.contains(m))?;
-            if present {
-                count += 1;
-                if let Some(l) = limit {
-                    if count >= l {
-                        break;
-                    }
-                }
-            }
-        }
-        Ok(count)
-    })??;
-    Ok(count.into())
+    let rkeys: Vec<RedisKey> = keys.iter().map(|k| ctx.open_key(k)).collect();
+    let operands = keys
+        .iter()
+        .zip(&rkeys)
+        .map(|(key, rkey)| open_operand(ctx, key, rkey))
+        .collect::<Result<Vec<_>>>()?;
+    let sets: Vec<&ScoreSet> = operands.iter().map(|op| op.set()).collect();
+    Ok((ScoreSet::intersection_card(&sets, limit) as i64).into())
 }
 
-fn gzscan(_ctx: &Context, args: Vec<RedisString>) -> Result {
+/// `GZSCAN key cursor [COUNT count]`.
+///
+/// The cursor is the last returned `(score, member)` pair and each call resumes
+/// strictly after it in score order, so it never depends on where the member
+/// lives in memory. Like `SCAN`, the guarantee is at-least-once: every member
+/// present for the whole scan with an unchanged score is returned. The cursor
+/// member itself may be deleted or rescored between calls; the scan resumes
+/// at the next pair after the old position. Members rescored mid-scan may be
+/// returned twice or, if moved behind the cursor, not at all.
+fn gzscan(ctx: &Context, args: Vec<RedisString>) -> Result {
     if args.len() < 3 {
         return Err(RedisError::WrongArity);
     }
@@ -705,10 +1831,13 @@ fn gzscan(_ctx: &Context, args: Vec<RedisString>) -> Result {
     let _ = key.try_as_str()?;
     let cursor = args[2].try_as_str()?;
 
-    const DEFAULT_COUNT: usize = 10;
-    const MAX_COUNT: usize = 1024;
-
-    let mut count = DEFAULT_COUNT;
+    let max_count = SCAN_MAX_COUNT
+        .load(Ordering::Relaxed)
+        .clamp(SCAN_COUNT_MIN, SCAN_COUNT_MAX);
+    // A default above the configured max is capped rather than rejected.
+    let mut count = SCAN_DEFAULT_COUNT
+        .load(Ordering::Relaxed)
+        .clamp(SCAN_COUNT_MIN, max_count) as usize;
     let mut idx = 3;
     let mut seen_count = false;
     while idx < args.len() {
@@ -722,8 +1851,10 @@ fn gzscan(_ctx: &Context, args: Vec<RedisString>) -> Result {
                 return Err(RedisError::Str("ERR syntax error"));
             }
             let raw = args[idx].parse_integer()?;
-            if raw <= 0 || raw as usize > MAX_COUNT {
-                return Err(RedisError::Str("ERR COUNT must be between 1 and 1024"));
+            if raw <= 0 || raw > max_count {
+                return Err(RedisError::String(format!(
+                    "ERR COUNT must be between 1 and {max_count}"
+                )));
             }
             count = raw as usize;
             seen_count = true;
@@ -796,43 +1927,31 @@ fn gzscan(_ctx: &Context, args: Vec<RedisString>) -> Result {
         Some(decode_cursor(cursor).ok_or(RedisError::Str("ERR invalid cursor"))?)
     };
 
-    let (arr, next) = with_set_read(
-        _ctx,
-        key,
-        move |s| -> rm::RedisResult<(Vec<RedisValue>, String)> {
-            if s.is_empty() {
-                return Ok((Vec::new(), "0".to_string()));
-            }
-
-            let mut iter = match parsed {
-                None => s
-                    .iter_from(OrderedFloat(f64::NEG_INFINITY), "", true)
-                    .peekable(),
-                Some((score, ref member)) => {
-                    s.iter_from(OrderedFloat(score), member, true).peekable()
-                }
-            };
-
-            let mut arr = Vec::new();
-            let mut last = None;
-            for _ in 0..count {
-                if let Some((m, sc)) = iter.next() {
-                    arr.push(m.to_owned().into());
-                    with_fmt_buf(|b| arr.push(fmt_f64(b, sc).to_owned().into()));
-                    last = Some((sc, m.to_owned()));
-                } else {
-                    break;
+    let raw = ctx.get_raw();
+    with_set_read(ctx, key, move |s| {
+        let mut iter = match parsed {
+            None => s
+                .iter_from(OrderedFloat(f64::NEG_INFINITY), "", true)
+                .peekable(),
+            Some((score, ref member)) => s.iter_from(OrderedFloat(score), member, true).peekable(),
+        };
+        // Borrow the batch so members are copied into the reply straight
+        // from the arena; the cursor has to be known before the batch is sent.
+        let batch: Vec<(&str, f64)> = iter.by_ref().take(count).collect();
+        unsafe {
+            RedisModule_ReplyWithArray.unwrap()(raw, 2);
+            // Only a batch with members left behind it hands out a cursor,
+            // so a short or exactly-`count` final batch ends the scan.
+            match batch.last() {
+                Some(&(m, sc)) if iter.peek().is_some() => {
+                    reply_with_str(raw, &encode_cursor(sc, m));
                 }
+                _ => reply_with_str(raw, "0"),
             }
-            let next = match last {
-                Some((sc, m)) if iter.peek().is_some() => encode_cursor(sc, &m),
-                _ => "0".to_string(),
-            };
-            Ok((arr, next))
-        },
-    )??;
-
-    Ok(RedisValue::Array(vec![next.into(), RedisValue::Array(arr)]))
+            reply_flat_pairs(raw, &batch, true);
+        }
+    })?;
+    Ok(RedisValue::NoReply)
 }
 
 /// Register all module commands with the server.
@@ -841,22 +1960,376 @@ fn gzscan(_ctx: &Context, args: Vec<RedisString>) -> Result {
 ///
 /// The `ctx` pointer must be a valid module context provided by Valkey/Redis.
 pub unsafe fn register_commands(ctx: *mut raw::RedisModuleCtx) -> rm::Status {
+    // Flags follow the matching Z* commands: `denyoom` on writes that can
+    // grow memory, so deletes, pops and `GZTRIM` still run over `maxmemory`,
+    // and `fast` only on O(1) or O(log N) commands.
     let result: rm::RedisResult<()> = (|| {
-        redis_command!(ctx, "GZADD", gzadd, "write fast", 1, 1, 1)?;
-        redis_command!(ctx, "GZRANK", gzrank, "readonly", 1, 1, 1)?;
-        redis_command!(ctx, "GZRANGE", gzrange, "readonly", 1, 1, 1)?;
-        redis_command!(ctx, "GZREM", gzrem, "write fast", 1, 1, 1)?;
-        redis_command!(ctx, "GZSCORE", gzscore, "readonly", 1, 1, 1)?;
-        redis_command!(ctx, "GZCARD", gzcard, "readonly", 1, 1, 1)?;
-        redis_command!(ctx, "GZPOPMIN", gzpopmin, "write fast", 1, 1, 1)?;
-        redis_command!(ctx, "GZPOPMAX", gzpopmax, "write fast", 1, 1, 1)?;
-        redis_command!(ctx, "GZRANDMEMBER", gzrandmember, "readonly", 1, 1, 1)?;
-        redis_command!(ctx, "GZMSCORE", gzmscore, "readonly", 1, 1, 1)?;
-        redis_command!(ctx, "GZUNION", gzunion, "readonly", 2, -1, 1)?;
-        redis_command!(ctx, "GZINTER", gzinter, "readonly", 2, -1, 1)?;
-        redis_command!(ctx, "GZDIFF", gzdiff, "readonly", 2, -1, 1)?;
-        redis_command!(ctx, "GZINTERCARD", gzintercard, "readonly", 1, 2, 1)?;
-        redis_command!(ctx, "GZSCAN", gzscan, "readonly", 1, 1, 1)?;
+        redis_command!(
+            ctx,
+            "GZADD",
+            gzadd,
+            "write denyoom fast",
+            1,
+            1,
+            1,
+            -4,
+            "Add members or update their scores"
+        )?;
+        redis_command!(
+            ctx,
+            "GZINCRBY",
+            gzincrby,
+            "write denyoom fast",
+            1,
+            1,
+            1,
+            4,
+            "Increment the score of a member"
+        )?;
+        redis_command!(
+            ctx,
+            "GZRANK",
+            gzrank,
+            "readonly fast",
+            1,
+            1,
+            1,
+            -3,
+            "Rank of a member, by ascending score"
+        )?;
+        redis_command!(
+            ctx,
+            "GZRANGE",
+            gzrange,
+            "readonly",
+            1,
+            1,
+            1,
+            -4,
+            "Members in a rank range, by ascending score"
+        )?;
+        redis_command!(
+            ctx,
+            "GZREVRANGE",
+            gzrevrange,
+            "readonly",
+            1,
+            1,
+            1,
+            -4,
+            "Members in a rank range, by descending score"
+        )?;
+        redis_command!(
+            ctx,
+            "GZRANGEBYSCORE",
+            gzrangebyscore,
+            "readonly",
+            1,
+            1,
+            1,
+            -4,
+            "Members in a score range"
+        )?;
+        redis_command!(
+            ctx,
+            "GZREM",
+            gzrem,
+            "write fast",
+            1,
+            1,
+            1,
+            -3,
+            "Remove members"
+        )?;
+        redis_command!(
+            ctx,
+            "GZSCORE",
+            gzscore,
+            "readonly fast",
+            1,
+            1,
+            1,
+            3,
+            "Score of a member"
+        )?;
+        redis_command!(
+            ctx,
+            "GZCARD",
+            gzcard,
+            "readonly fast",
+            1,
+            1,
+            1,
+            2,
+            "Number of members"
+        )?;
+        redis_command!(
+            ctx,
+            "GZPOPMIN",
+            gzpopmin,
+            "write fast",
+            1,
+            1,
+            1,
+            -2,
+            "Remove and return the lowest-scored members"
+        )?;
+        redis_command!(
+            ctx,
+            "GZPOPMAX",
+            gzpopmax,
+            "write fast",
+            1,
+            1,
+            1,
+            -2,
+            "Remove and return the highest-scored members"
+        )?;
+        redis_command!(
+            ctx,
+            "GZPOPRANGEBYSCORE",
+            gzpoprangebyscore,
+            "write",
+            1,
+            1,
+            1,
+            4,
+            "Remove and return the members in a score range"
+        )?;
+        redis_command!(
+            ctx,
+            "GZREMRANGEBYRANK",
+            gzremrangebyrank,
+            "write",
+            1,
+            1,
+            1,
+            4,
+            "Remove the members in a rank range"
+        )?;
+        redis_command!(
+            ctx,
+            "GZREMRANGEBYSCORE",
+            gzremrangebyscore,
+            "write",
+            1,
+            1,
+            1,
+            4,
+            "Remove the members in a score range"
+        )?;
+        redis_command!(
+            ctx,
+            "GZSPLIT",
+            gzsplit,
+            "write denyoom",
+            1,
+            2,
+            1,
+            4,
+            "Move the members scoring at least a score to another key"
+        )?;
+        redis_command!(
+            ctx,
+            "GZMOVE",
+            gzmove,
+            "write denyoom fast",
+            1,
+            2,
+            1,
+            4,
+            "Move a member to another key"
+        )?;
+        redis_command!(
+            ctx,
+            "GZRANGESTORE",
+            gzrangestore,
+            "write denyoom",
+            1,
+            2,
+            1,
+            5,
+            "Store a rank range of members in another key"
+        )?;
+        redis_command!(
+            ctx,
+            "GZRANDMEMBER",
+            gzrandmember,
+            "readonly",
+            1,
+            1,
+            1,
+            -2,
+            "Random members"
+        )?;
+        redis_command!(
+            ctx,
+            "GZMSCORE",
+            gzmscore,
+            "readonly fast",
+            1,
+            1,
+            1,
+            -3,
+            "Scores of several members"
+        )?;
+        // The numkeys commands declare their keys through `getkeys-api`. A
+        // firstkey/lastkey span would become a key spec that the server
+        // prefers over the callback, so they register without one.
+        redis_command!(
+            ctx,
+            "GZUNION",
+            gzunion,
+            "readonly getkeys-api",
+            0,
+            0,
+            0,
+            -3,
+            "Union of sets"
+        )?;
+        redis_command!(
+            ctx,
+            "GZUNIONSTORE",
+            gzunionstore,
+            "write denyoom getkeys-api",
+            0,
+            0,
+            0,
+            -4,
+            "Store the union of sets"
+        )?;
+        redis_command!(
+            ctx,
+            "GZINTER",
+            gzinter,
+            "readonly getkeys-api",
+            0,
+            0,
+            0,
+            -3,
+            "Intersection of sets"
+        )?;
+        redis_command!(
+            ctx,
+            "GZDIFF",
+            gzdiff,
+            "readonly getkeys-api",
+            0,
+            0,
+            0,
+            -3,
+            "Difference of sets"
+        )?;
+        redis_command!(
+            ctx,
+            "GZINTERCARD",
+            gzintercard,
+            "readonly getkeys-api",
+            0,
+            0,
+            0,
+            -3,
+            "Size of the intersection of sets"
+        )?;
+        redis_command!(
+            ctx,
+            "GZSCAN",
+            gzscan,
+            "readonly",
+            1,
+            1,
+            1,
+            -3,
+            "Incrementally iterate members and scores"
+        )?;
+        redis_command!(
+            ctx,
+            "GZMIN",
+            gzmin,
+            "readonly fast",
+            1,
+            1,
+            1,
+            2,
+            "Lowest-scored member"
+        )?;
+        redis_command!(
+            ctx,
+            "GZMAX",
+            gzmax,
+            "readonly fast",
+            1,
+            1,
+            1,
+            2,
+            "Highest-scored member"
+        )?;
+        redis_command!(
+            ctx,
+            "GZTRIM",
+            gztrim,
+            "write",
+            1,
+            1,
+            1,
+            2,
+            "Release spare capacity"
+        )?;
+        redis_command!(
+            ctx,
+            "GZSCORECARD",
+            gzscorecard,
+            "readonly fast",
+            1,
+            1,
+            1,
+            3,
+            "Number of members holding exactly a score"
+        )?;
+        redis_command!(
+            ctx,
+            "GZSCORERANK",
+            gzscorerank,
+            "readonly fast",
+            1,
+            1,
+            1,
+            3,
+            "Number of members scoring below a score"
+        )?;
+        redis_command!(
+            ctx,
+            "GZPERCENTILE",
+            gzpercentile,
+            "readonly fast",
+            1,
+            1,
+            1,
+            3,
+            "Score at a percentile of the set"
+        )?;
+        redis_command!(
+            ctx,
+            "GZDEBUG",
+            gzdebug,
+            "readonly",
+            2,
+            2,
+            1,
+            -2,
+            "Internal layout and memory diagnostics"
+        )?;
+        redis_command!(
+            ctx,
+            "GZMEMESTIMATE",
+            gzmemestimate,
+            "readonly fast",
+            0,
+            0,
+            0,
+            3,
+            "Predicted memory for a set of a given shape"
+        )?;
         Ok(())
     })();
     if result.is_err() {
@@ -887,7 +2360,84 @@ pub unsafe extern "C" fn gzset_on_load(
     if register_commands(ctx) == rm::Status::Err {
         return raw::Status::Err as c_int;
     }
-    raw::Status::Ok as c_int
+    if let Some(register_info) = raw::RedisModule_RegisterInfoFunc {
+        if register_info(ctx, Some(gzset_info)) == raw::Status::Err as c_int {
+            return raw::Status::Err as c_int;
+        }
+    }
+    register_configs(ctx)
+}
+
+/// `INFO gzset`: totals over every stored set, kept current by each write and
+/// free so the callback never walks the keyspace. `gzset_bytes` is an O(1)
+/// estimate; `MEMORY USAGE` gives a key's exact figure.
+unsafe extern "C" fn gzset_info(ctx: *mut raw::RedisModuleInfoCtx, _for_crash_report: c_int) {
+    let (Some(add_section), Some(add_field)) = (
+        raw::RedisModule_InfoAddSection,
+        raw::RedisModule_InfoAddFieldLongLong,
+    ) else {
+        return;
+    };
+    let totals = memory::totals();
+    add_section(ctx, std::ptr::null());
+    for (field, value) in [
+        (&b"keys\0"[..], totals.keys),
+        (b"members\0", totals.members),
+        (b"bytes\0", totals.bytes),
+        (b"spilled_buckets\0", totals.spilled_buckets),
+    ] {
+        add_field(ctx, field.as_ptr().cast::<c_char>(), value);
+    }
+}
+
+/// Registers module configs and applies any values passed at load time or
+/// present in the server config file.
+unsafe fn register_configs(ctx: *mut raw::RedisModuleCtx) -> c_int {
+    let context = Context::new(ctx);
+    rm::configuration::register_i64_configuration(
+        &context,
+        "bucket-shrink-threshold",
+        &BUCKET_SHRINK_THRESHOLD,
+        BUCKET_SHRINK_THRESHOLD_DEFAULT,
+        BUCKET_SHRINK_THRESHOLD_MIN,
+        BUCKET_SHRINK_THRESHOLD_MAX,
+        rm::configuration::ConfigurationFlags::DEFAULT,
+        None,
+    );
+    rm::configuration::register_i64_configuration(
+        &context,
+        "arena-chunk-size",
+        &ARENA_CHUNK_SIZE,
+        ARENA_CHUNK_SIZE_DEFAULT,
+        ARENA_CHUNK_SIZE_MIN,
+        ARENA_CHUNK_SIZE_MAX,
+        rm::configuration::ConfigurationFlags::MEMORY,
+        None,
+    );
+    rm::configuration::register_i64_configuration(
+        &context,
+        "scan-default-count",
+        &SCAN_DEFAULT_COUNT,
+        SCAN_DEFAULT_COUNT_DEFAULT,
+        SCAN_COUNT_MIN,
+        SCAN_COUNT_MAX,
+        rm::configuration::ConfigurationFlags::DEFAULT,
+        None,
+    );
+    rm::configuration::register_i64_configuration(
+        &context,
+        "scan-max-count",
+        &SCAN_MAX_COUNT,
+        SCAN_MAX_COUNT_DEFAULT,
+        SCAN_COUNT_MIN,
+        SCAN_COUNT_MAX,
+        rm::configuration::ConfigurationFlags::DEFAULT,
+        None,
+    );
+    match raw::RedisModule_LoadConfigs {
+        Some(load) => load(ctx),
+        None => raw::Status::Ok as c_int,
+    }
 }
 
 #[no_mangle]
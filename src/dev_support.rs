@@ -0,0 +1,94 @@
+//! Dataset generators and Valkey process helpers shared by the `xtask` binary
+//! and the benches. Not part of the module's API.
+
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+pub mod server;
+
+static BASE_SEED: Lazy<u64> = Lazy::new(|| {
+    std::env::var("GZSET_BENCH_SEED")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(0x7d11_5eed_f065_cafe)
+});
+
+static RNG_COUNTER: Lazy<Mutex<u64>> = Lazy::new(|| Mutex::new(0));
+
+#[inline]
+pub fn seeded_rng() -> StdRng {
+    let mut guard = RNG_COUNTER.lock().unwrap();
+    let seed = BASE_SEED.wrapping_add(*guard);
+    *guard = guard.wrapping_add(1);
+    StdRng::seed_from_u64(seed)
+}
+
+pub fn unique_increasing(n: usize) -> Vec<(f64, String)> {
+    (0..n).map(|i| (i as f64, format!("member:{i}"))).collect()
+}
+
+pub fn uniform_random(n: usize, score_range: f64) -> Vec<(f64, String)> {
+    let mut rng = seeded_rng();
+    (0..n)
+        .map(|i| (rng.gen_range(0.0..score_range), format!("rand:{i}")))
+        .collect()
+}
+
+pub fn same_score(n: usize, score: f64) -> Vec<(f64, String)> {
+    (0..n).map(|i| (score, format!("same:{i}"))).collect()
+}
+
+pub fn clustered(n: usize, clusters: usize, spread: f64) -> Vec<(f64, String)> {
+    assert!(clusters > 0, "clusters must be > 0");
+    let mut rng = seeded_rng();
+    let mut out = Vec::with_capacity(n);
+    let mut generated = 0usize;
+    let base_gap = spread.max(1.0);
+    for cluster_idx in 0..clusters {
+        if generated >= n {
+            break;
+        }
+        let remaining = n - generated;
+        let clusters_left = clusters - cluster_idx;
+        let target = remaining.div_ceil(clusters_left);
+        let center = cluster_idx as f64 * base_gap * 10.0;
+        for local in 0..target {
+            let delta = rng.gen_range(-spread..=spread);
+            let score = center + delta;
+            out.push((score, format!("cluster:{cluster_idx}:{local}")));
+        }
+        generated += target;
+    }
+    out
+}
+
+/// Consecutive integer scores, each shared by between one and `max_ties`
+/// members, in shuffled insertion order.
+pub fn small_ties(n: usize, max_ties: usize) -> Vec<(f64, String)> {
+    assert!(max_ties > 0, "max_ties must be > 0");
+    let mut rng = seeded_rng();
+    let mut out = Vec::with_capacity(n);
+    let mut score = 0usize;
+    while out.len() < n {
+        let ties = rng.gen_range(1..=max_ties).min(n - out.len());
+        for local in 0..ties {
+            out.push((score as f64, format!("tie:{score}:{local}")));
+        }
+        score += 1;
+    }
+    out.shuffle(&mut rng);
+    out
+}
+
+pub fn zipf_like(n: usize, s: f64) -> Vec<(f64, String)> {
+    let exponent = s.max(0.5);
+    (0..n)
+        .map(|i| {
+            let rank = (i + 1) as f64;
+            let score = rank.powf(exponent);
+            (score, format!("zipf:{i}"))
+        })
+        .collect()
+}
@@ -0,0 +1,118 @@
+//! Valkey process helpers shared by `xtask` and the end-to-end benches.
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+
+/// Build the module with the `redis-module` feature so Valkey's allocator and
+/// memory stats are used. `extra_rustflags` is appended to `RUSTFLAGS`.
+pub fn build_module(release: bool, extra_rustflags: Option<&str>) -> Result<()> {
+    let mut build = Command::new("cargo");
+    build.arg("build").arg("--package=gzset");
+    if release {
+        build.arg("--release");
+    }
+    build.arg("--features").arg("redis-module");
+
+    if let Some(flags) = extra_rustflags {
+        let mut rustflags = env::var("RUSTFLAGS").unwrap_or_default();
+        if !rustflags.is_empty() {
+            rustflags.push(' ');
+        }
+        rustflags.push_str(flags);
+        build.env("RUSTFLAGS", rustflags);
+    }
+
+    anyhow::ensure!(build.status()?.success(), "cargo build failed");
+    Ok(())
+}
+
+/// Path of the module built by [`build_module`].
+pub fn module_path(release: bool) -> Result<PathBuf> {
+    let so_name = format!(
+        "{}gzset{}",
+        env::consts::DLL_PREFIX,
+        env::consts::DLL_SUFFIX
+    );
+    let so_path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("target")
+        .join(if release { "release" } else { "debug" })
+        .join(&so_name);
+    anyhow::ensure!(so_path.exists(), "module not found at {so_path:?}");
+    Ok(so_path)
+}
+
+/// Start `valkey-server` on `port` with the module at `so_path` loaded and
+/// wait until it answers `PING`.
+pub fn launch(so_path: &Path, port: u16, extra_args: &[String]) -> Result<Child> {
+    let mut cmd = Command::new("valkey-server");
+    cmd.arg("--port")
+        .arg(port.to_string())
+        .arg("--loadmodule")
+        .arg(so_path)
+        .arg("--save")
+        .arg("") // disable RDB; keep gzset non-persistent in production until an RDB format exists
+        .arg("--daemonize")
+        .arg("no")
+        .stdin(Stdio::null())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit());
+    cmd.args(extra_args);
+
+    let mut child = cmd.spawn().context("failed to start valkey-server")?;
+
+    for _ in 0..50u8 {
+        if redis::Client::open(format!("redis://127.0.0.1:{port}"))
+            .and_then(|c| c.get_connection())
+            .and_then(|mut con| redis::cmd("PING").query::<String>(&mut con))
+            .map(|p| p == "PONG")
+            .unwrap_or(false)
+        {
+            return Ok(child);
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    let _ = child.kill();
+    anyhow::bail!("valkey-server failed to start");
+}
+
+/// A release-built server on a free port, shut down on drop.
+pub struct Server {
+    child: Child,
+    pub port: u16,
+}
+
+impl Server {
+    pub fn start() -> Result<Self> {
+        build_module(true, None)?;
+        let so_path = module_path(true)?;
+        let port = portpicker::pick_unused_port().context("no free ports")?;
+        let quiet = ["--loglevel".to_string(), "warning".to_string()];
+        let child = launch(&so_path, port, &quiet)?;
+        Ok(Self { child, port })
+    }
+
+    pub fn connection(&self) -> Result<redis::Connection> {
+        redis::Client::open(format!("redis://127.0.0.1:{}", self.port))?
+            .get_connection()
+            .context("failed to connect to valkey-server")
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = Command::new("valkey-cli")
+            .arg("-p")
+            .arg(self.port.to_string())
+            .arg("shutdown")
+            .arg("nosave")
+            .status();
+        let _ = self.child.wait();
+    }
+}
@@ -1,21 +1,334 @@
 use ryu::Buffer;
 use std::cell::RefCell;
 
-#[inline]
-pub fn fmt_f64(buf: &mut Buffer, score: f64) -> &str {
+/// Largest magnitude Redis prints as a plain integer (`LLONG_MAX / 2`, which
+/// rounds to 2^62 as a double).
+const INTEGER_LIMIT: f64 = 4_611_686_018_427_387_904.0;
+
+/// Scratch space for [`fmt_f64`]: ryu's shortest digits plus room for the
+/// Redis-style layout built from them. The longest output is 25 bytes.
+pub struct FmtBuf {
+    ryu: Buffer,
+    out: [u8; 32],
+    len: usize,
+}
+
+impl Default for FmtBuf {
+    fn default() -> Self {
+        Self {
+            ryu: Buffer::new(),
+            out: [0; 32],
+            len: 0,
+        }
+    }
+}
+
+impl FmtBuf {
+    #[inline]
+    fn push(&mut self, byte: u8) {
+        self.out[self.len] = byte;
+        self.len += 1;
+    }
+
+    #[inline]
+    fn extend(&mut self, bytes: &[u8]) {
+        self.out[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+    }
+
+    #[inline]
+    fn as_str(&self) -> &str {
+        // SAFETY: only ASCII digits, signs, '.', and 'e' are ever written.
+        unsafe { std::str::from_utf8_unchecked(&self.out[..self.len]) }
+    }
+}
+
+/// Format a finite score exactly as the server's `d2string` does for
+/// built-in sorted set replies, using the shortest digits that parse back to
+/// the same `f64` bits (`fmt_f64(x).parse::<f64>() == x`).
+///
+/// Integral values up to 2^62 print as integers (`3`, `-0`), never with a
+/// trailing `.0`. Otherwise the digits are laid out like fpconv: plain while
+/// the exponent stays small, `0.000001` down to six leading zeros, and
+/// `1.5e-7` / `1e+21` style exponents beyond that. Infinities print as `inf`
+/// and `-inf`. GZSCAN cursors depend on the round-trip guarantee.
+pub fn fmt_f64(buf: &mut FmtBuf, score: f64) -> &str {
+    debug_assert!(!score.is_nan());
+    buf.len = 0;
+    if score.is_sign_negative() {
+        buf.push(b'-');
+    }
+    let abs = score.abs();
+    if abs == 0.0 {
+        buf.push(b'0');
+        return buf.as_str();
+    }
+    if abs.is_infinite() {
+        buf.extend(b"inf");
+        return buf.as_str();
+    }
+    if abs <= INTEGER_LIMIT && abs.fract() == 0.0 {
+        let mut digits = [0u8; 20];
+        let mut n = abs as u64;
+        let mut at = digits.len();
+        while n > 0 {
+            at -= 1;
+            digits[at] = b'0' + (n % 10) as u8;
+            n /= 10;
+        }
+        buf.extend(&digits[at..]);
+        return buf.as_str();
+    }
+
+    // Recover the significant digits and decimal exponent from ryu's output,
+    // which may be plain (`0.00012`, `1234.5`) or scientific (`1.5e-7`).
+    let mut digits = [0u8; 32];
+    let mut ndigits = 0usize;
+    let mut seen = 0i32;
+    let mut int_len = None;
+    let mut leading_zeros = 0i32;
+    let formatted = buf.ryu.format_finite(abs);
+    let (mantissa, exp10) = match formatted.split_once('e') {
+        Some((m, e)) => (m, e.parse::<i32>().expect("ryu exponent")),
+        None => (formatted, 0),
+    };
+    for &c in mantissa.as_bytes() {
+        if c == b'.' {
+            int_len = Some(seen);
+            continue;
+        }
+        seen += 1;
+        if ndigits == 0 && c == b'0' {
+            leading_zeros += 1;
+        } else {
+            digits[ndigits] = c;
+            ndigits += 1;
+        }
+    }
+    while ndigits > 1 && digits[ndigits - 1] == b'0' {
+        ndigits -= 1;
+    }
+    let digits = &digits[..ndigits];
+    // `point` is the decimal exponent of the first significant digit and `k`
+    // that of the last, matching fpconv's `K`.
+    let point = exp10 + int_len.unwrap_or(seen) - 1 - leading_zeros;
+    let k = point - ndigits as i32 + 1;
+    let exp = point.abs();
+
+    if k >= 0 && exp < ndigits as i32 + 7 {
+        buf.extend(digits);
+        for _ in 0..k {
+            buf.push(b'0');
+        }
+    } else if k < 0 && (k > -7 || exp < 4) {
+        let offset = ndigits as i32 + k;
+        if offset <= 0 {
+            buf.extend(b"0.");
+            for _ in 0..-offset {
+                buf.push(b'0');
+            }
+            buf.extend(digits);
+        } else {
+            let (int, frac) = digits.split_at(offset as usize);
+            buf.extend(int);
+            buf.push(b'.');
+            buf.extend(frac);
+        }
+    } else {
+        buf.push(digits[0]);
+        if ndigits > 1 {
+            buf.push(b'.');
+            buf.extend(&digits[1..]);
+        }
+        buf.push(b'e');
+        buf.push(if point < 0 { b'-' } else { b'+' });
+        let exp = exp as u32;
+        if exp >= 100 {
+            buf.push(b'0' + (exp / 100) as u8);
+        }
+        if exp >= 10 {
+            buf.push(b'0' + (exp / 10 % 10) as u8);
+        }
+        buf.push(b'0' + (exp % 10) as u8);
+    }
+    buf.as_str()
+}
+
+/// Append `score` to `out` the way C's `printf("%.{precision}g")` would.
+///
+/// Use a precision of 17 to reproduce the classic Redis double replies. Any
+/// precision of 17 or more round-trips exactly, but unlike [`fmt_f64`] the
+/// output is not necessarily the shortest one.
+pub fn fmt_f64_precision(out: &mut String, score: f64, precision: usize) {
     debug_assert!(score.is_finite());
-    let formatted = buf.format_finite(score);
-    formatted.strip_suffix(".0").unwrap_or(formatted)
+    let precision = precision.max(1);
+    if score == 0.0 {
+        out.push_str(if score.is_sign_negative() { "-0" } else { "0" });
+        return;
+    }
+
+    let digits = precision - 1;
+    let sci = format!("{score:.digits$e}");
+    let (mantissa, exp) = sci.split_once('e').expect("exponent marker");
+    let exp: i32 = exp.parse().expect("exponent digits");
+    if exp < -4 || exp >= precision as i32 {
+        out.push_str(trim_fraction(mantissa));
+        out.push('e');
+        out.push(if exp < 0 { '-' } else { '+' });
+        let abs = exp.unsigned_abs();
+        if abs < 10 {
+            out.push('0');
+        }
+        out.push_str(&abs.to_string());
+    } else {
+        let decimals = (precision as i32 - 1 - exp) as usize;
+        let fixed = format!("{score:.decimals$}");
+        out.push_str(trim_fraction(&fixed));
+    }
+}
+
+#[inline]
+fn trim_fraction(s: &str) -> &str {
+    if s.contains('.') {
+        s.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        s
+    }
 }
 
 thread_local! {
-    static FMT_BUF: RefCell<Buffer> = RefCell::new(Buffer::new());
+    static FMT_BUF: RefCell<FmtBuf> = RefCell::new(FmtBuf::default());
 }
 
 #[inline]
 pub fn with_fmt_buf<F, R>(f: F) -> R
 where
-    F: FnOnce(&mut Buffer) -> R,
+    F: FnOnce(&mut FmtBuf) -> R,
 {
     FMT_BUF.with(|b| f(&mut b.borrow_mut()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+    use std::os::raw::c_char;
+
+    const SAMPLES: &[f64] = &[
+        0.0,
+        -0.0,
+        1.0,
+        -1.0,
+        0.1,
+        0.1 + 0.2,
+        1.5,
+        -2.75,
+        100.0,
+        123456789.0,
+        1e15,
+        1e16,
+        1e17,
+        1e21,
+        1e-4,
+        1e-5,
+        1.0 / 3.0,
+        std::f64::consts::PI,
+        9007199254740993.0,
+        f64::EPSILON,
+        f64::MIN_POSITIVE,
+        5e-324,
+        2.225_073_858_507_201e-308,
+        f64::MAX,
+        f64::MIN,
+    ];
+
+    fn c_g(score: f64, precision: usize) -> String {
+        let mut buf = [0 as c_char; 64];
+        let fmt = format!("%.{precision}g\0");
+        unsafe {
+            libc::snprintf(
+                buf.as_mut_ptr(),
+                buf.len(),
+                fmt.as_ptr() as *const c_char,
+                score,
+            );
+            CStr::from_ptr(buf.as_ptr()).to_str().unwrap().to_owned()
+        }
+    }
+
+    #[test]
+    fn shortest_round_trips_exact_bits() {
+        for &score in SAMPLES {
+            let shortest = with_fmt_buf(|b| fmt_f64(b, score).to_owned());
+            let parsed: f64 = shortest.parse().unwrap();
+            assert_eq!(parsed.to_bits(), score.to_bits(), "{score:e} -> {shortest}");
+
+            let redis_style: f64 = c_g(score, 17).parse().unwrap();
+            assert_eq!(parsed.to_bits(), redis_style.to_bits(), "{shortest}");
+        }
+    }
+
+    #[test]
+    fn random_bits_round_trip() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+        let mut rng = StdRng::seed_from_u64(0x5c0e);
+        for _ in 0..100_000 {
+            let score = f64::from_bits(rng.gen());
+            if score.is_nan() {
+                continue;
+            }
+            let out = with_fmt_buf(|b| fmt_f64(b, score).to_owned());
+            let parsed: f64 = out.parse().unwrap();
+            assert_eq!(parsed.to_bits(), score.to_bits(), "{score:e} -> {out}");
+        }
+    }
+
+    #[test]
+    fn matches_server_d2string_layout() {
+        let cases: &[(f64, &str)] = &[
+            (0.0, "0"),
+            (-0.0, "-0"),
+            (1.0, "1"),
+            (3.0, "3"),
+            (1.5, "1.5"),
+            (1e10, "10000000000"),
+            (-42.0, "-42"),
+            (-1.5, "-1.5"),
+            (0.1, "0.1"),
+            (0.1 + 0.2, "0.30000000000000004"),
+            (1e-4, "0.0001"),
+            (1e-6, "0.000001"),
+            (1e-7, "1e-7"),
+            (1.5e-7, "1.5e-7"),
+            (0.00123456789, "0.00123456789"),
+            (123456.789, "123456.789"),
+            (1e15, "1000000000000000"),
+            (4611686018427387904.0, "4611686018427387904"),
+            (9223372036854775808.0, "9223372036854776000"),
+            (1e21, "1e+21"),
+            (1.5e300, "1.5e+300"),
+            (f64::MAX, "1.7976931348623157e+308"),
+            (f64::MIN, "-1.7976931348623157e+308"),
+            (f64::MIN_POSITIVE, "2.2250738585072014e-308"),
+            (5e-324, "5e-324"),
+            (f64::EPSILON, "2.220446049250313e-16"),
+            (std::f64::consts::PI, "3.141592653589793"),
+            (f64::INFINITY, "inf"),
+            (f64::NEG_INFINITY, "-inf"),
+        ];
+        for &(score, want) in cases {
+            assert_eq!(with_fmt_buf(|b| fmt_f64(b, score).to_owned()), want);
+        }
+    }
+
+    #[test]
+    fn precision_matches_printf_g() {
+        for &score in SAMPLES {
+            for precision in [1, 6, 15, 17] {
+                let mut out = String::new();
+                fmt_f64_precision(&mut out, score, precision);
+                assert_eq!(out, c_g(score, precision), "{score:e} at %.{precision}g");
+            }
+        }
+    }
+}
@@ -8,18 +8,29 @@ static GLOBAL: redis_module::alloc::RedisAlloc = redis_module::alloc::RedisAlloc
 
 pub use crate::{
     command::register_commands,
-    format::{fmt_f64, with_fmt_buf},
-    pool::{FastHashMap, MemberId, StringPool},
-    score_set::{RangeIterFwd, ScoreIter, ScoreSet},
+    format::{fmt_f64, fmt_f64_precision, with_fmt_buf, FmtBuf},
+    persist::{DecodeError, ENCODING_COMPACT, ENCODING_PAIRS, ENCODING_VERSION},
+    pool::{FastHashMap, IdsExhausted, MemberId, StringPool},
+    score_set::{
+        Aggregate, Drain, IncrError, MembersInRange, RangeIterFwd, ScoreIter, ScoreOp, ScoreSet,
+        SortedPairsError, UpdateCond, UpsertOutcome,
+    },
 };
 
+#[cfg(feature = "memory-introspection")]
+pub use crate::score_set::MemBreakdown;
+
 #[cfg(feature = "bench-internals")]
 #[doc(hidden)]
 pub use crate::score_set::RankFind;
 
 mod buckets;
 mod command;
+#[doc(hidden)]
+pub mod dev_support;
 mod format;
 mod memory;
+mod persist;
 mod pool;
+mod range;
 mod score_set;
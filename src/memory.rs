@@ -6,6 +6,7 @@ use crate::{
 use redis_module::raw::RedisModule_MallocSize;
 use std::mem::size_of;
 use std::os::raw::c_void;
+use std::sync::atomic::{AtomicI64, Ordering};
 
 #[inline]
 const fn size_class(bytes: usize) -> usize {
@@ -25,13 +26,86 @@ unsafe fn ms(ptr: *const c_void) -> usize {
     }
 }
 
+/// What one stored set adds to the `INFO gzset` totals. Every field is O(1)
+/// to read, so writes can keep the totals current.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Footprint {
+    pub(crate) keys: i64,
+    pub(crate) members: i64,
+    pub(crate) bytes: i64,
+    pub(crate) spilled_buckets: i64,
+}
+
+impl Footprint {
+    pub(crate) fn of(set: &ScoreSet) -> Self {
+        Self {
+            keys: 1,
+            members: set.len() as i64,
+            bytes: approx_heap_bytes(set) as i64,
+            spilled_buckets: set.spilled_bucket_count() as i64,
+        }
+    }
+}
+
+static TOTAL_KEYS: AtomicI64 = AtomicI64::new(0);
+static TOTAL_MEMBERS: AtomicI64 = AtomicI64::new(0);
+static TOTAL_BYTES: AtomicI64 = AtomicI64::new(0);
+static TOTAL_SPILLED_BUCKETS: AtomicI64 = AtomicI64::new(0);
+
+/// Move the totals from a set's `before` footprint to its `after` one. A set
+/// entering the keyspace goes from `Footprint::default()`; `gzset_free` takes
+/// it back out, so the totals cover exactly the stored sets.
+pub(crate) fn track(before: Footprint, after: Footprint) {
+    TOTAL_KEYS.fetch_add(after.keys - before.keys, Ordering::Relaxed);
+    TOTAL_MEMBERS.fetch_add(after.members - before.members, Ordering::Relaxed);
+    TOTAL_BYTES.fetch_add(after.bytes - before.bytes, Ordering::Relaxed);
+    TOTAL_SPILLED_BUCKETS.fetch_add(
+        after.spilled_buckets - before.spilled_buckets,
+        Ordering::Relaxed,
+    );
+}
+
+/// The summed footprint of every stored set.
+pub(crate) fn totals() -> Footprint {
+    Footprint {
+        keys: TOTAL_KEYS.load(Ordering::Relaxed),
+        members: TOTAL_MEMBERS.load(Ordering::Relaxed),
+        bytes: TOTAL_BYTES.load(Ordering::Relaxed),
+        spilled_buckets: TOTAL_SPILLED_BUCKETS.load(Ordering::Relaxed),
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn gzset_free(value: *mut c_void) {
     if !value.is_null() {
-        drop(Box::from_raw(value as *mut ScoreSet));
+        let set = Box::from_raw(value as *mut ScoreSet);
+        track(Footprint::of(&set), Footprint::default());
+        drop(set);
     }
 }
 
+/// Heap bytes of `set` from container sizes alone: the tracked
+/// [`ScoreSet::mem_bytes`] plus the string pool and bucket store vectors.
+/// Unlike [`gzset_mem_usage`] it skips allocator size-class rounding and
+/// never walks the arena, so it stays O(1).
+fn approx_heap_bytes(set: &ScoreSet) -> usize {
+    let table = &set.pool.table;
+    let table_bytes = if table.buckets() > 0 {
+        table.allocation_info().1.size()
+    } else {
+        0
+    };
+    let bs = &set.bucket_store;
+    size_of::<ScoreSet>()
+        + set.mem_bytes()
+        + set.pool.arena_bytes()
+        + table_bytes
+        + set.pool.index.capacity() * size_of::<Option<IndexEntry>>()
+        + set.pool.free_ids.capacity() * size_of::<crate::pool::MemberId>()
+        + bs.buckets.capacity() * size_of::<Option<Bucket>>()
+        + bs.free.capacity() * size_of::<crate::buckets::BucketId>()
+}
+
 unsafe fn heap_size_of_score_set(set: &ScoreSet) -> usize {
     let mut total = ms(set as *const _ as *const _);
 
@@ -0,0 +1,470 @@
+//! Byte payloads for persisting a [`ScoreSet`].
+//!
+//! Every payload starts with a one-byte encoding tag so a loader can accept
+//! any format written by an earlier release:
+//!
+//! * [`ENCODING_PAIRS`]: member count, then each member as its score's
+//!   eight little-endian bytes followed by the length-prefixed name.
+//! * [`ENCODING_COMPACT`]: members grouped by score in ascending order. Each
+//!   group stores the gap from the previous score as a varint over an
+//!   order-preserving mapping of the `f64` bits, then its member count and
+//!   the length-prefixed names. Ties share one score, so clustered sets
+//!   shrink the most.
+//!
+//! Counts and lengths are LEB128 varints. Nothing here is wired to the RDB
+//! callbacks yet.
+//!
+//! The RDB record around a payload carries the data type's `encver`
+//! ([`ENCODING_VERSION`]). [`ScoreSet::deserialize_rdb`] refuses versions
+//! from a newer release up front. Layout changes that old loaders can simply
+//! refuse bump the version; a change that must not reach them at all would
+//! register a second type name (`gzsetmod2`) next to [`TYPE_NAME`], so dumps
+//! of the old type keep loading.
+
+use std::fmt;
+
+use crate::score_set::{ScoreSet, SortedPairsError};
+
+/// Name the module data type registers under. Redis derives the type id
+/// stored in each RDB record from it, so it never changes for existing dumps.
+pub(crate) const TYPE_NAME: &str = "gzsetmod1";
+
+/// `encver` the data type registers with, and the newest one the loader
+/// accepts.
+pub const ENCODING_VERSION: i32 = 0;
+
+/// Tag of the per-pair payload written by [`ScoreSet::serialize_pairs`].
+pub const ENCODING_PAIRS: u8 = 1;
+/// Tag of the grouped, delta-coded payload written by
+/// [`ScoreSet::serialize_compact`].
+pub const ENCODING_COMPACT: u8 = 2;
+
+/// Why [`ScoreSet::deserialize_compact`] rejected a payload.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The payload ended in the middle of a value.
+    Truncated,
+    /// The leading tag names no known encoding.
+    UnknownEncoding(u8),
+    /// A varint does not fit in 64 bits, or a count does not fit in memory.
+    Overflow,
+    /// A score decoded to NaN, or a compact group did not increase the score.
+    InvalidScore,
+    /// A member name is not valid UTF-8.
+    InvalidMember,
+    /// A member appears more than once.
+    DuplicateMember,
+    /// Bytes remain after the last member.
+    TrailingBytes,
+    /// The RDB record was written with an `encver` this release does not know.
+    UnsupportedVersion(i32),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Truncated => f.write_str("payload is truncated"),
+            DecodeError::UnknownEncoding(tag) => write!(f, "unknown encoding tag {tag}"),
+            DecodeError::Overflow => f.write_str("length or count overflows"),
+            DecodeError::InvalidScore => f.write_str("invalid score"),
+            DecodeError::InvalidMember => f.write_str("member is not valid UTF-8"),
+            DecodeError::DuplicateMember => f.write_str("a member appears more than once"),
+            DecodeError::TrailingBytes => f.write_str("trailing bytes after payload"),
+            DecodeError::UnsupportedVersion(encver) => {
+                write!(f, "unsupported encoding version {encver}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Map `f64` bits onto `u64` so that integer order matches score order.
+#[inline]
+fn ordered_bits(score: f64) -> u64 {
+    let bits = score.to_bits();
+    if bits >> 63 == 1 {
+        !bits
+    } else {
+        bits | (1 << 63)
+    }
+}
+
+#[inline]
+fn from_ordered_bits(key: u64) -> f64 {
+    let bits = if key >> 63 == 1 {
+        key & !(1 << 63)
+    } else {
+        !key
+    };
+    f64::from_bits(bits)
+}
+
+fn put_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn put_member(out: &mut Vec<u8>, member: &str) {
+    put_varint(out, member.len() as u64);
+    out.extend_from_slice(member.as_bytes());
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], DecodeError> {
+        if n > self.bytes.len() {
+            return Err(DecodeError::Truncated);
+        }
+        let (head, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Ok(head)
+    }
+
+    fn varint(&mut self) -> Result<u64, DecodeError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            let low = u64::from(byte & 0x7f);
+            if shift == 63 && low > 1 {
+                return Err(DecodeError::Overflow);
+            }
+            value |= low << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(DecodeError::Overflow)
+    }
+
+    fn len(&mut self) -> Result<usize, DecodeError> {
+        usize::try_from(self.varint()?).map_err(|_| DecodeError::Overflow)
+    }
+
+    fn member(&mut self) -> Result<&'a str, DecodeError> {
+        let len = self.len()?;
+        std::str::from_utf8(self.take(len)?).map_err(|_| DecodeError::InvalidMember)
+    }
+
+    fn score(&mut self) -> Result<f64, DecodeError> {
+        let raw = self.take(8)?;
+        let score = f64::from_le_bytes(raw.try_into().expect("took eight bytes"));
+        if score.is_nan() {
+            return Err(DecodeError::InvalidScore);
+        }
+        Ok(score)
+    }
+}
+
+impl ScoreSet {
+    /// Encode every member with its own score ([`ENCODING_PAIRS`]).
+    pub fn serialize_pairs(&self) -> Vec<u8> {
+        let mut out = vec![ENCODING_PAIRS];
+        put_varint(&mut out, self.len() as u64);
+        for (member, score) in self.iter_all() {
+            out.extend_from_slice(&score.to_le_bytes());
+            put_member(&mut out, member);
+        }
+        out
+    }
+
+    /// Encode members grouped by score with delta-coded scores
+    /// ([`ENCODING_COMPACT`]).
+    ///
+    /// ```
+    /// use gzset::ScoreSet;
+    ///
+    /// let mut set = ScoreSet::default();
+    /// set.insert(1.0, "a");
+    /// set.insert(1.0, "b");
+    /// set.insert(2.5, "c");
+    /// let bytes = set.serialize_compact();
+    /// assert!(bytes.len() < set.serialize_pairs().len());
+    /// let back = ScoreSet::deserialize_compact(&bytes).unwrap();
+    /// assert!(back.iter_all().eq(set.iter_all()));
+    /// ```
+    pub fn serialize_compact(&self) -> Vec<u8> {
+        let mut out = vec![ENCODING_COMPACT];
+        let mut groups: Vec<(f64, Vec<&str>)> = Vec::new();
+        for (member, score) in self.iter_all() {
+            match groups.last_mut() {
+                Some((last, members)) if *last == score => members.push(member),
+                _ => groups.push((score, vec![member])),
+            }
+        }
+        put_varint(&mut out, groups.len() as u64);
+        let mut prev = 0u64;
+        for (score, members) in &groups {
+            let key = ordered_bits(*score);
+            put_varint(&mut out, key - prev);
+            prev = key;
+            put_varint(&mut out, members.len() as u64);
+            for member in members {
+                put_member(&mut out, member);
+            }
+        }
+        out
+    }
+
+    /// Rebuild a set from a payload of either encoding, so payloads written
+    /// before [`ENCODING_COMPACT`] existed still load.
+    pub fn deserialize_compact(bytes: &[u8]) -> Result<ScoreSet, DecodeError> {
+        let mut reader = Reader { bytes };
+        let tag = reader.take(1)?[0];
+        let mut items: Vec<(f64, &str)> = Vec::new();
+        match tag {
+            ENCODING_PAIRS => {
+                let count = reader.len()?;
+                // Every pair takes at least nine bytes, so a hostile count
+                // cannot reserve more than the payload could hold.
+                items.reserve(count.min(reader.bytes.len() / 9));
+                for _ in 0..count {
+                    let score = reader.score()?;
+                    items.push((score, reader.member()?));
+                }
+            }
+            ENCODING_COMPACT => {
+                let groups = reader.len()?;
+                let mut prev = 0u64;
+                for group in 0..groups {
+                    let delta = reader.varint()?;
+                    if group > 0 && delta == 0 {
+                        return Err(DecodeError::InvalidScore);
+                    }
+                    prev = prev.checked_add(delta).ok_or(DecodeError::InvalidScore)?;
+                    let score = from_ordered_bits(prev);
+                    if score.is_nan() {
+                        return Err(DecodeError::InvalidScore);
+                    }
+                    let members = reader.len()?;
+                    items.reserve(members.min(reader.bytes.len()));
+                    for _ in 0..members {
+                        items.push((score, reader.member()?));
+                    }
+                }
+            }
+            other => return Err(DecodeError::UnknownEncoding(other)),
+        }
+        if !reader.bytes.is_empty() {
+            return Err(DecodeError::TrailingBytes);
+        }
+        // Both encodings are written in `iter_all` order, so a well-formed
+        // payload takes the checked bulk path. Anything else still loads
+        // through `insert_many`, as before. Neither encoding can repeat a
+        // member, so a repeat is corruption rather than a score update.
+        match ScoreSet::from_sorted_pairs(&items) {
+            Ok(set) => Ok(set),
+            Err(SortedPairsError::DuplicateMember) => Err(DecodeError::DuplicateMember),
+            Err(SortedPairsError::OutOfOrder(_)) => {
+                let mut set = ScoreSet::default();
+                if set.insert_many(items.iter().copied()) != items.len() {
+                    return Err(DecodeError::DuplicateMember);
+                }
+                Ok(set)
+            }
+        }
+    }
+
+    /// Rebuild a set from an RDB record written with `encver`.
+    ///
+    /// A version newer than [`ENCODING_VERSION`] fails before the payload is
+    /// read, since its layout may not match either encoding tag. The type
+    /// registers no RDB callbacks yet; this is groundwork for a future
+    /// `rdb_load`, which would turn any error into a null value so Redis
+    /// aborts the load.
+    pub fn deserialize_rdb(encver: i32, bytes: &[u8]) -> Result<ScoreSet, DecodeError> {
+        if !(0..=ENCODING_VERSION).contains(&encver) {
+            return Err(DecodeError::UnsupportedVersion(encver));
+        }
+        Self::deserialize_compact(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    fn build(pairs: &[(f64, String)]) -> ScoreSet {
+        let mut set = ScoreSet::default();
+        set.insert_many(pairs.iter().map(|(s, m)| (*s, m.as_str())));
+        set
+    }
+
+    fn decode_err(bytes: &[u8]) -> DecodeError {
+        match ScoreSet::deserialize_compact(bytes) {
+            Ok(_) => panic!("malformed payload decoded"),
+            Err(err) => err,
+        }
+    }
+
+    fn assert_round_trips(set: &ScoreSet) {
+        for bytes in [set.serialize_pairs(), set.serialize_compact()] {
+            let back = ScoreSet::deserialize_compact(&bytes).unwrap();
+            assert_eq!(back.len(), set.len());
+            assert!(back.iter_all().eq(set.iter_all()));
+        }
+    }
+
+    #[test]
+    fn ordered_bits_preserve_order() {
+        let scores = [
+            f64::NEG_INFINITY,
+            -1e300,
+            -1.0,
+            -f64::MIN_POSITIVE,
+            -0.0,
+            0.0,
+            f64::MIN_POSITIVE,
+            1.0,
+            1.5,
+            1e300,
+            f64::INFINITY,
+        ];
+        for pair in scores.windows(2) {
+            assert!(ordered_bits(pair[0]) < ordered_bits(pair[1]), "{pair:?}");
+        }
+        for score in scores {
+            assert_eq!(
+                from_ordered_bits(ordered_bits(score)).to_bits(),
+                score.to_bits()
+            );
+        }
+    }
+
+    #[test]
+    fn round_trips_across_distributions() {
+        let mut rng = StdRng::seed_from_u64(0x1853);
+        let uniform: Vec<(f64, String)> = (0..2000)
+            .map(|i| (rng.gen_range(-1e6..1e6), format!("u{i}")))
+            .collect();
+        let clustered: Vec<(f64, String)> = (0..2000)
+            .map(|i| (rng.gen_range(0..8) as f64, format!("c{i}")))
+            .collect();
+        let tied: Vec<(f64, String)> = (0..500).map(|i| (7.0, format!("t{i}"))).collect();
+        let edges: Vec<(f64, String)> = [
+            (f64::NEG_INFINITY, ""),
+            (-0.0, "neg-zero"),
+            (0.0, "zero"),
+            (f64::INFINITY, "inf"),
+            (1.0, "ünïcödé"),
+        ]
+        .iter()
+        .map(|(s, m)| (*s, m.to_string()))
+        .collect();
+
+        assert_round_trips(&ScoreSet::default());
+        for pairs in [&uniform, &clustered, &tied, &edges] {
+            assert_round_trips(&build(pairs));
+        }
+    }
+
+    #[test]
+    fn compact_is_smaller_for_clustered_scores() {
+        let pairs: Vec<(f64, String)> = (0..10_000)
+            .map(|i| ((i % 16) as f64, format!("member:{i}")))
+            .collect();
+        let set = build(&pairs);
+        let naive = set.serialize_pairs().len();
+        let compact = set.serialize_compact().len();
+        assert!(compact < naive, "compact {compact} >= pairs {naive}");
+    }
+
+    #[test]
+    fn rejects_malformed_payloads() {
+        let mut set = ScoreSet::default();
+        set.insert(1.0, "a");
+        set.insert(2.0, "b");
+
+        assert_eq!(decode_err(&[]), DecodeError::Truncated);
+        assert_eq!(decode_err(&[9]), DecodeError::UnknownEncoding(9));
+        for bytes in [set.serialize_pairs(), set.serialize_compact()] {
+            for cut in 1..bytes.len() {
+                assert_eq!(decode_err(&bytes[..cut]), DecodeError::Truncated);
+            }
+            let mut extra = bytes.clone();
+            extra.push(0);
+            assert_eq!(decode_err(&extra), DecodeError::TrailingBytes);
+        }
+
+        let mut nan = vec![ENCODING_PAIRS, 1];
+        nan.extend_from_slice(&f64::NAN.to_le_bytes());
+        nan.extend_from_slice(&[1, b'a']);
+        assert_eq!(decode_err(&nan), DecodeError::InvalidScore);
+
+        let mut bad_utf8 = vec![ENCODING_PAIRS, 1];
+        bad_utf8.extend_from_slice(&1.0f64.to_le_bytes());
+        bad_utf8.extend_from_slice(&[1, 0xff]);
+        assert_eq!(decode_err(&bad_utf8), DecodeError::InvalidMember);
+    }
+
+    #[test]
+    fn unsorted_pairs_payload_matches_checked_inserts() {
+        let pairs: [(f64, &str); 5] = [(3.0, "c"), (1.0, "a"), (2.0, "b"), (-1.0, "e"), (2.0, "d")];
+        let mut bytes = vec![ENCODING_PAIRS];
+        put_varint(&mut bytes, pairs.len() as u64);
+        let mut expected = ScoreSet::default();
+        for (score, member) in pairs {
+            bytes.extend_from_slice(&score.to_le_bytes());
+            put_member(&mut bytes, member);
+            expected.insert(score, member);
+        }
+
+        let back = ScoreSet::deserialize_compact(&bytes).unwrap();
+        back.validate();
+        assert_eq!(back, expected);
+        assert_eq!(back.mem_bytes(), back.debug_mem_breakdown().structural());
+    }
+
+    #[test]
+    fn repeated_member_is_rejected() {
+        let payload = |pairs: &[(f64, &str)]| {
+            let mut bytes = vec![ENCODING_PAIRS];
+            put_varint(&mut bytes, pairs.len() as u64);
+            for (score, member) in pairs {
+                bytes.extend_from_slice(&score.to_le_bytes());
+                put_member(&mut bytes, member);
+            }
+            bytes
+        };
+        // Sorted, then unsorted, then the same member under one score.
+        for pairs in [
+            &[(1.0, "a"), (2.0, "a")][..],
+            &[(2.0, "b"), (1.0, "a"), (-1.0, "a")],
+            &[(1.0, "a"), (1.0, "a")],
+        ] {
+            assert_eq!(decode_err(&payload(pairs)), DecodeError::DuplicateMember);
+        }
+
+        let mut compact = vec![ENCODING_COMPACT, 2];
+        put_varint(&mut compact, ordered_bits(1.0));
+        compact.push(1);
+        put_member(&mut compact, "a");
+        put_varint(&mut compact, ordered_bits(2.0) - ordered_bits(1.0));
+        compact.push(1);
+        put_member(&mut compact, "a");
+        assert_eq!(decode_err(&compact), DecodeError::DuplicateMember);
+    }
+
+    #[test]
+    fn rdb_load_rejects_future_encver() {
+        let mut set = ScoreSet::default();
+        set.insert(1.0, "a");
+        let bytes = set.serialize_compact();
+
+        let back = ScoreSet::deserialize_rdb(ENCODING_VERSION, &bytes).unwrap();
+        assert!(back.iter_all().eq(set.iter_all()));
+        for encver in [ENCODING_VERSION + 1, i32::MAX, -1] {
+            match ScoreSet::deserialize_rdb(encver, &bytes) {
+                Ok(_) => panic!("encver {encver} decoded"),
+                Err(err) => assert_eq!(err, DecodeError::UnsupportedVersion(encver)),
+            }
+        }
+    }
+}
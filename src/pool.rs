@@ -3,6 +3,7 @@ use std::convert::TryInto;
 use std::fmt;
 use std::hash::{BuildHasher, Hasher};
 use std::str;
+use std::sync::atomic::{AtomicI64, Ordering};
 
 #[cfg(feature = "fast-hash")]
 use rustc_hash::FxHasher;
@@ -22,6 +23,21 @@ pub type FastHashMap<K, V> = HashMap<K, V, Build>;
 
 pub type MemberId = u32;
 
+/// Number of distinct ids a pool can hand out.
+pub(crate) const MAX_IDS: usize = MemberId::MAX as usize + 1;
+
+/// Returned by [`StringPool::try_intern`] when every [`MemberId`] is in use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IdsExhausted;
+
+impl fmt::Display for IdsExhausted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("string pool ran out of member ids")
+    }
+}
+
+impl std::error::Error for IdsExhausted {}
+
 // Encodes location inside the arena.
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub(crate) struct Loc {
@@ -42,17 +58,36 @@ pub(crate) struct IndexEntry {
     loc: Loc,
 }
 
-// Arena parameters
-// Smaller chunks reduce worst-case slack kept in the last arena slice.
-const ARENA_CHUNK: usize = 1024 * 1024; // 1 MiB
+/// Default for the `gzset.arena-chunk-size` module config, in bytes.
+/// Smaller chunks reduce worst-case slack kept in the last arena slice.
+pub(crate) const ARENA_CHUNK_SIZE_DEFAULT: i64 = 1024 * 1024; // 1 MiB
+/// Lower bound for the configured arena chunk size.
+pub(crate) const ARENA_CHUNK_SIZE_MIN: i64 = 4 * 1024;
+/// Upper bound for the configured arena chunk size.
+pub(crate) const ARENA_CHUNK_SIZE_MAX: i64 = 64 * 1024 * 1024;
+/// Arena chunk size for newly created pools. Backs the
+/// `gzset.arena-chunk-size` module config.
+pub(crate) static ARENA_CHUNK_SIZE: AtomicI64 = AtomicI64::new(ARENA_CHUNK_SIZE_DEFAULT);
+
+/// Current arena chunk size, clamped to the supported range.
+#[inline]
+pub(crate) fn arena_chunk_size() -> usize {
+    ARENA_CHUNK_SIZE
+        .load(Ordering::Relaxed)
+        .clamp(ARENA_CHUNK_SIZE_MIN, ARENA_CHUNK_SIZE_MAX) as usize
+}
 
 pub struct StringPool {
     hasher: Build,
     // Big append-only chunks for string bytes
     pub(crate) arena: Vec<Box<[u8]>>,
-    // Current write head into last chunk
-    write_chunk: usize, // index into arena
-    write_off: usize,   // offset into arena[write_chunk]
+    // Size of regular arena chunks; longer strings get a chunk of their own
+    chunk_size: usize,
+    // Current write head; None until the first regular chunk exists
+    write_chunk: Option<usize>, // index into arena
+    write_off: usize,           // offset into arena[write_chunk]
+    // Summed length of the arena chunks
+    arena_bytes: usize,
     // Key lookup table: compares by bytes in arena
     pub(crate) table: RawTable<KeyEntry>,
     // id -> Loc mapping (None when freed)
@@ -61,19 +96,37 @@ pub struct StringPool {
     pub(crate) free_ids: Vec<MemberId>,
     // Fast length (live members)
     len: usize,
+    // Test hook lowering MAX_IDS so exhaustion can be exercised.
+    #[cfg(test)]
+    pub(crate) id_limit: usize,
 }
 
 impl Default for StringPool {
+    /// An empty pool using the configured `gzset.arena-chunk-size`.
     fn default() -> Self {
+        Self::with_chunk_size(arena_chunk_size())
+    }
+}
+
+impl StringPool {
+    /// An empty pool that stores member bytes in arena chunks of
+    /// `chunk_size` bytes. A string longer than that is given an exact-sized
+    /// chunk of its own. The size is capped at `u32::MAX`, the largest
+    /// offset a location can hold.
+    pub fn with_chunk_size(chunk_size: usize) -> Self {
         Self {
             hasher: Build::default(),
             arena: Vec::new(),
-            write_chunk: 0,
+            chunk_size: chunk_size.clamp(1, u32::MAX as usize),
+            write_chunk: None,
             write_off: 0,
+            arena_bytes: 0,
             table: RawTable::new(),
             index: Vec::new(),
             free_ids: Vec::new(),
             len: 0,
+            #[cfg(test)]
+            id_limit: MAX_IDS,
         }
     }
 }
@@ -82,6 +135,7 @@ impl fmt::Debug for StringPool {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("StringPool")
             .field("arena_chunks", &self.arena.len())
+            .field("chunk_size", &self.chunk_size)
             .field("write_chunk", &self.write_chunk)
             .field("write_off", &self.write_off)
             .field("len", &self.len)
@@ -91,7 +145,24 @@ impl fmt::Debug for StringPool {
 }
 
 impl StringPool {
+    /// Size of regular arena chunks in bytes.
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Intern `s`, returning its id.
+    ///
+    /// # Panics
+    ///
+    /// Panics if all ids are in use; see [`try_intern`](Self::try_intern).
     pub fn intern(&mut self, s: &str) -> MemberId {
+        self.try_intern(s).expect("too many members in string pool")
+    }
+
+    /// Intern `s`, returning its id, or [`IdsExhausted`] if `s` is new and
+    /// every id is live. Freed ids are reused before new ones are handed out,
+    /// so this only fails with that many members present at once.
+    pub fn try_intern(&mut self, s: &str) -> Result<MemberId, IdsExhausted> {
         let bytes = s.as_bytes();
         let hash = self.hash_bytes(bytes);
         if let Some(entry) = self.table.get(hash, |entry| {
@@ -99,24 +170,39 @@ impl StringPool {
             self.index_entry(entry.id)
                 .is_some_and(|index_entry| self.loc_bytes(index_entry.loc) == bytes)
         }) {
-            return entry.id;
+            return Ok(entry.id);
         }
 
-        let loc = self.write_bytes(bytes);
-        let id = if let Some(id) = self.free_ids.pop() {
-            self.index[id as usize] = Some(IndexEntry { loc });
-            id
-        } else {
-            let idx = self.index.len();
-            let id: MemberId = idx.try_into().expect("too many members in string pool");
-            self.index.push(Some(IndexEntry { loc }));
-            id
+        let id = match self.free_ids.pop() {
+            Some(id) => id,
+            None => {
+                let idx = self.index.len();
+                if idx >= self.id_limit() {
+                    return Err(IdsExhausted);
+                }
+                self.index.push(None);
+                idx as MemberId
+            }
         };
+        let loc = self.write_bytes(bytes);
+        self.index[id as usize] = Some(IndexEntry { loc });
 
         self.table
             .insert(hash, KeyEntry { hash, id }, |entry| entry.hash);
         self.len += 1;
-        id
+        Ok(id)
+    }
+
+    #[inline]
+    fn id_limit(&self) -> usize {
+        #[cfg(test)]
+        {
+            self.id_limit
+        }
+        #[cfg(not(test))]
+        {
+            MAX_IDS
+        }
     }
 
     pub fn lookup(&self, s: &str) -> Option<MemberId> {
@@ -166,10 +252,50 @@ impl StringPool {
         Some(len)
     }
 
+    /// Reserve room for at least `additional` more members in the lookup
+    /// table and id index. Arena chunks are still allocated on demand.
+    pub(crate) fn reserve(&mut self, additional: usize) {
+        self.table.reserve(additional, |entry| entry.hash);
+        let spare = additional.saturating_sub(self.free_ids.len());
+        self.index.reserve(spare);
+    }
+
+    /// Release spare capacity in the lookup table, id index and free list,
+    /// and trim the last arena chunk to its used length. Bytes of removed
+    /// strings stay in the arena; rebuilding the pool is the only way to
+    /// reclaim those.
+    pub fn shrink_to_fit(&mut self) {
+        self.table.shrink_to(self.len, |entry| entry.hash);
+        self.index.shrink_to_fit();
+        self.free_ids.shrink_to_fit();
+        // A zero-length chunk may still be referenced by empty members, and
+        // would leave a dangling allocation pointer; keep it as is.
+        let used = self.write_off;
+        if let Some(chunk) = self.write_chunk.and_then(|idx| self.arena.get_mut(idx)) {
+            if used > 0 && used < chunk.len() {
+                self.arena_bytes -= chunk.len() - used;
+                *chunk = chunk[..used].into();
+            }
+        }
+    }
+
     pub fn len(&self) -> usize {
         self.len
     }
 
+    /// Bytes held by arena chunks, including the unused tail of the current
+    /// chunk and the bytes of removed strings. O(1).
+    pub fn arena_bytes(&self) -> usize {
+        self.arena_bytes
+    }
+
+    /// How many more distinct strings can be interned before
+    /// [`try_intern`](Self::try_intern) reports [`IdsExhausted`]. Freed ids
+    /// count as spare.
+    pub fn spare_ids(&self) -> usize {
+        self.id_limit() - self.len
+    }
+
     pub fn allocated_ids(&self) -> usize {
         self.index.len()
     }
@@ -178,6 +304,9 @@ impl StringPool {
         self.len == 0
     }
 
+    /// Live members in ascending [`MemberId`] order. The iterator borrows the
+    /// pool, so it is a snapshot; use [`next_from`](Self::next_from) to walk
+    /// the pool across mutations.
     pub fn iter(&self) -> impl Iterator<Item = (&str, MemberId)> + '_ {
         self.index
             .iter()
@@ -189,6 +318,26 @@ impl StringPool {
             })
     }
 
+    /// First live member with id `>= start`, for resumable walks in id order.
+    ///
+    /// Interning and removing never move a live member to another id or arena
+    /// position, so resuming from `id + 1` after arbitrary mutations visits
+    /// every member that stayed live throughout exactly once. A member
+    /// interned mid-walk is visited only if it lands on an id not yet passed
+    /// (a new id or a freed one ahead of the walk). Note that `ScoreSet`
+    /// replaces its pool with a renumbered one after mass deletes.
+    pub fn next_from(&self, start: MemberId) -> Option<(MemberId, &str)> {
+        let start = start as usize;
+        let tail = self.index.get(start..)?;
+        tail.iter().enumerate().find_map(|(off, entry)| {
+            let entry = entry.as_ref()?;
+            let id: MemberId = (start + off)
+                .try_into()
+                .expect("too many members in string pool");
+            Some((id, self.loc_str(entry.loc)))
+        })
+    }
+
     fn index_entry(&self, id: MemberId) -> Option<IndexEntry> {
         self.index
             .get(id as usize)
@@ -203,8 +352,26 @@ impl StringPool {
     }
 
     fn write_bytes(&mut self, bytes: &[u8]) -> Loc {
+        if bytes.len() > self.chunk_size {
+            // Store oversized strings in an exact-sized chunk and keep the
+            // write head where it is, so the current chunk's tail stays usable.
+            self.arena.push(bytes.into());
+            self.arena_bytes += bytes.len();
+            return Loc {
+                chunk: (self.arena.len() - 1)
+                    .try_into()
+                    .expect("too many chunks in string pool"),
+                off: 0,
+                len: bytes
+                    .len()
+                    .try_into()
+                    .expect("string exceeds supported length"),
+            };
+        }
         self.ensure_capacity(bytes.len());
-        let chunk_idx = self.write_chunk;
+        let chunk_idx = self
+            .write_chunk
+            .expect("ensure_capacity sets a write chunk");
         let start = self.write_off;
         let end = start
             .checked_add(bytes.len())
@@ -229,11 +396,11 @@ impl StringPool {
     }
 
     fn ensure_capacity(&mut self, needed: usize) {
-        if self.arena.is_empty() {
+        let Some(chunk_idx) = self.write_chunk else {
             self.add_chunk(needed);
             return;
-        }
-        let chunk_len = self.arena[self.write_chunk].len();
+        };
+        let chunk_len = self.arena[chunk_idx].len();
         let end = self
             .write_off
             .checked_add(needed)
@@ -244,10 +411,11 @@ impl StringPool {
     }
 
     fn add_chunk(&mut self, needed: usize) {
-        let size = ARENA_CHUNK.max(needed);
+        let size = self.chunk_size.max(needed);
         let chunk = vec![0u8; size].into_boxed_slice();
         self.arena.push(chunk);
-        self.write_chunk = self.arena.len() - 1;
+        self.arena_bytes += size;
+        self.write_chunk = Some(self.arena.len() - 1);
         self.write_off = 0;
     }
 
@@ -272,6 +440,78 @@ mod tests {
     use crate::{memory::gzset_mem_usage, score_set::ScoreSet};
     use std::os::raw::c_void;
 
+    #[test]
+    fn default_chunk_size_follows_config() {
+        assert_eq!(
+            StringPool::default().chunk_size(),
+            ARENA_CHUNK_SIZE_DEFAULT as usize
+        );
+        assert_eq!(StringPool::with_chunk_size(0).chunk_size(), 1);
+    }
+
+    #[test]
+    fn sub_chunk_strings_share_chunks() {
+        let mut pool = StringPool::with_chunk_size(64);
+        let names: Vec<String> = (0..20).map(|i| format!("member-{i:03}")).collect();
+        let ids: Vec<MemberId> = names.iter().map(|n| pool.intern(n)).collect();
+        // Six 10-byte names fit in each 64-byte chunk.
+        assert_eq!(pool.arena.len(), 4);
+        assert!(pool.arena.iter().all(|chunk| chunk.len() == 64));
+        for (id, name) in ids.iter().zip(&names) {
+            assert_eq!(pool.get(*id), name);
+            assert_eq!(pool.lookup(name), Some(*id));
+        }
+    }
+
+    #[test]
+    fn over_chunk_strings_get_exact_chunks() {
+        let mut pool = StringPool::with_chunk_size(64);
+        let big = "x".repeat(200);
+        let bigger = "y".repeat(65);
+        // An oversized first string must not become the write chunk.
+        let big_id = pool.intern(&big);
+        assert_eq!(pool.arena.len(), 1);
+        assert_eq!(pool.arena[0].len(), 200);
+        let a = pool.intern("small-a");
+        let bigger_id = pool.intern(&bigger);
+        let b = pool.intern("small-b");
+        assert_eq!(pool.arena.len(), 3, "small strings share one chunk");
+        assert_eq!(pool.arena[1].len(), 64);
+        assert_eq!(pool.arena[2].len(), 65);
+        assert_eq!(pool.get(big_id), big);
+        assert_eq!(pool.get(bigger_id), bigger);
+        assert_eq!(pool.get(a), "small-a");
+        assert_eq!(pool.get(b), "small-b");
+        assert_eq!(pool.lookup(&big), Some(big_id));
+
+        // A string exactly one chunk long still uses a regular chunk.
+        let exact = "z".repeat(64);
+        let exact_id = pool.intern(&exact);
+        assert_eq!(pool.arena.len(), 4);
+        assert_eq!(pool.get(exact_id), exact);
+
+        pool.shrink_to_fit();
+        let c = pool.intern("after-shrink");
+        assert_eq!(pool.get(c), "after-shrink");
+        assert_eq!(pool.get(big_id), big);
+    }
+
+    #[test]
+    fn arena_bytes_tracks_chunks() {
+        let summed = |pool: &StringPool| pool.arena.iter().map(|c| c.len()).sum::<usize>();
+        let mut pool = StringPool::with_chunk_size(64);
+        assert_eq!(pool.arena_bytes(), 0);
+        pool.intern("small");
+        pool.intern(&"x".repeat(100));
+        assert_eq!(pool.arena_bytes(), 64 + 100);
+        pool.shrink_to_fit();
+        assert_eq!(pool.arena_bytes(), 5 + 100);
+        assert_eq!(pool.arena_bytes(), summed(&pool));
+        pool.intern("next");
+        pool.remove("small");
+        assert_eq!(pool.arena_bytes(), summed(&pool));
+    }
+
     #[test]
     fn test_stringpool_reuse_and_reclaim() {
         const N: usize = 100;
@@ -328,4 +568,112 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn next_from_walk_survives_interleaved_mutation() {
+        use std::collections::HashSet;
+
+        let mut pool = StringPool::default();
+        let stable: HashSet<String> = (0..500).map(|i| format!("s{i}")).collect();
+        let mut stable_sorted: Vec<&String> = stable.iter().collect();
+        stable_sorted.sort();
+        for name in &stable_sorted {
+            pool.intern(name);
+        }
+        for i in 0..200 {
+            pool.intern(&format!("t{i}"));
+        }
+
+        let mut visited = Vec::new();
+        let mut next = 0;
+        let mut step = 0;
+        while let Some((id, name)) = pool.next_from(next) {
+            assert_eq!(pool.get(id), name);
+            visited.push(name.to_owned());
+            next = id + 1;
+            // Churn transient members, reusing freed ids and appending new
+            // ones, on every step of the walk.
+            if step < 200 {
+                assert!(pool.remove(&format!("t{step}")).is_some());
+            }
+            pool.intern(&format!("n{step}"));
+            if step % 3 == 0 {
+                assert!(pool.remove(&format!("n{step}")).is_some());
+            }
+            step += 1;
+        }
+
+        let seen_stable: Vec<&String> = visited.iter().filter(|m| stable.contains(*m)).collect();
+        assert_eq!(seen_stable.len(), stable.len(), "each stable member once");
+        let unique: HashSet<&String> = seen_stable.iter().copied().collect();
+        assert_eq!(unique.len(), stable.len());
+
+        let live: HashSet<String> = pool.iter().map(|(m, _)| m.to_owned()).collect();
+        assert!(stable.iter().all(|m| live.contains(m)));
+        assert_eq!(live.len(), pool.len());
+        assert!(pool.next_from(pool.allocated_ids() as MemberId).is_none());
+    }
+
+    #[test]
+    fn freed_ids_are_reused_under_steady_churn() {
+        const LIVE: usize = 1_000;
+        let mut pool = StringPool::default();
+        for i in 0..LIVE {
+            pool.intern(&format!("k{i}"));
+        }
+        for i in LIVE..200_000 {
+            assert!(pool.remove(&format!("k{}", i - LIVE)).is_some());
+            pool.intern(&format!("k{i}"));
+            assert!(pool.allocated_ids() <= LIVE, "ids must come from free list");
+        }
+        assert_eq!(pool.len(), LIVE);
+
+        let mut set = ScoreSet::default();
+        for i in 0..200_000usize {
+            set.insert((i % 97) as f64, &format!("k{i}"));
+            if i >= LIVE {
+                assert!(set.remove(&format!("k{}", i - LIVE)));
+            }
+        }
+        assert!(set.pool.allocated_ids() <= LIVE + 1);
+    }
+
+    #[test]
+    fn id_exhaustion_is_reported_not_panicked() {
+        let mut pool = StringPool {
+            id_limit: 3,
+            ..StringPool::default()
+        };
+        for name in ["a", "b", "c"] {
+            assert!(pool.try_intern(name).is_ok());
+        }
+        assert_eq!(pool.try_intern("d"), Err(IdsExhausted));
+        assert_eq!(
+            pool.try_intern("b"),
+            Ok(1),
+            "existing members still resolve"
+        );
+        assert_eq!(pool.len(), 3);
+        assert_eq!(pool.lookup("d"), None);
+        pool.remove("a");
+        assert_eq!(pool.spare_ids(), 1);
+        assert_eq!(pool.try_intern("d"), Ok(0), "freed id is reused");
+
+        let mut set = ScoreSet::default();
+        set.pool.id_limit = 2;
+        assert_eq!(set.try_insert(1.0, "x"), Ok(true));
+        assert_eq!(set.try_insert(2.0, "y"), Ok(true));
+        let mem = set.mem_bytes();
+        assert_eq!(set.try_insert(3.0, "z"), Err(IdsExhausted));
+        assert_eq!(
+            set.incr_by("z", 1.0, Default::default()),
+            Err(crate::IncrError::IdsExhausted)
+        );
+        assert_eq!(set.spare_ids(), 0);
+        assert_eq!(set.try_insert(5.0, "x"), Ok(true), "rescoring needs no id");
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.score("z"), None);
+        assert_eq!(set.mem_bytes(), mem);
+        assert_eq!(set.mem_bytes(), set.debug_mem_breakdown().structural());
+    }
 }
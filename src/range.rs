@@ -0,0 +1,61 @@
+//! Bound parsing shared by the score range commands.
+//!
+//! Mirrors `zslParseRange` in Redis so every range command accepts the same
+//! syntax and reports the same errors.
+
+use redis_module::{RedisError, RedisResult};
+
+pub(crate) const ERR_SCORE_BOUND: &str = "ERR min or max is not a float";
+
+/// Parse a score bound such as `1.5`, `(1.5`, `-inf` or `+inf`.
+///
+/// Returns the bound value and whether it is exclusive.
+pub(crate) fn parse_score_bound(raw: &str) -> RedisResult<(f64, bool)> {
+    let (digits, exclusive) = match raw.strip_prefix('(') {
+        Some(rest) => (rest, true),
+        None => (raw, false),
+    };
+    match digits.parse::<f64>() {
+        Ok(value) if !value.is_nan() => Ok((value, exclusive)),
+        _ => Err(RedisError::Str(ERR_SCORE_BOUND)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score_err(raw: &str) -> &'static str {
+        match parse_score_bound(raw) {
+            Err(RedisError::Str(msg)) => msg,
+            other => panic!("expected error for {raw:?}, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn score_bounds() {
+        assert_eq!(parse_score_bound("1.5").unwrap(), (1.5, false));
+        assert_eq!(parse_score_bound("(1.5").unwrap(), (1.5, true));
+        assert_eq!(parse_score_bound("-3").unwrap(), (-3.0, false));
+        assert_eq!(parse_score_bound("(-3").unwrap(), (-3.0, true));
+        assert_eq!(parse_score_bound("1e3").unwrap(), (1000.0, false));
+        assert_eq!(
+            parse_score_bound("-inf").unwrap(),
+            (f64::NEG_INFINITY, false)
+        );
+        assert_eq!(parse_score_bound("+inf").unwrap(), (f64::INFINITY, false));
+        assert_eq!(parse_score_bound("inf").unwrap(), (f64::INFINITY, false));
+        assert_eq!(parse_score_bound("(+inf").unwrap(), (f64::INFINITY, true));
+        assert_eq!(
+            parse_score_bound("-INF").unwrap(),
+            (f64::NEG_INFINITY, false)
+        );
+    }
+
+    #[test]
+    fn malformed_score_bounds() {
+        for raw in ["", "(", "((1", "[1", "abc", "1.5x", "nan", "(nan", " 1"] {
+            assert_eq!(score_err(raw), ERR_SCORE_BOUND, "{raw:?}");
+        }
+    }
+}
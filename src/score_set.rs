@@ -1,29 +1,61 @@
 use ordered_float::OrderedFloat;
 use smallvec::SmallVec;
 use std::{
-    collections::{btree_map::Entry, BTreeMap},
+    cmp::Reverse,
+    collections::{btree_map::Entry, BTreeMap, BinaryHeap},
     convert::TryFrom,
+    fmt,
     hash::{Hash, Hasher},
     mem::size_of,
+    ops::Bound,
+    sync::atomic::{AtomicI64, Ordering},
 };
 
 use crate::{
-    buckets::{BucketRef, BucketStore},
-    pool::{MemberId, StringPool},
+    buckets::{BucketRef, BucketStore, BUCKET_SLOT_BYTES, INLINE_MEMBERS_MAX},
+    pool::{arena_chunk_size, IdsExhausted, IndexEntry, KeyEntry, MemberId, StringPool, MAX_IDS},
 };
 
-/// Buckets trim their heap capacity once they contain at most this many members.
-const BUCKET_SHRINK_THRESHOLD: usize = 64;
-/// Buckets created from Inline1 spillover start with this many slots.
-/// Must be <= BUCKET_SHRINK_THRESHOLD so shrink behavior remains predictable.
+/// Default for the `gzset.bucket-shrink-threshold` module config.
+pub(crate) const BUCKET_SHRINK_THRESHOLD_DEFAULT: i64 = 64;
+/// Lower bound for the shrink threshold; smaller values would compact buckets
+/// that were just spilled from the inline form.
+pub(crate) const BUCKET_SHRINK_THRESHOLD_MIN: i64 = BUCKET_INITIAL_CAPACITY as i64;
+/// Upper bound for the shrink threshold.
+pub(crate) const BUCKET_SHRINK_THRESHOLD_MAX: i64 = 65_536;
+/// Buckets trim their heap capacity once they contain at most this many
+/// members. Backs the `gzset.bucket-shrink-threshold` module config.
+pub(crate) static BUCKET_SHRINK_THRESHOLD: AtomicI64 =
+    AtomicI64::new(BUCKET_SHRINK_THRESHOLD_DEFAULT);
+/// Member ids are only renumbered once at least this many have been handed out.
+const ID_COMPACT_MIN: usize = 64;
+/// Buckets created from inline spillover start with at least this many slots.
+/// Must be <= the minimum shrink threshold so shrink behavior remains predictable.
 const BUCKET_INITIAL_CAPACITY: usize = 8;
-/// Local buffers for pop operations use the same inline capacity so future
-/// tuning keeps the thresholds in lockstep.
-const BUCKET_INLINE_CAPACITY: usize = BUCKET_SHRINK_THRESHOLD;
+/// Local buffers for pop operations are sized for the default shrink
+/// threshold; larger configured thresholds simply spill to the heap.
+const BUCKET_INLINE_CAPACITY: usize = BUCKET_SHRINK_THRESHOLD_DEFAULT as usize;
+
+/// Current bucket shrink threshold, clamped to the supported range.
+#[inline]
+pub(crate) fn bucket_shrink_threshold() -> usize {
+    BUCKET_SHRINK_THRESHOLD
+        .load(Ordering::Relaxed)
+        .clamp(BUCKET_SHRINK_THRESHOLD_MIN, BUCKET_SHRINK_THRESHOLD_MAX) as usize
+}
 
 const BTREE_NODE_CAP: usize = 11;
 const BTREE_NODE_HDR: usize = 48;
 
+/// Members per source probed by [`ScoreSet::union_overlap_is_low`].
+const UNION_OVERLAP_SAMPLE: usize = 64;
+
+/// [`ScoreSet::select_by_ranks`] steps bucket by bucket across gaps up to
+/// this many ranks and re-descends the rank index past longer ones.
+const SELECT_WALK_RANKS: usize = 32;
+
+/// Tombstone for free slots in `scores`. `insert_deferred` refuses NaN
+/// scores, so a NaN slot always means "no member with this id".
 const EMPTY_SCORE: f64 = f64::NAN;
 
 #[inline]
@@ -35,6 +67,213 @@ const fn size_class(bytes: usize) -> usize {
     }
 }
 
+/// [`size_class`], or `None` when the rounded size does not fit in `usize`.
+const fn checked_size_class(bytes: usize) -> Option<usize> {
+    if bytes <= 512 {
+        Some((bytes + 7) & !7)
+    } else {
+        bytes.checked_next_power_of_two()
+    }
+}
+
+/// Add to a byte counter. Debug builds panic on overflow with the operands;
+/// release builds wrap without a branch.
+#[inline]
+fn add_bytes(counter: &mut usize, bytes: usize) {
+    debug_assert!(
+        counter.checked_add(bytes).is_some(),
+        "memory accounting overflow: {counter} + {bytes}"
+    );
+    *counter = counter.wrapping_add(bytes);
+}
+
+/// Subtract from a byte counter. An underflow means some path released bytes
+/// it never accounted for, so debug builds panic there rather than let
+/// `MEMORY USAGE` wrap to an absurd value; release builds wrap without a branch.
+#[inline]
+fn sub_bytes(counter: &mut usize, bytes: usize) {
+    debug_assert!(
+        bytes <= *counter,
+        "memory accounting underflow: {counter} - {bytes}"
+    );
+    *counter = counter.wrapping_sub(bytes);
+}
+
+/// `ZADD`-style conditions gating a score write (`NX`, `XX`, `GT`, `LT`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UpdateCond {
+    /// Only add new members.
+    pub nx: bool,
+    /// Only update existing members.
+    pub xx: bool,
+    /// Only update existing members whose score would increase.
+    pub gt: bool,
+    /// Only update existing members whose score would decrease.
+    pub lt: bool,
+}
+
+impl UpdateCond {
+    /// Whether writing `new` over `current` (`None` if absent) is allowed.
+    /// `GT`/`LT` never block adding a new member.
+    pub fn permits(&self, current: Option<f64>, new: f64) -> bool {
+        match current {
+            None => !self.xx,
+            Some(cur) => !(self.nx || (self.gt && new <= cur) || (self.lt && new >= cur)),
+        }
+    }
+}
+
+/// A score write for [`ScoreSet::upsert`]. The named variants cover the
+/// common single-flag cases; [`ScoreOp::Conditional`] expresses any other
+/// `ZADD` flag combination such as `XX GT INCR`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScoreOp {
+    /// Set the score, adding the member if absent.
+    Set(f64),
+    /// Add to the score, starting from 0 if absent (`INCR`).
+    Incr(f64),
+    /// Set the score if it would increase; absent members are added (`GT`).
+    SetIfGreater(f64),
+    /// Set the score if it would decrease; absent members are added (`LT`).
+    SetIfLess(f64),
+    /// Add the member only if it is absent (`NX`).
+    SetIfAbsent(f64),
+    /// `value` is a score, or a delta when `incr` is set, written only if
+    /// `cond` permits it.
+    Conditional {
+        value: f64,
+        incr: bool,
+        cond: UpdateCond,
+    },
+}
+
+impl ScoreOp {
+    /// Build the op for `ZADD` flags, preferring a named variant when one
+    /// matches exactly.
+    pub fn from_flags(value: f64, incr: bool, cond: UpdateCond) -> Self {
+        let plain = UpdateCond::default();
+        let only = |flag: UpdateCond| cond == flag;
+        match incr {
+            false if only(plain) => ScoreOp::Set(value),
+            true if only(plain) => ScoreOp::Incr(value),
+            false if only(UpdateCond { gt: true, ..plain }) => ScoreOp::SetIfGreater(value),
+            false if only(UpdateCond { lt: true, ..plain }) => ScoreOp::SetIfLess(value),
+            false if only(UpdateCond { nx: true, ..plain }) => ScoreOp::SetIfAbsent(value),
+            _ => ScoreOp::Conditional { value, incr, cond },
+        }
+    }
+
+    /// The op as `(value, incr, cond)`.
+    fn parts(self) -> (f64, bool, UpdateCond) {
+        let plain = UpdateCond::default();
+        match self {
+            ScoreOp::Set(v) => (v, false, plain),
+            ScoreOp::Incr(d) => (d, true, plain),
+            ScoreOp::SetIfGreater(v) => (v, false, UpdateCond { gt: true, ..plain }),
+            ScoreOp::SetIfLess(v) => (v, false, UpdateCond { lt: true, ..plain }),
+            ScoreOp::SetIfAbsent(v) => (v, false, UpdateCond { nx: true, ..plain }),
+            ScoreOp::Conditional { value, incr, cond } => (value, incr, cond),
+        }
+    }
+}
+
+/// What [`ScoreSet::upsert`] did.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UpsertOutcome {
+    /// The member was new and now has this score.
+    Added(f64),
+    /// The member's score moved from `old` to `new`.
+    Updated { old: f64, new: f64 },
+    /// The write was allowed but the member already had this score.
+    Unchanged(f64),
+    /// The op's condition refused the write; holds the current score, if any.
+    Skipped(Option<f64>),
+}
+
+impl UpsertOutcome {
+    /// The member's score after the call, or `None` if it is absent.
+    pub fn score(self) -> Option<f64> {
+        match self {
+            UpsertOutcome::Added(s) | UpsertOutcome::Unchanged(s) => Some(s),
+            UpsertOutcome::Updated { new, .. } => Some(new),
+            UpsertOutcome::Skipped(current) => current,
+        }
+    }
+
+    /// The score written (or confirmed) by the call, `None` if it was skipped.
+    pub fn written(self) -> Option<f64> {
+        match self {
+            UpsertOutcome::Skipped(_) => None,
+            other => other.score(),
+        }
+    }
+}
+
+/// Why [`ScoreSet::upsert`] or [`ScoreSet::incr_by`] refused to write a score.
+/// More reasons may be added, so matches need a wildcard arm.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum IncrError {
+    /// The incremented score overflowed to an infinity, or the op's value
+    /// was NaN.
+    NotFinite,
+    /// The increment added an infinity to one of the opposite sign, so the
+    /// score would be NaN. Only reachable once a score is already infinite,
+    /// and only through the library: the commands refuse infinite scores and
+    /// increments before calling in.
+    NotANumber,
+    /// The member is new and the set has no member id left to give it.
+    IdsExhausted,
+}
+
+/// Why [`ScoreSet::from_sorted_pairs`] rejected its input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortedPairsError {
+    /// The pair at this index has a NaN score or does not sort strictly
+    /// after the one before it, by score and then member.
+    OutOfOrder(usize),
+    /// A member appears under more than one score.
+    DuplicateMember,
+}
+
+impl fmt::Display for SortedPairsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SortedPairsError::OutOfOrder(index) => write!(f, "pair {index} is out of order"),
+            SortedPairsError::DuplicateMember => f.write_str("a member appears more than once"),
+        }
+    }
+}
+
+impl std::error::Error for SortedPairsError {}
+
+/// How [`ScoreSet::union_into`] combines the scores of a member present in
+/// several sources (`AGGREGATE SUM|MIN|MAX`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Aggregate {
+    #[default]
+    Sum,
+    Min,
+    Max,
+}
+
+impl Aggregate {
+    /// Fold `value` into `acc`. Like Redis, `inf + -inf` yields 0 rather than NaN.
+    #[inline]
+    pub fn combine(self, acc: f64, value: f64) -> f64 {
+        let out = match self {
+            Aggregate::Sum => acc + value,
+            Aggregate::Min => acc.min(value),
+            Aggregate::Max => acc.max(value),
+        };
+        if out.is_nan() {
+            0.0
+        } else {
+            out
+        }
+    }
+}
+
 pub struct ScoreSet {
     pub(crate) by_score: BTreeMap<OrderedFloat<f64>, BucketRef>,
     pub(crate) bucket_store: BucketStore,
@@ -42,7 +281,7 @@ pub struct ScoreSet {
     pub(crate) pool: StringPool,
     bucket_index: OrderStatsIndex,
     mem_bytes: usize,
-    #[cfg(test)]
+    #[cfg(any(test, gzset_mem_breakdown))]
     mem_breakdown: MemBreakdown,
 }
 
@@ -66,28 +305,58 @@ impl Default for ScoreSet {
             pool: StringPool::default(),
             bucket_index: OrderStatsIndex::new(),
             mem_bytes: 0,
-            #[cfg(test)]
+            #[cfg(any(test, gzset_mem_breakdown))]
             mem_breakdown: MemBreakdown::default(),
         }
     }
 }
 
-#[cfg(test)]
+/// Logical equality: the same members with the same scores, however they
+/// were inserted or laid out in buckets. Test-only, like [`ScoreSet::debug_dump`].
+#[cfg(any(test, feature = "test-utils"))]
+impl PartialEq for ScoreSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .iter_all()
+                .all(|(member, score)| other.score(member) == Some(score))
+    }
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+impl fmt::Debug for ScoreSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter_all()).finish()
+    }
+}
+
+/// Per-component view of a set's heap usage. `structural()` is what
+/// `mem_bytes` tracks; member strings are accounted by the pool separately.
+/// The running copy is kept in debug builds, under `test`, the `mem-check`
+/// feature, or the `memory-introspection` feature, which also exports this
+/// type.
+#[cfg(any(test, gzset_mem_breakdown))]
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub struct MemBreakdown {
+    /// Nodes of the score-to-bucket map, one entry per distinct score.
     pub score_map: usize,
+    /// Spilled buckets holding more tied members than fit inline.
     pub buckets: usize,
+    /// The per-member score table, including reserved slack.
     pub member_table: usize,
+    /// Member name bytes, excluding arena slack.
     pub strings: usize,
 }
 
-#[cfg(test)]
+#[cfg(any(test, gzset_mem_breakdown))]
 impl MemBreakdown {
+    /// Bytes counted by [`ScoreSet::mem_bytes`].
     #[inline]
     pub fn structural(&self) -> usize {
         self.score_map + self.buckets + self.member_table
     }
 
+    /// [`structural`](Self::structural) plus the member name bytes.
     #[inline]
     pub fn total(&self) -> usize {
         self.structural() + self.strings
@@ -173,18 +442,14 @@ impl<'a> Iterator for ScoreIterDesc<'a> {
                 self.cur = None;
             }
             let (score, bucket_ref) = self.outer.next()?;
-            match *bucket_ref {
-                BucketRef::Inline1(member) => {
-                    return Some((self.pool.get(member), score.0));
-                }
-                BucketRef::Handle(bucket_id) => {
-                    let slice = self.store.slice(bucket_id);
-                    if slice.is_empty() {
-                        continue;
-                    }
-                    self.cur = Some((BackState::Slice(slice.iter().rev()), *score));
-                }
+            if let BucketRef::Inline1(member) = *bucket_ref {
+                return Some((self.pool.get(member), score.0));
+            }
+            let slice = bucket_ref.ids(self.store);
+            if slice.is_empty() {
+                continue;
             }
+            self.cur = Some((BackState::Slice(slice.iter().rev()), *score));
         }
     }
 
@@ -215,9 +480,43 @@ impl OrderStatsIndex {
         self.root = OrderStatsNode::remove(self.root.take(), key);
     }
 
+    /// Add `(key, count)` entries given in ascending key order, all above
+    /// every key already indexed, in O(m + log n) rather than one descent
+    /// each.
+    fn append_sorted<I>(&mut self, entries: I)
+    where
+        I: IntoIterator<Item = (OrderedFloat<f64>, usize)>,
+    {
+        let built = OrderStatsNode::build_sorted(entries);
+        self.root = OrderStatsNode::merge(self.root.take(), built);
+    }
+
     fn prefix_before(&self, key: OrderedFloat<f64>) -> usize {
         OrderStatsNode::prefix_before(&self.root, key)
     }
+
+    /// Score key holding the member at 0-based rank `r`, and that member's
+    /// offset within its bucket. `None` when `r` is out of bounds.
+    fn select(&self, mut r: usize) -> Option<(OrderedFloat<f64>, usize)> {
+        let mut node = self.root.as_deref();
+        while let Some(n) = node {
+            let left = OrderStatsNode::subtree_size(&n.left);
+            if r < left {
+                node = n.left.as_deref();
+            } else if r < left + n.count {
+                return Some((n.key, r - left));
+            } else {
+                r -= left + n.count;
+                node = n.right.as_deref();
+            }
+        }
+        None
+    }
+
+    /// Sum of all bucket counts tracked by the index.
+    fn total(&self) -> usize {
+        OrderStatsNode::subtree_size(&self.root)
+    }
 }
 
 #[derive(Clone)]
@@ -320,6 +619,44 @@ impl OrderStatsNode {
         }
     }
 
+    /// Treap over ascending `entries`, built along its right spine. Each
+    /// node pops the spine nodes with a larger priority and adopts them as
+    /// its left subtree, so every node is pushed and popped once.
+    fn build_sorted<I>(entries: I) -> Option<Box<Self>>
+    where
+        I: IntoIterator<Item = (OrderedFloat<f64>, usize)>,
+    {
+        // Each spine node's right child is the node above it on the stack,
+        // linked in once it can no longer change.
+        let mut spine: Vec<Box<Self>> = Vec::new();
+        for (key, count) in entries {
+            if count == 0 {
+                continue;
+            }
+            debug_assert!(
+                spine.last().is_none_or(|last| last.key < key),
+                "build_sorted keys must ascend"
+            );
+            let mut node = Self::new(key, count);
+            let mut popped: Option<Box<Self>> = None;
+            while spine.last().is_some_and(|top| top.priority > node.priority) {
+                let mut top = spine.pop().expect("checked non-empty");
+                top.right = popped;
+                top.update_size();
+                popped = Some(top);
+            }
+            node.left = popped;
+            spine.push(node);
+        }
+        let mut root = None;
+        while let Some(mut node) = spine.pop() {
+            node.right = root;
+            node.update_size();
+            root = Some(node);
+        }
+        root
+    }
+
     fn merge(left: Option<Box<Self>>, right: Option<Box<Self>>) -> Option<Box<Self>> {
         match (left, right) {
             (None, other) => other,
@@ -462,25 +799,22 @@ impl<'a> Iterator for RangeIterFwd<'a> {
                 self.remaining_take = 0;
                 return None;
             };
-            match *bucket_ref {
-                BucketRef::Inline1(member) => {
-                    self.current = Some(CurrentBucket::Inline {
-                        score: score.0,
-                        member,
-                    });
-                }
-                BucketRef::Handle(bucket_id) => {
-                    let slice = self.store.slice(bucket_id);
-                    if slice.is_empty() {
-                        continue;
-                    }
-                    self.current = Some(CurrentBucket::Slice {
-                        score: score.0,
-                        members: slice,
-                        index: 0,
-                    });
-                }
+            if let BucketRef::Inline1(member) = *bucket_ref {
+                self.current = Some(CurrentBucket::Inline {
+                    score: score.0,
+                    member,
+                });
+                continue;
+            }
+            let slice = bucket_ref.ids(self.store);
+            if slice.is_empty() {
+                continue;
             }
+            self.current = Some(CurrentBucket::Slice {
+                score: score.0,
+                members: slice,
+                index: 0,
+            });
         }
     }
 
@@ -496,6 +830,69 @@ impl<'a> ExactSizeIterator for RangeIterFwd<'a> {
     }
 }
 
+/// Members-only counterpart of [`RangeIterFwd`]. It walks the score map's
+/// values without reading the keys, for replies that never print a score.
+pub struct MembersInRange<'a> {
+    pool: &'a StringPool,
+    store: &'a BucketStore,
+    outer: std::collections::btree_map::Values<'a, OrderedFloat<f64>, BucketRef>,
+    current: &'a [MemberId],
+    remaining_skip: usize,
+    remaining_take: usize,
+}
+
+impl<'a> MembersInRange<'a> {
+    fn new(
+        map: &'a BTreeMap<OrderedFloat<f64>, BucketRef>,
+        store: &'a BucketStore,
+        pool: &'a StringPool,
+        skip: usize,
+        take: usize,
+    ) -> Self {
+        Self {
+            pool,
+            store,
+            outer: map.values(),
+            current: &[],
+            remaining_skip: skip,
+            remaining_take: take,
+        }
+    }
+}
+
+impl<'a> Iterator for MembersInRange<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining_take == 0 {
+            return None;
+        }
+        while self.current.is_empty() {
+            let Some(bucket_ref) = self.outer.next() else {
+                self.remaining_take = 0;
+                return None;
+            };
+            let ids = bucket_ref.ids(self.store);
+            if self.remaining_skip >= ids.len() {
+                self.remaining_skip -= ids.len();
+                continue;
+            }
+            self.current = &ids[self.remaining_skip..];
+            self.remaining_skip = 0;
+        }
+        let (&id, rest) = self.current.split_first()?;
+        self.current = rest;
+        self.remaining_take -= 1;
+        Some(self.pool.get(id))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining_take, Some(self.remaining_take))
+    }
+}
+
+impl<'a> ExactSizeIterator for MembersInRange<'a> {}
+
 struct IterFromFwd<'a> {
     pool: &'a StringPool,
     store: &'a BucketStore,
@@ -529,8 +926,8 @@ impl<'a> IterFromFwd<'a> {
                             inline_first = Some((name, s_key.0));
                         }
                     }
-                    BucketRef::Handle(bucket_id) => {
-                        let slice = store.slice(bucket_id);
+                    _ => {
+                        let slice = bucket_ref.ids(store);
                         if !slice.is_empty() {
                             let pos = match slice.binary_search_by(|&m| pool.get(m).cmp(member)) {
                                 Ok(p) => {
@@ -555,8 +952,8 @@ impl<'a> IterFromFwd<'a> {
                         let name = pool.get(mid);
                         inline_first = Some((name, s_key.0));
                     }
-                    BucketRef::Handle(bucket_id) => {
-                        let slice = store.slice(bucket_id);
+                    _ => {
+                        let slice = bucket_ref.ids(store);
                         if !slice.is_empty() {
                             cur = Some((slice.iter(), *s_key));
                         }
@@ -590,18 +987,14 @@ impl<'a> Iterator for IterFromFwd<'a> {
                 self.cur = None;
             }
             let (score, bucket_ref) = self.outer.next()?;
-            match *bucket_ref {
-                BucketRef::Inline1(mid) => {
-                    return Some((self.pool.get(mid), score.0));
-                }
-                BucketRef::Handle(bucket_id) => {
-                    let slice = self.store.slice(bucket_id);
-                    if slice.is_empty() {
-                        continue;
-                    }
-                    self.cur = Some((slice.iter(), *score));
-                }
+            if let BucketRef::Inline1(mid) = *bucket_ref {
+                return Some((self.pool.get(mid), score.0));
             }
+            let slice = bucket_ref.ids(self.store);
+            if slice.is_empty() {
+                continue;
+            }
+            self.cur = Some((slice.iter(), *score));
         }
     }
 
@@ -699,10 +1092,7 @@ impl<'a> Iterator for ScoreIter<'a> {
                 Some((score, bucket_ref)) => {
                     let state = match *bucket_ref {
                         BucketRef::Inline1(member) => FrontState::Inline(InlineIter::new(member)),
-                        BucketRef::Handle(bucket_id) => {
-                            let slice = self.store.slice(bucket_id);
-                            FrontState::Slice(slice.iter())
-                        }
+                        _ => FrontState::Slice(bucket_ref.ids(self.store).iter()),
                     };
                     self.front_current = Some((state, *score));
                 }
@@ -739,10 +1129,7 @@ impl<'a> DoubleEndedIterator for ScoreIter<'a> {
                 Some((score, bucket_ref)) => {
                     let state = match *bucket_ref {
                         BucketRef::Inline1(member) => BackState::Inline(InlineIter::new(member)),
-                        BucketRef::Handle(bucket_id) => {
-                            let slice = self.store.slice(bucket_id);
-                            BackState::Slice(slice.iter().rev())
-                        }
+                        _ => BackState::Slice(bucket_ref.ids(self.store).iter().rev()),
                     };
                     self.back_current = Some((state, *score));
                 }
@@ -758,12 +1145,41 @@ impl<'a> ExactSizeIterator for ScoreIter<'a> {
     }
 }
 
+/// Removing iterator returned by [`ScoreSet::drain`]. Each step pops the
+/// lowest-scored member, so only the member being yielded is ever copied out
+/// and memory accounting follows every removal. Members left when it is
+/// dropped are removed too.
+pub struct Drain<'a> {
+    set: &'a mut ScoreSet,
+}
+
+impl<'a> Iterator for Drain<'a> {
+    type Item = (String, f64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.set.pop_one(true)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.set.len();
+        (len, Some(len))
+    }
+}
+
+impl<'a> ExactSizeIterator for Drain<'a> {}
+
+impl<'a> std::iter::FusedIterator for Drain<'a> {}
+
+impl<'a> Drop for Drain<'a> {
+    fn drop(&mut self) {
+        let len = self.set.len();
+        self.set.pop_n_visit(true, len, |_, _| {});
+    }
+}
+
 impl ScoreSet {
     fn bucket_len(&self, bucket_ref: BucketRef) -> usize {
-        match bucket_ref {
-            BucketRef::Inline1(_) => 1,
-            BucketRef::Handle(bucket_id) => self.bucket_store.len(bucket_id),
-        }
+        bucket_ref.len(&self.bucket_store)
     }
 
     fn refresh_bucket_index(&mut self, key: OrderedFloat<f64>) {
@@ -775,17 +1191,182 @@ impl ScoreSet {
         }
     }
 
+    /// Debug-build guard against cardinality drift: the rank index sums every
+    /// bucket length, so its total must always equal the live member count.
+    /// The check is O(1) and compiles away in release builds.
+    #[inline]
+    fn debug_assert_len_consistent(&self) {
+        debug_assert_eq!(
+            self.bucket_index.total(),
+            self.pool.len(),
+            "summed bucket lengths diverged from pool length",
+        );
+    }
+
     #[inline]
     pub fn mem_bytes(&self) -> usize {
         self.mem_bytes
     }
 
-    #[cfg(test)]
+    /// Predicted memory, in bytes, of a set holding `members` members with
+    /// distinct scores and names of `avg_len` bytes, added one at a time: the
+    /// [`mem_bytes`](Self::mem_bytes) the accounting would reach plus the
+    /// string pool's arena, hash table and id index. Tied scores would add
+    /// spilled bucket capacity on top. `None` when no set of that shape can
+    /// exist: more members than the pool has ids, or a size past `usize`.
+    pub fn estimate_memory(members: usize, avg_len: usize) -> Option<usize> {
+        if members == 0 {
+            return Some(0);
+        }
+        if members > MAX_IDS {
+            return None;
+        }
+        let score_map =
+            Self::btree_nodes(members).checked_mul(size_class(Self::map_node_bytes::<
+                OrderedFloat<f64>,
+                BucketRef,
+            >()))?;
+        let scores = Self::pushed_capacity(members)?.checked_mul(size_of::<f64>())?;
+        let pool = Self::estimate_pool_bytes(members, avg_len, arena_chunk_size())?;
+        score_map.checked_add(scores)?.checked_add(pool)
+    }
+
+    fn estimate_pool_bytes(members: usize, avg_len: usize, chunk_size: usize) -> Option<usize> {
+        // Strings never straddle chunks, so each chunk holds a whole number
+        // of them; empty names all share the first chunk.
+        let arena = match chunk_size.checked_div(avg_len) {
+            _ if avg_len > chunk_size => members.checked_mul(checked_size_class(avg_len)?)?,
+            Some(per_chunk) => members.div_ceil(per_chunk) * size_class(chunk_size),
+            None => size_class(chunk_size),
+        };
+        let index = checked_size_class(
+            Self::pushed_capacity(members)?.checked_mul(size_of::<Option<IndexEntry>>())?,
+        )?;
+        // hashbrown keeps power-of-two buckets at most 7/8 full (one slot free
+        // below 8), followed by a control byte per bucket and a trailing group.
+        let load = |buckets: usize| {
+            if buckets < 8 {
+                buckets - 1
+            } else {
+                buckets / 8 * 7
+            }
+        };
+        let mut buckets = 4usize;
+        while load(buckets) < members {
+            buckets = buckets.checked_mul(2)?;
+        }
+        let table = checked_size_class(
+            buckets
+                .checked_mul(size_of::<KeyEntry>())?
+                .checked_next_multiple_of(16)?
+                .checked_add(buckets)?
+                .checked_add(16)?,
+        )?;
+        arena.checked_add(index)?.checked_add(table)
+    }
+
+    /// Capacity a `Vec` reaches after `len` single pushes, or `None` past
+    /// `usize`.
+    #[inline]
+    fn pushed_capacity(len: usize) -> Option<usize> {
+        if len == 0 {
+            Some(0)
+        } else {
+            Some(len.checked_next_power_of_two()?.max(4))
+        }
+    }
+
+    /// Running per-component byte counts behind [`mem_bytes`](Self::mem_bytes),
+    /// kept up to date on every write. Available with the
+    /// `memory-introspection` feature (or `mem-check`); reading it is O(1).
+    ///
+    /// ```
+    /// # #[cfg(feature = "memory-introspection")]
+    /// # {
+    /// use gzset::ScoreSet;
+    ///
+    /// let mut set = ScoreSet::default();
+    /// for i in 0..1_000 {
+    ///     set.insert((i % 10) as f64, &format!("member:{i}"));
+    /// }
+    /// let breakdown = set.debug_mem_breakdown();
+    /// assert_eq!(breakdown.structural(), set.mem_bytes());
+    /// assert!(breakdown.buckets > 0, "100 members per score spill");
+    /// assert_eq!(breakdown.strings, (0..1_000).map(|i| format!("member:{i}").len()).sum());
+    /// # }
+    /// ```
+    #[cfg(any(test, gzset_mem_breakdown))]
     #[inline]
     pub fn debug_mem_breakdown(&self) -> MemBreakdown {
         self.mem_breakdown
     }
 
+    /// Rebuild the memory breakdown from the current structures, ignoring
+    /// the running counters. `structural()` of the result should always equal
+    /// `mem_bytes()`; any difference means an accounting delta was missed.
+    #[cfg(any(test, gzset_mem_breakdown))]
+    pub fn recompute_mem_breakdown(&self) -> MemBreakdown {
+        let buckets = self
+            .by_score
+            .values()
+            .map(|bucket| match *bucket {
+                BucketRef::Handle(id) => self.bucket_store.capacity_bytes(id),
+                _ => 0,
+            })
+            .sum();
+        MemBreakdown {
+            score_map: Self::score_map_bytes(&self.by_score),
+            buckets,
+            member_table: Self::scores_bytes(&self.scores),
+            strings: self.pool.iter().map(|(name, _)| name.len()).sum(),
+        }
+    }
+
+    /// Assert every structural invariant: buckets are non-empty, sorted and
+    /// duplicate-free, each member's score slot matches its bucket's key, the
+    /// bucket lengths sum to the pool length, and `mem_bytes` agrees with the
+    /// running breakdown. Tests call it after mutating so a broken invariant
+    /// surfaces at the operation that broke it.
+    #[cfg(test)]
+    pub(crate) fn validate(&self) {
+        let mut total = 0usize;
+        for (&score, &bucket_ref) in &self.by_score {
+            let ids = bucket_ref.ids(&self.bucket_store);
+            assert!(!ids.is_empty(), "score {score:?} has an empty bucket");
+            for pair in ids.windows(2) {
+                let (prev, next) = (self.pool.get(pair[0]), self.pool.get(pair[1]));
+                assert!(
+                    prev < next,
+                    "bucket {score:?} is unsorted or repeats a member: {prev:?} before {next:?}",
+                );
+            }
+            for &id in ids {
+                assert_eq!(
+                    self.get_score_by_id(id),
+                    Some(score.0),
+                    "member {:?} has a score slot that disagrees with its bucket",
+                    self.pool.get(id),
+                );
+            }
+            total += ids.len();
+        }
+        assert_eq!(
+            total,
+            self.pool.len(),
+            "bucket lengths do not sum to pool length"
+        );
+        assert_eq!(
+            self.bucket_index.total(),
+            total,
+            "rank index total diverged"
+        );
+        assert_eq!(
+            self.mem_bytes,
+            self.mem_breakdown.structural(),
+            "mem_bytes diverged from the running breakdown",
+        );
+    }
+
     #[inline]
     fn get_score_by_id(&self, id: MemberId) -> Option<f64> {
         let idx = id as usize;
@@ -814,6 +1395,56 @@ impl ScoreSet {
         }
     }
 
+    /// Renumber member ids densely once most handed-out ids are free.
+    ///
+    /// `scores` is indexed by id and the pool only reuses ids from its free
+    /// list, so after heavy deletes a few survivors with high ids would
+    /// otherwise pin a table sized for the peak. Compaction is O(len) and
+    /// requires at least three frees per surviving member since the last one.
+    fn maybe_compact_ids(&mut self) {
+        let allocated = self.pool.allocated_ids();
+        if allocated < ID_COMPACT_MIN || self.pool.len().saturating_mul(4) > allocated {
+            return;
+        }
+        self.compact_ids();
+    }
+
+    /// Rebuild the string pool and `scores` with ids assigned in score order.
+    fn compact_ids(&mut self) {
+        let prev_scores = Self::scores_bytes(&self.scores);
+        let old_pool = std::mem::take(&mut self.pool);
+        let old_scores = std::mem::take(&mut self.scores);
+        let mut pool = StringPool::with_chunk_size(old_pool.chunk_size());
+        pool.reserve(old_pool.len());
+        let mut scores = Vec::with_capacity(old_pool.len());
+        let mut relocate = |old: MemberId| -> MemberId {
+            let id = pool.intern(old_pool.get(old));
+            debug_assert_eq!(
+                id as usize,
+                scores.len(),
+                "fresh pool must hand out dense ids"
+            );
+            scores.push(old_scores[old as usize]);
+            id
+        };
+        for bucket_ref in self.by_score.values_mut() {
+            match bucket_ref {
+                BucketRef::Inline1(id) => *id = relocate(*id),
+                BucketRef::InlineN { len, ids } => {
+                    for id in &mut ids[..usize::from(*len)] {
+                        *id = relocate(*id);
+                    }
+                }
+                BucketRef::Handle(bucket_id) => {
+                    self.bucket_store.remap_members(*bucket_id, &mut relocate)
+                }
+            }
+        }
+        self.pool = pool;
+        self.scores = scores;
+        self.apply_scores_delta(prev_scores);
+    }
+
     #[inline]
     fn scores_bytes(scores: &Vec<f64>) -> usize {
         scores.capacity() * size_of::<f64>()
@@ -833,11 +1464,11 @@ impl ScoreSet {
 
     #[inline]
     fn account_removed_string(&mut self, removed_len: Option<usize>) {
-        #[cfg(test)]
+        #[cfg(any(test, gzset_mem_breakdown))]
         if let Some(len) = removed_len {
-            self.mem_breakdown.strings -= len;
+            sub_bytes(&mut self.mem_breakdown.strings, len);
         }
-        #[cfg(not(test))]
+        #[cfg(not(any(test, gzset_mem_breakdown)))]
         {
             let _ = removed_len;
         }
@@ -868,75 +1499,638 @@ impl ScoreSet {
         if delta == 0 {
             return;
         }
+        let bytes = delta.unsigned_abs();
         if delta > 0 {
-            let bytes = delta as usize;
-            self.mem_bytes += bytes;
-            #[cfg(test)]
-            {
-                self.mem_breakdown.buckets += bytes;
-            }
+            add_bytes(&mut self.mem_bytes, bytes);
+            #[cfg(any(test, gzset_mem_breakdown))]
+            add_bytes(&mut self.mem_breakdown.buckets, bytes);
         } else {
-            let bytes = (-delta) as usize;
-            self.mem_bytes -= bytes;
-            #[cfg(test)]
-            {
-                self.mem_breakdown.buckets -= bytes;
-            }
+            sub_bytes(&mut self.mem_bytes, bytes);
+            #[cfg(any(test, gzset_mem_breakdown))]
+            sub_bytes(&mut self.mem_breakdown.buckets, bytes);
         }
     }
 
+    /// Set `member`'s score, adding it if absent. Returns `false` only when
+    /// the member already had an equal score.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `member` is new and no member id is left; command paths use
+    /// [`try_insert`](Self::try_insert) instead.
     pub fn insert(&mut self, score: f64, member: &str) -> bool {
-        let key = OrderedFloat(score);
-        let is_new = self.pool.lookup(member).is_none();
+        self.try_insert(score, member)
+            .expect("too many members in sorted set")
+    }
+
+    /// How many new members still fit before inserts report
+    /// [`IdsExhausted`]. Rescoring an existing member needs no id.
+    pub fn spare_ids(&self) -> usize {
+        self.pool.spare_ids()
+    }
+
+    /// Like [`insert`](Self::insert), but reports id exhaustion instead of
+    /// panicking. The set is unchanged on error.
+    pub fn try_insert(&mut self, score: f64, member: &str) -> Result<bool, IdsExhausted> {
         let prev_scores = Self::scores_bytes(&self.scores);
         let prev_map = Self::score_map_bytes(&self.by_score);
-        let mut old_key_removed = false;
-        let id = self.pool.intern(member);
-        let idx = id as usize;
-        let old_score = self.get_score_by_id(id);
-        if self.scores.len() <= idx {
-            self.scores.resize(idx + 1, EMPTY_SCORE);
-        }
-        let new_scores = Self::scores_bytes(&self.scores);
-        if new_scores >= prev_scores {
-            let delta = new_scores - prev_scores;
-            self.mem_bytes += delta;
-            #[cfg(test)]
-            {
-                self.mem_breakdown.member_table += delta;
-            }
-        } else {
-            let delta = prev_scores - new_scores;
-            self.mem_bytes -= delta;
-            #[cfg(test)]
-            {
-                self.mem_breakdown.member_table -= delta;
-            }
+        let (inserted, map_changed) = self.insert_deferred(score, member)?;
+        self.apply_scores_delta(prev_scores);
+        if map_changed {
+            self.apply_score_map_delta(prev_map);
         }
-        if is_new {
-            #[cfg(test)]
-            {
-                self.mem_breakdown.strings += member.len();
+        Ok(inserted)
+    }
+
+    /// Like [`insert`](Self::insert) but also returns the score stored for
+    /// `member` afterwards. This can differ from `score` when the member was
+    /// already present at an equal key, e.g. `-0.0` against a stored `0.0`.
+    pub fn insert_with_score(&mut self, score: f64, member: &str) -> (bool, f64) {
+        let inserted = self.insert(score, member);
+        let stored = self.score(member).unwrap_or(score);
+        (inserted, stored)
+    }
+
+    /// Reserve room for at least `additional` more members in the string pool
+    /// and score table ahead of a bulk load. The reserved score-table slack is
+    /// counted in [`mem_bytes`](Self::mem_bytes) right away. Buckets are not
+    /// pre-sized since their number depends on how many scores tie.
+    pub fn reserve(&mut self, additional: usize) {
+        let prev_scores = Self::scores_bytes(&self.scores);
+        self.reserve_members(additional);
+        self.apply_scores_delta(prev_scores);
+    }
+
+    /// Release capacity left behind by deletes. Ids are renumbered densely,
+    /// which also repacks member strings into a fresh arena, and the score
+    /// table, string pool and buckets drop their spare capacity. O(len);
+    /// [`mem_bytes`](Self::mem_bytes) is updated to match.
+    pub fn shrink_to_fit(&mut self) {
+        self.compact_ids();
+        self.pool.shrink_to_fit();
+        let prev_scores = Self::scores_bytes(&self.scores);
+        self.scores.shrink_to_fit();
+        self.apply_scores_delta(prev_scores);
+        let bucket_delta = self.bucket_store.shrink_to_fit();
+        self.apply_bucket_mem_delta(bucket_delta);
+    }
+
+    /// Capacity half of [`reserve`](Self::reserve); the caller settles the
+    /// score table accounting.
+    fn reserve_members(&mut self, additional: usize) {
+        if additional == 0 {
+            return;
+        }
+        self.pool.reserve(additional);
+        let wanted = self.pool.allocated_ids().saturating_add(additional);
+        self.scores
+            .reserve(wanted.saturating_sub(self.scores.len()));
+    }
+
+    /// Add `delta` to `member`'s score (starting from 0 if absent) subject to
+    /// `cond`, returning the new score. `Ok(None)` means `cond` suppressed the
+    /// write; the set is unchanged in that case and on error.
+    pub fn incr_by(
+        &mut self,
+        member: &str,
+        delta: f64,
+        cond: UpdateCond,
+    ) -> Result<Option<f64>, IncrError> {
+        let op = ScoreOp::from_flags(delta, true, cond);
+        Ok(self.upsert(member, op)?.written())
+    }
+
+    /// Apply `op` to `member`, adding, updating or leaving it alone as the op
+    /// dictates. The condition is checked against the score the write would
+    /// produce, so `GT`/`LT` compare the incremented score under `INCR`. The
+    /// set is unchanged on error and for `Unchanged`/`Skipped` outcomes.
+    pub fn upsert(&mut self, member: &str, op: ScoreOp) -> Result<UpsertOutcome, IncrError> {
+        let (value, incr, cond) = op.parts();
+        if value.is_nan() {
+            return Err(IncrError::NotFinite);
+        }
+        let current = self.score(member);
+        let new = if incr {
+            current.unwrap_or(0.0) + value
+        } else {
+            value
+        };
+        // Checked ahead of the condition, as Redis does.
+        if new.is_nan() {
+            return Err(IncrError::NotANumber);
+        }
+        if !cond.permits(current, new) {
+            return Ok(UpsertOutcome::Skipped(current));
+        }
+        if incr && !new.is_finite() {
+            return Err(IncrError::NotFinite);
+        }
+        match current {
+            Some(cur) if cur == new => Ok(UpsertOutcome::Unchanged(cur)),
+            _ => {
+                self.try_insert(new, member)
+                    .map_err(|IdsExhausted| IncrError::IdsExhausted)?;
+                Ok(match current {
+                    None => UpsertOutcome::Added(new),
+                    Some(old) => UpsertOutcome::Updated { old, new },
+                })
+            }
+        }
+    }
+
+    /// Insert a batch of `(score, member)` pairs, returning how many members
+    /// were newly added (score updates of existing members are not counted).
+    ///
+    /// Behaves like calling [`ScoreSet::insert`] for each item in order, but
+    /// reserves pool and score-table capacity up front from the iterator's
+    /// size hint and settles the score map and score table accounting once at
+    /// the end instead of per element. Panics on id exhaustion like `insert`.
+    pub fn insert_many<'m, I>(&mut self, items: I) -> usize
+    where
+        I: IntoIterator<Item = (f64, &'m str)>,
+    {
+        let iter = items.into_iter();
+        let (lower, _) = iter.size_hint();
+        let prev_scores = Self::scores_bytes(&self.scores);
+        let prev_map = Self::score_map_bytes(&self.by_score);
+        self.reserve_members(lower);
+        let len_before = self.pool.len();
+        let mut map_changed = false;
+        for (score, member) in iter {
+            let (_, changed) = self
+                .insert_deferred(score, member)
+                .expect("too many members in sorted set");
+            map_changed |= changed;
+        }
+        self.apply_scores_delta(prev_scores);
+        if map_changed {
+            self.apply_score_map_delta(prev_map);
+        }
+        self.pool.len() - len_before
+    }
+
+    /// Build a set from `pairs` sorted by score, then member, with no member
+    /// repeated: the order [`iter_all`](Self::iter_all) yields. The input is
+    /// checked in one pass and then loaded through
+    /// [`extend_from_sorted`](Self::extend_from_sorted).
+    ///
+    /// ```
+    /// use gzset::{ScoreSet, SortedPairsError};
+    ///
+    /// let set = ScoreSet::from_sorted_pairs(&[(1.0, "a"), (2.0, "b"), (2.0, "c")]).unwrap();
+    /// assert_eq!(set.members_in_range(0, -1).collect::<Vec<_>>(), ["a", "b", "c"]);
+    ///
+    /// let err = ScoreSet::from_sorted_pairs(&[(2.0, "b"), (1.0, "a")]).err();
+    /// assert_eq!(err, Some(SortedPairsError::OutOfOrder(1)));
+    /// let err = ScoreSet::from_sorted_pairs(&[(1.0, "a"), (2.0, "a")]).err();
+    /// assert_eq!(err, Some(SortedPairsError::DuplicateMember));
+    /// ```
+    pub fn from_sorted_pairs(pairs: &[(f64, &str)]) -> Result<ScoreSet, SortedPairsError> {
+        let mut prev: Option<(OrderedFloat<f64>, &str)> = None;
+        for (index, &(score, member)) in pairs.iter().enumerate() {
+            let key = (OrderedFloat(score), member);
+            if score.is_nan() || prev.is_some_and(|prev| prev >= key) {
+                return Err(SortedPairsError::OutOfOrder(index));
+            }
+            prev = Some(key);
+        }
+        let mut set = ScoreSet::default();
+        // Sorted input only repeats a member under different scores, which
+        // the load collapses into one.
+        if set.extend_from_sorted(pairs.iter().copied()) != pairs.len() {
+            return Err(SortedPairsError::DuplicateMember);
+        }
+        Ok(set)
+    }
+
+    /// Insert `(score, member)` pairs given in non-decreasing score order,
+    /// such as an RDB stream or another set's [`iter_all`](Self::iter_all),
+    /// returning how many members were newly added.
+    ///
+    /// The result matches [`insert_many`](Self::insert_many). New members
+    /// whose score is above every score already present are gathered per
+    /// score and appended as a whole bucket, sized once, with no binary
+    /// search in the score map or the bucket. Any other item (an existing
+    /// member, a score that goes backwards or lands on an existing bucket)
+    /// takes the regular insert path, so out-of-order input is still correct,
+    /// only slower.
+    ///
+    /// ```
+    /// use gzset::ScoreSet;
+    ///
+    /// let mut set = ScoreSet::default();
+    /// let added = set.extend_from_sorted([(1.0, "a"), (2.0, "b"), (2.0, "c"), (0.5, "z")]);
+    /// assert_eq!(added, 4);
+    /// assert_eq!(set.members_in_range(0, -1).collect::<Vec<_>>(), ["z", "a", "b", "c"]);
+    /// ```
+    pub fn extend_from_sorted<'m, I>(&mut self, items: I) -> usize
+    where
+        I: IntoIterator<Item = (f64, &'m str)>,
+    {
+        let iter = items.into_iter();
+        let (lower, _) = iter.size_hint();
+        let prev_scores = Self::scores_bytes(&self.scores);
+        let prev_map = Self::score_map_bytes(&self.by_score);
+        self.reserve_members(lower);
+        let len_before = self.pool.len();
+        let mut map_changed = false;
+        // Members gathered for `run_key`, and finished runs waiting in `tail`.
+        // Both sort above every key in `by_score` and reach the map and the
+        // rank index in bulk, before any item takes the regular path.
+        let mut run: Vec<MemberId> = Vec::new();
+        let mut run_key: Option<OrderedFloat<f64>> = None;
+        let mut tail: Vec<(OrderedFloat<f64>, BucketRef, usize)> = Vec::new();
+        let mut floor = self.by_score.last_key_value().map(|(&key, _)| key);
+        for (score, member) in iter {
+            let key = OrderedFloat(score);
+            let appendable = !score.is_nan()
+                && (run_key == Some(key) || run_key.or(floor).is_none_or(|last| key > last));
+            if appendable {
+                let live_before = self.pool.len();
+                let id = self
+                    .pool
+                    .try_intern(member)
+                    .expect("too many members in sorted set");
+                if self.pool.len() != live_before {
+                    if run_key != Some(key) {
+                        floor = run_key.or(floor);
+                        self.finish_sorted_run(run_key.take(), &mut run, &mut tail);
+                        run_key = Some(key);
+                        map_changed = true;
+                    }
+                    let idx = id as usize;
+                    if self.scores.len() <= idx {
+                        self.scores.resize(idx + 1, EMPTY_SCORE);
+                    }
+                    self.scores[idx] = score;
+                    #[cfg(any(test, gzset_mem_breakdown))]
+                    add_bytes(&mut self.mem_breakdown.strings, member.len());
+                    run.push(id);
+                    continue;
+                }
+            }
+            self.finish_sorted_run(run_key.take(), &mut run, &mut tail);
+            self.append_sorted_tail(&mut tail);
+            let (_, changed) = self
+                .insert_deferred(score, member)
+                .expect("too many members in sorted set");
+            map_changed |= changed;
+            floor = self.by_score.last_key_value().map(|(&key, _)| key);
+        }
+        self.finish_sorted_run(run_key, &mut run, &mut tail);
+        self.append_sorted_tail(&mut tail);
+        self.apply_scores_delta(prev_scores);
+        if map_changed {
+            self.apply_score_map_delta(prev_map);
+        }
+        self.debug_assert_len_consistent();
+        self.pool.len() - len_before
+    }
+
+    /// Turn the members gathered by
+    /// [`extend_from_sorted`](Self::extend_from_sorted) for `key` into a
+    /// score entry queued on `tail`, and clear `run`.
+    fn finish_sorted_run(
+        &mut self,
+        key: Option<OrderedFloat<f64>>,
+        run: &mut Vec<MemberId>,
+        tail: &mut Vec<(OrderedFloat<f64>, BucketRef, usize)>,
+    ) {
+        let Some(key) = key else {
+            debug_assert!(run.is_empty(), "members gathered without a score");
+            return;
+        };
+        let pool = &self.pool;
+        if !run.windows(2).all(|w| pool.get(w[0]) < pool.get(w[1])) {
+            run.sort_unstable_by(|&a, &b| pool.get(a).cmp(pool.get(b)));
+        }
+        let bucket_ref = if let Some(inline) = BucketRef::inline(run) {
+            inline
+        } else {
+            let bucket_id = self
+                .bucket_store
+                .alloc_with(BUCKET_INITIAL_CAPACITY.max(run.len()));
+            let delta = isize::try_from(self.bucket_store.capacity_bytes(bucket_id))
+                .expect("bucket prealloc overflow")
+                + self
+                    .bucket_store
+                    .extend_sorted(bucket_id, run, |m| pool.get(m));
+            self.apply_bucket_mem_delta(delta);
+            BucketRef::Handle(bucket_id)
+        };
+        tail.push((key, bucket_ref, run.len()));
+        run.clear();
+    }
+
+    /// Move queued score entries, all above the current last key, into
+    /// `by_score` and the rank index.
+    fn append_sorted_tail(&mut self, tail: &mut Vec<(OrderedFloat<f64>, BucketRef, usize)>) {
+        let Some(&(first, _, _)) = tail.first() else {
+            return;
+        };
+        debug_assert!(
+            self.by_score
+                .last_key_value()
+                .is_none_or(|(&last, _)| last < first),
+            "sorted tail must extend past the last score"
+        );
+        let entries = tail.iter().map(|&(key, bucket_ref, _)| (key, bucket_ref));
+        // `append` rebuilds both maps in O(n + m), which only pays off once
+        // the tail is about as large as the map it joins.
+        if tail.len() >= self.by_score.len() {
+            let mut appended: BTreeMap<_, _> = entries.collect();
+            self.by_score.append(&mut appended);
+        } else {
+            self.by_score.extend(entries);
+        }
+        self.bucket_index
+            .append_sorted(tail.iter().map(|&(key, _, count)| (key, count)));
+        tail.clear();
+    }
+
+    /// Replace `dst` with the union of `sources`, each score multiplied by the
+    /// matching entry of `weights` (1.0 when `None`) and merged with `agg`.
+    /// Returns the resulting cardinality.
+    ///
+    /// Members are interned straight into `dst`'s pool and their scores
+    /// accumulated in its score table, so unlike collecting into a map keyed
+    /// by `String` there is no per-member allocation. Buckets are built in a
+    /// single pass once all scores are final.
+    ///
+    /// ```
+    /// use gzset::{Aggregate, ScoreSet};
+    ///
+    /// let mut a = ScoreSet::default();
+    /// a.insert(1.0, "x");
+    /// a.insert(2.0, "y");
+    /// let mut b = ScoreSet::default();
+    /// b.insert(5.0, "y");
+    ///
+    /// let mut out = ScoreSet::default();
+    /// let len = ScoreSet::union_into(&mut out, &[&a, &b], Some(&[1.0, 2.0]), Aggregate::Sum);
+    /// assert_eq!(len, 2);
+    /// assert_eq!(out.score("y"), Some(12.0));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `weights` is shorter than `sources`.
+    pub fn union_into(
+        dst: &mut ScoreSet,
+        sources: &[&ScoreSet],
+        weights: Option<&[f64]>,
+        agg: Aggregate,
+    ) -> usize {
+        *dst = ScoreSet::default();
+        let largest = sources.iter().map(|s| s.len()).max().unwrap_or(0);
+        dst.reserve_members(largest);
+
+        for (i, src) in sources.iter().enumerate() {
+            let weight = weights.map_or(1.0, |w| w[i]);
+            for (member, score) in src.iter_all() {
+                let mut value = score * weight;
+                if value.is_nan() {
+                    value = 0.0;
+                }
+                let idx = dst.pool.intern(member) as usize;
+                if idx == dst.scores.len() {
+                    dst.scores.push(value);
+                    #[cfg(any(test, gzset_mem_breakdown))]
+                    add_bytes(&mut dst.mem_breakdown.strings, member.len());
+                } else {
+                    dst.scores[idx] = agg.combine(dst.scores[idx], value);
+                }
+            }
+        }
+
+        let pool = &dst.pool;
+        let scores = &dst.scores;
+        let mut order: Vec<MemberId> = (0..scores.len() as MemberId).collect();
+        order.sort_unstable_by(|&a, &b| {
+            OrderedFloat(scores[a as usize])
+                .cmp(&OrderedFloat(scores[b as usize]))
+                .then_with(|| pool.get(a).cmp(pool.get(b)))
+        });
+
+        let mut bucket_delta: isize = 0;
+        for run in order
+            .chunk_by(|&a, &b| OrderedFloat(scores[a as usize]) == OrderedFloat(scores[b as usize]))
+        {
+            let key = OrderedFloat(scores[run[0] as usize]);
+            let bucket_ref = if let Some(inline) = BucketRef::inline(run) {
+                inline
+            } else {
+                let bucket_id = dst
+                    .bucket_store
+                    .alloc_with(BUCKET_INITIAL_CAPACITY.max(run.len()));
+                bucket_delta += isize::try_from(dst.bucket_store.capacity_bytes(bucket_id))
+                    .expect("bucket prealloc overflow");
+                for &id in run {
+                    let (_, delta, _, _, _) = dst
+                        .bucket_store
+                        .insert_sorted(bucket_id, id, |m| pool.get(m));
+                    bucket_delta += delta;
+                }
+                BucketRef::Handle(bucket_id)
+            };
+            dst.by_score.insert(key, bucket_ref);
+            dst.bucket_index.set(key, run.len());
+        }
+
+        dst.apply_bucket_mem_delta(bucket_delta);
+        dst.apply_scores_delta(0);
+        dst.apply_score_map_delta(0);
+        dst.debug_assert_len_consistent();
+        dst.len()
+    }
+
+    /// Union of `sources` as `(member, score)` pairs in score order, ties by
+    /// member, with scores weighted and combined as in
+    /// [`union_into`](Self::union_into) but without a map of owned names.
+    ///
+    /// Every source is already sorted, so a k-way merge over their iterators
+    /// (walked backwards under a negative weight) yields the weighted scores
+    /// in order. A member held by several sources is emitted once, from the
+    /// first of them, with its scores folded in source order; such members
+    /// land out of place and a final stable sort, linear on the nearly sorted
+    /// output, moves them back. Each member costs `k - 1` membership probes,
+    /// which pays off when the sources barely overlap; see
+    /// [`union_overlap_is_low`](Self::union_overlap_is_low).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `weights` is shorter than `sources`.
+    pub fn union_merged<'a>(
+        sources: &[&'a ScoreSet],
+        weights: Option<&[f64]>,
+        agg: Aggregate,
+    ) -> Vec<(&'a str, f64)> {
+        let weight = |i: usize| weights.map_or(1.0, |w| w[i]);
+        let weighted = |i: usize, score: f64| {
+            let value = score * weight(i);
+            if value.is_nan() {
+                0.0
+            } else {
+                value
+            }
+        };
+        let mut iters: Vec<Box<dyn Iterator<Item = (&'a str, f64)> + 'a>> = sources
+            .iter()
+            .enumerate()
+            .map(
+                |(i, &src)| -> Box<dyn Iterator<Item = (&'a str, f64)> + 'a> {
+                    if weight(i) < 0.0 {
+                        Box::new(src.iter_range_rev(0, -1))
+                    } else {
+                        Box::new(src.iter_range_fwd(0, -1))
+                    }
+                },
+            )
+            .collect();
+        let mut heap = BinaryHeap::with_capacity(iters.len());
+        for (i, iter) in iters.iter_mut().enumerate() {
+            if let Some((member, score)) = iter.next() {
+                heap.push(Reverse((OrderedFloat(weighted(i, score)), member, i)));
             }
         }
 
+        let total = sources.iter().map(|s| s.len()).sum();
+        let mut out: Vec<(&'a str, f64)> = Vec::with_capacity(total);
+        let mut shared: Vec<(&'a str, f64)> = Vec::new();
+        while let Some(Reverse((OrderedFloat(value), member, i))) = heap.pop() {
+            if let Some((next, score)) = iters[i].next() {
+                heap.push(Reverse((OrderedFloat(weighted(i, score)), next, i)));
+            }
+            if sources[..i].iter().any(|s| s.contains(member)) {
+                continue;
+            }
+            let mut combined = value;
+            let mut in_others = false;
+            for (j, other) in sources.iter().enumerate().skip(i + 1) {
+                if let Some(score) = other.score(member) {
+                    combined = agg.combine(combined, weighted(j, score));
+                    in_others = true;
+                }
+            }
+            if in_others {
+                shared.push((member, combined));
+            } else {
+                out.push((member, value));
+            }
+        }
+        // Also restores member order among scores a weight collapsed together.
+        out.append(&mut shared);
+        out.sort_by(|a, b| {
+            OrderedFloat(a.1)
+                .cmp(&OrderedFloat(b.1))
+                .then_with(|| a.0.cmp(b.0))
+        });
+        out
+    }
+
+    /// Whether `sources` overlap little enough for
+    /// [`union_merged`](Self::union_merged) to beat aggregating into a map of
+    /// owned names. Probes up to `UNION_OVERLAP_SAMPLE` members of each
+    /// source, scattered over its ids, against the other sources. A shared
+    /// member costs the merge a score lookup per other source, so the shared
+    /// fraction is weighed by `k - 1` and must stay below 2: two sources always
+    /// merge, while six sharing half their members do not.
+    pub fn union_overlap_is_low(sources: &[&ScoreSet]) -> bool {
+        let mut probed = 0usize;
+        let mut shared = 0usize;
+        for (i, src) in sources.iter().enumerate() {
+            let ids = src.scores.len();
+            for n in 0..UNION_OVERLAP_SAMPLE.min(ids) {
+                // Fibonacci hashing scatters the probes so regular layouts
+                // (every tenth member shared, say) do not alias with them.
+                let id = (n as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) % ids as u64;
+                if src.scores[id as usize].is_nan() {
+                    continue;
+                }
+                let member = src.pool.get(id as MemberId);
+                probed += 1;
+                let in_others = sources
+                    .iter()
+                    .enumerate()
+                    .any(|(j, other)| j != i && other.contains(member));
+                if in_others {
+                    shared += 1;
+                }
+            }
+        }
+        shared * sources.len().saturating_sub(1) < 2 * probed.max(1)
+    }
+
+    /// Number of members present in every one of `sets`, stopping as soon as
+    /// the count reaches `limit` (`0` means no limit, as with `ZINTERCARD`).
+    ///
+    /// Only the smallest set is walked, in member id order, and each member is
+    /// probed against the others with [`contains`](Self::contains), smallest
+    /// first. The cost is bounded by the smallest set's size times one hash
+    /// lookup per other set, however large those are.
+    pub fn intersection_card(sets: &[&ScoreSet], limit: usize) -> usize {
+        let mut sorted: SmallVec<[&ScoreSet; 8]> = sets.iter().copied().collect();
+        sorted.sort_by_key(|set| set.len());
+        let Some((smallest, rest)) = sorted.split_first() else {
+            return 0;
+        };
+        let mut count = 0usize;
+        for (member, _) in smallest.pool.iter() {
+            if rest.iter().all(|other| other.contains(member)) {
+                count += 1;
+                if count == limit {
+                    break;
+                }
+            }
+        }
+        count
+    }
+
+    /// Structural half of [`ScoreSet::insert`]. Bucket and string accounting is
+    /// applied immediately; the caller settles the score table and score map
+    /// byte deltas. Returns `(inserted, score_map_changed)`; nothing is
+    /// touched when interning a new member fails.
+    fn insert_deferred(&mut self, score: f64, member: &str) -> Result<(bool, bool), IdsExhausted> {
+        debug_assert!(
+            !score.is_nan(),
+            "NaN score for {member:?}: NaN marks free score table slots"
+        );
+        if score.is_nan() {
+            // Storing it would read back as a free slot and wedge the NaN key
+            // at the top of `by_score`; leave the set untouched instead.
+            return Ok((false, false));
+        }
+        let key = OrderedFloat(score);
+        // The live count only grows when `try_intern` adds the name, which
+        // saves a separate lookup to tell new members apart.
+        let live_before = self.pool.len();
+        let mut old_key_removed = false;
+        let id = self.pool.try_intern(member)?;
+        let idx = id as usize;
+        let is_new = self.pool.len() != live_before;
+        if self.scores.len() <= idx {
+            self.scores.resize(idx + 1, EMPTY_SCORE);
+        }
+        if is_new {
+            #[cfg(any(test, gzset_mem_breakdown))]
+            add_bytes(&mut self.mem_breakdown.strings, member.len());
+        }
+
         let mut bucket_delta: isize = 0;
-        let name = self.pool.get(id);
+        // A new member has no old score, so only a move leaves a bucket.
+        let old_score = if is_new {
+            None
+        } else {
+            self.get_score_by_id(id)
+        };
         if let Some(old_score) = old_score {
+            let name = self.pool.get(id);
             let old_key = OrderedFloat(old_score);
             if old_key == key {
-                return false;
+                return Ok((false, false));
             }
             if let Some(bucket_ref) = self.by_score.get(&old_key).copied() {
                 match bucket_ref {
-                    BucketRef::Inline1(existing) => {
-                        debug_assert_eq!(
-                            existing, id,
-                            "inline bucket must contain relocating member",
-                        );
-                        self.by_score.remove(&old_key);
-                        old_key_removed = true;
-                    }
                     BucketRef::Handle(bucket_id) => {
                         let (removed, delta, now_empty) =
                             self.bucket_store
@@ -958,10 +2152,19 @@ impl ScoreSet {
                             } else {
                                 bucket_delta += self
                                     .bucket_store
-                                    .maybe_shrink(bucket_id, BUCKET_SHRINK_THRESHOLD);
+                                    .maybe_shrink(bucket_id, bucket_shrink_threshold());
                             }
                         }
                     }
+                    inline => match inline.inline_removed(id) {
+                        Some(rest) => {
+                            self.by_score.insert(old_key, rest);
+                        }
+                        None => {
+                            self.by_score.remove(&old_key);
+                            old_key_removed = true;
+                        }
+                    },
                 }
             }
 
@@ -974,24 +2177,6 @@ impl ScoreSet {
 
         let inserted = match self.by_score.entry(key) {
             Entry::Occupied(mut entry) => match *entry.get() {
-                BucketRef::Inline1(existing_id) => {
-                    let bucket_id = self.bucket_store.alloc_with(BUCKET_INITIAL_CAPACITY);
-                    let prealloc_bytes = self.bucket_store.capacity_bytes(bucket_id);
-                    if prealloc_bytes > 0 {
-                        bucket_delta +=
-                            isize::try_from(prealloc_bytes).expect("bucket prealloc overflow");
-                    }
-                    let (_, delta_existing, _spilled_before, _spilled_after, _pos) = self
-                        .bucket_store
-                        .insert_sorted(bucket_id, existing_id, |m| self.pool.get(m));
-                    bucket_delta += delta_existing;
-                    let (did_insert, delta_new, _sb, _sa, _p) =
-                        self.bucket_store
-                            .insert_sorted(bucket_id, id, |m| self.pool.get(m));
-                    bucket_delta += delta_new;
-                    entry.insert(BucketRef::Handle(bucket_id));
-                    did_insert
-                }
                 BucketRef::Handle(bucket_id) => {
                     let (did_insert, delta, _spilled_before, _spilled_after, _pos) = self
                         .bucket_store
@@ -999,7 +2184,36 @@ impl ScoreSet {
                     bucket_delta += delta;
                     did_insert
                 }
+                inline => {
+                    let pool = &self.pool;
+                    let ids = inline.inline_ids();
+                    let name = pool.get(id);
+                    match ids.binary_search_by(|&m| pool.get(m).cmp(name)) {
+                        Ok(_) => false,
+                        Err(pos) => {
+                            if let Some(grown) = inline.inline_inserted(pos, id) {
+                                entry.insert(grown);
+                            } else {
+                                // Full: spill every member to a heap bucket.
+                                let bucket_id = self
+                                    .bucket_store
+                                    .alloc_with(BUCKET_INITIAL_CAPACITY.max(ids.len() + 1));
+                                bucket_delta +=
+                                    isize::try_from(self.bucket_store.capacity_bytes(bucket_id))
+                                        .expect("bucket prealloc overflow");
+                                for run in [&ids[..pos], &[id], &ids[pos..]] {
+                                    bucket_delta +=
+                                        self.bucket_store
+                                            .extend_sorted(bucket_id, run, |m| pool.get(m));
+                                }
+                                entry.insert(BucketRef::Handle(bucket_id));
+                            }
+                            true
+                        }
+                    }
+                }
             },
+
             Entry::Vacant(entry) => {
                 entry.insert(BucketRef::Inline1(id));
                 new_key_created = true;
@@ -1007,15 +2221,18 @@ impl ScoreSet {
             }
         };
 
-        self.refresh_bucket_index(key);
-
-        if old_key_removed || new_key_created {
-            self.apply_score_map_delta(prev_map);
+        if new_key_created {
+            // The slot was vacant a moment ago; skip re-reading it.
+            self.bucket_index.set(key, 1);
+        } else {
+            self.refresh_bucket_index(key);
         }
+
         if bucket_delta != 0 {
             self.apply_bucket_mem_delta(bucket_delta);
         }
-        inserted
+        self.debug_assert_len_consistent();
+        Ok((inserted, old_key_removed || new_key_created))
     }
 
     pub fn remove(&mut self, member: &str) -> bool {
@@ -1023,6 +2240,18 @@ impl ScoreSet {
             Some(id) => id,
             None => return false,
         };
+        if !self.remove_by_id(id) {
+            return false;
+        }
+        self.maybe_compact_ids();
+        true
+    }
+
+    /// [`remove`](Self::remove) for callers that already hold a live member's
+    /// id, skipping the name hash lookup. Ids are never renumbered here, so a
+    /// batch of collected ids stays valid across calls; the caller runs
+    /// `maybe_compact_ids` once the batch is done.
+    pub(crate) fn remove_by_id(&mut self, id: MemberId) -> bool {
         let score = match self.get_score_by_id(id) {
             Some(s) => OrderedFloat(s),
             None => return false,
@@ -1032,11 +2261,8 @@ impl ScoreSet {
         let mut remove_score_key = false;
         match self.by_score.entry(score) {
             Entry::Occupied(mut entry) => match *entry.get() {
-                BucketRef::Inline1(mid) => {
-                    debug_assert_eq!(mid, id, "inline bucket must contain member when removing");
-                    remove_score_key = true;
-                }
                 BucketRef::Handle(bucket_id) => {
+                    let member = self.pool.get(id);
                     let (removed, delta, now_empty) =
                         self.bucket_store
                             .remove_by_name(bucket_id, member, |m| self.pool.get(m));
@@ -1056,10 +2282,16 @@ impl ScoreSet {
                         } else {
                             bucket_delta += self
                                 .bucket_store
-                                .maybe_shrink(bucket_id, BUCKET_SHRINK_THRESHOLD);
+                                .maybe_shrink(bucket_id, bucket_shrink_threshold());
                         }
                     }
                 }
+                inline => match inline.inline_removed(id) {
+                    Some(rest) => {
+                        entry.insert(rest);
+                    }
+                    None => remove_score_key = true,
+                },
             },
             Entry::Vacant(_) => return false,
         }
@@ -1081,33 +2313,74 @@ impl ScoreSet {
         // Try to reclaim tail capacity if we just cleared the last live slot(s).
         self.compact_scores_tail();
 
-        let new_scores = Self::scores_bytes(&self.scores);
-        if new_scores >= prev_scores {
-            let delta = new_scores - prev_scores;
-            self.mem_bytes += delta;
-            #[cfg(test)]
-            {
-                self.mem_breakdown.member_table += delta;
-            }
-        } else {
-            let delta = prev_scores - new_scores;
-            self.mem_bytes -= delta;
-            #[cfg(test)]
-            {
-                self.mem_breakdown.member_table -= delta;
-            }
-        }
+        self.apply_scores_delta(prev_scores);
 
-        if self.pool.remove(member).is_some() {
-            #[cfg(test)]
-            {
-                self.mem_breakdown.strings -= member.len();
-            }
-        }
+        let removed_len = self.pool.remove_by_id(id);
+        self.account_removed_string(removed_len);
+        self.debug_assert_len_consistent();
 
         true
     }
 
+    /// Number of members whose score equals `score` exactly, i.e. the length
+    /// of its bucket. O(log n).
+    pub fn count_at_score(&self, score: f64) -> usize {
+        self.by_score
+            .get(&OrderedFloat(score))
+            .map_or(0, |&bucket_ref| self.bucket_len(bucket_ref))
+    }
+
+    /// Whether any member holds exactly `score`. O(log n), without walking
+    /// the bucket; `-0.0` and `0.0` are the same score.
+    pub fn contains_score(&self, score: f64) -> bool {
+        self.by_score.contains_key(&OrderedFloat(score))
+    }
+
+    /// Number of members with a score strictly below `score`, i.e. the rank
+    /// a new member at `score` would get ahead of any ties. O(log n) via the
+    /// rank index. A NaN `score` counts every member.
+    pub fn rank_of_score(&self, score: f64) -> usize {
+        self.bucket_index.prefix_before(OrderedFloat(score))
+    }
+
+    /// Number of distinct scores, i.e. score buckets. O(1).
+    pub fn distinct_scores(&self) -> usize {
+        self.by_score.len()
+    }
+
+    /// Number of score buckets that have spilled from the inline form into a
+    /// `BucketStore` bucket. Spilling happens per score once more members tie
+    /// than fit inline, so unlike the listpack to skiplist conversion of core
+    /// sorted sets a set can mix both forms, and a bucket goes back inline
+    /// when it shrinks to one member. O(1): every
+    /// live bucket in the store backs exactly one score.
+    pub fn spilled_bucket_count(&self) -> usize {
+        self.bucket_store.live_count()
+    }
+
+    /// The `n` scores shared by the most members, as `(score, members)`,
+    /// largest first and ties in ascending score order. Surfaces same-score
+    /// clustering, which slows inserts and name lookups within a bucket.
+    /// O(distinct scores · log n), holding at most `n` entries at a time.
+    pub fn largest_buckets(&self, n: usize) -> Vec<(f64, usize)> {
+        if n == 0 {
+            return Vec::new();
+        }
+        // Min-heap on (members, Reverse(score)): the top is the entry to evict.
+        let mut heap = BinaryHeap::with_capacity(n.min(self.by_score.len()) + 1);
+        for (&score, &bucket_ref) in &self.by_score {
+            let members = bucket_ref.len(&self.bucket_store);
+            heap.push(Reverse((members, Reverse(score))));
+            if heap.len() > n {
+                heap.pop();
+            }
+        }
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((members, Reverse(score)))| (score.0, members))
+            .collect()
+    }
+
     pub fn score(&self, member: &str) -> Option<f64> {
         let id = self.pool.lookup(member)?;
         self.get_score_by_id(id)
@@ -1123,18 +2396,10 @@ impl ScoreSet {
         let score_key = OrderedFloat(self.get_score_by_id(id)?);
         let bucket_ref = *self.by_score.get(&score_key)?;
         let pos = match bucket_ref {
-            BucketRef::Inline1(mid) => {
-                if mid == id {
-                    Some(0)
-                } else {
-                    None
-                }
-            }
             BucketRef::Handle(bucket_id) => self
                 .bucket_store
-                .slice(bucket_id)
-                .binary_search_by(|&m| self.pool.get(m).cmp(member))
-                .ok(),
+                .position(bucket_id, member, |m| self.pool.get(m)),
+            inline => inline.inline_ids().iter().position(|&mid| mid == id),
         }?;
         Some(RankFind { score_key, pos })
     }
@@ -1150,47 +2415,114 @@ impl ScoreSet {
     }
 
     pub fn rank(&self, member: &str) -> Option<usize> {
+        self.score_and_rank(member).map(|(_, rank)| rank)
+    }
+
+    /// Score and 0-based rank of `member` from a single pool lookup.
+    ///
+    /// Cheaper than calling [`ScoreSet::score`] and [`ScoreSet::rank`]
+    /// back to back for "where am I" leaderboard queries. A missing member
+    /// costs one hash probe: it allocates nothing and never reaches the
+    /// score map or a bucket. An id whose score slot is a tombstone also
+    /// reads as missing.
+    pub fn score_and_rank(&self, member: &str) -> Option<(f64, usize)> {
         let id = self.pool.lookup(member)?;
-        let score_key = OrderedFloat(self.get_score_by_id(id)?);
+        let score = self.get_score_by_id(id)?;
+        let score_key = OrderedFloat(score);
         let bucket_ref = *self.by_score.get(&score_key)?;
         let pos = match bucket_ref {
-            BucketRef::Inline1(mid) => {
-                if mid == id {
-                    Some(0)
-                } else {
-                    None
-                }
-            }
             BucketRef::Handle(bucket_id) => self
                 .bucket_store
-                .slice(bucket_id)
-                .binary_search_by(|&m| self.pool.get(m).cmp(member))
-                .ok(),
+                .position(bucket_id, member, |m| self.pool.get(m)),
+            inline => inline.inline_ids().iter().position(|&mid| mid == id),
         }?;
         let prefix = self.bucket_index.prefix_before(score_key);
-        Some(prefix + pos)
+        Some((score, prefix + pos))
     }
 
-    pub fn select_by_rank(&self, mut r: usize) -> (&str, f64) {
-        for (score, bucket_ref) in &self.by_score {
-            match *bucket_ref {
-                BucketRef::Inline1(mid) => {
-                    if r == 0 {
-                        return (self.pool.get(mid), score.0);
+    /// Member and score at 0-based rank `r`, in O(log n) via the rank index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `r >= self.len()`.
+    pub fn select_by_rank(&self, r: usize) -> (&str, f64) {
+        let (key, offset) = self.bucket_index.select(r).expect("rank out of bounds");
+        let id = self.by_score[&key].ids(&self.bucket_store)[offset];
+        (self.pool.get(id), key.0)
+    }
+
+    /// Score at percentile `p` by the nearest-rank method: the lowest score
+    /// that at least `p` percent of members are at or below. `p` is clamped
+    /// to `0..=100`, so 0 gives the minimum and 100 the maximum. One rank
+    /// index descent, O(log n). `None` for an empty set or a NaN `p`.
+    ///
+    /// ```
+    /// use gzset::ScoreSet;
+    ///
+    /// let mut set = ScoreSet::default();
+    /// for (i, member) in ["a", "b", "c", "d"].into_iter().enumerate() {
+    ///     set.insert(10.0 * (i + 1) as f64, member);
+    /// }
+    /// assert_eq!(set.score_at_percentile(50.0), Some(20.0));
+    /// assert_eq!(set.score_at_percentile(51.0), Some(30.0));
+    /// assert_eq!(set.score_at_percentile(-5.0), Some(10.0));
+    /// assert_eq!(set.score_at_percentile(100.0), Some(40.0));
+    /// ```
+    pub fn score_at_percentile(&self, p: f64) -> Option<f64> {
+        let len = self.len();
+        if len == 0 || p.is_nan() {
+            return None;
+        }
+        // Multiplying first keeps whole percentiles of whole counts exact.
+        let rank = (p.clamp(0.0, 100.0) * len as f64 / 100.0).ceil() as usize;
+        let (key, _) = self.bucket_index.select(rank.clamp(1, len) - 1)?;
+        Some(key.0)
+    }
+
+    /// Members and scores at each of `sorted_ranks`, in the same order.
+    ///
+    /// Equivalent to calling [`ScoreSet::select_by_rank`] per rank, but one
+    /// forward pass serves them all: nearby ranks step through the score
+    /// map and only long gaps descend the rank index again. Repeated ranks
+    /// are allowed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a rank is `>= self.len()`. `sorted_ranks` must be
+    /// non-decreasing.
+    pub fn select_by_ranks(&self, sorted_ranks: &[usize]) -> Vec<(&str, f64)> {
+        debug_assert!(
+            sorted_ranks.windows(2).all(|w| w[0] <= w[1]),
+            "ranks must be sorted"
+        );
+        let bucket_len = |bucket_ref: BucketRef| bucket_ref.len(&self.bucket_store);
+        let mut out = Vec::with_capacity(sorted_ranks.len());
+        let mut buckets = self.by_score.range(..);
+        // Current bucket: score, reference, and the rank of its first member.
+        let mut current: Option<(OrderedFloat<f64>, BucketRef, usize)> = None;
+        for &r in sorted_ranks {
+            loop {
+                let end = current.map(|(_, bucket_ref, start)| start + bucket_len(bucket_ref));
+                match end {
+                    Some(end) if r < end => break,
+                    Some(end) if r - end <= SELECT_WALK_RANKS => {
+                        let (&key, &bucket_ref) = buckets.next().expect("rank out of bounds");
+                        current = Some((key, bucket_ref, end));
                     }
-                    r -= 1;
-                }
-                BucketRef::Handle(bucket_id) => {
-                    let bucket = self.bucket_store.slice(bucket_id);
-                    if r < bucket.len() {
-                        let id = bucket[r];
-                        return (self.pool.get(id), score.0);
+                    _ => {
+                        let (key, offset) =
+                            self.bucket_index.select(r).expect("rank out of bounds");
+                        buckets = self.by_score.range(key..);
+                        let (_, &bucket_ref) = buckets.next().expect("selected bucket exists");
+                        current = Some((key, bucket_ref, r - offset));
                     }
-                    r -= bucket.len();
                 }
             }
+            let (key, bucket_ref, start) = current.expect("bucket resolved above");
+            let id = bucket_ref.ids(&self.bucket_store)[r - start];
+            out.push((self.pool.get(id), key.0));
         }
-        unreachable!("rank out of bounds");
+        out
     }
 
     pub fn iter_range(&self, start: isize, stop: isize) -> ScoreIter<'_> {
@@ -1222,32 +2554,78 @@ impl ScoreSet {
         )
     }
 
+    /// Members ranked `start..=stop` counting from the highest score, yielded
+    /// highest first with ties in descending name order, as `ZREVRANGE`
+    /// returns them. Negative ranks count from the lowest score; a window
+    /// that selects nothing yields nothing.
+    ///
+    /// ```
+    /// use gzset::ScoreSet;
+    ///
+    /// let mut set = ScoreSet::default();
+    /// for (score, member) in [(1.0, "a"), (2.0, "b"), (2.0, "c"), (3.0, "d")] {
+    ///     set.insert(score, member);
+    /// }
+    /// let top: Vec<_> = set.iter_range_rev(0, 2).collect();
+    /// assert_eq!(top, [("d", 3.0), ("c", 2.0), ("b", 2.0)]);
+    /// assert_eq!(set.iter_range_rev(-1, -1).collect::<Vec<_>>(), [("a", 1.0)]);
+    /// assert_eq!(set.iter_range_rev(3, 1).count(), 0);
+    /// ```
+    pub fn iter_range_rev(&self, start: isize, stop: isize) -> std::iter::Rev<ScoreIter<'_>> {
+        let Some((skip, take)) = self.clamp_rank_range(start, stop) else {
+            return ScoreIter::empty(&self.by_score, &self.bucket_store, &self.pool).rev();
+        };
+        let len = self.len();
+        let fwd_stop = len - 1 - skip;
+        let fwd_start = fwd_stop + 1 - take;
+        ScoreIter::new(
+            &self.by_score,
+            &self.bucket_store,
+            &self.pool,
+            fwd_start,
+            fwd_stop,
+            len,
+        )
+        .rev()
+    }
+
     pub fn iter_range_fwd(&self, start: isize, stop: isize) -> RangeIterFwd<'_> {
+        let Some((skip, take)) = self.clamp_rank_range(start, stop) else {
+            return RangeIterFwd::empty(&self.by_score, &self.bucket_store, &self.pool);
+        };
+        RangeIterFwd::new(&self.by_score, &self.bucket_store, &self.pool, skip, take)
+    }
+
+    /// Members ranked `start..=stop` (negative ranks count from the end), as
+    /// [`iter_range_fwd`](Self::iter_range_fwd) but without the scores.
+    ///
+    /// ```
+    /// use gzset::ScoreSet;
+    ///
+    /// let mut set = ScoreSet::default();
+    /// for (score, member) in [(1.0, "a"), (2.0, "b"), (2.0, "c"), (3.0, "d")] {
+    ///     set.insert(score, member);
+    /// }
+    /// assert_eq!(set.members_in_range(1, -2).collect::<Vec<_>>(), ["b", "c"]);
+    /// ```
+    pub fn members_in_range(&self, start: isize, stop: isize) -> MembersInRange<'_> {
+        let (skip, take) = self.clamp_rank_range(start, stop).unwrap_or((0, 0));
+        MembersInRange::new(&self.by_score, &self.bucket_store, &self.pool, skip, take)
+    }
+
+    /// Resolve an inclusive, possibly negative rank range into `(skip, take)`,
+    /// or `None` when it selects nothing.
+    fn clamp_rank_range(&self, start: isize, stop: isize) -> Option<(usize, usize)> {
         let len = self.pool.len() as isize;
         if len == 0 {
-            return RangeIterFwd::empty(&self.by_score, &self.bucket_store, &self.pool);
-        }
-        let mut start = if start < 0 { len + start } else { start };
-        let mut stop = if stop < 0 { len + stop } else { stop };
-        if start < 0 {
-            start = 0;
-        }
-        if stop < 0 {
-            return RangeIterFwd::empty(&self.by_score, &self.bucket_store, &self.pool);
-        }
-        if stop >= len {
-            stop = len - 1;
+            return None;
         }
-        if start > stop {
-            return RangeIterFwd::empty(&self.by_score, &self.bucket_store, &self.pool);
+        let start = if start < 0 { len + start } else { start }.max(0);
+        let stop = if stop < 0 { len + stop } else { stop }.min(len - 1);
+        if stop < 0 || start > stop {
+            return None;
         }
-        RangeIterFwd::new(
-            &self.by_score,
-            &self.bucket_store,
-            &self.pool,
-            start as usize,
-            (stop - start + 1) as usize,
-        )
+        Some((start as usize, (stop - start + 1) as usize))
     }
 
     pub fn range_iter(&self, start: isize, stop: isize) -> Vec<(f64, String)> {
@@ -1256,23 +2634,113 @@ impl ScoreSet {
             .collect()
     }
 
+    /// Copy the members ranked `start..=stop` (indices as in
+    /// [`iter_range_fwd`](Self::iter_range_fwd)) into a new set. The slice is
+    /// streamed in order through [`extend_from_sorted`](Self::extend_from_sorted),
+    /// so each name is interned straight into the new pool and the copy
+    /// allocates in proportion to the slice, not to `self`.
+    ///
+    /// ```
+    /// use gzset::ScoreSet;
+    ///
+    /// let set = ScoreSet::from_sorted_pairs(&[(1.0, "a"), (2.0, "b"), (3.0, "c")]).unwrap();
+    /// let slice = set.copy_range(1, -1);
+    /// assert_eq!(slice.members_in_range(0, -1).collect::<Vec<_>>(), ["b", "c"]);
+    /// ```
+    pub fn copy_range(&self, start: isize, stop: isize) -> ScoreSet {
+        let mut out = ScoreSet::default();
+        out.extend_from_sorted(
+            self.iter_range_fwd(start, stop)
+                .map(|(member, score)| (score, member)),
+        );
+        out
+    }
+
     pub fn is_empty(&self) -> bool {
         self.pool.len() == 0
     }
 
+    /// Number of members. O(1): the string pool tracks its live count.
     pub fn len(&self) -> usize {
         self.pool.len()
     }
 
-    pub fn iter_all(&self) -> impl Iterator<Item = (&str, f64)> + '_ {
-        self.iter_range_fwd(0, self.len() as isize - 1)
+    /// Every member in ascending order. The iterator is double-ended and
+    /// exact-size, so `.rev()` walks the set from the highest score.
+    pub fn iter_all(&self) -> ScoreIter<'_> {
+        self.iter_range(0, -1)
     }
 
-    pub fn iter_desc(&self) -> ScoreIterDesc<'_> {
-        ScoreIterDesc::new(&self.by_score, &self.bucket_store, &self.pool)
+    /// Members with scores between `min` and `max` in ascending order, ties
+    /// broken by name. Either bound may be infinite, and `min_excl`/`max_excl`
+    /// make the matching endpoint exclusive. An inverted or empty interval
+    /// yields nothing.
+    ///
+    /// ```
+    /// use gzset::ScoreSet;
+    ///
+    /// let mut set = ScoreSet::default();
+    /// for (score, member) in [(1.0, "a"), (2.0, "b"), (2.0, "c"), (3.0, "d")] {
+    ///     set.insert(score, member);
+    /// }
+    /// let hits: Vec<_> = set.iter_by_score_range(2.0, 3.0, false, true).collect();
+    /// assert_eq!(hits, [("b", 2.0), ("c", 2.0)]);
+    ///
+    /// let tail: Vec<_> = set
+    ///     .iter_by_score_range(1.0, f64::INFINITY, true, false)
+    ///     .map(|(member, _)| member)
+    ///     .collect();
+    /// assert_eq!(tail, ["b", "c", "d"]);
+    /// ```
+    pub fn iter_by_score_range(
+        &self,
+        min: f64,
+        max: f64,
+        min_excl: bool,
+        max_excl: bool,
+    ) -> impl Iterator<Item = (&str, f64)> + '_ {
+        self.ids_by_score_range(min, max, min_excl, max_excl)
+            .map(|(id, score)| (self.pool.get(id), score))
     }
 
-    pub fn iter_from<'a>(
+    /// Id-level walk behind [`iter_by_score_range`](Self::iter_by_score_range).
+    fn ids_by_score_range(
+        &self,
+        min: f64,
+        max: f64,
+        min_excl: bool,
+        max_excl: bool,
+    ) -> impl Iterator<Item = (MemberId, f64)> + '_ {
+        let (lo, hi) = (OrderedFloat(min), OrderedFloat(max));
+        // `BTreeMap::range` panics on inverted or doubly-excluded equal bounds.
+        let empty = lo > hi || (lo == hi && (min_excl || max_excl));
+        let bounds = (
+            if min_excl {
+                Bound::Excluded(lo)
+            } else {
+                Bound::Included(lo)
+            },
+            if max_excl {
+                Bound::Excluded(hi)
+            } else {
+                Bound::Included(hi)
+            },
+        );
+        let store = &self.bucket_store;
+        (!empty)
+            .then(|| self.by_score.range(bounds))
+            .into_iter()
+            .flatten()
+            .flat_map(move |(score, bucket_ref)| {
+                bucket_ref.ids(store).iter().map(move |&id| (id, score.0))
+            })
+    }
+
+    pub fn iter_desc(&self) -> ScoreIterDesc<'_> {
+        ScoreIterDesc::new(&self.by_score, &self.bucket_store, &self.pool)
+    }
+
+    pub fn iter_from<'a>(
         &'a self,
         score: OrderedFloat<f64>,
         member: &'a str,
@@ -1292,21 +2760,14 @@ impl ScoreSet {
     pub fn all_items(&self) -> Vec<(f64, String)> {
         let mut out = Vec::new();
         for (score, bucket_ref) in &self.by_score {
-            match *bucket_ref {
-                BucketRef::Inline1(mid) => {
-                    out.push((score.0, self.pool.get(mid).to_owned()));
-                }
-                BucketRef::Handle(bucket_id) => {
-                    let bucket = self.bucket_store.slice(bucket_id);
-                    for id in bucket {
-                        out.push((score.0, self.pool.get(*id).to_owned()));
-                    }
-                }
+            for &id in bucket_ref.ids(&self.bucket_store) {
+                out.push((score.0, self.pool.get(id).to_owned()));
             }
         }
         out
     }
 
+    /// Snapshot of member names in string pool id order, not score order.
     #[cfg(any(test, feature = "bench"))]
     pub fn member_names(&self) -> Vec<String> {
         self.pool.iter().map(|(name, _)| name.to_owned()).collect()
@@ -1329,6 +2790,47 @@ impl ScoreSet {
             .is_some_and(|id| self.get_score_by_id(id).is_some())
     }
 
+    /// Lowest-scored member (ties broken by name) without removing it.
+    pub fn first(&self) -> Option<(&str, f64)> {
+        let (score, bucket_ref) = self.by_score.first_key_value()?;
+        let id = *bucket_ref.ids(&self.bucket_store).first()?;
+        Some((self.pool.get(id), score.0))
+    }
+
+    /// Highest-scored member (ties broken by name) without removing it.
+    pub fn last(&self) -> Option<(&str, f64)> {
+        let (score, bucket_ref) = self.by_score.last_key_value()?;
+        let id = *bucket_ref.ids(&self.bucket_store).last()?;
+        Some((self.pool.get(id), score.0))
+    }
+
+    /// Up to `n` highest-scored members with their scores, highest first and
+    /// ties in descending name order, as `ZREVRANGE key 0 n-1` returns them.
+    /// Walks only the members it returns.
+    ///
+    /// ```
+    /// use gzset::ScoreSet;
+    ///
+    /// let set = ScoreSet::from_sorted_pairs(&[(1.0, "a"), (2.0, "b"), (3.0, "c")]).unwrap();
+    /// assert_eq!(set.top_n(2), [("c".to_owned(), 3.0), ("b".to_owned(), 2.0)]);
+    /// ```
+    pub fn top_n(&self, n: usize) -> Vec<(String, f64)> {
+        self.iter_all()
+            .rev()
+            .take(n)
+            .map(|(member, score)| (member.to_owned(), score))
+            .collect()
+    }
+
+    /// Up to `n` lowest-scored members with their scores, in ascending order
+    /// as `ZRANGE key 0 n-1` returns them. Walks only the members it returns.
+    pub fn bottom_n(&self, n: usize) -> Vec<(String, f64)> {
+        self.iter_all()
+            .take(n)
+            .map(|(member, score)| (member.to_owned(), score))
+            .collect()
+    }
+
     pub fn peek_pop_count(&self, min: bool, n: usize) -> usize {
         if n == 0 || self.is_empty() {
             return 0;
@@ -1342,10 +2844,7 @@ impl ScoreSet {
                 if remaining == 0 {
                     break;
                 }
-                let bucket_len = match bucket_ref {
-                    BucketRef::Inline1(_) => 1,
-                    BucketRef::Handle(bucket_id) => self.bucket_store.len(*bucket_id),
-                };
+                let bucket_len = bucket_ref.len(&self.bucket_store);
                 let take = bucket_len.min(remaining);
                 total += take;
                 remaining -= take;
@@ -1358,10 +2857,7 @@ impl ScoreSet {
                 if remaining == 0 {
                     break;
                 }
-                let bucket_len = match bucket_ref {
-                    BucketRef::Inline1(_) => 1,
-                    BucketRef::Handle(bucket_id) => self.bucket_store.len(*bucket_id),
-                };
+                let bucket_len = bucket_ref.len(&self.bucket_store);
                 let take = bucket_len.min(remaining);
                 total += take;
                 remaining -= take;
@@ -1394,6 +2890,126 @@ impl ScoreSet {
         out
     }
 
+    /// Remove and return every member whose score lies in the given range, in
+    /// ascending order. Bounds follow [`iter_by_score_range`]. A range that
+    /// starts at the lowest score (the sliding-window trim) goes through the
+    /// bulk pop path; otherwise members are removed one by one.
+    ///
+    /// [`iter_by_score_range`]: Self::iter_by_score_range
+    pub fn pop_range_by_score(
+        &mut self,
+        min: f64,
+        max: f64,
+        min_excl: bool,
+        max_excl: bool,
+    ) -> Vec<(String, f64)> {
+        let ids: Vec<(MemberId, f64)> = self
+            .ids_by_score_range(min, max, min_excl, max_excl)
+            .collect();
+        let hits: Vec<(String, f64)> = ids
+            .iter()
+            .map(|&(id, score)| (self.pool.get(id).to_owned(), score))
+            .collect();
+        let ids: Vec<MemberId> = ids.into_iter().map(|(id, _)| id).collect();
+        self.remove_id_run(&ids);
+        hits
+    }
+
+    /// Remove the members ranked `start..=stop` (negative ranks count from
+    /// the end) and return how many were removed.
+    pub fn remove_range_by_rank(&mut self, start: isize, stop: isize) -> usize {
+        let Some((skip, take)) = self.clamp_rank_range(start, stop) else {
+            return 0;
+        };
+        let (key, offset) = self.bucket_index.select(skip).expect("rank in bounds");
+        let store = &self.bucket_store;
+        let ids: Vec<MemberId> = self
+            .by_score
+            .range(key..)
+            .flat_map(|(_, bucket_ref)| bucket_ref.ids(store))
+            .skip(offset)
+            .take(take)
+            .copied()
+            .collect();
+        self.remove_id_run(&ids);
+        ids.len()
+    }
+
+    /// Remove every member whose score lies in the given range and return how
+    /// many were removed. Bounds follow
+    /// [`iter_by_score_range`](Self::iter_by_score_range).
+    pub fn remove_range_by_score(
+        &mut self,
+        min: f64,
+        max: f64,
+        min_excl: bool,
+        max_excl: bool,
+    ) -> usize {
+        let ids: Vec<MemberId> = self
+            .ids_by_score_range(min, max, min_excl, max_excl)
+            .map(|(id, _)| id)
+            .collect();
+        self.remove_id_run(&ids);
+        ids.len()
+    }
+
+    /// Move every member scoring at least `min` (strictly above it when
+    /// `excl`) into a new set and return it, keeping the rest in `self`.
+    /// Together the two hold exactly the original members and scores. The
+    /// moved members are a run at the top of the order, so they leave `self`
+    /// through the bulk pop path.
+    ///
+    /// ```
+    /// use gzset::ScoreSet;
+    ///
+    /// let mut set = ScoreSet::default();
+    /// set.insert(1.0, "a");
+    /// set.insert(2.0, "b");
+    /// set.insert(3.0, "c");
+    /// let upper = set.split_off_by_score(2.0, false);
+    /// assert_eq!(upper.iter_all().collect::<Vec<_>>(), [("b", 2.0), ("c", 3.0)]);
+    /// assert_eq!(set.iter_all().collect::<Vec<_>>(), [("a", 1.0)]);
+    /// ```
+    pub fn split_off_by_score(&mut self, min: f64, excl: bool) -> ScoreSet {
+        let ids: Vec<(MemberId, f64)> = self
+            .ids_by_score_range(min, f64::INFINITY, excl, false)
+            .collect();
+        let mut upper = ScoreSet::default();
+        upper.insert_many(ids.iter().map(|&(id, score)| (score, self.pool.get(id))));
+        let ids: Vec<MemberId> = ids.into_iter().map(|(id, _)| id).collect();
+        self.remove_id_run(&ids);
+        upper
+    }
+
+    /// Remove `ids`, a run of consecutive ranks in ascending order. A run at
+    /// either end goes through the bulk pop path; otherwise members are
+    /// removed one by one.
+    fn remove_id_run(&mut self, ids: &[MemberId]) {
+        let (Some(&lo), Some(&hi)) = (ids.first(), ids.last()) else {
+            return;
+        };
+        let at_end = |entry: Option<(&str, f64)>, id| {
+            entry.is_some_and(|(name, _)| name == self.pool.get(id))
+        };
+        let from_min = if at_end(self.first(), lo) {
+            Some(true)
+        } else if at_end(self.last(), hi) {
+            Some(false)
+        } else {
+            None
+        };
+        if let Some(min) = from_min {
+            let popped = self.pop_n_visit(min, ids.len(), |_, _| {});
+            debug_assert_eq!(popped, ids.len());
+        } else {
+            for &id in ids {
+                let removed = self.remove_by_id(id);
+                debug_assert!(removed, "ranged member must be present");
+            }
+            self.maybe_compact_ids();
+        }
+    }
+
     pub fn pop_n_visit<F>(&mut self, min: bool, n: usize, mut visit: F) -> usize
     where
         F: FnMut(&str, f64),
@@ -1405,6 +3021,7 @@ impl ScoreSet {
         let mut prev_scores: Option<usize> = None;
         let mut prev_map: Option<usize> = None;
         let mut member_buffer: SmallVec<[MemberId; BUCKET_INLINE_CAPACITY]> = SmallVec::new();
+        let shrink_threshold = bucket_shrink_threshold();
 
         while emitted < n {
             let (score_key, bucket_ref) = if min {
@@ -1438,6 +3055,42 @@ impl ScoreSet {
                     self.refresh_bucket_index(score_key);
                     emitted += 1;
                 }
+                BucketRef::InlineN { .. } => {
+                    let ids = bucket_ref.inline_ids();
+                    let to_take = (n - emitted).min(ids.len());
+                    member_buffer.clear();
+                    if min {
+                        member_buffer.extend_from_slice(&ids[..to_take]);
+                    } else {
+                        member_buffer.extend(ids.iter().rev().take(to_take).copied());
+                    }
+                    for &member_id in &member_buffer {
+                        let name = self.pool.get(member_id);
+                        visit(name, score);
+                        self.clear_score_slot(member_id);
+                        let removed = self.pool.remove_by_id(member_id);
+                        self.account_removed_string(removed);
+                    }
+                    emitted += to_take;
+
+                    let rest = if min {
+                        &ids[to_take..]
+                    } else {
+                        &ids[..ids.len() - to_take]
+                    };
+                    if let Some(rest) = BucketRef::inline(rest) {
+                        if let Some(entry) = self.by_score.get_mut(&score_key) {
+                            *entry = rest;
+                        }
+                    } else {
+                        if prev_map.is_none() {
+                            prev_map = Some(Self::score_map_bytes(&self.by_score));
+                        }
+                        self.by_score.remove(&score_key);
+                    }
+                    self.refresh_bucket_index(score_key);
+                }
+
                 BucketRef::Handle(bucket_id) => {
                     let remaining = n - emitted;
                     let bucket_len = self.bucket_store.len(bucket_id);
@@ -1468,17 +3121,11 @@ impl ScoreSet {
                     emitted += popped_here;
 
                     let (now_empty, mut bucket_delta) = if min {
-                        self.bucket_store.advance_front_k(
-                            bucket_id,
-                            popped_here,
-                            BUCKET_SHRINK_THRESHOLD,
-                        )
+                        self.bucket_store
+                            .advance_front_k(bucket_id, popped_here, shrink_threshold)
                     } else {
-                        self.bucket_store.drain_back_k(
-                            bucket_id,
-                            popped_here,
-                            BUCKET_SHRINK_THRESHOLD,
-                        )
+                        self.bucket_store
+                            .drain_back_k(bucket_id, popped_here, shrink_threshold)
                     };
 
                     if now_empty {
@@ -1504,63 +3151,46 @@ impl ScoreSet {
         }
 
         if let Some(prev_map) = prev_map.take() {
-            let new_map = Self::score_map_bytes(&self.by_score);
-            if new_map >= prev_map {
-                let delta = new_map - prev_map;
-                self.mem_bytes += delta;
-                #[cfg(test)]
-                {
-                    self.mem_breakdown.score_map += delta;
-                }
-            } else {
-                let delta = prev_map - new_map;
-                self.mem_bytes -= delta;
-                #[cfg(test)]
-                {
-                    self.mem_breakdown.score_map -= delta;
-                }
-            }
+            self.apply_score_map_delta(prev_map);
         }
 
         if let Some(prev_scores) = prev_scores {
             self.compact_scores_tail();
-            let new_scores = Self::scores_bytes(&self.scores);
-            if new_scores >= prev_scores {
-                let delta = new_scores - prev_scores;
-                self.mem_bytes += delta;
-                #[cfg(test)]
-                {
-                    self.mem_breakdown.member_table += delta;
-                }
-            } else {
-                let delta = prev_scores - new_scores;
-                self.mem_bytes -= delta;
-                #[cfg(test)]
-                {
-                    self.mem_breakdown.member_table -= delta;
-                }
-            }
+            self.apply_scores_delta(prev_scores);
         }
+        self.maybe_compact_ids();
+        self.debug_assert_len_consistent();
 
         emitted
     }
 
+    fn apply_scores_delta(&mut self, prev_scores: usize) {
+        let new_scores = Self::scores_bytes(&self.scores);
+        if new_scores >= prev_scores {
+            let delta = new_scores - prev_scores;
+            add_bytes(&mut self.mem_bytes, delta);
+            #[cfg(any(test, gzset_mem_breakdown))]
+            add_bytes(&mut self.mem_breakdown.member_table, delta);
+        } else {
+            let delta = prev_scores - new_scores;
+            sub_bytes(&mut self.mem_bytes, delta);
+            #[cfg(any(test, gzset_mem_breakdown))]
+            sub_bytes(&mut self.mem_breakdown.member_table, delta);
+        }
+    }
+
     fn apply_score_map_delta(&mut self, prev_map: usize) {
         let new_map = Self::score_map_bytes(&self.by_score);
         if new_map >= prev_map {
             let delta = new_map - prev_map;
-            self.mem_bytes += delta;
-            #[cfg(test)]
-            {
-                self.mem_breakdown.score_map += delta;
-            }
+            add_bytes(&mut self.mem_bytes, delta);
+            #[cfg(any(test, gzset_mem_breakdown))]
+            add_bytes(&mut self.mem_breakdown.score_map, delta);
         } else {
             let delta = prev_map - new_map;
-            self.mem_bytes -= delta;
-            #[cfg(test)]
-            {
-                self.mem_breakdown.score_map -= delta;
-            }
+            sub_bytes(&mut self.mem_bytes, delta);
+            #[cfg(any(test, gzset_mem_breakdown))]
+            sub_bytes(&mut self.mem_breakdown.score_map, delta);
         }
     }
 
@@ -1572,16 +3202,35 @@ impl ScoreSet {
         out
     }
 
+    /// Remove every member lazily, yielding `(member, score)` in ascending
+    /// order. Unlike `pop_all` nothing is collected up front, so dumping or
+    /// converting a large set holds one member at a time. The set is empty
+    /// once the iterator is exhausted or dropped.
+    pub fn drain(&mut self) -> Drain<'_> {
+        Drain { set: self }
+    }
+
+    /// Owned `(score, member)` pairs sorted by score, then member, for
+    /// comparing against a model in tests. Not a stable API: it only exists
+    /// under `test` or the `test-utils` feature.
+    #[cfg(any(test, feature = "test-utils"))]
+    pub fn debug_dump(&self) -> Vec<(f64, String)> {
+        self.iter_all()
+            .map(|(member, score)| (score, member.to_owned()))
+            .collect()
+    }
+
     #[doc(hidden)]
     pub fn bucket_capacity_for_test(&self, score: f64) -> Option<usize> {
         match self.by_score.get(&OrderedFloat(score))? {
             BucketRef::Inline1(_) => Some(1),
+            BucketRef::InlineN { .. } => Some(INLINE_MEMBERS_MAX),
             BucketRef::Handle(id) => {
                 let bytes = self.bucket_store.capacity_bytes(*id);
                 Some(if bytes == 0 {
                     0
                 } else {
-                    bytes / size_of::<MemberId>()
+                    bytes / BUCKET_SLOT_BYTES
                 })
             }
         }
@@ -1600,15 +3249,20 @@ impl ScoreSet {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::buckets::{Bucket, BucketRef, BucketStore};
+    use crate::buckets::{sort_key, Bucket, BucketRef, BucketStore};
     use crate::memory::gzset_mem_usage;
     use crate::pool::{IndexEntry, MemberId};
     use ordered_float::OrderedFloat;
     use rand::{rngs::StdRng, Rng, SeedableRng};
     use redis_module::raw::RedisModule_MallocSize;
-    use std::collections::HashSet;
+    use std::collections::{HashMap, HashSet};
     use std::mem::size_of;
     use std::os::raw::c_void;
+    use std::sync::RwLock;
+
+    /// Tests that depend on the shrink threshold hold a read guard; tests that
+    /// change `gzset.bucket-shrink-threshold` take the write guard.
+    static SHRINK_CONFIG: RwLock<()> = RwLock::new(());
 
     #[inline]
     unsafe fn ms(ptr: *const c_void) -> usize {
@@ -1683,45 +3337,26 @@ mod tests {
         let mut expected_rank = 0usize;
         let mut iter_total = 0usize;
         for (score, bucket_ref) in &set.by_score {
-            match *bucket_ref {
-                BucketRef::Inline1(mid) => {
-                    let member = set.pool.get(mid);
-                    let actual = set.rank(member).unwrap_or_else(|| {
-                        panic!(
-                            "seed {seed} round {round} stage {stage} missing rank for member {member}"
-                        )
-                    });
-                    assert_eq!(
-                        actual,
-                        expected_rank,
-                        "seed {seed} round {round} stage {stage} member {member} expected rank {expected_rank} got {actual}",
-                    );
-                    expected_rank += 1;
-                    iter_total += 1;
-                }
-                BucketRef::Handle(bucket_id) => {
-                    let bucket = set.bucket_store.slice(bucket_id);
-                    assert!(
-                        !bucket.is_empty(),
-                        "seed {seed} round {round} stage {stage} score {score:?} has empty bucket",
-                    );
-                    for id in bucket {
-                        let member = set.pool.get(*id);
-                        let actual = set.rank(member).unwrap_or_else(|| {
-                            panic!(
-                                "seed {seed} round {round} stage {stage} missing rank for member {member}"
-                            )
-                        });
-                        assert_eq!(
-                            actual,
-                            expected_rank,
-                            "seed {seed} round {round} stage {stage} member {member} expected rank {expected_rank} got {actual}",
-                        );
-                        expected_rank += 1;
-                    }
-                    iter_total += bucket.len();
-                }
+            let bucket = bucket_ref.ids(&set.bucket_store);
+            assert!(
+                !bucket.is_empty(),
+                "seed {seed} round {round} stage {stage} score {score:?} has empty bucket",
+            );
+            for id in bucket {
+                let member = set.pool.get(*id);
+                let actual = set.rank(member).unwrap_or_else(|| {
+                    panic!(
+                        "seed {seed} round {round} stage {stage} missing rank for member {member}"
+                    )
+                });
+                assert_eq!(
+                    actual,
+                    expected_rank,
+                    "seed {seed} round {round} stage {stage} member {member} expected rank {expected_rank} got {actual}",
+                );
+                expected_rank += 1;
             }
+            iter_total += bucket.len();
         }
 
         assert_eq!(
@@ -1754,27 +3389,93 @@ mod tests {
             .all(|bucket_ref| matches!(bucket_ref, BucketRef::Inline1(_))));
     }
 
+    #[test]
+    #[cfg(feature = "inline-ties")]
+    fn small_ties_stay_inline_in_name_order() {
+        // The tag and `len` share the first word, so each slot costs 4 bytes.
+        assert_eq!(
+            size_of::<BucketRef>(),
+            size_of::<MemberId>() * (INLINE_MEMBERS_MAX + 1)
+        );
+        let mut set = ScoreSet::default();
+        let mut ties: Vec<String> = (0..INLINE_MEMBERS_MAX).map(|i| format!("t{i}")).collect();
+        for name in ties.iter().rev() {
+            assert!(set.insert(1.0, name));
+        }
+        set.insert(0.0, "low");
+        set.insert(2.0, "high");
+        assert_eq!(set.spilled_bucket_count(), 0);
+        assert!(matches!(
+            set.by_score[&OrderedFloat(1.0)],
+            BucketRef::InlineN { .. }
+        ));
+        ties.sort();
+
+        let mut expected = vec!["low"];
+        expected.extend(ties.iter().map(String::as_str));
+        expected.push("high");
+        let forward: Vec<&str> = set.iter_all().map(|(name, _)| name).collect();
+        assert_eq!(forward, expected);
+        let backward: Vec<&str> = set.iter_desc().map(|(name, _)| name).collect();
+        assert!(backward.iter().eq(expected.iter().rev()));
+        let ranked: Vec<&str> = set.iter_range(1, -2).map(|(name, _)| name).collect();
+        assert_eq!(ranked, &expected[1..expected.len() - 1]);
+        for (rank, name) in expected.iter().enumerate() {
+            assert_eq!(set.rank(name), Some(rank));
+            assert_eq!(set.select_by_rank(rank).0, *name);
+        }
+        let from: Vec<&str> = set
+            .iter_from(OrderedFloat(1.0), &ties[1], true)
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(from, &expected[3..]);
+        assert_eq!(set.count_at_score(1.0), ties.len());
+
+        // Moving a tie away and back keeps the entry inline and ordered.
+        set.insert(5.0, &ties[1]);
+        assert_eq!(set.count_at_score(1.0), ties.len() - 1);
+        set.insert(1.0, &ties[1]);
+        assert_eq!(
+            set.iter_all().map(|(name, _)| name).collect::<Vec<_>>(),
+            expected
+        );
+        assert_eq!(set.recompute_mem_breakdown(), set.debug_mem_breakdown());
+
+        let popped = set.pop_n(true, 2);
+        assert_eq!(popped, [("low".into(), 0.0), (ties[0].clone(), 1.0)]);
+        let popped = set.pop_n(false, 2);
+        let last = ties.last().unwrap().clone();
+        assert_eq!(popped, [("high".into(), 2.0), (last, 1.0)]);
+        assert_eq!(set.len(), ties.len() - 2);
+        assert_eq!(set.recompute_mem_breakdown(), set.debug_mem_breakdown());
+        assert_eq!(set.mem_bytes(), set.debug_mem_breakdown().structural());
+    }
+
     #[test]
     fn handle_reverts_to_inline_after_removal() {
         let mut set = ScoreSet::default();
-        assert!(set.insert(1.0, "a"));
-        assert!(set.insert(1.0, "b"));
+        let names: Vec<String> = (0..=INLINE_MEMBERS_MAX).map(|i| format!("m{i}")).collect();
+        for name in &names {
+            assert!(set.insert(1.0, name));
+        }
         let bucket_ref = *set
             .by_score
             .get(&OrderedFloat(1.0))
             .expect("score should exist");
         let bucket_id = match bucket_ref {
             BucketRef::Handle(id) => id,
-            BucketRef::Inline1(_) => panic!("expected handle after inserting two members"),
+            _ => panic!("expected handle once the inline slots overflow"),
         };
-        assert!(set.remove("a"));
+        for name in &names[..INLINE_MEMBERS_MAX] {
+            assert!(set.remove(name));
+        }
         let new_ref = *set
             .by_score
             .get(&OrderedFloat(1.0))
             .expect("score should remain present");
         match new_ref {
-            BucketRef::Inline1(mid) => assert_eq!(set.pool.get(mid), "b"),
-            BucketRef::Handle(_) => panic!("bucket should convert back to inline"),
+            BucketRef::Inline1(mid) => assert_eq!(set.pool.get(mid), names[INLINE_MEMBERS_MAX]),
+            _ => panic!("bucket should convert back to inline"),
         }
         match set.bucket_store.buckets.get(bucket_id as usize) {
             Some(slot) => assert!(slot.is_none(), "bucket slot should be freed"),
@@ -1909,6 +3610,7 @@ mod tests {
                     "seed {seed} round {round} stage after_insert_more member tracking diverged",
                 );
                 assert_rank_matches(&set, seed, round, "after_insert_more");
+                set.validate();
             }
         }
     }
@@ -1949,15 +3651,122 @@ mod tests {
         }
     }
 
+    #[test]
+    fn recomputed_breakdown_matches_running_counters() {
+        let mut set = ScoreSet::default();
+        let check = |set: &ScoreSet| {
+            assert_eq!(set.recompute_mem_breakdown(), set.debug_mem_breakdown());
+            assert_eq!(set.recompute_mem_breakdown().structural(), set.mem_bytes());
+        };
+        check(&set);
+        for i in 0..200 {
+            assert!(set.insert((i % 7) as f64, &format!("m{i}")));
+        }
+        for i in 200..300 {
+            assert!(set.insert(i as f64, &format!("m{i}")));
+        }
+        check(&set);
+        for i in (0..300).step_by(3) {
+            assert!(set.remove(&format!("m{i}")));
+        }
+        check(&set);
+        let _ = set.pop_n_visit(true, 40, |_, _| {});
+        let _ = set.pop_n_visit(false, 40, |_, _| {});
+        check(&set);
+        set.shrink_to_fit();
+        check(&set);
+    }
+
+    #[test]
+    fn balanced_insert_remove_restores_mem_bytes() {
+        let mut set = ScoreSet::default();
+        for i in 0..100 {
+            set.insert((i / 3) as f64, &format!("m{i}"));
+        }
+        let before = set.mem_bytes();
+        let breakdown = set.debug_mem_breakdown();
+
+        // New scores at the top, in the middle and at the bottom, each undone
+        // by a removal path. Ties are left out: a spilled bucket may keep or
+        // trim capacity on removal, which is not an accounting imbalance.
+        set.insert(1000.0, "highest");
+        assert!(set.remove("highest"));
+        assert_eq!(set.mem_bytes(), before);
+
+        set.insert(5.5, "middle");
+        assert!(set.remove("middle"));
+        assert_eq!(set.mem_bytes(), before);
+
+        set.insert(-1.0, "lowest");
+        assert_eq!(set.pop_n(true, 1), vec![("lowest".to_owned(), -1.0)]);
+        assert_eq!(set.mem_bytes(), before);
+        assert_eq!(set.debug_mem_breakdown(), breakdown);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "memory accounting underflow")]
+    fn accounting_underflow_panics_in_debug() {
+        let mut counter = 8;
+        sub_bytes(&mut counter, 16);
+    }
+
+    #[test]
+    #[cfg(not(debug_assertions))]
+    fn accounting_underflow_wraps_in_release() {
+        let mut counter = 8;
+        sub_bytes(&mut counter, 16);
+        assert_eq!(counter, usize::MAX - 7);
+    }
+
+    #[test]
+    fn estimate_memory_matches_built_sets() {
+        assert_eq!(ScoreSet::estimate_memory(0, 10), Some(0));
+        for members in [1, 3, 7, 12, 100, 1000, 20_000] {
+            for avg_len in [0, 1, 8, 40] {
+                let mut set = ScoreSet::default();
+                for i in 0..members {
+                    let name = format!("{i:0>avg_len$}");
+                    let name = &name[name.len() - avg_len..];
+                    let name = if avg_len == 0 { "" } else { name };
+                    set.insert(i as f64, name);
+                }
+                if set.len() != members {
+                    // Short names collide; only exact populations are comparable.
+                    continue;
+                }
+                let actual = unsafe { expected_usage(&set) };
+                assert_eq!(
+                    ScoreSet::estimate_memory(members, avg_len),
+                    Some(actual),
+                    "{members} members of {avg_len} bytes"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn estimate_memory_rejects_impossible_shapes() {
+        let max = i64::MAX as usize;
+        assert_eq!(ScoreSet::estimate_memory(max, 10), None);
+        assert_eq!(ScoreSet::estimate_memory(MAX_IDS + 1, 0), None);
+        assert_eq!(ScoreSet::estimate_memory(1000, max), None);
+        assert_eq!(ScoreSet::estimate_memory(max, max), None);
+        assert_eq!(ScoreSet::estimate_memory(usize::MAX, usize::MAX), None);
+        assert!(ScoreSet::estimate_memory(MAX_IDS, 16).is_some());
+        assert_eq!(ScoreSet::estimate_memory(0, max), Some(0));
+    }
+
     #[test]
     fn compacts_bucket_store_after_freeing_tail() {
         let mut set = Box::new(ScoreSet::default());
         let bucket_count = 128usize;
+        // One tie past the inline slots, so every score spills.
+        let ties = INLINE_MEMBERS_MAX + 1;
         for score in 0..bucket_count {
-            let member_a = format!("m{score}_a");
-            let member_b = format!("m{score}_b");
-            assert!(set.insert(score as f64, &member_a));
-            assert!(set.insert(score as f64, &member_b));
+            for tie in 0..ties {
+                assert!(set.insert(score as f64, &format!("m{score}_{tie}")));
+            }
         }
         assert_eq!(set.bucket_store.buckets.len(), bucket_count);
         let before_capacity = set.bucket_store.buckets.capacity();
@@ -1965,10 +3774,9 @@ mod tests {
         let before_usage = unsafe { gzset_mem_usage((&*set as *const ScoreSet) as *const c_void) };
 
         for score in (bucket_count / 2..bucket_count).rev() {
-            let member_a = format!("m{score}_a");
-            let member_b = format!("m{score}_b");
-            assert!(set.remove(&member_a));
-            assert!(set.remove(&member_b));
+            for tie in 0..ties {
+                assert!(set.remove(&format!("m{score}_{tie}")));
+            }
         }
 
         assert_eq!(set.bucket_store.buckets.len(), bucket_count / 2);
@@ -2026,24 +3834,7 @@ mod tests {
             );
         }
 
-        let set_ref = set.as_ref();
-        let mut total_members = 0usize;
-        for (score, bucket_ref) in &set_ref.by_score {
-            match *bucket_ref {
-                BucketRef::Inline1(_) => {
-                    total_members += 1;
-                }
-                BucketRef::Handle(bucket_id) => {
-                    let bucket = set_ref.bucket_store.slice(bucket_id);
-                    assert!(
-                        !bucket.is_empty(),
-                        "score {score:?} should not have empty bucket",
-                    );
-                    total_members += bucket.len();
-                }
-            }
-        }
-        assert_eq!(total_members, set_ref.len());
+        set.validate();
 
         let remaining = set.range_iter(0, -1);
         for (idx, (_, member)) in remaining.iter().enumerate() {
@@ -2054,6 +3845,7 @@ mod tests {
         assert!(set.is_empty());
         let set_ref = set.as_ref();
         assert!(set_ref.by_score.is_empty());
+        set.validate();
     }
 
     #[test]
@@ -2092,11 +3884,16 @@ mod tests {
         assert!(!streaming.by_score.contains_key(&OrderedFloat(1.0)));
         let two_ref = streaming.by_score.get(&OrderedFloat(2.0)).copied();
         assert!(matches!(two_ref, Some(BucketRef::Inline1(_))));
+        streaming.validate();
+        sequential.validate();
     }
 
     fn bucket_shrink_mem_on_pop(min: bool) {
+        let _cfg = SHRINK_CONFIG.read().unwrap();
         let mut set = ScoreSet::default();
-        let total = super::BUCKET_SHRINK_THRESHOLD * 2;
+        // One past a power of two, so the buffer has doubled beyond twice the
+        // threshold and popping back down to it leaves more than 2x slack.
+        let total = super::bucket_shrink_threshold() * 2 + 1;
         for i in 0..total {
             let member = format!("m{i}");
             assert!(set.insert(1.0, &member));
@@ -2107,13 +3904,13 @@ mod tests {
             .expect("bucket should exist");
         let bucket_id = match bucket_ref {
             BucketRef::Handle(id) => id,
-            BucketRef::Inline1(_) => panic!("bucket should spill when over threshold"),
+            _ => panic!("bucket should spill when over threshold"),
         };
         let initial_bytes = set.bucket_store.capacity_bytes(bucket_id);
         assert!(initial_bytes > 0, "expected spill before pops");
-        let initial_cap = initial_bytes / size_of::<MemberId>();
+        let initial_cap = initial_bytes / BUCKET_SLOT_BYTES;
         assert!(
-            initial_cap > super::BUCKET_SHRINK_THRESHOLD,
+            initial_cap > super::bucket_shrink_threshold(),
             "expected spill before pops",
         );
 
@@ -2124,11 +3921,11 @@ mod tests {
             "bucket accounting should reflect spill"
         );
 
-        for _ in 0..super::BUCKET_SHRINK_THRESHOLD {
+        for _ in 0..=super::bucket_shrink_threshold() {
             assert!(set.pop_one(min).is_some());
         }
 
-        assert_eq!(set.len(), super::BUCKET_SHRINK_THRESHOLD);
+        assert_eq!(set.len(), super::bucket_shrink_threshold());
 
         let after_mem = set.mem_bytes();
         let after_buckets = set.debug_mem_breakdown().buckets;
@@ -2141,11 +3938,11 @@ mod tests {
             .get(&OrderedFloat(1.0))
             .expect("bucket should remain present");
         let remaining_bytes = match remaining_ref {
-            BucketRef::Inline1(_) => 0,
             BucketRef::Handle(id) => set.bucket_store.capacity_bytes(*id),
+            _ => 0,
         };
         assert!(
-            remaining_bytes <= super::BUCKET_SHRINK_THRESHOLD * size_of::<MemberId>(),
+            remaining_bytes <= super::bucket_shrink_threshold() * BUCKET_SLOT_BYTES,
             "remaining capacity should be bounded by threshold",
         );
         assert_eq!(
@@ -2164,8 +3961,9 @@ mod tests {
         );
         assert_eq!(
             set.bucket_capacity_for_test(1.0),
-            Some(super::BUCKET_SHRINK_THRESHOLD)
+            Some(super::bucket_shrink_threshold())
         );
+        set.validate();
     }
 
     #[test]
@@ -2178,10 +3976,39 @@ mod tests {
         bucket_shrink_mem_on_pop(false);
     }
 
+    #[test]
+    fn shrink_threshold_config_controls_bucket_capacity() {
+        let _cfg = SHRINK_CONFIG.write().unwrap();
+        let capacity_after_pops = |threshold: i64| {
+            BUCKET_SHRINK_THRESHOLD.store(threshold, Ordering::Relaxed);
+            let mut set = ScoreSet::default();
+            for i in 0..128 {
+                assert!(set.insert(1.0, &format!("m{i}")));
+            }
+            assert_eq!(set.pop_n_visit(true, 96, |_, _| {}), 96);
+            let cap = set.bucket_capacity_for_test(1.0);
+            assert_eq!(set.mem_bytes(), set.debug_mem_breakdown().structural());
+            cap
+        };
+
+        assert_eq!(
+            capacity_after_pops(BUCKET_SHRINK_THRESHOLD_DEFAULT),
+            Some(32)
+        );
+        let cap = capacity_after_pops(16).expect("bucket remains");
+        assert!(cap > 32, "bucket above threshold keeps its capacity: {cap}");
+        // Out-of-range values are clamped rather than disabling shrinking.
+        assert_eq!(capacity_after_pops(0), Some(cap));
+        assert_eq!(bucket_shrink_threshold(), BUCKET_INITIAL_CAPACITY);
+        BUCKET_SHRINK_THRESHOLD.store(BUCKET_SHRINK_THRESHOLD_DEFAULT, Ordering::Relaxed);
+        assert_eq!(bucket_shrink_threshold(), 64);
+    }
+
     #[test]
     fn repeated_min_pops_from_single_bucket() {
+        let _cfg = SHRINK_CONFIG.read().unwrap();
         let mut set = ScoreSet::default();
-        let total = super::BUCKET_SHRINK_THRESHOLD * 8;
+        let total = super::bucket_shrink_threshold() * 8;
         let names: Vec<String> = (0..total).map(|i| format!("member-{i:05}")).collect();
         for name in &names {
             assert!(set.insert(1.0, name));
@@ -2194,11 +4021,11 @@ mod tests {
             .expect("bucket should exist after inserts");
         let bucket_id = match bucket_ref {
             BucketRef::Handle(id) => id,
-            BucketRef::Inline1(_) => panic!("bucket must spill for repeated pops"),
+            _ => panic!("bucket must spill for repeated pops"),
         };
         let initial_bucket_bytes = set.bucket_store.capacity_bytes(bucket_id);
         assert!(
-            initial_bucket_bytes > super::BUCKET_SHRINK_THRESHOLD * size_of::<MemberId>(),
+            initial_bucket_bytes > super::bucket_shrink_threshold() * BUCKET_SLOT_BYTES,
             "expected spilled capacity before pops",
         );
 
@@ -2254,19 +4081,19 @@ mod tests {
                 match entry {
                     BucketRef::Handle(id) => {
                         let cap_bytes = set.bucket_store.capacity_bytes(id);
-                        if remaining > super::BUCKET_SHRINK_THRESHOLD {
+                        if remaining > super::bucket_shrink_threshold() {
                             assert_eq!(
                                 cap_bytes, initial_bucket_bytes,
                                 "capacity should remain until shrink threshold",
                             );
-                        } else if remaining == super::BUCKET_SHRINK_THRESHOLD {
+                        } else if remaining == super::bucket_shrink_threshold() {
                             assert!(
-                                cap_bytes >= super::BUCKET_SHRINK_THRESHOLD * size_of::<MemberId>(),
+                                cap_bytes >= super::bucket_shrink_threshold() * BUCKET_SLOT_BYTES,
                                 "capacity should stay at or above threshold before shrinking",
                             );
                         } else {
                             assert!(
-                                cap_bytes <= super::BUCKET_SHRINK_THRESHOLD * size_of::<MemberId>(),
+                                cap_bytes <= super::bucket_shrink_threshold() * BUCKET_SLOT_BYTES,
                                 "capacity should shrink near threshold",
                             );
                         }
@@ -2275,7 +4102,7 @@ mod tests {
                             "bucket accounting should match capacity",
                         );
                     }
-                    BucketRef::Inline1(_) => {
+                    _ => {
                         panic!("bucket should remain spilled while more than one member remains");
                     }
                 }
@@ -2293,5 +4120,1562 @@ mod tests {
 
         assert_eq!(expected_index, total);
         assert!(set.is_empty());
+        set.validate();
+    }
+
+    #[test]
+    fn pop_n_across_exact_bucket_boundary() {
+        let _cfg = SHRINK_CONFIG.read().unwrap();
+        let bucket_len = super::bucket_shrink_threshold() * 3;
+        for (min, extra) in [(true, 0), (true, 1), (false, 0), (false, 1)] {
+            // The wide bucket sits at the popped end, followed by a small
+            // bucket and an inline score.
+            let (wide, next, far) = if min {
+                (1.0, 2.0, 3.0)
+            } else {
+                (3.0, 2.0, 1.0)
+            };
+            // Enough ties at `next` to spill past the inline slots.
+            let mut next_names: Vec<String> =
+                (0..=INLINE_MEMBERS_MAX).map(|i| format!("n{i}")).collect();
+            if !min {
+                next_names.reverse();
+            }
+            let next_len = next_names.len();
+            let mut set = ScoreSet::default();
+            for i in 0..bucket_len {
+                set.insert(wide, &format!("w{i:05}"));
+            }
+            for name in &next_names {
+                set.insert(next, name);
+            }
+            set.insert(far, "far");
+            assert_eq!(set.spilled_bucket_count(), 2);
+
+            let mut popped = Vec::new();
+            let emitted = set.pop_n_visit(min, bucket_len + extra, |name, score| {
+                popped.push((name.to_owned(), score));
+            });
+            assert_eq!(emitted, bucket_len + extra);
+            assert!(popped[..bucket_len].iter().all(|&(_, score)| score == wide));
+            if extra == 1 {
+                assert_eq!(popped[bucket_len], (next_names[0].to_owned(), next));
+            }
+            assert!(!set.by_score.contains_key(&OrderedFloat(wide)));
+            assert_eq!(set.len(), next_len + 1 - extra);
+            assert_eq!(set.recompute_mem_breakdown(), set.debug_mem_breakdown());
+            assert_eq!(set.recompute_mem_breakdown().structural(), set.mem_bytes());
+
+            // The emptied bucket is freed; the next pop starts on `next`.
+            let rest = set.pop_n(min, next_len + 1);
+            let mut want: Vec<(String, f64)> = next_names[extra..]
+                .iter()
+                .map(|name| (name.clone(), next))
+                .collect();
+            want.push(("far".to_owned(), far));
+            assert_eq!(rest, want);
+            assert!(set.is_empty());
+            assert_eq!(set.spilled_bucket_count(), 0);
+            assert_eq!(set.recompute_mem_breakdown(), set.debug_mem_breakdown());
+            assert_eq!(set.recompute_mem_breakdown().structural(), set.mem_bytes());
+            set.validate();
+        }
+    }
+
+    #[test]
+    fn hot_bucket_pop_insert_cycle_rarely_reallocates() {
+        let _cfg = SHRINK_CONFIG.read().unwrap();
+        let live = super::bucket_shrink_threshold() / 2 + 3;
+        let mut set = ScoreSet::default();
+        for i in 0..live {
+            assert!(set.insert(1.0, &format!("m{i:06}")));
+        }
+
+        let cycles = 4_000;
+        let mut reallocs = 0;
+        let mut prev_cap = set.bucket_capacity_for_test(1.0);
+        for i in live..live + cycles {
+            assert!(set.pop_one(true).is_some());
+            let mid_cap = set.bucket_capacity_for_test(1.0);
+            assert!(set.insert(1.0, &format!("m{i:06}")));
+            let cap = set.bucket_capacity_for_test(1.0);
+            for observed in [mid_cap, cap] {
+                if observed != prev_cap {
+                    reallocs += 1;
+                    prev_cap = observed;
+                }
+            }
+            let cap = cap.expect("hot bucket stays live");
+            assert!(cap <= 2 * live + 2, "capacity unbounded: {cap}");
+            assert_eq!(set.mem_bytes(), set.debug_mem_breakdown().structural());
+        }
+        assert_eq!(set.len(), live);
+        set.validate();
+        assert!(
+            reallocs <= 4,
+            "expected the hot bucket to settle, saw {reallocs} capacity changes"
+        );
+    }
+
+    #[test]
+    fn extend_from_sorted_matches_insert_many() {
+        let mut rng = StdRng::seed_from_u64(0x1866);
+        let mut items: Vec<(f64, String)> = (0..3_000)
+            .map(|i| {
+                (
+                    rng.gen_range(0..400) as f64,
+                    format!("m{}", rng.gen_range(0..i + 1)),
+                )
+            })
+            .collect();
+        items.sort_by(|a, b| a.0.total_cmp(&b.0));
+        // Sprinkle in steps backwards and names out of order within a score.
+        for i in (0..items.len()).step_by(97) {
+            items[i].0 = rng.gen_range(0..400) as f64;
+        }
+        items.swap(10, 11);
+
+        for base in [Vec::new(), vec![(5.0, "m1"), (399.0, "m2"), (-1.0, "zz")]] {
+            let mut expected = ScoreSet::default();
+            expected.insert_many(base.iter().copied());
+            let mut got = ScoreSet::default();
+            got.insert_many(base.iter().copied());
+
+            let want = expected.insert_many(items.iter().map(|(s, m)| (*s, m.as_str())));
+            let added = got.extend_from_sorted(items.iter().map(|(s, m)| (*s, m.as_str())));
+            assert_eq!(added, want);
+            assert_eq!(got.all_items(), expected.all_items());
+            for (rank, (score, member)) in expected.all_items().iter().enumerate() {
+                assert_eq!(got.select_by_rank(rank), (member.as_str(), *score));
+                assert_eq!(got.rank(member), Some(rank));
+            }
+            assert_eq!(got.mem_bytes(), got.debug_mem_breakdown().structural());
+            assert_eq!(got.mem_bytes(), got.recompute_mem_breakdown().structural());
+        }
+
+        // A short tail after a large set joins the map entry by entry.
+        let mut big = ScoreSet::default();
+        let names: Vec<String> = (0..2_000).map(|i| format!("b{i}")).collect();
+        big.extend_from_sorted(
+            names
+                .iter()
+                .enumerate()
+                .map(|(i, m)| (i as f64, m.as_str())),
+        );
+        assert_eq!(
+            big.extend_from_sorted([(5e3, "x"), (5e3, "y"), (6e3, "z")]),
+            3
+        );
+        assert_eq!(big.rank("z"), Some(big.len() - 1));
+        assert_eq!(big.select_by_rank(big.len() - 3), ("x", 5e3));
+        assert_eq!(big.mem_bytes(), big.recompute_mem_breakdown().structural());
+
+        let mut set = ScoreSet::default();
+        assert_eq!(set.extend_from_sorted([]), 0);
+        assert_eq!(
+            set.extend_from_sorted([(1.0, "b"), (1.0, "a"), (1.0, "b")]),
+            2
+        );
+        assert_eq!(set.members_in_range(0, -1).collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(set.mem_bytes(), set.debug_mem_breakdown().structural());
+    }
+
+    #[test]
+    fn from_sorted_pairs_checks_order_and_duplicates() {
+        let mut rng = StdRng::seed_from_u64(0x1876);
+        let mut owned: Vec<(f64, String)> = (0..2_000)
+            .map(|i| (rng.gen_range(0..300) as f64, format!("m{i}")))
+            .collect();
+        owned.sort_by(|a, b| a.0.total_cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+        let pairs: Vec<(f64, &str)> = owned.iter().map(|(s, m)| (*s, m.as_str())).collect();
+        let set = ScoreSet::from_sorted_pairs(&pairs).unwrap();
+        let mut want = ScoreSet::default();
+        want.insert_many(pairs.iter().copied());
+        assert!(set.iter_all().eq(want.iter_all()));
+        assert_eq!(set.mem_bytes(), set.recompute_mem_breakdown().structural());
+
+        assert!(ScoreSet::from_sorted_pairs(&[]).unwrap().is_empty());
+        for (bad, index) in [
+            (vec![(1.0, "b"), (1.0, "a")], 1),
+            (vec![(1.0, "a"), (1.0, "a")], 1),
+            (vec![(1.0, "a"), (2.0, "b"), (0.5, "c")], 2),
+            (vec![(f64::NAN, "a")], 0),
+            (vec![(1.0, "a"), (f64::NAN, "b")], 1),
+        ] {
+            assert_eq!(
+                ScoreSet::from_sorted_pairs(&bad).err(),
+                Some(SortedPairsError::OutOfOrder(index)),
+                "{bad:?}"
+            );
+        }
+        assert_eq!(
+            ScoreSet::from_sorted_pairs(&[(1.0, "a"), (1.0, "b"), (3.0, "a")]).err(),
+            Some(SortedPairsError::DuplicateMember)
+        );
+    }
+
+    #[test]
+    fn insert_many_matches_insert_loop() {
+        let mut rng = StdRng::seed_from_u64(0x1800);
+        let mut items = Vec::new();
+        for i in 0..2_000 {
+            let score = rng.gen_range(0..200) as f64;
+            items.push((score, format!("m{}", i % 1_500)));
+        }
+
+        let mut looped = ScoreSet::default();
+        for (score, member) in &items {
+            looped.insert(*score, member);
+        }
+
+        let mut batched = ScoreSet::default();
+        let added = batched.insert_many(items.iter().map(|(s, m)| (*s, m.as_str())));
+
+        assert_eq!(added, looped.len());
+        assert_eq!(batched.all_items(), looped.all_items());
+        for (_, member) in batched.all_items() {
+            assert_eq!(batched.rank(&member), looped.rank(&member));
+        }
+
+        let bd = batched.debug_mem_breakdown();
+        let ld = looped.debug_mem_breakdown();
+        assert_eq!(batched.mem_bytes(), bd.structural());
+        assert_eq!(bd.score_map, ld.score_map);
+        assert_eq!(bd.buckets, ld.buckets);
+        assert_eq!(bd.strings, ld.strings);
+        assert_eq!(
+            bd.member_table,
+            batched.scores.capacity() * size_of::<f64>(),
+            "score table accounting must track reserved capacity",
+        );
+
+        // A second batch over existing members only moves scores.
+        let moved = batched.insert_many([(1_000.0, "m0"), (1_000.0, "m1")]);
+        assert_eq!(moved, 0);
+        assert_eq!(batched.score("m0"), Some(1_000.0));
+        assert_eq!(batched.rank("m1"), Some(batched.len() - 1));
+        assert_eq!(
+            batched.mem_bytes(),
+            batched.debug_mem_breakdown().structural()
+        );
+    }
+
+    #[test]
+    fn first_and_last_break_ties_by_name() {
+        let mut set = ScoreSet::default();
+        assert_eq!(set.first(), None);
+        assert_eq!(set.last(), None);
+
+        assert!(set.insert(5.0, "solo"));
+        assert_eq!(set.first(), Some(("solo", 5.0)));
+        assert_eq!(set.last(), Some(("solo", 5.0)));
+
+        for member in ["b", "c", "a"] {
+            assert!(set.insert(1.0, member));
+        }
+        for member in ["y", "z", "x"] {
+            assert!(set.insert(9.0, member));
+        }
+        assert_eq!(set.first(), Some(("a", 1.0)));
+        assert_eq!(set.last(), Some(("z", 9.0)));
+
+        assert_eq!(set.pop_one(true), Some(("a".to_string(), 1.0)));
+        assert_eq!(set.pop_one(false), Some(("z".to_string(), 9.0)));
+        assert_eq!(set.first(), Some(("b", 1.0)));
+        assert_eq!(set.last(), Some(("y", 9.0)));
+
+        assert!(set.remove("b"));
+        assert!(set.remove("c"));
+        assert_eq!(set.first(), Some(("solo", 5.0)));
+    }
+
+    #[test]
+    fn top_and_bottom_n_cut_through_ties() {
+        let owned = |pairs: &[(&str, f64)]| -> Vec<(String, f64)> {
+            pairs.iter().map(|&(m, s)| (m.to_owned(), s)).collect()
+        };
+        let mut set = ScoreSet::default();
+        assert!(set.top_n(3).is_empty());
+        assert!(set.bottom_n(3).is_empty());
+
+        for (score, member) in [(1.0, "a"), (2.0, "d"), (2.0, "b"), (2.0, "c"), (3.0, "e")] {
+            assert!(set.insert(score, member));
+        }
+        // The boundary falls inside the tie at 2.0 from both ends.
+        assert_eq!(set.top_n(3), owned(&[("e", 3.0), ("d", 2.0), ("c", 2.0)]));
+        assert_eq!(
+            set.bottom_n(3),
+            owned(&[("a", 1.0), ("b", 2.0), ("c", 2.0)])
+        );
+        assert_eq!(set.top_n(0), owned(&[]));
+
+        let all: Vec<(String, f64)> = set
+            .range_iter(0, -1)
+            .into_iter()
+            .map(|(s, m)| (m, s))
+            .collect();
+        assert_eq!(set.bottom_n(10), all);
+        let mut rev = all;
+        rev.reverse();
+        assert_eq!(set.top_n(usize::MAX), rev);
+    }
+
+    #[test]
+    fn len_matches_summed_bucket_lengths_under_churn() {
+        let mut set = ScoreSet::default();
+        let mut rng = StdRng::seed_from_u64(0x1803);
+        for round in 0..4_000 {
+            let member = format!("m{}", rng.gen_range(0..600));
+            match rng.gen_range(0..4) {
+                0 => {
+                    set.remove(&member);
+                }
+                1 => {
+                    set.pop_n(rng.gen_bool(0.5), rng.gen_range(1..4));
+                }
+                _ => {
+                    set.insert(rng.gen_range(0..40) as f64, &member);
+                }
+            }
+            let summed: usize = set
+                .by_score
+                .values()
+                .map(|bucket_ref| set.bucket_len(*bucket_ref))
+                .sum();
+            assert_eq!(summed, set.len(), "round {round}");
+            assert_eq!(set.bucket_index.total(), set.len(), "round {round}");
+        }
+        set.validate();
+    }
+
+    #[test]
+    fn bucket_sort_keys_order_like_full_names() {
+        let mut names: Vec<String> = vec![
+            "".into(),
+            "a".into(),
+            "a\0".into(),
+            "a\0\0b".into(),
+            "ab".into(),
+            "abcdefgh".into(),
+            "abcdefgh\0".into(),
+            "abcdefghi".into(),
+            "abcdefgha".into(),
+            "b".into(),
+            "\u{7f}".into(),
+            "é".into(),
+            "雪".into(),
+        ];
+        let mut rng = StdRng::seed_from_u64(0x1806);
+        for i in 0..200 {
+            names.push(format!("shared-prefix:{:03}", rng.gen_range(0..1_000) + i));
+        }
+        names.sort();
+        names.dedup();
+
+        let mut set = ScoreSet::default();
+        let mut shuffled = names.clone();
+        for i in (1..shuffled.len()).rev() {
+            shuffled.swap(i, rng.gen_range(0..=i));
+        }
+        for name in &shuffled {
+            assert!(set.insert(1.0, name));
+        }
+        let got: Vec<String> = set.iter_all().map(|(m, _)| m.to_owned()).collect();
+        assert_eq!(got, names);
+        for (rank, name) in names.iter().enumerate() {
+            assert_eq!(set.rank(name), Some(rank));
+        }
+
+        for name in names.iter().step_by(2) {
+            assert!(set.remove(name));
+            assert!(!set.remove(name));
+        }
+        let kept: Vec<&String> = names.iter().skip(1).step_by(2).collect();
+        let got: Vec<String> = set.iter_all().map(|(m, _)| m.to_owned()).collect();
+        assert_eq!(got.iter().collect::<Vec<_>>(), kept);
+        assert_eq!(set.mem_bytes(), set.debug_mem_breakdown().structural());
+    }
+
+    #[test]
+    fn long_shared_prefix_members_rank_by_full_name() {
+        // Every name shares its first eight bytes, so the cached sort keys
+        // all tie and each comparison falls back to the full name.
+        let names: Vec<String> = (1..=999).map(|i| format!("user:{i:09}")).collect();
+        assert!(names.windows(2).all(|w| sort_key(&w[0]) == sort_key(&w[1])));
+
+        let mut set = ScoreSet::default();
+        set.insert(0.0, "below");
+        set.insert(2.0, "above");
+        let mut shuffled = names.clone();
+        let mut rng = StdRng::seed_from_u64(0x1897);
+        for i in (1..shuffled.len()).rev() {
+            shuffled.swap(i, rng.gen_range(0..=i));
+        }
+        for name in &shuffled {
+            assert!(set.insert(1.0, name));
+        }
+        set.validate();
+
+        assert_eq!(set.count_at_score(1.0), names.len());
+        let tied: Vec<&str> = set.members_in_range(1, names.len() as isize).collect();
+        assert_eq!(tied, names);
+        for (i, name) in names.iter().enumerate() {
+            assert_eq!(set.score_and_rank(name), Some((1.0, i + 1)), "{name}");
+            assert_eq!(set.select_by_rank(i + 1), (name.as_str(), 1.0));
+        }
+        let after: Vec<&str> = set
+            .iter_from(OrderedFloat(1.0), &names[499], true)
+            .map(|(name, _)| name)
+            .take(2)
+            .collect();
+        assert_eq!(after, [names[500].as_str(), names[501].as_str()]);
+
+        for name in names.iter().filter(|name| name.ends_with('7')) {
+            assert!(set.remove(name));
+        }
+        set.validate();
+        let kept: Vec<&String> = names.iter().filter(|name| !name.ends_with('7')).collect();
+        for (i, name) in kept.iter().enumerate() {
+            assert_eq!(set.rank(name), Some(i + 1), "{name}");
+        }
+        assert_eq!(set.rank("above"), Some(kept.len() + 1));
+    }
+
+    #[test]
+    fn score_and_rank_matches_separate_calls() {
+        let mut set = ScoreSet::default();
+        let mut rng = StdRng::seed_from_u64(0x1807);
+        for i in 0..500 {
+            set.insert(rng.gen_range(0..50) as f64, &format!("m{i}"));
+        }
+        for i in 0..520 {
+            let member = format!("m{i}");
+            let expected = set.score(&member).zip(set.rank(&member));
+            assert_eq!(set.score_and_rank(&member), expected, "{member}");
+        }
+        assert_eq!(set.score_and_rank("missing"), None);
+    }
+
+    #[test]
+    fn insert_with_score_reports_stored_score() {
+        let mut set = ScoreSet::default();
+        assert_eq!(set.insert_with_score(1.5, "a"), (true, 1.5));
+        assert_eq!(set.insert_with_score(2.5, "a"), (true, 2.5));
+        assert_eq!(set.insert_with_score(0.0, "b"), (true, 0.0));
+        let (inserted, stored) = set.insert_with_score(-0.0, "b");
+        assert!(!inserted);
+        assert!(stored.is_sign_positive());
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn ids_compact_after_mass_delete() {
+        const N: usize = 1_000_000;
+        const KEEP: usize = 10;
+        let mut set = ScoreSet::default();
+        let names: Vec<String> = (0..N).map(|i| format!("m{i}")).collect();
+        set.insert_many(
+            names
+                .iter()
+                .enumerate()
+                .map(|(i, m)| ((i % 7) as f64, m.as_str())),
+        );
+        // Remove everything but the highest ids so tail trimming can't help.
+        for name in &names[..N - KEEP] {
+            assert!(set.remove(name));
+        }
+        assert_eq!(set.len(), KEEP);
+        assert!(
+            set.scores.capacity() <= 64,
+            "scores capacity {} for {KEEP} members",
+            set.scores.capacity()
+        );
+        assert!(set.pool.allocated_ids() <= 64);
+        assert_eq!(set.mem_bytes(), set.debug_mem_breakdown().structural());
+        for (i, name) in names.iter().enumerate().skip(N - KEEP) {
+            assert_eq!(set.score(name), Some((i % 7) as f64));
+        }
+        let ranked: Vec<String> = set.range_iter(0, -1).into_iter().map(|(_, m)| m).collect();
+        for (rank, name) in ranked.iter().enumerate() {
+            assert_eq!(set.rank(name), Some(rank));
+        }
+
+        // Popping drains through the same compaction path.
+        let mut set = ScoreSet::default();
+        set.insert_many(
+            names[..ID_COMPACT_MIN * 2]
+                .iter()
+                .map(|m| (1.0, m.as_str())),
+        );
+        let popped = set.pop_n(true, ID_COMPACT_MIN * 2 - KEEP);
+        assert_eq!(popped.len(), ID_COMPACT_MIN * 2 - KEEP);
+        assert!(set.scores.capacity() <= 64);
+        assert_eq!(set.mem_bytes(), set.debug_mem_breakdown().structural());
+        let rest: Vec<_> = set.range_iter(0, -1).into_iter().map(|(_, m)| m).collect();
+        assert_eq!(rest.len(), KEEP);
+        for name in &rest {
+            assert!(set.remove(name));
+        }
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn reserve_then_insert_only_adds_reserved_slack() {
+        const N: usize = 1_000;
+        let names: Vec<String> = (0..N).map(|i| format!("m{i}")).collect();
+
+        let mut reserved = ScoreSet::default();
+        reserved.reserve(N);
+        let reserved_cap = reserved.scores.capacity();
+        assert!(reserved_cap >= N);
+        assert_eq!(reserved.mem_bytes(), reserved_cap * size_of::<f64>());
+        assert_eq!(
+            reserved.mem_bytes(),
+            reserved.debug_mem_breakdown().structural()
+        );
+
+        let mut plain = ScoreSet::default();
+        for (i, name) in names.iter().enumerate() {
+            reserved.insert((i % 10) as f64, name);
+            plain.insert((i % 10) as f64, name);
+        }
+        assert_eq!(reserved.scores.capacity(), reserved_cap, "no regrowth");
+        let slack = |set: &ScoreSet| set.scores.capacity() * size_of::<f64>();
+        assert_eq!(
+            reserved.mem_bytes() - slack(&reserved),
+            plain.mem_bytes() - slack(&plain)
+        );
+        assert_eq!(
+            reserved.mem_bytes(),
+            reserved.debug_mem_breakdown().structural()
+        );
+    }
+
+    #[test]
+    fn incr_by_respects_conditions() {
+        let mut set = ScoreSet::default();
+        let none = UpdateCond::default();
+        let nx = UpdateCond {
+            nx: true,
+            ..UpdateCond::default()
+        };
+        let xx = UpdateCond {
+            xx: true,
+            ..UpdateCond::default()
+        };
+        let gt = UpdateCond {
+            gt: true,
+            ..UpdateCond::default()
+        };
+        let lt = UpdateCond {
+            lt: true,
+            ..UpdateCond::default()
+        };
+
+        assert_eq!(set.incr_by("a", 1.0, xx), Ok(None));
+        assert!(set.is_empty());
+        assert_eq!(set.incr_by("a", 2.0, none), Ok(Some(2.0)));
+        assert_eq!(set.incr_by("a", 3.0, nx), Ok(None));
+        assert_eq!(set.incr_by("a", 3.0, xx), Ok(Some(5.0)));
+        assert_eq!(set.incr_by("a", -1.0, gt), Ok(None));
+        assert_eq!(set.incr_by("a", 0.0, gt), Ok(None));
+        assert_eq!(set.incr_by("a", 1.0, lt), Ok(None));
+        assert_eq!(set.incr_by("a", 0.0, lt), Ok(None));
+        assert_eq!(set.score("a"), Some(5.0));
+        assert_eq!(set.incr_by("a", 1.0, gt), Ok(Some(6.0)));
+        assert_eq!(set.incr_by("a", -2.0, lt), Ok(Some(4.0)));
+        // GT/LT still add absent members.
+        assert_eq!(set.incr_by("b", -7.0, gt), Ok(Some(-7.0)));
+
+        assert_eq!(set.incr_by("c", f64::MAX, none), Ok(Some(f64::MAX)));
+        assert_eq!(set.incr_by("c", f64::MAX, none), Err(IncrError::NotFinite));
+        assert_eq!(set.score("c"), Some(f64::MAX));
+        assert_eq!(set.len(), 3);
+        assert_eq!(set.mem_bytes(), set.debug_mem_breakdown().structural());
+    }
+
+    #[test]
+    fn incr_by_nan_result_leaves_set_unchanged() {
+        let none = UpdateCond::default();
+        let gt = UpdateCond {
+            gt: true,
+            ..UpdateCond::default()
+        };
+        let mut set = ScoreSet::default();
+        set.insert(f64::INFINITY, "a");
+        set.insert(1.0, "b");
+        let items = set.all_items();
+        let mem = set.mem_bytes();
+
+        assert_eq!(
+            set.incr_by("a", f64::NEG_INFINITY, none),
+            Err(IncrError::NotANumber)
+        );
+        // The NaN check comes before GT/LT, which would otherwise skip it.
+        assert_eq!(
+            set.incr_by("a", f64::NEG_INFINITY, gt),
+            Err(IncrError::NotANumber)
+        );
+        assert_eq!(set.all_items(), items);
+        assert_eq!(set.mem_bytes(), mem);
+        assert_eq!(
+            set.incr_by("a", f64::INFINITY, none),
+            Err(IncrError::NotFinite)
+        );
+    }
+
+    #[test]
+    fn upsert_covers_each_score_op() {
+        use UpsertOutcome::*;
+        let mut set = ScoreSet::default();
+
+        assert_eq!(set.upsert("a", ScoreOp::Set(1.0)), Ok(Added(1.0)));
+        assert_eq!(
+            set.upsert("a", ScoreOp::Set(2.0)),
+            Ok(Updated { old: 1.0, new: 2.0 })
+        );
+        assert_eq!(set.upsert("a", ScoreOp::Set(2.0)), Ok(Unchanged(2.0)));
+
+        assert_eq!(set.upsert("b", ScoreOp::Incr(1.5)), Ok(Added(1.5)));
+        assert_eq!(
+            set.upsert("b", ScoreOp::Incr(1.0)),
+            Ok(Updated { old: 1.5, new: 2.5 })
+        );
+        assert_eq!(set.upsert("b", ScoreOp::Incr(0.0)), Ok(Unchanged(2.5)));
+        assert_eq!(
+            set.upsert("b", ScoreOp::Incr(f64::INFINITY)),
+            Err(IncrError::NotFinite)
+        );
+
+        assert_eq!(
+            set.upsert("a", ScoreOp::SetIfGreater(1.0)),
+            Ok(Skipped(Some(2.0)))
+        );
+        assert_eq!(
+            set.upsert("a", ScoreOp::SetIfGreater(2.0)),
+            Ok(Skipped(Some(2.0)))
+        );
+        assert_eq!(
+            set.upsert("a", ScoreOp::SetIfGreater(3.0)),
+            Ok(Updated { old: 2.0, new: 3.0 })
+        );
+        assert_eq!(
+            set.upsert("c", ScoreOp::SetIfGreater(-5.0)),
+            Ok(Added(-5.0))
+        );
+
+        assert_eq!(
+            set.upsert("a", ScoreOp::SetIfLess(4.0)),
+            Ok(Skipped(Some(3.0)))
+        );
+        assert_eq!(
+            set.upsert("a", ScoreOp::SetIfLess(3.0)),
+            Ok(Skipped(Some(3.0)))
+        );
+        assert_eq!(
+            set.upsert("a", ScoreOp::SetIfLess(0.5)),
+            Ok(Updated { old: 3.0, new: 0.5 })
+        );
+        assert_eq!(set.upsert("d", ScoreOp::SetIfLess(9.0)), Ok(Added(9.0)));
+
+        assert_eq!(
+            set.upsert("a", ScoreOp::SetIfAbsent(7.0)),
+            Ok(Skipped(Some(0.5)))
+        );
+        assert_eq!(set.upsert("e", ScoreOp::SetIfAbsent(7.0)), Ok(Added(7.0)));
+
+        let xx_gt_incr = ScoreOp::Conditional {
+            value: 1.0,
+            incr: true,
+            cond: UpdateCond {
+                xx: true,
+                gt: true,
+                ..UpdateCond::default()
+            },
+        };
+        assert_eq!(set.upsert("missing", xx_gt_incr), Ok(Skipped(None)));
+        assert_eq!(
+            set.upsert("a", xx_gt_incr),
+            Ok(Updated { old: 0.5, new: 1.5 })
+        );
+
+        assert_eq!(
+            set.upsert("a", ScoreOp::Set(f64::NAN)),
+            Err(IncrError::NotFinite)
+        );
+        assert_eq!(set.score("a"), Some(1.5));
+        assert!(!set.contains("missing"));
+        assert_eq!(set.len(), 5);
+        assert_eq!(set.mem_bytes(), set.debug_mem_breakdown().structural());
+    }
+
+    #[test]
+    fn score_op_from_flags_prefers_named_variants() {
+        let plain = UpdateCond::default();
+        let gt = UpdateCond { gt: true, ..plain };
+        let xx = UpdateCond { xx: true, ..plain };
+        assert_eq!(ScoreOp::from_flags(1.0, false, plain), ScoreOp::Set(1.0));
+        assert_eq!(ScoreOp::from_flags(1.0, true, plain), ScoreOp::Incr(1.0));
+        assert_eq!(
+            ScoreOp::from_flags(1.0, false, gt),
+            ScoreOp::SetIfGreater(1.0)
+        );
+        assert_eq!(
+            ScoreOp::from_flags(1.0, true, gt),
+            ScoreOp::Conditional {
+                value: 1.0,
+                incr: true,
+                cond: gt
+            }
+        );
+        assert_eq!(
+            ScoreOp::from_flags(1.0, false, xx),
+            ScoreOp::Conditional {
+                value: 1.0,
+                incr: false,
+                cond: xx
+            }
+        );
+        for op in [
+            ScoreOp::Set(2.0),
+            ScoreOp::Incr(2.0),
+            ScoreOp::SetIfGreater(2.0),
+            ScoreOp::SetIfLess(2.0),
+            ScoreOp::SetIfAbsent(2.0),
+        ] {
+            let (value, incr, cond) = op.parts();
+            assert_eq!(ScoreOp::from_flags(value, incr, cond), op);
+        }
+    }
+
+    #[test]
+    fn iter_by_score_range_matches_filter() {
+        let mut rng = StdRng::seed_from_u64(17);
+        let mut set = ScoreSet::default();
+        for i in 0..300 {
+            set.insert(rng.gen_range(0..20) as f64, &format!("m{i}"));
+        }
+        let all: Vec<(String, f64)> = set.iter_all().map(|(m, s)| (m.to_owned(), s)).collect();
+        let bounds = [
+            f64::NEG_INFINITY,
+            -1.0,
+            0.0,
+            3.0,
+            3.5,
+            10.0,
+            19.0,
+            f64::INFINITY,
+        ];
+        for &min in &bounds {
+            for &max in &bounds {
+                for (min_excl, max_excl) in
+                    [(false, false), (true, false), (false, true), (true, true)]
+                {
+                    let got: Vec<(String, f64)> = set
+                        .iter_by_score_range(min, max, min_excl, max_excl)
+                        .map(|(m, s)| (m.to_owned(), s))
+                        .collect();
+                    let expected: Vec<(String, f64)> = all
+                        .iter()
+                        .filter(|(_, s)| {
+                            (if min_excl { *s > min } else { *s >= min })
+                                && (if max_excl { *s < max } else { *s <= max })
+                        })
+                        .cloned()
+                        .collect();
+                    assert_eq!(got, expected, "{min} {max} {min_excl} {max_excl}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn iter_range_rev_matches_forward_reversed() {
+        let mut rng = StdRng::seed_from_u64(31);
+        let mut set = ScoreSet::default();
+        assert_eq!(set.iter_range_rev(0, -1).count(), 0);
+        for i in 0..150 {
+            set.insert(rng.gen_range(0..12) as f64, &format!("m{i}"));
+        }
+        let len = set.len() as isize;
+        let mut reversed: Vec<(&str, f64)> = set.iter_all().collect();
+        reversed.reverse();
+        let ranks = [-200, -151, -150, -40, -1, 0, 1, 7, 100, 149, 150, 400];
+        for &start in &ranks {
+            for &stop in &ranks {
+                let rev = set.iter_range_rev(start, stop);
+                let n = rev.len();
+                let got: Vec<(&str, f64)> = rev.collect();
+                let norm = |r: isize| if r < 0 { r + len } else { r };
+                let (s, e) = (norm(start).max(0), norm(stop).min(len - 1));
+                let expected = if s > e {
+                    &[][..]
+                } else {
+                    &reversed[s as usize..=e as usize]
+                };
+                assert_eq!(got, expected, "{start} {stop}");
+                assert_eq!(n, expected.len(), "{start} {stop}");
+            }
+        }
+    }
+
+    #[test]
+    fn clamp_rank_range_matches_zrange_edges() {
+        let mut set = ScoreSet::default();
+        let bounds = [isize::MIN, -2, -1, 0, 1, isize::MAX];
+        for &start in &bounds {
+            for &stop in &bounds {
+                assert_eq!(set.clamp_rank_range(start, stop), None);
+                assert_eq!(set.iter_range(start, stop).count(), 0);
+                assert_eq!(set.iter_range_fwd(start, stop).len(), 0);
+                assert_eq!(set.iter_range_rev(start, stop).count(), 0);
+                assert_eq!(set.members_in_range(start, stop).count(), 0);
+                assert!(set.copy_range(start, stop).is_empty());
+            }
+        }
+        for (score, member) in [(1.0, "a"), (2.0, "b"), (3.0, "c")] {
+            set.insert(score, member);
+        }
+        let cases = [
+            ((2, 1), None),
+            ((3, 5), None),
+            ((1, 10), Some((1, 2))),
+            ((-4, 0), Some((0, 1))),
+            ((0, -4), None),
+            ((-1, -3), None),
+            ((isize::MIN, isize::MAX), Some((0, 3))),
+            ((isize::MAX, isize::MIN), None),
+            ((isize::MIN, isize::MIN), None),
+        ];
+        for ((start, stop), expected) in cases {
+            assert_eq!(
+                set.clamp_rank_range(start, stop),
+                expected,
+                "{start} {stop}"
+            );
+        }
+    }
+
+    #[test]
+    fn members_in_range_matches_iter_range_fwd() {
+        let mut rng = StdRng::seed_from_u64(23);
+        let mut set = ScoreSet::default();
+        assert_eq!(set.members_in_range(0, -1).count(), 0);
+        for i in 0..200 {
+            set.insert(rng.gen_range(0..15) as f64, &format!("m{i}"));
+        }
+        let ranks = [-250, -201, -200, -37, -1, 0, 1, 5, 99, 199, 200, 500];
+        for &start in &ranks {
+            for &stop in &ranks {
+                let members = set.members_in_range(start, stop);
+                let len = members.len();
+                let got: Vec<&str> = members.collect();
+                let expected: Vec<&str> = set.iter_range_fwd(start, stop).map(|(m, _)| m).collect();
+                assert_eq!(got, expected, "{start} {stop}");
+                assert_eq!(len, expected.len(), "{start} {stop}");
+            }
+        }
+    }
+
+    #[test]
+    fn remove_range_matches_model() {
+        let build = || {
+            let mut rng = StdRng::seed_from_u64(29);
+            let mut set = ScoreSet::default();
+            for i in 0..120 {
+                set.insert(rng.gen_range(0..10) as f64, &format!("m{i}"));
+            }
+            set
+        };
+        let names =
+            |set: &ScoreSet| -> Vec<String> { set.iter_all().map(|(m, _)| m.to_owned()).collect() };
+        let full = names(&build());
+        for (start, stop) in [(0, 9), (0, -1), (5, 30), (-20, -1), (110, 200), (40, 20)] {
+            let mut set = build();
+            let gone: Vec<String> = set
+                .iter_range_fwd(start, stop)
+                .map(|(m, _)| m.to_owned())
+                .collect();
+            assert_eq!(set.remove_range_by_rank(start, stop), gone.len());
+            let expected: Vec<String> =
+                full.iter().filter(|m| !gone.contains(m)).cloned().collect();
+            assert_eq!(names(&set), expected, "{start} {stop}");
+            assert_eq!(set.recompute_mem_breakdown(), set.debug_mem_breakdown());
+        }
+        for (min, max, min_excl) in [
+            (f64::NEG_INFINITY, 2.0, false),
+            (3.0, 6.0, true),
+            (8.0, f64::INFINITY, false),
+            (f64::NEG_INFINITY, f64::INFINITY, false),
+        ] {
+            let mut set = build();
+            let gone: Vec<String> = set
+                .iter_by_score_range(min, max, min_excl, false)
+                .map(|(m, _)| m.to_owned())
+                .collect();
+            assert_eq!(
+                set.remove_range_by_score(min, max, min_excl, false),
+                gone.len()
+            );
+            let expected: Vec<String> =
+                full.iter().filter(|m| !gone.contains(m)).cloned().collect();
+            assert_eq!(names(&set), expected, "{min} {max}");
+            assert_eq!(set.recompute_mem_breakdown(), set.debug_mem_breakdown());
+        }
+    }
+
+    #[test]
+    fn pop_range_by_score_matches_expected() {
+        let entries: Vec<(f64, String)> = (0..400)
+            .map(|i| ((i % 10) as f64, format!("m{i:03}")))
+            .collect();
+        let build = || {
+            let mut set = ScoreSet::default();
+            for (score, member) in &entries {
+                set.insert(*score, member);
+            }
+            set
+        };
+        let cases = [
+            (f64::NEG_INFINITY, 3.0, false, true),
+            (2.0, 5.0, false, false),
+            (2.0, 5.0, true, true),
+            (8.0, f64::INFINITY, false, false),
+            (4.5, 4.7, false, false),
+            (f64::NEG_INFINITY, f64::INFINITY, false, false),
+        ];
+        for (min, max, min_excl, max_excl) in cases {
+            let mut set = build();
+            let before = set.mem_bytes();
+            let in_range = |s: f64| {
+                (if min_excl { s > min } else { s >= min })
+                    && (if max_excl { s < max } else { s <= max })
+            };
+            let mut expected: Vec<(String, f64)> = entries
+                .iter()
+                .filter(|(s, _)| in_range(*s))
+                .map(|(s, m)| (m.clone(), *s))
+                .collect();
+            expected.sort_by(|a, b| a.1.total_cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+
+            let popped = set.pop_range_by_score(min, max, min_excl, max_excl);
+            assert_eq!(popped, expected, "{min} {max} {min_excl} {max_excl}");
+            assert_eq!(set.len(), entries.len() - expected.len());
+            assert!(set
+                .iter_by_score_range(min, max, min_excl, max_excl)
+                .next()
+                .is_none());
+            set.validate();
+            if !expected.is_empty() {
+                assert!(set.mem_bytes() < before, "mem_bytes should shrink");
+            }
+            for (_, score) in &expected {
+                assert!(set.bucket_capacity_for_test(*score).is_none());
+            }
+        }
+
+        // Draining spilled buckets frees them; the range never splits a score.
+        let mut set = ScoreSet::default();
+        for i in 0..20 {
+            set.insert(1.0, &format!("a{i:02}"));
+        }
+        set.insert(2.0, "b");
+        set.remove("a19");
+        set.insert(3.0, "a19");
+        let popped = set.pop_range_by_score(1.0, 2.0, false, true);
+        assert_eq!(popped.len(), 19);
+        assert_eq!(set.bucket_capacity_for_test(1.0), None);
+        set.insert(1.5, "x");
+        set.insert(1.5, "y");
+        let popped = set.pop_range_by_score(1.5, 1.5, false, false);
+        assert_eq!(popped, [("x".to_owned(), 1.5), ("y".to_owned(), 1.5)]);
+        assert_eq!(
+            set.iter_all().map(|(m, _)| m).collect::<Vec<_>>(),
+            ["b", "a19"]
+        );
+        set.validate();
+    }
+
+    #[test]
+    fn intersection_card_respects_limit() {
+        let mut tiny = ScoreSet::default();
+        for i in 0..10 {
+            tiny.insert(i as f64, &format!("m{i}"));
+        }
+        tiny.insert(99.0, "only-tiny");
+        let mut big = ScoreSet::default();
+        let mut other = ScoreSet::default();
+        for i in 0..1_000 {
+            big.insert(i as f64, &format!("m{i}"));
+            if i % 2 == 0 {
+                other.insert(-(i as f64), &format!("m{i}"));
+            }
+        }
+
+        assert_eq!(ScoreSet::intersection_card(&[], 0), 0);
+        assert_eq!(ScoreSet::intersection_card(&[&tiny], 0), 11);
+        assert_eq!(ScoreSet::intersection_card(&[&big, &tiny], 0), 10);
+        assert_eq!(ScoreSet::intersection_card(&[&big, &other, &tiny], 0), 5);
+        assert_eq!(ScoreSet::intersection_card(&[&big, &other, &tiny], 3), 3);
+        assert_eq!(ScoreSet::intersection_card(&[&big, &other, &tiny], 50), 5);
+        let empty = ScoreSet::default();
+        assert_eq!(ScoreSet::intersection_card(&[&big, &empty], 0), 0);
+    }
+
+    #[test]
+    fn union_into_matches_map_path() {
+        let mut rng = StdRng::seed_from_u64(0x1821);
+        let sources: Vec<ScoreSet> = (0..3)
+            .map(|_| {
+                let mut set = ScoreSet::default();
+                for _ in 0..600 {
+                    let score = rng.gen_range(-20..20) as f64;
+                    set.insert(score, &format!("m{}", rng.gen_range(0..1_000)));
+                }
+                set
+            })
+            .collect();
+        let refs: Vec<&ScoreSet> = sources.iter().collect();
+
+        let mut dst = ScoreSet::default();
+        dst.insert(1.0, "stale");
+        for agg in [Aggregate::Sum, Aggregate::Min, Aggregate::Max] {
+            for weights in [None, Some([2.0, -1.0, 0.5])] {
+                let mut map: HashMap<String, f64> = HashMap::new();
+                for (i, src) in sources.iter().enumerate() {
+                    let w = weights.map_or(1.0, |w| w[i]);
+                    for (member, score) in src.iter_all() {
+                        map.entry(member.to_owned())
+                            .and_modify(|acc| *acc = agg.combine(*acc, score * w))
+                            .or_insert(score * w);
+                    }
+                }
+                let mut expected = ScoreSet::default();
+                for (member, score) in &map {
+                    expected.insert(*score, member);
+                }
+
+                let len =
+                    ScoreSet::union_into(&mut dst, &refs, weights.as_ref().map(|w| &w[..]), agg);
+                assert_eq!(len, map.len(), "{agg:?} {weights:?}");
+                assert_eq!(dst.all_items(), expected.all_items(), "{agg:?} {weights:?}");
+                for (_, member) in dst.all_items() {
+                    assert_eq!(dst.rank(&member), expected.rank(&member));
+                }
+                let bd = dst.debug_mem_breakdown();
+                assert_eq!(dst.mem_bytes(), bd.structural());
+                assert_eq!(bd.score_map, expected.debug_mem_breakdown().score_map);
+                assert_eq!(bd.strings, expected.debug_mem_breakdown().strings);
+            }
+        }
+
+        assert_eq!(ScoreSet::union_into(&mut dst, &[], None, Aggregate::Sum), 0);
+        assert!(dst.is_empty());
+        assert_eq!(dst.mem_bytes(), 0);
+    }
+
+    #[test]
+    fn union_merged_matches_map_model() {
+        let mut rng = StdRng::seed_from_u64(0x1855);
+        // `shared` of every 100 members also appear in the other sources.
+        let family = |rng: &mut StdRng, shared: usize| -> Vec<ScoreSet> {
+            (0..4)
+                .map(|s| {
+                    let mut set = ScoreSet::default();
+                    for n in 0..500 {
+                        let member = if n % 100 < shared {
+                            format!("shared{n}")
+                        } else {
+                            format!("s{s}:{n}")
+                        };
+                        set.insert(rng.gen_range(-30..30) as f64 * 0.5, &member);
+                    }
+                    set
+                })
+                .collect()
+        };
+        let disjoint = family(&mut rng, 2);
+        let overlapping = family(&mut rng, 90);
+        fn refs(sets: &[ScoreSet]) -> Vec<&ScoreSet> {
+            sets.iter().collect()
+        }
+        assert!(ScoreSet::union_overlap_is_low(&refs(&disjoint)));
+        assert!(!ScoreSet::union_overlap_is_low(&refs(&overlapping)));
+        assert!(ScoreSet::union_overlap_is_low(&[]));
+
+        for sources in [&disjoint, &overlapping] {
+            let refs = refs(sources);
+            for agg in [Aggregate::Sum, Aggregate::Min, Aggregate::Max] {
+                for weights in [None, Some([2.0, -1.0, 0.0, 1e-300])] {
+                    let mut map: HashMap<String, f64> = HashMap::new();
+                    for (i, src) in sources.iter().enumerate() {
+                        let w = weights.map_or(1.0, |w| w[i]);
+                        for (member, score) in src.iter_all() {
+                            map.entry(member.to_owned())
+                                .and_modify(|acc| *acc = agg.combine(*acc, score * w))
+                                .or_insert(score * w);
+                        }
+                    }
+                    let mut expected: Vec<(String, f64)> = map.into_iter().collect();
+                    expected.sort_by(|a, b| {
+                        OrderedFloat(a.1)
+                            .cmp(&OrderedFloat(b.1))
+                            .then_with(|| a.0.cmp(&b.0))
+                    });
+
+                    let weights = weights.as_ref().map(|w| &w[..]);
+                    let merged: Vec<(String, f64)> = ScoreSet::union_merged(&refs, weights, agg)
+                        .into_iter()
+                        .map(|(member, score)| (member.to_owned(), score))
+                        .collect();
+                    assert_eq!(merged, expected, "{agg:?} {weights:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn remove_by_id_matches_remove() {
+        let mut rng = StdRng::seed_from_u64(0x1824);
+        let entries: Vec<(f64, String)> = (0..500)
+            .map(|i| (rng.gen_range(0..40) as f64, format!("m{i}")))
+            .collect();
+        let build = || {
+            let mut set = ScoreSet::default();
+            for (score, member) in &entries {
+                set.insert(*score, member);
+            }
+            set
+        };
+        let mut by_name = build();
+        let mut by_id = build();
+
+        for (i, (_, member)) in entries.iter().enumerate() {
+            if i % 5 == 4 {
+                continue;
+            }
+            let id = by_id.pool.lookup(member).expect("member present");
+            assert!(by_name.remove(member));
+            assert!(by_id.remove_by_id(id));
+            assert!(!by_id.remove_by_id(id), "stale id must be a no-op");
+        }
+        by_id.maybe_compact_ids();
+
+        assert_eq!(by_id.all_items(), by_name.all_items());
+        for (_, member) in by_id.all_items() {
+            assert_eq!(by_id.rank(&member), by_name.rank(&member));
+        }
+        // Only the score table may differ: `remove` compacts ids as soon as the
+        // threshold is crossed, the batch above compacts once at the end.
+        let (bi, bn) = (by_id.debug_mem_breakdown(), by_name.debug_mem_breakdown());
+        assert_eq!(by_id.mem_bytes(), bi.structural());
+        assert_eq!(bi.score_map, bn.score_map);
+        assert_eq!(bi.buckets, bn.buckets);
+        assert_eq!(bi.strings, bn.strings);
+        assert_eq!(by_id.pool.allocated_ids(), by_id.len());
+    }
+
+    #[test]
+    fn shrink_to_fit_releases_slack_after_deletes() {
+        let mut set = ScoreSet::default();
+        let names: Vec<String> = (0..100_000).map(|i| format!("member-{i:06}")).collect();
+        for (i, name) in names.iter().enumerate() {
+            set.insert((i % 5_000) as f64, name);
+        }
+        // Keep 30%, above the ratio that triggers automatic id compaction.
+        for (i, name) in names.iter().enumerate() {
+            if i % 10 >= 3 {
+                assert!(set.remove(name));
+            }
+        }
+        let survivors = set.all_items();
+        assert_eq!(set.pool.allocated_ids(), names.len());
+        let usage = |set: &ScoreSet| unsafe { gzset_mem_usage((set as *const ScoreSet).cast()) };
+        let before = usage(&set);
+
+        set.shrink_to_fit();
+        let after = usage(&set);
+        assert!(after * 2 < before, "usage {before} -> {after}");
+        assert_eq!(set.all_items(), survivors);
+        assert_eq!(set.pool.allocated_ids(), set.len());
+        assert_eq!(set.mem_bytes(), set.debug_mem_breakdown().structural());
+
+        // The trimmed arena chunk is never written past its end.
+        for i in 0..1_000 {
+            assert!(set.insert(-1.0, &format!("fresh-{i}")));
+        }
+        assert_eq!(set.rank("fresh-0"), Some(0));
+        assert_eq!(set.score(&survivors[0].1), Some(survivors[0].0));
+        assert_eq!(set.mem_bytes(), set.debug_mem_breakdown().structural());
+    }
+
+    #[test]
+    fn spilled_bucket_count_follows_ties() {
+        let mut set = ScoreSet::default();
+        assert_eq!((set.distinct_scores(), set.spilled_bucket_count()), (0, 0));
+        for i in 0..10 {
+            set.insert(i as f64, &format!("m{i}"));
+        }
+        assert_eq!((set.distinct_scores(), set.spilled_bucket_count()), (10, 0));
+        // Ties fill the inline slots before the score spills.
+        let ties: Vec<String> = (0..INLINE_MEMBERS_MAX).map(|i| format!("tie{i}")).collect();
+        for (i, tie) in ties.iter().enumerate() {
+            set.insert(3.0, tie);
+            let spilled = usize::from(i + 1 == ties.len());
+            assert_eq!(
+                (set.distinct_scores(), set.spilled_bucket_count()),
+                (10, spilled)
+            );
+        }
+        for tie in &ties {
+            set.insert(3.5, tie);
+        }
+        assert_eq!((set.distinct_scores(), set.spilled_bucket_count()), (11, 0));
+
+        for i in 0..100 {
+            set.insert(42.0, &format!("big{i}"));
+        }
+        assert_eq!((set.distinct_scores(), set.spilled_bucket_count()), (12, 1));
+        for i in 0..99 {
+            set.remove(&format!("big{i}"));
+        }
+        assert_eq!(set.spilled_bucket_count(), 0, "a lone survivor goes inline");
+    }
+
+    #[test]
+    fn spilled_bucket_count_matches_handles_under_mixed_ops() {
+        let handles = |set: &ScoreSet| {
+            set.by_score
+                .values()
+                .filter(|bucket_ref| matches!(bucket_ref, BucketRef::Handle(_)))
+                .count()
+        };
+        let mut rng = StdRng::seed_from_u64(0x1872);
+        let mut set = ScoreSet::default();
+        for step in 0..20_000 {
+            let score = rng.gen_range(0..40) as f64;
+            match rng.gen_range(0..10) {
+                0..=4 => {
+                    set.insert(score, &format!("m{}", rng.gen_range(0..500)));
+                }
+                5 | 6 => {
+                    set.remove(&format!("m{}", rng.gen_range(0..500)));
+                }
+                7 => {
+                    set.pop_n(rng.gen_bool(0.5), rng.gen_range(1..20));
+                }
+                8 => {
+                    set.remove_range_by_score(score, score + 2.0, false, true);
+                }
+                _ => {
+                    let start = rng.gen_range(0..50);
+                    set.remove_range_by_rank(start, start + 5);
+                }
+            }
+            if step % 1000 == 0 {
+                set.shrink_to_fit();
+                let upper = set.split_off_by_score(35.0, false);
+                assert_eq!(upper.spilled_bucket_count(), handles(&upper));
+            }
+            assert_eq!(set.spilled_bucket_count(), handles(&set), "step {step}");
+        }
+    }
+
+    #[test]
+    fn largest_buckets_ranks_scores_by_members() {
+        let mut set = ScoreSet::default();
+        assert!(set.largest_buckets(3).is_empty());
+        for (score, members) in [(5.0, 3), (1.0, 10), (2.0, 3), (9.0, 1), (-4.0, 7)] {
+            for i in 0..members {
+                set.insert(score, &format!("s{score}-{i}"));
+            }
+        }
+        assert_eq!(
+            set.largest_buckets(4),
+            [(1.0, 10), (-4.0, 7), (2.0, 3), (5.0, 3)]
+        );
+        assert_eq!(set.largest_buckets(1), [(1.0, 10)]);
+        assert_eq!(set.largest_buckets(0), []);
+        assert_eq!(set.largest_buckets(100).len(), set.distinct_scores());
+    }
+
+    #[test]
+    fn rank_of_score_counts_strictly_below() {
+        let mut set = ScoreSet::default();
+        assert_eq!(set.rank_of_score(0.0), 0);
+        set.insert(1.0, "a");
+        for i in 0..5 {
+            set.insert(2.0, &format!("tie{i}"));
+        }
+        set.insert(3.0, "c");
+        set.insert(0.0, "zero");
+
+        assert_eq!(set.rank_of_score(f64::NEG_INFINITY), 0);
+        assert_eq!(set.rank_of_score(0.0), 0);
+        assert_eq!(set.rank_of_score(-0.0), 0);
+        assert_eq!(set.rank_of_score(0.5), 1);
+        assert_eq!(set.rank_of_score(1.0), 1);
+        assert_eq!(set.rank_of_score(2.0), 2, "ties are not below");
+        assert_eq!(set.rank_of_score(2.0 + f64::EPSILON * 2.0), 7);
+        assert_eq!(set.rank_of_score(3.0), 7);
+        assert_eq!(set.rank_of_score(f64::INFINITY), 8);
+        assert_eq!(
+            set.rank_of_score(set.score("c").unwrap()),
+            set.rank("c").unwrap()
+        );
+
+        set.remove("tie0");
+        set.remove("a");
+        assert_eq!(set.rank_of_score(2.5), 5);
+    }
+
+    #[test]
+    fn count_at_score_tracks_bucket_lengths() {
+        let mut set = ScoreSet::default();
+        assert_eq!(set.count_at_score(1.0), 0);
+        set.insert(1.0, "solo");
+        assert_eq!(set.count_at_score(1.0), 1);
+        for i in 0..100 {
+            set.insert(2.0, &format!("tied{i}"));
+        }
+        assert_eq!(set.count_at_score(2.0), 100);
+        assert_eq!(set.count_at_score(-0.0), 0);
+        set.insert(0.0, "zero");
+        assert_eq!(set.count_at_score(-0.0), 1, "-0 and 0 share a bucket");
+        for i in 0..99 {
+            set.remove(&format!("tied{i}"));
+        }
+        assert_eq!(set.count_at_score(2.0), 1);
+        set.remove("tied99");
+        assert_eq!(set.count_at_score(2.0), 0);
+        assert_eq!(set.count_at_score(1.5), 0);
+    }
+
+    #[test]
+    fn split_off_by_score_partitions_the_set() {
+        let mut rng = StdRng::seed_from_u64(0x1858);
+        let entries: Vec<(f64, String)> = (0..400)
+            .map(|i| (rng.gen_range(-10..10) as f64, format!("m{i}")))
+            .collect();
+        for (min, excl) in [
+            (0.0, false),
+            (0.0, true),
+            (-100.0, false),
+            (100.0, false),
+            (9.0, true),
+        ] {
+            let mut lower = ScoreSet::default();
+            for (score, member) in &entries {
+                lower.insert(*score, member);
+            }
+            let original = lower.all_items();
+            let upper = lower.split_off_by_score(min, excl);
+
+            let above = |score: f64| if excl { score > min } else { score >= min };
+            assert!(upper.iter_all().all(|(_, score)| above(score)));
+            assert!(lower.iter_all().all(|(_, score)| !above(score)));
+            let mut rejoined = lower.all_items();
+            rejoined.extend(upper.all_items());
+            assert_eq!(rejoined, original, "{min} {excl}");
+
+            for part in [&lower, &upper] {
+                let bd = part.debug_mem_breakdown();
+                assert_eq!(part.mem_bytes(), bd.structural());
+                assert_eq!(part.recompute_mem_breakdown(), bd);
+            }
+        }
+    }
+
+    #[test]
+    fn equality_ignores_insertion_order_and_layout() {
+        let mut forward = ScoreSet::default();
+        let mut backward = ScoreSet::default();
+        for i in 0..200 {
+            forward.insert((i % 5) as f64, &format!("m{i}"));
+        }
+        for i in (0..200).rev() {
+            backward.insert(-1.0, &format!("m{i}"));
+            backward.insert((i % 5) as f64, &format!("m{i}"));
+        }
+        assert_eq!(forward, backward);
+        assert_eq!(forward.debug_dump(), backward.debug_dump());
+        assert_eq!(forward.copy_range(0, -1), forward);
+
+        let dump = forward.debug_dump();
+        assert!(dump
+            .windows(2)
+            .all(|w| (w[0].0, &w[0].1) < (w[1].0, &w[1].1)));
+
+        backward.insert(0.5, "m0");
+        assert_ne!(forward, backward);
+        backward.insert(0.0, "m0");
+        assert_eq!(forward, backward);
+        backward.remove("m199");
+        assert_ne!(forward, backward);
+        assert_eq!(
+            format!("{:?}", forward.copy_range(0, 1)),
+            r#"{"m0": 0.0, "m10": 0.0}"#
+        );
+    }
+
+    #[test]
+    fn contains_score_sees_ties_and_absent_scores() {
+        let mut set = ScoreSet::default();
+        assert!(!set.contains_score(1.0));
+        set.insert(1.0, "a");
+        set.insert(1.0, "b");
+        set.insert(3.0, "c");
+        assert!(set.contains_score(1.0));
+        assert!(set.contains_score(3.0));
+        assert!(!set.contains_score(2.0));
+        assert!(!set.contains_score(f64::INFINITY));
+
+        set.remove("a");
+        assert!(set.contains_score(1.0), "one tie still holds the score");
+        set.remove("b");
+        assert!(!set.contains_score(1.0));
+
+        set.insert(0.0, "zero");
+        assert!(set.contains_score(-0.0));
+        set.insert(5.0, "c");
+        assert!(!set.contains_score(3.0), "rescoring vacates the old score");
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "NaN score")]
+    fn nan_score_panics_in_debug() {
+        let mut set = ScoreSet::default();
+        set.insert(f64::NAN, "a");
+    }
+
+    #[test]
+    #[cfg(not(debug_assertions))]
+    fn nan_score_is_rejected() {
+        let mut set = ScoreSet::default();
+        set.insert(1.0, "a");
+        let mem = set.mem_bytes();
+        assert!(!set.insert(f64::NAN, "a"));
+        assert!(!set.insert(f64::NAN, "b"));
+        assert_eq!(set.score("a"), Some(1.0));
+        assert_eq!(set.score("b"), None);
+        assert_eq!(set.len(), 1);
+        assert_eq!(set.pool.len(), 1);
+        assert!(!set.by_score.keys().any(|k| k.0.is_nan()));
+        assert_eq!(set.mem_bytes(), mem);
+    }
+
+    #[test]
+    fn select_by_rank_matches_iteration_under_churn() {
+        let mut rng = StdRng::seed_from_u64(0x1831);
+        let mut set = ScoreSet::default();
+        for round in 0..20 {
+            for _ in 0..300 {
+                let member = format!("m{}", rng.gen_range(0..800));
+                if rng.gen_bool(0.7) {
+                    set.insert(rng.gen_range(0..50) as f64, &member);
+                } else {
+                    set.remove(&member);
+                }
+            }
+            let items = set.all_items();
+            for (rank, (score, member)) in items.iter().enumerate() {
+                assert_eq!(
+                    set.select_by_rank(rank),
+                    (member.as_str(), *score),
+                    "round {round} rank {rank}"
+                );
+            }
+            assert!(set.bucket_index.select(items.len()).is_none());
+            set.validate();
+        }
+    }
+
+    #[test]
+    fn rank_of_missing_or_tombstoned_member_is_none() {
+        let mut set = ScoreSet::default();
+        assert_eq!(set.rank("a"), None);
+        set.insert(1.0, "a");
+        set.insert(1.0, "b");
+        set.insert(2.0, "c");
+        assert_eq!(set.rank("nope"), None);
+        assert_eq!(set.score_and_rank("nope"), None);
+
+        // A name still interned in the pool whose score slot was freed.
+        let id = set.pool.lookup("b").expect("b is interned");
+        set.scores[id as usize] = EMPTY_SCORE;
+        assert_eq!(set.rank("b"), None);
+        assert_eq!(set.score_and_rank("b"), None);
+        assert_eq!(set.score("b"), None);
+        assert_eq!(set.score_and_rank("c"), Some((2.0, 2)));
+    }
+
+    #[test]
+    fn score_at_percentile_follows_nearest_rank() {
+        let mut set = ScoreSet::default();
+        assert_eq!(set.score_at_percentile(50.0), None);
+        for i in 1..=10 {
+            set.insert(i as f64, &format!("m{i}"));
+        }
+        for (p, expected) in [
+            (0.0, 1.0),
+            (5.0, 1.0),
+            (10.0, 1.0),
+            (10.5, 2.0),
+            (30.0, 3.0),
+            (50.0, 5.0),
+            (99.9, 10.0),
+            (100.0, 10.0),
+            (-1.0, 1.0),
+            (250.0, 10.0),
+            (f64::NEG_INFINITY, 1.0),
+            (f64::INFINITY, 10.0),
+        ] {
+            assert_eq!(set.score_at_percentile(p), Some(expected), "p={p}");
+        }
+        assert_eq!(set.score_at_percentile(f64::NAN), None);
+
+        let mut rng = StdRng::seed_from_u64(0x1894);
+        let mut tied = ScoreSet::default();
+        for i in 0..500 {
+            tied.insert(rng.gen_range(0..20) as f64, &format!("t{i}"));
+        }
+        let sorted: Vec<f64> = tied.iter_all().map(|(_, score)| score).collect();
+        for p in 0..=100 {
+            let rank = (p * sorted.len()).div_ceil(100).max(1) - 1;
+            assert_eq!(
+                tied.score_at_percentile(p as f64),
+                Some(sorted[rank]),
+                "{p}"
+            );
+        }
+    }
+
+    #[test]
+    fn select_by_ranks_matches_select_by_rank() {
+        let mut rng = StdRng::seed_from_u64(0x1862);
+        let mut set = ScoreSet::default();
+        assert!(set.select_by_ranks(&[]).is_empty());
+        for i in 0..5_000 {
+            // Mix singleton scores with a few wide tied buckets.
+            let score = if i % 3 == 0 {
+                rng.gen_range(0..4) as f64
+            } else {
+                rng.gen_range(0.0..1e6)
+            };
+            set.insert(score, &format!("m{i}"));
+        }
+        let len = set.len();
+        for density in [1, 10, 100, len / 2, len] {
+            let mut ranks: Vec<usize> = (0..density).map(|_| rng.gen_range(0..len)).collect();
+            ranks.push(0);
+            ranks.push(len - 1);
+            ranks.sort_unstable();
+            let expected: Vec<_> = ranks.iter().map(|&r| set.select_by_rank(r)).collect();
+            assert_eq!(set.select_by_ranks(&ranks), expected, "density {density}");
+        }
+        let all: Vec<usize> = (0..len).collect();
+        let expected: Vec<_> = set.iter_all().collect();
+        assert_eq!(set.select_by_ranks(&all), expected);
     }
 }
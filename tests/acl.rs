@@ -0,0 +1,80 @@
+mod helpers;
+
+/// A connection authenticated as a fresh user holding `rules`.
+fn connect_as(
+    admin: &mut redis::Connection,
+    url: &str,
+    user: &str,
+    rules: &[&str],
+) -> redis::RedisResult<redis::Connection> {
+    redis::cmd("ACL")
+        .arg("SETUSER")
+        .arg(user)
+        .arg("on")
+        .arg("nopass")
+        .arg("~*")
+        .arg(rules)
+        .query::<()>(admin)?;
+    let mut con = redis::Client::open(url)?.get_connection()?;
+    redis::cmd("AUTH")
+        .arg(user)
+        .arg("x")
+        .query::<()>(&mut con)?;
+    Ok(con)
+}
+
+#[test]
+fn acl_categories_gate_gzset_commands() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut admin = redis::Client::open(vk.url())?.get_connection()?;
+    redis::cmd("GZADD")
+        .arg("s")
+        .arg(1)
+        .arg("a")
+        .arg(2)
+        .arg("b")
+        .query::<i64>(&mut admin)?;
+
+    let mut reader = connect_as(&mut admin, &vk.url(), "reader", &["+@read"])?;
+    let range: Vec<String> = redis::cmd("GZRANGE")
+        .arg("s")
+        .arg(0)
+        .arg(-1)
+        .query(&mut reader)?;
+    assert_eq!(range, ["a", "b"]);
+    let score: f64 = redis::cmd("GZSCORE").arg("s").arg("b").query(&mut reader)?;
+    assert_eq!(score, 2.0);
+    for args in [&["GZADD", "s", "3", "c"][..], &["GZREM", "s", "a"]] {
+        let err = redis::cmd(args[0])
+            .arg(&args[1..])
+            .query::<i64>(&mut reader)
+            .unwrap_err();
+        assert_eq!(err.code(), Some("NOPERM"), "{args:?}: {err}");
+    }
+
+    let mut writer = connect_as(&mut admin, &vk.url(), "writer", &["+@sortedset", "-@read"])?;
+    let added: i64 = redis::cmd("GZADD")
+        .arg("s")
+        .arg(3)
+        .arg("c")
+        .query(&mut writer)?;
+    assert_eq!(added, 1);
+    let err = redis::cmd("GZRANGE")
+        .arg("s")
+        .arg(0)
+        .arg(-1)
+        .query::<Vec<String>>(&mut writer)
+        .unwrap_err();
+    assert_eq!(err.code(), Some("NOPERM"), "{err}");
+
+    let mut fast = connect_as(&mut admin, &vk.url(), "fast", &["+@fast"])?;
+    let card: i64 = redis::cmd("GZCARD").arg("s").query(&mut fast)?;
+    assert_eq!(card, 3);
+    let err = redis::cmd("GZUNION")
+        .arg(1)
+        .arg("s")
+        .query::<Vec<String>>(&mut fast)
+        .unwrap_err();
+    assert_eq!(err.code(), Some("NOPERM"), "{err}");
+    Ok(())
+}
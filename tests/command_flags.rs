@@ -0,0 +1,197 @@
+mod helpers;
+
+/// The flags `COMMAND INFO` reports for `name`.
+fn flags(con: &mut redis::Connection, name: &str) -> redis::RedisResult<Vec<String>> {
+    let info: Vec<redis::Value> = redis::cmd("COMMAND").arg("INFO").arg(name).query(con)?;
+    let redis::Value::Bulk(fields) = &info[0] else {
+        panic!("no COMMAND INFO for {name}: {info:?}");
+    };
+    redis::from_redis_value(&fields[2])
+}
+
+#[test]
+fn commands_register_accurate_flags() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+
+    for name in ["GZADD", "GZINCRBY", "GZMOVE"] {
+        let f = flags(&mut con, name)?;
+        for flag in ["write", "denyoom", "fast"] {
+            assert!(f.iter().any(|x| x == flag), "{name} lacks {flag}: {f:?}");
+        }
+    }
+    for name in ["GZUNIONSTORE", "GZSPLIT", "GZRANGESTORE"] {
+        let f = flags(&mut con, name)?;
+        assert!(f.iter().any(|x| x == "write"), "{name}: {f:?}");
+        assert!(f.iter().any(|x| x == "denyoom"), "{name}: {f:?}");
+    }
+    for name in [
+        "GZREM",
+        "GZPOPMIN",
+        "GZPOPMAX",
+        "GZTRIM",
+        "GZREMRANGEBYRANK",
+    ] {
+        let f = flags(&mut con, name)?;
+        assert!(f.iter().any(|x| x == "write"), "{name}: {f:?}");
+        assert!(!f.iter().any(|x| x == "denyoom"), "{name}: {f:?}");
+    }
+    for name in ["GZUNION", "GZINTER", "GZDIFF", "GZRANGE", "GZSCAN"] {
+        let f = flags(&mut con, name)?;
+        assert!(f.iter().any(|x| x == "readonly"), "{name}: {f:?}");
+        assert!(!f.iter().any(|x| x == "fast"), "{name}: {f:?}");
+    }
+    for name in [
+        "GZSCORE",
+        "GZCARD",
+        "GZRANK",
+        "GZMSCORE",
+        "GZMIN",
+        "GZPERCENTILE",
+    ] {
+        let f = flags(&mut con, name)?;
+        assert!(f.iter().any(|x| x == "readonly"), "{name}: {f:?}");
+        assert!(f.iter().any(|x| x == "fast"), "{name}: {f:?}");
+    }
+    Ok(())
+}
+
+#[test]
+fn denyoom_commands_are_rejected_over_maxmemory() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+
+    redis::cmd("GZADD")
+        .arg("src")
+        .arg(1)
+        .arg("a")
+        .arg(2)
+        .arg("b")
+        .query::<i64>(&mut con)?;
+    redis::cmd("CONFIG")
+        .arg("SET")
+        .arg("maxmemory")
+        .arg(1)
+        .query::<()>(&mut con)?;
+
+    let err = redis::cmd("GZUNIONSTORE")
+        .arg("dst")
+        .arg(1)
+        .arg("src")
+        .query::<i64>(&mut con)
+        .unwrap_err();
+    assert_eq!(err.code(), Some("OOM"), "{err}");
+    let err = redis::cmd("GZADD")
+        .arg("src")
+        .arg(3)
+        .arg("c")
+        .query::<i64>(&mut con)
+        .unwrap_err();
+    assert_eq!(err.code(), Some("OOM"), "{err}");
+
+    // Commands that only free memory keep working.
+    let removed: i64 = redis::cmd("GZREM").arg("src").arg("a").query(&mut con)?;
+    assert_eq!(removed, 1);
+    let card: i64 = redis::cmd("GZCARD").arg("src").query(&mut con)?;
+    assert_eq!(card, 1);
+    let exists: i64 = redis::cmd("EXISTS").arg("dst").query(&mut con)?;
+    assert_eq!(exists, 0);
+
+    redis::cmd("CONFIG")
+        .arg("SET")
+        .arg("maxmemory")
+        .arg(0)
+        .query::<()>(&mut con)?;
+    Ok(())
+}
+
+/// Every gzset command with the arity `COMMAND INFO` should report.
+const ARITIES: [(&str, i64); 33] = [
+    ("GZADD", -4),
+    ("GZINCRBY", 4),
+    ("GZRANK", -3),
+    ("GZRANGE", -4),
+    ("GZREVRANGE", -4),
+    ("GZRANGEBYSCORE", -4),
+    ("GZREM", -3),
+    ("GZSCORE", 3),
+    ("GZCARD", 2),
+    ("GZPOPMIN", -2),
+    ("GZPOPMAX", -2),
+    ("GZPOPRANGEBYSCORE", 4),
+    ("GZREMRANGEBYRANK", 4),
+    ("GZREMRANGEBYSCORE", 4),
+    ("GZSPLIT", 4),
+    ("GZMOVE", 4),
+    ("GZRANGESTORE", 5),
+    ("GZRANDMEMBER", -2),
+    ("GZMSCORE", -3),
+    ("GZUNION", -3),
+    ("GZUNIONSTORE", -4),
+    ("GZINTER", -3),
+    ("GZDIFF", -3),
+    ("GZINTERCARD", -3),
+    ("GZSCAN", -3),
+    ("GZMIN", 2),
+    ("GZMAX", 2),
+    ("GZTRIM", 2),
+    ("GZSCORECARD", 3),
+    ("GZSCORERANK", 3),
+    ("GZPERCENTILE", 3),
+    ("GZDEBUG", -2),
+    ("GZMEMESTIMATE", 3),
+];
+
+#[test]
+fn command_info_lists_every_command_with_its_arity() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+
+    let listed: Vec<String> = redis::cmd("COMMAND").arg("LIST").query(&mut con)?;
+    let count: usize = redis::cmd("COMMAND").arg("COUNT").query(&mut con)?;
+    assert_eq!(count, listed.len());
+    let mut registered: Vec<String> = listed
+        .iter()
+        .map(|name| name.to_ascii_uppercase())
+        .filter(|name| name.starts_with("GZ"))
+        .collect();
+    registered.sort();
+    let mut expected: Vec<String> = ARITIES.iter().map(|(name, _)| name.to_string()).collect();
+    expected.sort();
+    assert_eq!(registered, expected);
+
+    for (name, arity) in ARITIES {
+        let info: Vec<redis::Value> = redis::cmd("COMMAND")
+            .arg("INFO")
+            .arg(name)
+            .query(&mut con)?;
+        let redis::Value::Bulk(fields) = &info[0] else {
+            panic!("no COMMAND INFO for {name}: {info:?}");
+        };
+        let got: i64 = redis::from_redis_value(&fields[1])?;
+        assert_eq!(got, arity, "{name}");
+
+        let docs: Vec<redis::Value> = redis::cmd("COMMAND")
+            .arg("DOCS")
+            .arg(name)
+            .query(&mut con)?;
+        let doc: Vec<redis::Value> = redis::from_redis_value(&docs[1])?;
+        let summary = doc
+            .chunks_exact(2)
+            .find(|pair| redis::from_redis_value::<String>(&pair[0]).is_ok_and(|k| k == "summary"))
+            .map(|pair| redis::from_redis_value::<String>(&pair[1]))
+            .transpose()?;
+        assert!(
+            summary.is_some_and(|s| !s.is_empty()),
+            "{name} has no summary"
+        );
+    }
+
+    // The server now rejects a short call before the command runs.
+    let err = redis::cmd("GZSCORE")
+        .arg("s")
+        .query::<Option<f64>>(&mut con)
+        .unwrap_err();
+    assert!(err.to_string().contains("wrong number"), "{err}");
+    Ok(())
+}
@@ -0,0 +1,61 @@
+mod helpers;
+
+/// The keys the server extracts for a command, as `COMMAND GETKEYS` reports
+/// them. Cluster mode hashes exactly these to route a call and to reply
+/// `CROSSSLOT` when they span slots, so a single-node server is enough to
+/// check them.
+fn getkeys(con: &mut redis::Connection, args: &[&str]) -> redis::RedisResult<Vec<String>> {
+    redis::cmd("COMMAND").arg("GETKEYS").arg(args).query(con)
+}
+
+#[test]
+fn numkeys_commands_declare_only_their_keys() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+
+    assert_eq!(
+        getkeys(
+            &mut con,
+            &["GZUNIONSTORE", "dst", "2", "a", "b", "WEIGHTS", "1", "2"]
+        )?,
+        ["dst", "a", "b"]
+    );
+    assert_eq!(
+        getkeys(&mut con, &["GZUNION", "2", "a", "b", "WITHSCORES"])?,
+        ["a", "b"]
+    );
+    assert_eq!(
+        getkeys(
+            &mut con,
+            &["GZINTER", "3", "a", "b", "c", "AGGREGATE", "MAX"]
+        )?,
+        ["a", "b", "c"]
+    );
+    assert_eq!(getkeys(&mut con, &["GZDIFF", "1", "a"])?, ["a"]);
+    assert_eq!(
+        getkeys(&mut con, &["GZINTERCARD", "2", "a", "b", "LIMIT", "5"])?,
+        ["a", "b"]
+    );
+    // A numkeys past the end declares only the keys that are present.
+    assert_eq!(getkeys(&mut con, &["GZUNION", "5", "a", "b"])?, ["a", "b"]);
+
+    // The commands still run normally.
+    redis::cmd("GZADD")
+        .arg("a")
+        .arg(1)
+        .arg("x")
+        .query::<i64>(&mut con)?;
+    redis::cmd("GZADD")
+        .arg("b")
+        .arg(2)
+        .arg("y")
+        .query::<i64>(&mut con)?;
+    let stored: i64 = redis::cmd("GZUNIONSTORE")
+        .arg("dst")
+        .arg(2)
+        .arg("a")
+        .arg("b")
+        .query(&mut con)?;
+    assert_eq!(stored, 2);
+    Ok(())
+}
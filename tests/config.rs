@@ -0,0 +1,144 @@
+mod helpers;
+
+#[test]
+fn bucket_shrink_threshold_config_roundtrip() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+
+    let value: Vec<String> = redis::cmd("CONFIG")
+        .arg("GET")
+        .arg("gzset.bucket-shrink-threshold")
+        .query(&mut con)?;
+    assert_eq!(value, ["gzset.bucket-shrink-threshold", "64"]);
+
+    redis::cmd("CONFIG")
+        .arg("SET")
+        .arg("gzset.bucket-shrink-threshold")
+        .arg(16)
+        .query::<()>(&mut con)?;
+    let value: Vec<String> = redis::cmd("CONFIG")
+        .arg("GET")
+        .arg("gzset.bucket-shrink-threshold")
+        .query(&mut con)?;
+    assert_eq!(value[1], "16");
+
+    let err = redis::cmd("CONFIG")
+        .arg("SET")
+        .arg("gzset.bucket-shrink-threshold")
+        .arg(1)
+        .query::<()>(&mut con)
+        .unwrap_err();
+    assert!(err.to_string().contains("argument must be between"));
+    Ok(())
+}
+
+#[test]
+fn scan_count_configs_bound_gzscan() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+
+    let get = |con: &mut redis::Connection, name: &str| -> redis::RedisResult<String> {
+        let value: Vec<String> = redis::cmd("CONFIG").arg("GET").arg(name).query(con)?;
+        Ok(value[1].clone())
+    };
+    assert_eq!(get(&mut con, "gzset.scan-default-count")?, "10");
+    assert_eq!(get(&mut con, "gzset.scan-max-count")?, "1024");
+
+    let mut pipe = redis::pipe();
+    for i in 0..50 {
+        pipe.cmd("GZADD").arg("s").arg(i).arg(format!("m{i}"));
+    }
+    pipe.query::<()>(&mut con)?;
+    let scan = |con: &mut redis::Connection,
+                count: Option<i64>|
+     -> redis::RedisResult<(String, Vec<String>)> {
+        let mut cmd = redis::cmd("GZSCAN");
+        cmd.arg("s").arg("0");
+        if let Some(count) = count {
+            cmd.arg("COUNT").arg(count);
+        }
+        cmd.query(con)
+    };
+    assert_eq!(scan(&mut con, None)?.1.len(), 20);
+    assert!(scan(&mut con, Some(2000)).is_err());
+
+    redis::cmd("CONFIG")
+        .arg("SET")
+        .arg("gzset.scan-max-count")
+        .arg(4096)
+        .arg("gzset.scan-default-count")
+        .arg(3)
+        .query::<()>(&mut con)?;
+    assert_eq!(scan(&mut con, None)?.1.len(), 6);
+    assert_eq!(scan(&mut con, Some(2000))?.1.len(), 100);
+
+    // Lowering the max rejects a COUNT that used to be allowed, and caps
+    // the default.
+    redis::cmd("CONFIG")
+        .arg("SET")
+        .arg("gzset.scan-max-count")
+        .arg(2)
+        .query::<()>(&mut con)?;
+    let err = scan(&mut con, Some(5)).unwrap_err();
+    assert!(
+        err.to_string().contains("COUNT must be between 1 and 2"),
+        "unexpected error: {err}"
+    );
+    assert_eq!(scan(&mut con, None)?.1.len(), 4);
+
+    assert!(redis::cmd("CONFIG")
+        .arg("SET")
+        .arg("gzset.scan-max-count")
+        .arg(0)
+        .query::<()>(&mut con)
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn arena_chunk_size_config_roundtrip() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+
+    let value: Vec<String> = redis::cmd("CONFIG")
+        .arg("GET")
+        .arg("gzset.arena-chunk-size")
+        .query(&mut con)?;
+    assert_eq!(value, ["gzset.arena-chunk-size", "1048576"]);
+
+    redis::cmd("CONFIG")
+        .arg("SET")
+        .arg("gzset.arena-chunk-size")
+        .arg("64kb")
+        .query::<()>(&mut con)?;
+    let value: Vec<String> = redis::cmd("CONFIG")
+        .arg("GET")
+        .arg("gzset.arena-chunk-size")
+        .query(&mut con)?;
+    assert_eq!(value[1], "65536");
+
+    // New sets pick up the smaller chunks; members larger than a chunk
+    // still round-trip.
+    let big = "m".repeat(100_000);
+    redis::cmd("GZADD")
+        .arg("s")
+        .arg(1)
+        .arg(&big)
+        .arg(2)
+        .arg("small")
+        .query::<i64>(&mut con)?;
+    let members: Vec<String> = redis::cmd("GZRANGE")
+        .arg("s")
+        .arg(0)
+        .arg(-1)
+        .query(&mut con)?;
+    assert_eq!(members, [big, "small".to_owned()]);
+
+    assert!(redis::cmd("CONFIG")
+        .arg("SET")
+        .arg("gzset.arena-chunk-size")
+        .arg(16)
+        .query::<()>(&mut con)
+        .is_err());
+    Ok(())
+}
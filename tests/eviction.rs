@@ -0,0 +1,76 @@
+use std::{thread, time::Duration};
+
+mod helpers;
+
+fn info_field(con: &mut redis::Connection, section: &str, field: &str) -> i64 {
+    let info: String = redis::cmd("INFO").arg(section).query(con).unwrap();
+    info.lines()
+        .find_map(|l| l.strip_prefix(field)?.strip_prefix(':'))
+        .unwrap()
+        .trim()
+        .parse()
+        .unwrap()
+}
+
+#[test]
+fn lfu_eviction_frees_gzset_keys() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start_with(&[
+        "--maxmemory",
+        "16mb",
+        "--maxmemory-policy",
+        "allkeys-lfu",
+        // Every access bumps the LFU counter, so its growth is deterministic.
+        "--lfu-log-factor",
+        "0",
+    ]);
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+
+    const KEYS: usize = 2_000;
+    const MEMBERS: usize = 500;
+    for k in 0..KEYS {
+        let mut cmd = redis::cmd("GZADD");
+        cmd.arg(format!("gz:{k}"));
+        for m in 0..MEMBERS {
+            cmd.arg(m).arg(format!("member:{k}:{m}"));
+        }
+        let added: i64 = cmd.query(&mut con)?;
+        assert_eq!(added, MEMBERS as i64);
+    }
+
+    let evicted = info_field(&mut con, "stats", "evicted_keys");
+    let dbsize: i64 = redis::cmd("DBSIZE").query(&mut con)?;
+    assert!(evicted > 0, "writing past maxmemory must evict");
+    assert_eq!(dbsize + evicted, KEYS as i64);
+
+    let last = format!("gz:{}", KEYS - 1);
+    let card: i64 = redis::cmd("GZCARD").arg(&last).query(&mut con)?;
+    assert_eq!(card, MEMBERS as i64, "the newest key survives intact");
+    let freq = |con: &mut redis::Connection| -> redis::RedisResult<i64> {
+        redis::cmd("OBJECT").arg("FREQ").arg(&last).query(con)
+    };
+    let before = freq(&mut con)?;
+    for _ in 0..5 {
+        redis::cmd("GZCARD").arg(&last).query::<i64>(&mut con)?;
+    }
+    let after = freq(&mut con)?;
+    assert!(
+        after > before,
+        "OBJECT FREQ {before} -> {after} after five reads"
+    );
+
+    redis::cmd("CONFIG")
+        .arg("SET")
+        .arg("maxmemory-policy")
+        .arg("allkeys-lru")
+        .query::<()>(&mut con)?;
+    let idle = |con: &mut redis::Connection| -> redis::RedisResult<i64> {
+        redis::cmd("OBJECT").arg("IDLETIME").arg(&last).query(con)
+    };
+    redis::cmd("GZCARD").arg(&last).query::<i64>(&mut con)?;
+    let fresh = idle(&mut con)?;
+    assert!(fresh <= 1, "OBJECT IDLETIME {fresh} right after a read");
+    thread::sleep(Duration::from_secs(3));
+    let stale = idle(&mut con)?;
+    assert!(stale >= 2, "OBJECT IDLETIME {stale} after a 3s sleep");
+    Ok(())
+}
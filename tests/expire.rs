@@ -0,0 +1,113 @@
+use std::{thread, time::Duration};
+
+mod helpers;
+
+fn ttl(con: &mut redis::Connection, key: &str) -> redis::RedisResult<i64> {
+    redis::cmd("TTL").arg(key).query(con)
+}
+
+#[test]
+fn expired_keys_read_as_missing_and_are_recreated_fresh() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start_with(&["--enable-debug-command", "yes"]);
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+    // Only the lazy check on key open may expire keys, so every command below
+    // has to see through the stale value itself.
+    redis::cmd("DEBUG")
+        .arg("SET-ACTIVE-EXPIRE")
+        .arg(0)
+        .query::<()>(&mut con)?;
+
+    redis::cmd("GZADD")
+        .arg("s")
+        .arg(1)
+        .arg("a")
+        .arg(2)
+        .arg("b")
+        .query::<i64>(&mut con)?;
+    redis::cmd("GZADD")
+        .arg("src")
+        .arg(1)
+        .arg("a")
+        .query::<i64>(&mut con)?;
+    for key in ["s", "src"] {
+        redis::cmd("PEXPIRE")
+            .arg(key)
+            .arg(50)
+            .query::<i64>(&mut con)?;
+    }
+    thread::sleep(Duration::from_millis(150));
+
+    let card: i64 = redis::cmd("GZCARD").arg("s").query(&mut con)?;
+    assert_eq!(card, 0);
+    let range: Vec<String> = redis::cmd("GZRANGE")
+        .arg("s")
+        .arg(0)
+        .arg(-1)
+        .query(&mut con)?;
+    assert!(range.is_empty());
+    let score: Option<f64> = redis::cmd("GZSCORE").arg("s").arg("a").query(&mut con)?;
+    assert_eq!(score, None);
+    let moved: i64 = redis::cmd("GZMOVE")
+        .arg("src")
+        .arg("dst")
+        .arg("a")
+        .query(&mut con)?;
+    assert_eq!(moved, 0);
+
+    // The write starts from an empty set with no TTL.
+    let reply: (i64, i64) = redis::cmd("GZADD")
+        .arg("s")
+        .arg("GETCREATED")
+        .arg(3)
+        .arg("c")
+        .query(&mut con)?;
+    assert_eq!(reply, (1, 1));
+    let members: Vec<String> = redis::cmd("GZRANGE")
+        .arg("s")
+        .arg(0)
+        .arg(-1)
+        .query(&mut con)?;
+    assert_eq!(members, ["c"]);
+    assert_eq!(ttl(&mut con, "s")?, -1);
+    Ok(())
+}
+
+#[test]
+fn writes_keep_ttl_and_store_commands_clear_it() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+
+    for key in ["s", "dst"] {
+        redis::cmd("GZADD")
+            .arg(key)
+            .arg(1)
+            .arg("a")
+            .query::<i64>(&mut con)?;
+        redis::cmd("EXPIRE")
+            .arg(key)
+            .arg(100)
+            .query::<i64>(&mut con)?;
+    }
+    redis::cmd("GZADD")
+        .arg("s")
+        .arg(2)
+        .arg("b")
+        .query::<i64>(&mut con)?;
+    redis::cmd("GZREM")
+        .arg("s")
+        .arg("a")
+        .query::<i64>(&mut con)?;
+    assert!(ttl(&mut con, "s")? > 0, "in-place writes keep the TTL");
+
+    redis::cmd("GZUNIONSTORE")
+        .arg("dst")
+        .arg(1)
+        .arg("s")
+        .query::<i64>(&mut con)?;
+    assert_eq!(
+        ttl(&mut con, "dst")?,
+        -1,
+        "a stored result replaces the key"
+    );
+    Ok(())
+}
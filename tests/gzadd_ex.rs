@@ -0,0 +1,136 @@
+mod helpers;
+
+fn ttl(con: &mut redis::Connection, key: &str) -> redis::RedisResult<i64> {
+    redis::cmd("TTL").arg(key).query(con)
+}
+
+#[test]
+fn gzadd_ex_adds_and_sets_ttl() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+
+    let added: i64 = redis::cmd("GZADD")
+        .arg("session")
+        .arg("EX")
+        .arg(100)
+        .arg(1)
+        .arg("alice")
+        .query(&mut con)?;
+    assert_eq!(added, 1);
+    let left = ttl(&mut con, "session")?;
+    assert!((1..=100).contains(&left), "ttl {left}");
+    let score: f64 = redis::cmd("GZSCORE")
+        .arg("session")
+        .arg("alice")
+        .query(&mut con)?;
+    assert_eq!(score, 1.0);
+
+    // A later touch refreshes the TTL even when the score is unchanged, and
+    // combines with the other options.
+    let changed: i64 = redis::cmd("GZADD")
+        .arg("session")
+        .arg("xx")
+        .arg("ch")
+        .arg("ex")
+        .arg(5000)
+        .arg(1)
+        .arg("alice")
+        .query(&mut con)?;
+    assert_eq!(changed, 0);
+    assert!(ttl(&mut con, "session")? > 100);
+    let score: f64 = redis::cmd("GZADD")
+        .arg("session")
+        .arg("INCR")
+        .arg("EX")
+        .arg(200)
+        .arg(2)
+        .arg("alice")
+        .query(&mut con)?;
+    assert_eq!(score, 3.0);
+    assert!(ttl(&mut con, "session")? <= 200);
+
+    // Without EX the TTL is left alone.
+    redis::cmd("GZADD")
+        .arg("session")
+        .arg(4)
+        .arg("bob")
+        .query::<i64>(&mut con)?;
+    assert!(ttl(&mut con, "session")? > 0);
+
+    // Nothing stored means no key to expire.
+    let added: i64 = redis::cmd("GZADD")
+        .arg("absent")
+        .arg("XX")
+        .arg("EX")
+        .arg(10)
+        .arg(1)
+        .arg("a")
+        .query(&mut con)?;
+    assert_eq!(added, 0);
+    assert_eq!(ttl(&mut con, "absent")?, -2);
+    Ok(())
+}
+
+#[test]
+fn gzadd_ex_rejects_bad_seconds() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+
+    for seconds in ["0", "-5", "abc", "1.5", "9223372036854775807"] {
+        let res: redis::RedisResult<i64> = redis::cmd("GZADD")
+            .arg("s")
+            .arg("EX")
+            .arg(seconds)
+            .arg(1)
+            .arg("a")
+            .query(&mut con);
+        assert!(res.is_err(), "EX {seconds} accepted");
+    }
+    let err = redis::cmd("GZADD")
+        .arg("s")
+        .arg("EX")
+        .arg(0)
+        .arg(1)
+        .arg("a")
+        .query::<i64>(&mut con)
+        .unwrap_err();
+    assert!(err.to_string().contains("invalid expire time"), "{err}");
+    let err = redis::cmd("GZADD")
+        .arg("s")
+        .arg("EX")
+        .arg(10)
+        .arg("EX")
+        .arg(20)
+        .arg(1)
+        .arg("a")
+        .query::<i64>(&mut con)
+        .unwrap_err();
+    assert!(err.to_string().contains("syntax error"), "{err}");
+    let exists: i64 = redis::cmd("EXISTS").arg("s").query(&mut con)?;
+    assert_eq!(exists, 0, "a rejected call stores nothing");
+    Ok(())
+}
+
+#[test]
+fn gzadd_ex_leaves_ttl_alone_when_the_write_fails() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+
+    redis::cmd("GZADD")
+        .arg("s")
+        .arg(1e308)
+        .arg("a")
+        .query::<i64>(&mut con)?;
+    let err = redis::cmd("GZADD")
+        .arg("s")
+        .arg("INCR")
+        .arg("EX")
+        .arg(100)
+        .arg(1e308)
+        .arg("a")
+        .query::<f64>(&mut con)
+        .unwrap_err();
+    assert!(err.to_string().contains("not a finite number"), "{err}");
+    assert_eq!(ttl(&mut con, "s")?, -1, "a failed write sets no TTL");
+    Ok(())
+}
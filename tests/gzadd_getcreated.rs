@@ -0,0 +1,79 @@
+mod helpers;
+
+#[test]
+fn gzadd_getcreated_reports_key_creation() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+
+    let reply: (i64, i64) = redis::cmd("GZADD")
+        .arg("s")
+        .arg("GETCREATED")
+        .arg(1)
+        .arg("a")
+        .arg(2)
+        .arg("b")
+        .query(&mut con)?;
+    assert_eq!(reply, (2, 1));
+    let reply: (i64, i64) = redis::cmd("GZADD")
+        .arg("s")
+        .arg("getcreated")
+        .arg(3)
+        .arg("c")
+        .query(&mut con)?;
+    assert_eq!(reply, (1, 0), "adding to an existing key");
+    let reply: (i64, i64) = redis::cmd("GZADD")
+        .arg("s")
+        .arg("CH")
+        .arg("GETCREATED")
+        .arg(5)
+        .arg("a")
+        .query(&mut con)?;
+    assert_eq!(reply, (1, 0), "updating an existing key");
+
+    // XX on a missing key stores nothing, so nothing is created.
+    let reply: (i64, i64) = redis::cmd("GZADD")
+        .arg("t")
+        .arg("XX")
+        .arg("GETCREATED")
+        .arg(1)
+        .arg("a")
+        .query(&mut con)?;
+    assert_eq!(reply, (0, 0));
+    let exists: i64 = redis::cmd("EXISTS").arg("t").query(&mut con)?;
+    assert_eq!(exists, 0);
+
+    let reply: (f64, i64) = redis::cmd("GZADD")
+        .arg("t")
+        .arg("INCR")
+        .arg("GETCREATED")
+        .arg(1.5)
+        .arg("a")
+        .query(&mut con)?;
+    assert_eq!(reply, (1.5, 1));
+    let reply: (f64, i64) = redis::cmd("GZADD")
+        .arg("t")
+        .arg("GETSCORE")
+        .arg("GETCREATED")
+        .arg(9)
+        .arg("a")
+        .query(&mut con)?;
+    assert_eq!(reply, (9.0, 0));
+    let reply: (Option<f64>, i64) = redis::cmd("GZADD")
+        .arg("u")
+        .arg("XX")
+        .arg("INCR")
+        .arg("GETCREATED")
+        .arg(1)
+        .arg("a")
+        .query(&mut con)?;
+    assert_eq!(reply, (None, 0));
+
+    // Without the option the reply is unchanged.
+    let added: i64 = redis::cmd("GZADD")
+        .arg("v")
+        .arg(1)
+        .arg("a")
+        .query(&mut con)?;
+    assert_eq!(added, 1);
+    Ok(())
+}
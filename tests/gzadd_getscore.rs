@@ -0,0 +1,53 @@
+mod helpers;
+
+#[test]
+fn gzadd_getscore_replies_stored_score() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+
+    let added: i64 = redis::cmd("GZADD")
+        .arg("s")
+        .arg(1)
+        .arg("a")
+        .query(&mut con)?;
+    assert_eq!(added, 1);
+
+    let score: f64 = redis::cmd("GZADD")
+        .arg("s")
+        .arg("GETSCORE")
+        .arg(2.5)
+        .arg("a")
+        .query(&mut con)?;
+    assert_eq!(score, 2.5);
+    let score: f64 = redis::cmd("GZADD")
+        .arg("s")
+        .arg("getscore")
+        .arg(-3)
+        .arg("b")
+        .query(&mut con)?;
+    assert_eq!(score, -3.0);
+
+    let card: i64 = redis::cmd("GZCARD").arg("s").query(&mut con)?;
+    assert_eq!(card, 2);
+    let stored: f64 = redis::cmd("GZSCORE").arg("s").arg("a").query(&mut con)?;
+    assert_eq!(stored, 2.5);
+
+    let res: redis::RedisResult<()> = redis::cmd("GZADD")
+        .arg("s")
+        .arg("GETSCORE")
+        .arg(1)
+        .query(&mut con);
+    assert!(res.unwrap_err().to_string().contains("syntax error"));
+
+    let res: redis::RedisResult<()> = redis::cmd("GZADD")
+        .arg("s")
+        .arg("INCR")
+        .arg("GETSCORE")
+        .arg(1)
+        .arg("a")
+        .query(&mut con);
+    assert!(res.unwrap_err().to_string().contains("not compatible"));
+    let stored: f64 = redis::cmd("GZSCORE").arg("s").arg("a").query(&mut con)?;
+    assert_eq!(stored, 2.5, "a rejected call leaves the set alone");
+    Ok(())
+}
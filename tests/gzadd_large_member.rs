@@ -0,0 +1,39 @@
+mod helpers;
+
+#[test]
+fn gzadd_accepts_member_larger_than_arena_chunk() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+
+    let big = "g".repeat(6 * 1024 * 1024);
+    let added: i64 = redis::cmd("GZADD")
+        .arg("s")
+        .arg(0)
+        .arg(&big)
+        .arg(1)
+        .arg("small")
+        .query(&mut con)?;
+    assert_eq!(added, 2);
+
+    let score: Option<f64> = redis::cmd("GZSCORE").arg("s").arg(&big).query(&mut con)?;
+    assert_eq!(score, Some(0.0));
+    let first: Vec<String> = redis::cmd("GZRANGE")
+        .arg("s")
+        .arg(0)
+        .arg(0)
+        .query(&mut con)?;
+    assert!(
+        first.len() == 1 && first[0] == big,
+        "oversized member came back altered"
+    );
+
+    let removed: i64 = redis::cmd("GZREM").arg("s").arg(&big).query(&mut con)?;
+    assert_eq!(removed, 1);
+    let rest: Vec<String> = redis::cmd("GZRANGE")
+        .arg("s")
+        .arg(0)
+        .arg(-1)
+        .query(&mut con)?;
+    assert_eq!(rest, ["small"]);
+    Ok(())
+}
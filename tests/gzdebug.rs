@@ -0,0 +1,81 @@
+mod helpers;
+
+#[test]
+fn gzdebug_encoding_counts_inline_and_spilled_scores() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+
+    let encoding = |con: &mut redis::Connection,
+                    key: &str|
+     -> redis::RedisResult<(String, i64, String, i64)> {
+        redis::cmd("GZDEBUG").arg("ENCODING").arg(key).query(con)
+    };
+
+    let mut pipe = redis::pipe();
+    for i in 0..20 {
+        pipe.cmd("GZADD").arg("s").arg(i).arg(format!("m{i}"));
+    }
+    pipe.cmd("GZADD").arg("s").arg(3).arg("tie-a");
+    pipe.cmd("GZADD").arg("s").arg(7).arg("tie-b");
+    pipe.query::<()>(&mut con)?;
+
+    assert_eq!(
+        encoding(&mut con, "s")?,
+        ("inline".into(), 18, "spilled".into(), 2)
+    );
+    redis::cmd("GZREM")
+        .arg("s")
+        .arg("tie-a")
+        .query::<i64>(&mut con)?;
+    assert_eq!(encoding(&mut con, "s")?.1, 19);
+    assert_eq!(encoding(&mut con, "nokey")?.1, 0);
+
+    let unknown: redis::RedisResult<redis::Value> =
+        redis::cmd("GZDEBUG").arg("NOPE").arg("s").query(&mut con);
+    assert!(unknown.is_err());
+    Ok(())
+}
+
+#[test]
+fn gzdebug_topbuckets_lists_largest_scores() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+
+    let mut pipe = redis::pipe();
+    for (score, members) in [(1.5, 4), (2.0, 9), (3.0, 4), (4.0, 1)] {
+        for i in 0..members {
+            pipe.cmd("GZADD")
+                .arg("s")
+                .arg(score)
+                .arg(format!("m{score}-{i}"));
+        }
+    }
+    pipe.query::<()>(&mut con)?;
+
+    let top: Vec<(String, i64)> = redis::cmd("GZDEBUG")
+        .arg("TOPBUCKETS")
+        .arg("s")
+        .arg(3)
+        .query(&mut con)?;
+    assert_eq!(top, [("2".into(), 9), ("1.5".into(), 4), ("3".into(), 4)]);
+    let all: Vec<(String, i64)> = redis::cmd("GZDEBUG")
+        .arg("TOPBUCKETS")
+        .arg("s")
+        .arg(100)
+        .query(&mut con)?;
+    assert_eq!(all.len(), 4);
+    let none: Vec<(String, i64)> = redis::cmd("GZDEBUG")
+        .arg("TOPBUCKETS")
+        .arg("nokey")
+        .arg(5)
+        .query(&mut con)?;
+    assert!(none.is_empty());
+
+    let negative: redis::RedisResult<redis::Value> = redis::cmd("GZDEBUG")
+        .arg("TOPBUCKETS")
+        .arg("s")
+        .arg(-1)
+        .query(&mut con);
+    assert!(negative.is_err());
+    Ok(())
+}
@@ -0,0 +1,47 @@
+mod helpers;
+
+#[test]
+fn gzmemestimate_grows_with_shape() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+
+    let empty: i64 = redis::cmd("GZMEMESTIMATE").arg(0).arg(16).query(&mut con)?;
+    assert_eq!(empty, 0);
+
+    let small: i64 = redis::cmd("GZMEMESTIMATE")
+        .arg(1000)
+        .arg(16)
+        .query(&mut con)?;
+    let large: i64 = redis::cmd("GZMEMESTIMATE")
+        .arg(100_000)
+        .arg(16)
+        .query(&mut con)?;
+    let longer: i64 = redis::cmd("GZMEMESTIMATE")
+        .arg(1000)
+        .arg(256)
+        .query(&mut con)?;
+    assert!(0 < small && small < large);
+    assert!(small < longer);
+
+    let keys: Vec<String> = redis::cmd("KEYS").arg("*").query(&mut con)?;
+    assert!(keys.is_empty());
+
+    let err = redis::cmd("GZMEMESTIMATE")
+        .arg(-1)
+        .arg(16)
+        .query::<i64>(&mut con)
+        .unwrap_err();
+    assert!(err.to_string().contains("out of range"));
+
+    for (members, avg_len) in [(i64::MAX, 10), (1000, i64::MAX), (i64::MAX, i64::MAX)] {
+        let err = redis::cmd("GZMEMESTIMATE")
+            .arg(members)
+            .arg(avg_len)
+            .query::<i64>(&mut con)
+            .unwrap_err();
+        assert!(err.to_string().contains("out of range"), "{err}");
+    }
+    let pong: String = redis::cmd("PING").query(&mut con)?;
+    assert_eq!(pong, "PONG");
+    Ok(())
+}
@@ -0,0 +1,27 @@
+mod helpers;
+
+#[test]
+fn gzmin_gzmax_peek_without_removing() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+
+    let missing: Option<Vec<String>> = redis::cmd("GZMIN").arg("s").query(&mut con)?;
+    assert_eq!(missing, None);
+    let missing: Option<Vec<String>> = redis::cmd("GZMAX").arg("s").query(&mut con)?;
+    assert_eq!(missing, None);
+
+    let mut pipe = redis::pipe();
+    for (score, member) in [(2, "b"), (1, "c"), (1, "a"), (3, "y"), (3, "x")] {
+        pipe.cmd("GZADD").arg("s").arg(score).arg(member);
+    }
+    pipe.query::<()>(&mut con)?;
+
+    let min: Vec<String> = redis::cmd("GZMIN").arg("s").query(&mut con)?;
+    assert_eq!(min, vec!["a".to_string(), "1".to_string()]);
+    let max: Vec<String> = redis::cmd("GZMAX").arg("s").query(&mut con)?;
+    assert_eq!(max, vec!["y".to_string(), "3".to_string()]);
+
+    let card: i64 = redis::cmd("GZCARD").arg("s").query(&mut con)?;
+    assert_eq!(card, 5);
+    Ok(())
+}
@@ -0,0 +1,102 @@
+mod helpers;
+
+fn range(con: &mut redis::Connection, key: &str) -> redis::RedisResult<Vec<(String, f64)>> {
+    redis::cmd("GZRANGE")
+        .arg(key)
+        .arg(0)
+        .arg(-1)
+        .arg("WITHSCORES")
+        .query(con)
+}
+
+fn gzmove(
+    con: &mut redis::Connection,
+    src: &str,
+    dst: &str,
+    member: &str,
+) -> redis::RedisResult<i64> {
+    redis::cmd("GZMOVE")
+        .arg(src)
+        .arg(dst)
+        .arg(member)
+        .query(con)
+}
+
+#[test]
+fn gzmove_relocates_present_members() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+    redis::cmd("GZADD")
+        .arg("src")
+        .arg(1.5)
+        .arg("a")
+        .arg(2)
+        .arg("b")
+        .query::<i64>(&mut con)?;
+    redis::cmd("GZADD")
+        .arg("dst")
+        .arg(9)
+        .arg("b")
+        .query::<i64>(&mut con)?;
+
+    assert_eq!(gzmove(&mut con, "src", "fresh", "a")?, 1);
+    assert_eq!(range(&mut con, "fresh")?, [("a".to_owned(), 1.5)]);
+    assert_eq!(range(&mut con, "src")?, [("b".to_owned(), 2.0)]);
+
+    // A member already in `dst` takes the score it had in `src`.
+    assert_eq!(gzmove(&mut con, "src", "dst", "b")?, 1);
+    assert_eq!(range(&mut con, "dst")?, [("b".to_owned(), 2.0)]);
+    let exists: i64 = redis::cmd("EXISTS").arg("src").query(&mut con)?;
+    assert_eq!(exists, 0, "an emptied src is deleted");
+    Ok(())
+}
+
+#[test]
+fn gzmove_absent_and_self_are_no_ops() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+    redis::cmd("GZADD")
+        .arg("s")
+        .arg(1)
+        .arg("a")
+        .query::<i64>(&mut con)?;
+
+    assert_eq!(gzmove(&mut con, "s", "d", "nope")?, 0);
+    assert_eq!(gzmove(&mut con, "missing", "d", "a")?, 0);
+    let exists: i64 = redis::cmd("EXISTS").arg("d").query(&mut con)?;
+    assert_eq!(exists, 0);
+
+    assert_eq!(gzmove(&mut con, "s", "s", "a")?, 1);
+    assert_eq!(gzmove(&mut con, "s", "s", "nope")?, 0);
+    assert_eq!(range(&mut con, "s")?, [("a".to_owned(), 1.0)]);
+    Ok(())
+}
+
+#[test]
+fn gzmove_wrong_type_leaves_src_alone() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+    redis::cmd("GZADD")
+        .arg("s")
+        .arg(1)
+        .arg("a")
+        .query::<i64>(&mut con)?;
+    redis::cmd("SET")
+        .arg("str")
+        .arg("x")
+        .query::<()>(&mut con)?;
+
+    let err = gzmove(&mut con, "s", "str", "a").unwrap_err();
+    assert!(err.to_string().contains("WRONGTYPE"), "{err}");
+    let err = gzmove(&mut con, "str", "s", "a").unwrap_err();
+    assert!(err.to_string().contains("WRONGTYPE"), "{err}");
+    assert_eq!(range(&mut con, "s")?, [("a".to_owned(), 1.0)]);
+
+    let err = redis::cmd("GZMOVE")
+        .arg("s")
+        .arg("d")
+        .query::<i64>(&mut con)
+        .unwrap_err();
+    assert!(err.to_string().contains("wrong number"), "{err}");
+    Ok(())
+}
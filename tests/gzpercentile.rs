@@ -0,0 +1,34 @@
+mod helpers;
+
+#[test]
+fn gzpercentile_selects_nearest_rank_score() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+
+    let mut add = redis::cmd("GZADD");
+    add.arg("lat");
+    for i in 1..=100 {
+        add.arg(i).arg(format!("req{i}"));
+    }
+    add.query::<i64>(&mut con)?;
+
+    let pct = |con: &mut redis::Connection, p: &str| -> redis::RedisResult<Option<f64>> {
+        redis::cmd("GZPERCENTILE").arg("lat").arg(p).query(con)
+    };
+    assert_eq!(pct(&mut con, "0")?, Some(1.0));
+    assert_eq!(pct(&mut con, "50")?, Some(50.0));
+    assert_eq!(pct(&mut con, "95")?, Some(95.0));
+    assert_eq!(pct(&mut con, "99.5")?, Some(100.0));
+    assert_eq!(pct(&mut con, "100")?, Some(100.0));
+    assert_eq!(pct(&mut con, "-10")?, Some(1.0), "p clamps to 0");
+    assert_eq!(pct(&mut con, "+inf")?, Some(100.0), "p clamps to 100");
+    assert!(pct(&mut con, "abc").is_err());
+    assert!(pct(&mut con, "nan").is_err());
+
+    let missing: Option<f64> = redis::cmd("GZPERCENTILE")
+        .arg("nokey")
+        .arg(50)
+        .query(&mut con)?;
+    assert_eq!(missing, None);
+    Ok(())
+}
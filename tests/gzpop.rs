@@ -118,3 +118,52 @@ fn gzpopmin_updates_state_and_memory() -> redis::RedisResult<()> {
 
     Ok(())
 }
+
+fn resp3_exchange(stream: &mut std::net::TcpStream, args: &[&str]) -> String {
+    use std::io::{Read, Write};
+    let mut req = format!("*{}\r\n", args.len());
+    for arg in args {
+        req.push_str(&format!("${}\r\n{arg}\r\n", arg.len()));
+    }
+    req.push_str("*1\r\n$4\r\nPING\r\n");
+    stream.write_all(req.as_bytes()).unwrap();
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    while !buf.ends_with(b"+PONG\r\n") {
+        let n = stream.read(&mut chunk).unwrap();
+        assert!(n > 0, "connection closed");
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    let reply = String::from_utf8(buf).unwrap();
+    reply.strip_suffix("+PONG\r\n").unwrap().to_string()
+}
+
+#[test]
+fn gzpop_count_nests_pairs_under_resp3() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut stream = std::net::TcpStream::connect(("127.0.0.1", vk.port))?;
+    let hello = resp3_exchange(&mut stream, &["HELLO", "3"]);
+    assert!(hello.starts_with('%'), "unexpected HELLO reply: {hello:?}");
+
+    for (score, member) in [("1", "a"), ("2", "b"), ("3", "c"), ("4", "d")] {
+        resp3_exchange(&mut stream, &["GZADD", "resp3", score, member]);
+    }
+
+    assert_eq!(
+        resp3_exchange(&mut stream, &["GZPOPMIN", "resp3"]),
+        "*2\r\n$1\r\na\r\n,1\r\n"
+    );
+    assert_eq!(
+        resp3_exchange(&mut stream, &["GZPOPMAX", "resp3", "1"]),
+        "*1\r\n*2\r\n$1\r\nd\r\n,4\r\n"
+    );
+    assert_eq!(
+        resp3_exchange(&mut stream, &["GZPOPMIN", "resp3", "5"]),
+        "*2\r\n*2\r\n$1\r\nb\r\n,2\r\n*2\r\n$1\r\nc\r\n,3\r\n"
+    );
+    assert_eq!(
+        resp3_exchange(&mut stream, &["GZPOPMIN", "resp3", "2"]),
+        "*0\r\n"
+    );
+    Ok(())
+}
@@ -0,0 +1,58 @@
+mod helpers;
+
+#[test]
+fn gzpoprangebyscore_trims_window() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+
+    let mut pipe = redis::pipe();
+    for t in 0..10 {
+        pipe.cmd("GZADD").arg("events").arg(t).arg(format!("e{t}"));
+    }
+    pipe.query::<()>(&mut con)?;
+
+    let popped: Vec<String> = redis::cmd("GZPOPRANGEBYSCORE")
+        .arg("events")
+        .arg("-inf")
+        .arg("(3")
+        .query(&mut con)?;
+    assert_eq!(popped, ["e0", "0", "e1", "1", "e2", "2"]);
+
+    let popped: Vec<String> = redis::cmd("GZPOPRANGEBYSCORE")
+        .arg("events")
+        .arg("(5")
+        .arg("7")
+        .query(&mut con)?;
+    assert_eq!(popped, ["e6", "6", "e7", "7"]);
+
+    let rest: Vec<String> = redis::cmd("GZRANGE")
+        .arg("events")
+        .arg(0)
+        .arg(-1)
+        .query(&mut con)?;
+    assert_eq!(rest, ["e3", "e4", "e5", "e8", "e9"]);
+
+    let none: Vec<String> = redis::cmd("GZPOPRANGEBYSCORE")
+        .arg("events")
+        .arg(100)
+        .arg(200)
+        .query(&mut con)?;
+    assert!(none.is_empty());
+
+    let err = redis::cmd("GZPOPRANGEBYSCORE")
+        .arg("events")
+        .arg("x")
+        .arg(1)
+        .query::<Vec<String>>(&mut con)
+        .unwrap_err();
+    assert!(err.to_string().contains("min or max is not a float"));
+
+    let _: Vec<String> = redis::cmd("GZPOPRANGEBYSCORE")
+        .arg("events")
+        .arg("-inf")
+        .arg("+inf")
+        .query(&mut con)?;
+    let exists: i64 = redis::cmd("EXISTS").arg("events").query(&mut con)?;
+    assert_eq!(exists, 0);
+    Ok(())
+}
@@ -54,5 +54,34 @@ fn gzrange_empty_set() -> redis::RedisResult<()> {
         .arg(-1)
         .query(&mut con)?;
     assert!(res.is_empty());
+
+    for cmd in ["GZRANGE", "GZREVRANGE"] {
+        for (start, stop) in [(0, 0), (0, -1), (-1, -1), (-1, 0), (5, 10)] {
+            for with_scores in [false, true] {
+                let mut c = redis::cmd(cmd);
+                c.arg("missing").arg(start).arg(stop);
+                if with_scores {
+                    c.arg("WITHSCORES");
+                }
+                let res: Vec<String> = c.query(&mut con)?;
+                assert!(res.is_empty(), "{cmd} {start} {stop} {with_scores}");
+            }
+        }
+    }
+
+    // A key emptied by its last removal is deleted and reads the same way.
+    redis::cmd("GZADD")
+        .arg("s")
+        .arg(1)
+        .arg("a")
+        .execute(&mut con);
+    redis::cmd("GZREM").arg("s").arg("a").execute(&mut con);
+    let res: Vec<String> = redis::cmd("GZRANGE")
+        .arg("s")
+        .arg(0)
+        .arg(0)
+        .arg("WITHSCORES")
+        .query(&mut con)?;
+    assert!(res.is_empty());
     Ok(())
 }
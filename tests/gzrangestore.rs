@@ -0,0 +1,97 @@
+mod helpers;
+
+fn range(con: &mut redis::Connection, key: &str) -> redis::RedisResult<Vec<String>> {
+    redis::cmd("GZRANGE")
+        .arg(key)
+        .arg(0)
+        .arg(-1)
+        .arg("WITHSCORES")
+        .query(con)
+}
+
+fn rangestore(
+    con: &mut redis::Connection,
+    dst: &str,
+    src: &str,
+    start: i64,
+    stop: i64,
+) -> redis::RedisResult<i64> {
+    redis::cmd("GZRANGESTORE")
+        .arg(dst)
+        .arg(src)
+        .arg(start)
+        .arg(stop)
+        .query(con)
+}
+
+#[test]
+fn gzrangestore_copies_a_rank_range() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+    let mut pipe = redis::pipe();
+    for (score, member) in [(1, "a"), (2, "b"), (2, "c"), (3, "d")] {
+        pipe.cmd("GZADD").arg("src").arg(score).arg(member).ignore();
+    }
+    pipe.query::<()>(&mut con)?;
+    redis::cmd("SET")
+        .arg("dst")
+        .arg("stale")
+        .query::<()>(&mut con)?;
+    redis::cmd("EXPIRE")
+        .arg("dst")
+        .arg(100)
+        .query::<i64>(&mut con)?;
+
+    assert_eq!(rangestore(&mut con, "dst", "src", 1, 2)?, 2);
+    assert_eq!(range(&mut con, "dst")?, ["b", "2", "c", "2"]);
+    let ttl: i64 = redis::cmd("TTL").arg("dst").query(&mut con)?;
+    assert_eq!(ttl, -1, "the stored result replaces the key");
+    assert_eq!(range(&mut con, "src")?.len(), 8, "src is left alone");
+
+    assert_eq!(rangestore(&mut con, "dst", "src", -2, -1)?, 2);
+    assert_eq!(range(&mut con, "dst")?, ["c", "2", "d", "3"]);
+
+    // Storing into the source keeps only the slice.
+    assert_eq!(rangestore(&mut con, "src", "src", 0, 1)?, 2);
+    assert_eq!(range(&mut con, "src")?, ["a", "1", "b", "2"]);
+    Ok(())
+}
+
+#[test]
+fn gzrangestore_empty_range_deletes_dst() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+    redis::cmd("GZADD")
+        .arg("src")
+        .arg(1)
+        .arg("a")
+        .query::<i64>(&mut con)?;
+    redis::cmd("GZADD")
+        .arg("dst")
+        .arg(1)
+        .arg("x")
+        .query::<i64>(&mut con)?;
+
+    assert_eq!(rangestore(&mut con, "dst", "src", 5, 10)?, 0);
+    assert_eq!(rangestore(&mut con, "other", "missing", 0, -1)?, 0);
+    for key in ["dst", "other"] {
+        let exists: i64 = redis::cmd("EXISTS").arg(key).query(&mut con)?;
+        assert_eq!(exists, 0, "{key}");
+    }
+
+    redis::cmd("SET")
+        .arg("str")
+        .arg("x")
+        .query::<()>(&mut con)?;
+    let err = rangestore(&mut con, "dst", "str", 0, -1).unwrap_err();
+    assert!(err.to_string().contains("WRONGTYPE"), "{err}");
+    let err = redis::cmd("GZRANGESTORE")
+        .arg("dst")
+        .arg("src")
+        .arg(0)
+        .arg("x")
+        .query::<i64>(&mut con)
+        .unwrap_err();
+    assert!(err.to_string().contains("not an integer"), "{err}");
+    Ok(())
+}
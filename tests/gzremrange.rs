@@ -0,0 +1,103 @@
+mod helpers;
+
+fn fill(con: &mut redis::Connection, key: &str, n: i64) -> redis::RedisResult<()> {
+    let mut pipe = redis::pipe();
+    for i in 0..n {
+        pipe.cmd("GZADD").arg(key).arg(i % 4).arg(format!("m{i}"));
+    }
+    pipe.query(con)
+}
+
+fn members(con: &mut redis::Connection, key: &str) -> redis::RedisResult<Vec<String>> {
+    redis::cmd("GZRANGE").arg(key).arg(0).arg(-1).query(con)
+}
+
+#[test]
+fn gzremrangebyrank_removes_ranks() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+    fill(&mut con, "s", 8)?;
+
+    let removed: i64 = redis::cmd("GZREMRANGEBYRANK")
+        .arg("s")
+        .arg(2)
+        .arg(-3)
+        .query(&mut con)?;
+    assert_eq!(removed, 4);
+    assert_eq!(members(&mut con, "s")?, ["m0", "m4", "m3", "m7"]);
+
+    let removed: i64 = redis::cmd("GZREMRANGEBYRANK")
+        .arg("s")
+        .arg(10)
+        .arg(20)
+        .query(&mut con)?;
+    assert_eq!(removed, 0);
+    let removed: i64 = redis::cmd("GZREMRANGEBYRANK")
+        .arg("nokey")
+        .arg(0)
+        .arg(-1)
+        .query(&mut con)?;
+    assert_eq!(removed, 0);
+    Ok(())
+}
+
+#[test]
+fn gzremrangebyscore_removes_scores() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+    fill(&mut con, "s", 8)?;
+
+    let removed: i64 = redis::cmd("GZREMRANGEBYSCORE")
+        .arg("s")
+        .arg("(0")
+        .arg(2)
+        .query(&mut con)?;
+    assert_eq!(removed, 4);
+    assert_eq!(members(&mut con, "s")?, ["m0", "m4", "m3", "m7"]);
+
+    let err = redis::cmd("GZREMRANGEBYSCORE")
+        .arg("s")
+        .arg("x")
+        .arg(1)
+        .query::<i64>(&mut con)
+        .unwrap_err();
+    assert!(err.to_string().contains("min or max is not a float"));
+    Ok(())
+}
+
+#[test]
+fn emptying_writes_delete_the_key() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+    let exists = |con: &mut redis::Connection| -> redis::RedisResult<i64> {
+        redis::cmd("EXISTS").arg("s").query(con)
+    };
+
+    fill(&mut con, "s", 6)?;
+    let removed: i64 = redis::cmd("GZREMRANGEBYRANK")
+        .arg("s")
+        .arg(0)
+        .arg(-1)
+        .query(&mut con)?;
+    assert_eq!(removed, 6);
+    assert_eq!(exists(&mut con)?, 0);
+
+    fill(&mut con, "s", 6)?;
+    let removed: i64 = redis::cmd("GZREMRANGEBYSCORE")
+        .arg("s")
+        .arg("-inf")
+        .arg("+inf")
+        .query(&mut con)?;
+    assert_eq!(removed, 6);
+    assert_eq!(exists(&mut con)?, 0);
+
+    fill(&mut con, "s", 6)?;
+    let _: Vec<String> = redis::cmd("GZPOPMIN").arg("s").arg(10).query(&mut con)?;
+    assert_eq!(exists(&mut con)?, 0);
+
+    fill(&mut con, "s", 2)?;
+    let _: Vec<String> = redis::cmd("GZPOPMAX").arg("s").query(&mut con)?;
+    let _: Vec<String> = redis::cmd("GZPOPMAX").arg("s").query(&mut con)?;
+    assert_eq!(exists(&mut con)?, 0);
+    Ok(())
+}
@@ -105,6 +105,66 @@ fn gzscan_cursor_encoding_special_members() -> redis::RedisResult<()> {
     Ok(())
 }
 
+#[test]
+fn gzscan_cursor_round_trips_extreme_scores() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+
+    let mut scores = [
+        f64::MIN,
+        -1e300,
+        -0.1,
+        5e-324,
+        f64::MIN_POSITIVE,
+        0.1 + 0.2,
+        1.0 / 3.0,
+        9007199254740993.0,
+        1e21,
+        f64::MAX,
+    ];
+    scores.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    let mut pipe = redis::pipe();
+    for (i, score) in scores.iter().enumerate() {
+        let arg = with_fmt_buf(|b| fmt_f64(b, *score).to_owned());
+        pipe.cmd("GZADD").arg("s").arg(arg).arg(format!("m{i}"));
+    }
+    pipe.query::<()>(&mut con)?;
+
+    let mut cursor = "0".to_string();
+    let mut seen = Vec::new();
+    loop {
+        let (next, arr): (String, Vec<String>) = redis::cmd("GZSCAN")
+            .arg("s")
+            .arg(&cursor)
+            .arg("COUNT")
+            .arg(1)
+            .query(&mut con)?;
+        for chunk in arr.chunks(2) {
+            let parsed: f64 = chunk[1].parse().unwrap();
+            seen.push((chunk[0].clone(), parsed.to_bits()));
+        }
+        if next != "0" {
+            let (score_part, _) = next.split_once('|').unwrap();
+            let parsed: f64 = score_part.parse().unwrap();
+            let (_, last_bits) = seen.last().unwrap();
+            assert_eq!(parsed.to_bits(), *last_bits, "cursor {next}");
+        }
+        cursor = next;
+        if cursor == "0" {
+            break;
+        }
+    }
+
+    let expected: Vec<(String, u64)> = scores
+        .iter()
+        .enumerate()
+        .map(|(i, s)| (format!("m{i}"), s.to_bits()))
+        .collect();
+    assert_eq!(seen, expected);
+    Ok(())
+}
+
 #[test]
 fn gzscan_mutation_between_calls() -> redis::RedisResult<()> {
     let vk = helpers::ValkeyInstance::start();
@@ -219,3 +279,192 @@ fn gzscan_rejects_invalid_count() -> redis::RedisResult<()> {
 
     Ok(())
 }
+
+fn scan_step(
+    con: &mut redis::Connection,
+    cursor: &str,
+    count: usize,
+) -> redis::RedisResult<(String, Vec<String>)> {
+    let (next, arr): (String, Vec<String>) = redis::cmd("GZSCAN")
+        .arg("s")
+        .arg(cursor)
+        .arg("COUNT")
+        .arg(count)
+        .query(con)?;
+    Ok((next, arr.chunks(2).map(|c| c[0].clone()).collect()))
+}
+
+#[test]
+fn gzscan_final_batch_ends_the_scan() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+
+    const COUNT: usize = 8;
+    for i in 0..COUNT {
+        redis::cmd("GZADD")
+            .arg("s")
+            .arg(i)
+            .arg(format!("m{i}"))
+            .execute(&mut con);
+    }
+    // Exactly COUNT members: one full batch, and it is the last.
+    let (next, members) = scan_step(&mut con, "0", COUNT)?;
+    assert_eq!((next.as_str(), members.len()), ("0", COUNT));
+
+    // COUNT + 1 members: a full batch, then a single-member final one.
+    redis::cmd("GZADD")
+        .arg("s")
+        .arg(COUNT)
+        .arg(format!("m{COUNT}"))
+        .execute(&mut con);
+    let (cursor, members) = scan_step(&mut con, "0", COUNT)?;
+    assert_ne!(cursor, "0");
+    assert_eq!(members.len(), COUNT);
+    let (last, members) = scan_step(&mut con, &cursor, COUNT)?;
+    assert_eq!((last.as_str(), members), ("0", vec![format!("m{COUNT}")]));
+
+    // A COUNT above the size returns everything at once.
+    let (next, members) = scan_step(&mut con, "0", 1_000)?;
+    assert_eq!((next.as_str(), members.len()), ("0", COUNT + 1));
+
+    // When the members past a cursor are gone, the resumed call is empty
+    // and final rather than handing the same cursor back.
+    redis::cmd("GZREM")
+        .arg("s")
+        .arg(format!("m{COUNT}"))
+        .execute(&mut con);
+    let (last, members) = scan_step(&mut con, &cursor, COUNT)?;
+    assert_eq!((last.as_str(), members.len()), ("0", 0));
+    Ok(())
+}
+
+#[test]
+fn gzscan_resumes_after_cursor_member_deleted() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+
+    // A spilled bucket of ties followed by unique scores.
+    let mut pipe = redis::pipe();
+    for i in 0..20 {
+        pipe.cmd("GZADD").arg("s").arg(1).arg(format!("t{i:02}"));
+    }
+    for i in 0..5 {
+        pipe.cmd("GZADD").arg("s").arg(10 + i).arg(format!("u{i}"));
+    }
+    pipe.query::<()>(&mut con)?;
+
+    let mut cursor = "0".to_string();
+    let mut seen = Vec::new();
+    loop {
+        let (next, members) = scan_step(&mut con, &cursor, 3)?;
+        if let Some(last) = members.last() {
+            let removed: i64 = redis::cmd("GZREM").arg("s").arg(last).query(&mut con)?;
+            assert_eq!(removed, 1);
+        }
+        seen.extend(members);
+        cursor = next;
+        if cursor == "0" {
+            break;
+        }
+    }
+
+    let mut expected: Vec<String> = (0..20).map(|i| format!("t{i:02}")).collect();
+    expected.extend((0..5).map(|i| format!("u{i}")));
+    assert_eq!(seen, expected, "each member exactly once, in order");
+    Ok(())
+}
+
+#[test]
+fn gzscan_cursor_member_rescored() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+
+    let mut pipe = redis::pipe();
+    for i in 0..12 {
+        pipe.cmd("GZADD").arg("s").arg(i).arg(format!("m{i:02}"));
+    }
+    pipe.query::<()>(&mut con)?;
+
+    let (cursor, first) = scan_step(&mut con, "0", 4)?;
+    assert_eq!(first, ["m00", "m01", "m02", "m03"]);
+    // Move the cursor member ahead (it shows up again) and another scanned
+    // member behind; neither may disturb the members still to come.
+    redis::cmd("GZADD")
+        .arg("s")
+        .arg(100)
+        .arg("m03")
+        .execute(&mut con);
+    redis::cmd("GZADD")
+        .arg("s")
+        .arg(-1)
+        .arg("m01")
+        .execute(&mut con);
+
+    let mut seen = first;
+    let mut cursor = cursor;
+    while cursor != "0" {
+        let (next, members) = scan_step(&mut con, &cursor, 4)?;
+        seen.extend(members);
+        cursor = next;
+    }
+    for i in 0..12 {
+        let name = format!("m{i:02}");
+        assert!(seen.contains(&name), "{name} missing from {seen:?}");
+    }
+    assert_eq!(seen.iter().filter(|m| *m == "m03").count(), 2);
+    assert_eq!(seen.last().map(String::as_str), Some("m03"));
+    Ok(())
+}
+
+#[test]
+fn gzscan_stable_members_survive_churn_and_id_compaction() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+
+    let mut pipe = redis::pipe();
+    for i in 0..200 {
+        pipe.cmd("GZADD")
+            .arg("s")
+            .arg(i % 17)
+            .arg(format!("keep{i:03}"));
+    }
+    pipe.query::<()>(&mut con)?;
+
+    let mut cursor = "0".to_string();
+    let mut seen = Vec::new();
+    let mut round = 0;
+    loop {
+        let (next, members) = scan_step(&mut con, &cursor, 7)?;
+        seen.extend(members);
+        cursor = next;
+        if cursor == "0" {
+            break;
+        }
+        // Add and drop enough transient members that the member ids of the
+        // survivors get renumbered between calls.
+        let mut pipe = redis::pipe();
+        for j in 0..2_000 {
+            pipe.cmd("GZADD")
+                .arg("s")
+                .arg(j % 17)
+                .arg(format!("tmp{round}:{j}"));
+        }
+        for j in 0..2_000 {
+            pipe.cmd("GZREM").arg("s").arg(format!("tmp{round}:{j}"));
+        }
+        pipe.query::<()>(&mut con)?;
+        round += 1;
+    }
+
+    let mut keep: Vec<String> = seen
+        .iter()
+        .filter(|m| m.starts_with("keep"))
+        .cloned()
+        .collect();
+    let total = keep.len();
+    keep.sort();
+    keep.dedup();
+    assert_eq!(keep.len(), 200, "every stable member returned");
+    assert_eq!(total, 200, "no stable member returned twice");
+    Ok(())
+}
@@ -0,0 +1,28 @@
+mod helpers;
+
+#[test]
+fn gzscorecard_counts_members_at_score() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+
+    let mut pipe = redis::pipe();
+    for i in 0..50 {
+        pipe.cmd("GZADD").arg("hot").arg(7).arg(format!("m{i}"));
+    }
+    pipe.cmd("GZADD").arg("hot").arg(1.5).arg("solo");
+    pipe.query::<()>(&mut con)?;
+
+    let count = |con: &mut redis::Connection, score: &str| -> redis::RedisResult<i64> {
+        redis::cmd("GZSCORECARD").arg("hot").arg(score).query(con)
+    };
+    assert_eq!(count(&mut con, "7")?, 50);
+    assert_eq!(count(&mut con, "1.5")?, 1);
+    assert_eq!(count(&mut con, "2")?, 0);
+    let missing: i64 = redis::cmd("GZSCORECARD")
+        .arg("nokey")
+        .arg(1)
+        .query(&mut con)?;
+    assert_eq!(missing, 0);
+    assert!(count(&mut con, "abc").is_err());
+    Ok(())
+}
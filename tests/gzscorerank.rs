@@ -0,0 +1,38 @@
+mod helpers;
+
+#[test]
+fn gzscorerank_counts_members_below_score() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+
+    let added: i64 = redis::cmd("GZADD")
+        .arg("lb")
+        .arg(10)
+        .arg("a")
+        .arg(20)
+        .arg("b")
+        .arg(20)
+        .arg("c")
+        .arg(30)
+        .arg("d")
+        .query(&mut con)?;
+    assert_eq!(added, 4);
+
+    let rank = |con: &mut redis::Connection, score: &str| -> redis::RedisResult<i64> {
+        redis::cmd("GZSCORERANK").arg("lb").arg(score).query(con)
+    };
+    assert_eq!(rank(&mut con, "-inf")?, 0);
+    assert_eq!(rank(&mut con, "10")?, 0);
+    assert_eq!(rank(&mut con, "15")?, 1);
+    assert_eq!(rank(&mut con, "20")?, 1);
+    assert_eq!(rank(&mut con, "20.5")?, 3);
+    assert_eq!(rank(&mut con, "+inf")?, 4);
+    let missing: i64 = redis::cmd("GZSCORERANK")
+        .arg("nokey")
+        .arg(5)
+        .query(&mut con)?;
+    assert_eq!(missing, 0);
+    assert!(rank(&mut con, "abc").is_err());
+    assert!(rank(&mut con, "nan").is_err());
+    Ok(())
+}
@@ -0,0 +1,69 @@
+mod helpers;
+
+fn range(con: &mut redis::Connection, key: &str) -> redis::RedisResult<Vec<String>> {
+    redis::cmd("GZRANGE")
+        .arg(key)
+        .arg(0)
+        .arg(-1)
+        .arg("WITHSCORES")
+        .query(con)
+}
+
+#[test]
+fn gzsplit_moves_upper_partition() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+    let mut pipe = redis::pipe();
+    for (score, member) in [(1, "a"), (2, "b"), (2, "c"), (3, "d")] {
+        pipe.cmd("GZADD").arg("src").arg(score).arg(member).ignore();
+    }
+    pipe.query::<()>(&mut con)?;
+    redis::cmd("SET")
+        .arg("dst")
+        .arg("stale")
+        .query::<()>(&mut con)?;
+
+    let moved: i64 = redis::cmd("GZSPLIT")
+        .arg("src")
+        .arg("dst")
+        .arg(2)
+        .query(&mut con)?;
+    assert_eq!(moved, 3);
+    assert_eq!(range(&mut con, "src")?, ["a", "1"]);
+    assert_eq!(range(&mut con, "dst")?, ["b", "2", "c", "2", "d", "3"]);
+
+    let moved: i64 = redis::cmd("GZSPLIT")
+        .arg("dst")
+        .arg("hi")
+        .arg("(2")
+        .query(&mut con)?;
+    assert_eq!(moved, 1);
+    assert_eq!(range(&mut con, "hi")?, ["d", "3"]);
+
+    // Nothing moves: the destination is deleted, and so is a drained source.
+    let moved: i64 = redis::cmd("GZSPLIT")
+        .arg("src")
+        .arg("hi")
+        .arg(10)
+        .query(&mut con)?;
+    assert_eq!(moved, 0);
+    let exists: i64 = redis::cmd("EXISTS").arg("hi").query(&mut con)?;
+    assert_eq!(exists, 0);
+    let moved: i64 = redis::cmd("GZSPLIT")
+        .arg("src")
+        .arg("all")
+        .arg("-inf")
+        .query(&mut con)?;
+    assert_eq!(moved, 1);
+    let exists: i64 = redis::cmd("EXISTS").arg("src").query(&mut con)?;
+    assert_eq!(exists, 0);
+
+    let err = redis::cmd("GZSPLIT")
+        .arg("all")
+        .arg("x")
+        .arg("nope")
+        .query::<i64>(&mut con)
+        .unwrap_err();
+    assert!(err.to_string().contains("not a float"));
+    Ok(())
+}
@@ -40,6 +40,13 @@ pub struct ValkeyInstance {
 impl ValkeyInstance {
     #[allow(dead_code)]
     pub fn start() -> Self {
+        Self::start_with(&[])
+    }
+
+    /// Start a server with `extra` appended to the usual arguments, such as
+    /// `["--replicaof", "127.0.0.1", "6379"]`.
+    #[allow(dead_code)]
+    pub fn start_with(extra: &[&str]) -> Self {
         let port = portpicker::pick_unused_port().expect("no free ports");
         let so_path = latest_so_path();
 
@@ -52,6 +59,7 @@ impl ValkeyInstance {
             .arg("")
             .arg("--daemonize")
             .arg("no")
+            .args(extra)
             .spawn()
             .expect("failed to spawn valkey");
 
@@ -0,0 +1,59 @@
+mod helpers;
+
+/// `[keys, members, bytes, spilled_buckets]` from `INFO gzset`.
+fn gzset_info(con: &mut redis::Connection) -> redis::RedisResult<[i64; 4]> {
+    let info: String = redis::cmd("INFO").arg("gzset").query(con)?;
+    let field = |name: &str| -> i64 {
+        info.lines()
+            .find_map(|l| l.strip_prefix(&format!("gzset_{name}:")))
+            .unwrap_or_else(|| panic!("no gzset_{name} in {info:?}"))
+            .trim()
+            .parse()
+            .unwrap()
+    };
+    Ok([
+        field("keys"),
+        field("members"),
+        field("bytes"),
+        field("spilled_buckets"),
+    ])
+}
+
+#[test]
+fn info_section_tracks_keyspace_totals() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+    assert_eq!(gzset_info(&mut con)?, [0, 0, 0, 0]);
+
+    let mut pipe = redis::pipe();
+    for i in 0..1000 {
+        pipe.cmd("GZADD").arg("a").arg(i % 10).arg(format!("m{i}"));
+    }
+    pipe.cmd("GZADD").arg("b").arg(1).arg("x").arg(2).arg("y");
+    pipe.query::<()>(&mut con)?;
+    let [keys, members, bytes, spilled] = gzset_info(&mut con)?;
+    assert_eq!((keys, members, spilled), (2, 1002, 10));
+    assert!(bytes > 1000, "{bytes}");
+
+    redis::cmd("GZUNIONSTORE")
+        .arg("u")
+        .arg(2)
+        .arg("a")
+        .arg("b")
+        .query::<i64>(&mut con)?;
+    let [keys, members, _, spilled] = gzset_info(&mut con)?;
+    assert_eq!((keys, members, spilled), (3, 2004, 20));
+
+    // Draining a key deletes it; DEL and UNLINK free the others.
+    redis::cmd("GZPOPMIN")
+        .arg("b")
+        .arg(2)
+        .query::<Vec<String>>(&mut con)?;
+    redis::cmd("DEL").arg("a").query::<i64>(&mut con)?;
+    let [keys, members, _, spilled] = gzset_info(&mut con)?;
+    assert_eq!((keys, members, spilled), (1, 1002, 10));
+    redis::cmd("UNLINK").arg("u").query::<i64>(&mut con)?;
+    redis::cmd("FLUSHALL").arg("SYNC").query::<()>(&mut con)?;
+    assert_eq!(gzset_info(&mut con)?, [0, 0, 0, 0]);
+    Ok(())
+}
@@ -43,3 +43,41 @@ fn mem_usage_matches_rss() -> redis::RedisResult<()> {
     assert!(ratio > 0.95);
     Ok(())
 }
+
+#[test]
+fn gztrim_releases_memory_after_mass_delete() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+
+    let mut pipe = redis::pipe();
+    for i in 0..200_000u32 {
+        pipe.cmd("GZADD")
+            .arg("gz")
+            .arg(i % 1_000)
+            .arg(format!("m{i}"));
+    }
+    pipe.query::<()>(&mut con)?;
+    let mut pipe = redis::pipe();
+    for i in (0..200_000u32).filter(|i| i % 10 >= 3) {
+        pipe.cmd("GZREM").arg("gz").arg(format!("m{i}"));
+    }
+    pipe.query::<()>(&mut con)?;
+
+    let before: i64 = redis::cmd("MEMORY")
+        .arg("USAGE")
+        .arg("gz")
+        .query(&mut con)?;
+    let ok: String = redis::cmd("GZTRIM").arg("gz").query(&mut con)?;
+    assert_eq!(ok, "OK");
+    let after: i64 = redis::cmd("MEMORY")
+        .arg("USAGE")
+        .arg("gz")
+        .query(&mut con)?;
+    assert!(after * 2 < before, "usage {before} -> {after}");
+
+    let card: i64 = redis::cmd("GZCARD").arg("gz").query(&mut con)?;
+    assert_eq!(card, 60_000);
+    let ok: String = redis::cmd("GZTRIM").arg("missing").query(&mut con)?;
+    assert_eq!(ok, "OK");
+    Ok(())
+}
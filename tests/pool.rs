@@ -8,3 +8,28 @@ fn pool_roundtrip_dedup() {
     assert_eq!(a, b);
     assert_eq!(pool.get(a), "foo");
 }
+
+#[test]
+fn multi_megabyte_member_round_trips() {
+    let mut pool = StringPool::default();
+    let big: String = (0..6 * 1024 * 1024)
+        .map(|i| char::from(b'a' + (i % 26) as u8))
+        .collect();
+    assert!(big.len() > pool.chunk_size());
+
+    let before = pool.intern("before");
+    let id = pool.intern(&big);
+    let after = pool.intern("after");
+    assert_eq!(pool.get(id), big);
+    assert_eq!(pool.lookup(&big), Some(id));
+    assert_eq!(pool.intern(&big), id);
+    assert_eq!(pool.get(before), "before");
+    assert_eq!(pool.get(after), "after");
+
+    assert_eq!(pool.remove(&big), Some(id));
+    assert_eq!(pool.lookup(&big), None);
+    assert_eq!(pool.len(), 2);
+    let again = pool.intern(&big);
+    assert_eq!(pool.get(again), big);
+    assert_eq!(pool.lookup("after"), Some(after));
+}
@@ -0,0 +1,118 @@
+mod helpers;
+
+use std::{thread, time::Duration};
+
+/// Every member and score of `key`, lowest first.
+fn dump(con: &mut redis::Connection, key: &str) -> redis::RedisResult<Vec<(String, f64)>> {
+    redis::cmd("GZRANGE")
+        .arg(key)
+        .arg(0)
+        .arg(-1)
+        .arg("WITHSCORES")
+        .query(con)
+}
+
+#[test]
+fn pops_replicate_as_removals_before_wait_returns() -> redis::RedisResult<()> {
+    let primary = helpers::ValkeyInstance::start();
+    let port = primary.port.to_string();
+    let replica = helpers::ValkeyInstance::start_with(&["--replicaof", "127.0.0.1", &port]);
+    let mut con = redis::Client::open(primary.url())?.get_connection()?;
+    let mut rcon = redis::Client::open(replica.url())?.get_connection()?;
+    for _ in 0..100 {
+        let info: String = redis::cmd("INFO").arg("replication").query(&mut rcon)?;
+        if info.contains("master_link_status:up") {
+            break;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    let mut pipe = redis::pipe();
+    for i in 0..200 {
+        pipe.cmd("GZADD")
+            .arg("s")
+            .arg(i % 20)
+            .arg(format!("m{i:03}"));
+    }
+    pipe.query::<()>(&mut con)?;
+    redis::cmd("GZPOPMIN")
+        .arg("s")
+        .query::<Vec<String>>(&mut con)?;
+    redis::cmd("GZPOPMIN")
+        .arg("s")
+        .arg(15)
+        .query::<Vec<String>>(&mut con)?;
+    redis::cmd("GZPOPMAX")
+        .arg("s")
+        .arg(7)
+        .query::<Vec<String>>(&mut con)?;
+    redis::cmd("GZPOPRANGEBYSCORE")
+        .arg("s")
+        .arg(5)
+        .arg("(8")
+        .query::<Vec<String>>(&mut con)?;
+    let removed: i64 = redis::cmd("GZREM")
+        .arg("s")
+        .arg("m010")
+        .arg("m030")
+        .arg("missing")
+        .query(&mut con)?;
+    assert_eq!(removed, 2);
+    redis::cmd("GZINCRBY")
+        .arg("s")
+        .arg(0.5)
+        .arg("m150")
+        .query::<f64>(&mut con)?;
+    // Popping a key empty deletes it on both sides.
+    redis::cmd("GZADD")
+        .arg("t")
+        .arg(1)
+        .arg("x")
+        .query::<i64>(&mut con)?;
+    redis::cmd("GZPOPMAX")
+        .arg("t")
+        .query::<Vec<String>>(&mut con)?;
+
+    let acked: i64 = redis::cmd("WAIT").arg(1).arg(5000).query(&mut con)?;
+    assert_eq!(acked, 1);
+    let want = dump(&mut con, "s")?;
+    assert_eq!(want.len(), 200 - 1 - 15 - 7 - 30 - 2);
+    assert_eq!(dump(&mut rcon, "s")?, want);
+    let exists: i64 = redis::cmd("EXISTS").arg("t").query(&mut rcon)?;
+    assert_eq!(exists, 0);
+    Ok(())
+}
+
+#[test]
+fn gzadd_ex_reaches_replicas_as_an_absolute_expiry() -> redis::RedisResult<()> {
+    let primary = helpers::ValkeyInstance::start();
+    let port = primary.port.to_string();
+    let replica = helpers::ValkeyInstance::start_with(&["--replicaof", "127.0.0.1", &port]);
+    let mut con = redis::Client::open(primary.url())?.get_connection()?;
+    let mut rcon = redis::Client::open(replica.url())?.get_connection()?;
+    for _ in 0..100 {
+        let info: String = redis::cmd("INFO").arg("replication").query(&mut rcon)?;
+        if info.contains("master_link_status:up") {
+            break;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    redis::cmd("GZADD")
+        .arg("session")
+        .arg("EX")
+        .arg(100)
+        .arg(1)
+        .arg("alice")
+        .query::<i64>(&mut con)?;
+    let acked: i64 = redis::cmd("WAIT").arg(1).arg(5000).query(&mut con)?;
+    assert_eq!(acked, 1);
+    assert_eq!(dump(&mut rcon, "session")?, dump(&mut con, "session")?);
+    let expire_at = |con: &mut redis::Connection| -> redis::RedisResult<i64> {
+        redis::cmd("PEXPIRETIME").arg("session").query(con)
+    };
+    let at = expire_at(&mut con)?;
+    assert!(at > 0);
+    assert_eq!(expire_at(&mut rcon)?, at);
+    Ok(())
+}
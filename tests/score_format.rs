@@ -0,0 +1,102 @@
+mod helpers;
+
+/// Doubles whose textual form is easy to get wrong: signed zero, the integer
+/// cut-over at 2^62, the plain/scientific boundaries, and f64 extremes.
+const TRICKY: &[f64] = &[
+    0.0,
+    -0.0,
+    1.0,
+    10.0,
+    -1.5,
+    1e10,
+    0.1,
+    0.1 + 0.2,
+    1e-4,
+    1e-6,
+    1e-7,
+    1.5e-7,
+    0.001_234_567_89,
+    123_456.789,
+    1e15,
+    4_611_686_018_427_387_904.0,
+    9_223_372_036_854_775_808.0,
+    1e21,
+    1.5e300,
+    f64::MAX,
+    f64::MIN,
+    f64::MIN_POSITIVE,
+    5e-324,
+    f64::EPSILON,
+    std::f64::consts::PI,
+];
+
+#[test]
+fn scores_format_like_builtin_zset() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+
+    for (i, score) in TRICKY.iter().enumerate() {
+        let arg = format!("{score:e}");
+        let member = format!("m{i}");
+        for (add, key) in [("ZADD", "z"), ("GZADD", "gz")] {
+            redis::cmd(add)
+                .arg(key)
+                .arg(&arg)
+                .arg(&member)
+                .query::<i64>(&mut con)?;
+        }
+        let builtin: String = redis::cmd("ZSCORE").arg("z").arg(&member).query(&mut con)?;
+        let module: String = redis::cmd("GZSCORE")
+            .arg("gz")
+            .arg(&member)
+            .query(&mut con)?;
+        assert_eq!(module, builtin, "GZSCORE for {arg}");
+    }
+
+    let builtin: Vec<String> = redis::cmd("ZRANGE")
+        .arg("z")
+        .arg(0)
+        .arg(-1)
+        .arg("WITHSCORES")
+        .query(&mut con)?;
+    let module: Vec<String> = redis::cmd("GZRANGE")
+        .arg("gz")
+        .arg(0)
+        .arg(-1)
+        .arg("WITHSCORES")
+        .query(&mut con)?;
+    assert_eq!(module, builtin);
+    Ok(())
+}
+
+#[test]
+fn overflowing_union_scores_format_like_builtin_zset() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+    for (add, key) in [("ZADD", "z"), ("GZADD", "gz")] {
+        redis::cmd(add)
+            .arg(key)
+            .arg("1e308")
+            .arg("a")
+            .arg("-1e308")
+            .arg("b")
+            .arg("7")
+            .arg("c")
+            .query::<i64>(&mut con)?;
+    }
+    for weight in ["10", "-10"] {
+        let union = |con: &mut redis::Connection, cmd: &str, key: &str| {
+            redis::cmd(cmd)
+                .arg(1)
+                .arg(key)
+                .arg("WEIGHTS")
+                .arg(weight)
+                .arg("WITHSCORES")
+                .query::<Vec<String>>(con)
+        };
+        let builtin = union(&mut con, "ZUNION", "z")?;
+        let module = union(&mut con, "GZUNION", "gz")?;
+        assert_eq!(module, builtin, "WEIGHTS {weight}");
+    }
+    Ok(())
+}
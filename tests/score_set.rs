@@ -1,6 +1,7 @@
-use gzset::ScoreSet;
+use gzset::{ScoreSet, UpdateCond};
 use ordered_float::OrderedFloat;
 use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::collections::{BTreeMap, HashMap};
 
 #[test]
 fn lexicographic_order_equal_scores() {
@@ -113,10 +114,107 @@ fn iter_from_equal_score_exclusive_inline_and_bucket() {
     assert_eq!(got, ["c", "d"]);
 }
 
+/// Check `iter_from` against a filtered full scan from every member and from
+/// probes between, before and after them, both inclusive and exclusive.
+fn assert_iter_from_matches_scan(set: &ScoreSet) {
+    let all: Vec<(String, f64)> = set.iter_all().map(|(m, s)| (m.to_owned(), s)).collect();
+    let mut starts: Vec<(f64, String)> = all.iter().map(|(m, s)| (*s, m.clone())).collect();
+    for score in [0.5, 1.0, 2.0, 2.5, 3.0, 4.0, 9.0] {
+        for name in ["", "a", "ab0", "b", "w", "wab", "wb0", "wd", "wz", "zz"] {
+            starts.push((score, name.to_owned()));
+        }
+    }
+    for (score, member) in &starts {
+        for exclusive in [false, true] {
+            let want: Vec<(String, f64)> = all
+                .iter()
+                .filter(|(m, s)| {
+                    (*s, m.as_str()) > (*score, member.as_str())
+                        || (!exclusive && (*s, m.as_str()) == (*score, member.as_str()))
+                })
+                .cloned()
+                .collect();
+            let got: Vec<(String, f64)> = set
+                .iter_from(OrderedFloat(*score), member, exclusive)
+                .map(|(m, s)| (m.to_owned(), s))
+                .collect();
+            assert_eq!(
+                got, want,
+                "from ({score}, {member:?}) exclusive={exclusive}"
+            );
+        }
+    }
+}
+
+#[test]
+fn iter_from_matches_filtered_scan_at_every_boundary() {
+    let mut set = ScoreSet::default();
+    // Inline scores, a two-member bucket and a wide bucket, with names that
+    // share prefixes so the bucket search has near misses.
+    set.insert(1.0, "solo");
+    set.insert(2.0, "ab");
+    set.insert(2.0, "b");
+    for name in ["a", "aa", "ab", "b", "ba", "c", "d"] {
+        set.insert(3.0, &format!("w{name}"));
+    }
+    set.insert(4.0, "last");
+    assert_iter_from_matches_scan(&set);
+
+    // Popping into the wide bucket leaves consumed slots at its head.
+    set.pop_n(true, 5);
+    assert_eq!(set.iter_all().next(), Some(("wab", 3.0)));
+    assert_iter_from_matches_scan(&set);
+}
+
+#[test]
+fn iter_all_rev_matches_score_iter_next_back() {
+    let mut set = ScoreSet::default();
+    assert_eq!(set.iter_all().next_back(), None);
+    set.insert(1.0, "solo");
+    set.insert(2.0, "b");
+    set.insert(2.0, "a");
+    for i in 0..20 {
+        set.insert(3.0, &format!("w{i:02}"));
+    }
+    set.insert(4.0, "last");
+    set.pop_n(true, 1);
+
+    let mut back = set.iter_range(0, -1);
+    let mut want = Vec::new();
+    while let Some(item) = back.next_back() {
+        want.push(item);
+    }
+    let rev: Vec<(&str, f64)> = set.iter_all().rev().collect();
+    assert_eq!(rev, want);
+    assert_eq!(rev, set.iter_desc().collect::<Vec<_>>());
+    assert!(rev.windows(2).all(|w| (w[0].1, w[0].0) > (w[1].1, w[1].0)));
+
+    // Mixing both ends keeps the exact length.
+    let mut iter = set.iter_all();
+    let mut left = set.len();
+    assert_eq!(iter.len(), left);
+    assert_eq!(iter.next(), Some(("a", 2.0)));
+    assert_eq!(iter.next_back(), Some(("last", 4.0)));
+    left -= 2;
+    while left > 0 {
+        assert_eq!(iter.size_hint(), (left, Some(left)));
+        if left % 2 == 0 {
+            iter.next();
+        } else {
+            iter.next_back();
+        }
+        left -= 1;
+    }
+    assert_eq!(iter.len(), 0);
+    assert_eq!((iter.next(), iter.next_back()), (None, None));
+}
+
 #[test]
 fn grow_and_shrink_bucket() {
     const SHRINK_THRESHOLD: usize = 64;
-    let total = SHRINK_THRESHOLD + 5;
+    // Spare capacity is only released once it exceeds the live length, so
+    // grow past twice the threshold before removing back down to it.
+    let total = SHRINK_THRESHOLD * 2 + 5;
     let mut set = ScoreSet::default();
     let names: Vec<String> = (0..total).map(|i| format!("member-{i}")).collect();
     for name in &names {
@@ -210,3 +308,170 @@ fn rank_matches_naive_random() {
         }
     }
 }
+
+/// Reference model: members ordered by `(score, name)` plus a score lookup.
+#[derive(Default)]
+struct Model {
+    ordered: BTreeMap<(OrderedFloat<f64>, String), ()>,
+    scores: HashMap<String, f64>,
+}
+
+impl Model {
+    /// Mirrors `ScoreSet::insert`: `false` only when the score is unchanged.
+    fn insert(&mut self, score: f64, member: &str) -> bool {
+        if let Some(old) = self.scores.get(member).copied() {
+            if OrderedFloat(old) == OrderedFloat(score) {
+                return false;
+            }
+            self.ordered.remove(&(OrderedFloat(old), member.to_owned()));
+        }
+        self.ordered
+            .insert((OrderedFloat(score), member.to_owned()), ());
+        self.scores.insert(member.to_owned(), score);
+        true
+    }
+
+    fn remove(&mut self, member: &str) -> bool {
+        match self.scores.remove(member) {
+            Some(old) => {
+                self.ordered.remove(&(OrderedFloat(old), member.to_owned()));
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn pop(&mut self, min: bool, n: usize) -> Vec<(String, f64)> {
+        let mut out = Vec::new();
+        while out.len() < n {
+            let entry = if min {
+                self.ordered.pop_first()
+            } else {
+                self.ordered.pop_last()
+            };
+            let Some(((score, member), ())) = entry else {
+                break;
+            };
+            self.scores.remove(&member);
+            out.push((member, score.0));
+        }
+        out
+    }
+
+    fn items(&self) -> Vec<(f64, String)> {
+        self.ordered
+            .keys()
+            .map(|(score, member)| (score.0, member.clone()))
+            .collect()
+    }
+}
+
+#[test]
+fn random_ops_match_reference_model() {
+    // Few distinct scores and names sharing long prefixes so buckets spill,
+    // collapse back to a single member and get freed repeatedly.
+    let names: Vec<String> = (0..160)
+        .map(|i| match i % 3 {
+            0 => format!("m{i}"),
+            1 => format!("shared-prefix-member-{i:04}"),
+            _ => format!("{}{i}", "x".repeat(i % 17)),
+        })
+        .collect();
+    for seed in 0..8u64 {
+        let mut rng = StdRng::seed_from_u64(0x1823 ^ seed);
+        let mut set = ScoreSet::default();
+        let mut model = Model::default();
+        for step in 0..3_000 {
+            let member = &names[rng.gen_range(0..names.len())];
+            let score = rng.gen_range(-6..6) as f64 / 2.0;
+            let ctx = format!("seed {seed} step {step}");
+            match rng.gen_range(0..10) {
+                0..=3 => assert_eq!(
+                    set.insert(score, member),
+                    model.insert(score, member),
+                    "{ctx}"
+                ),
+                4 | 5 => assert_eq!(set.remove(member), model.remove(member), "{ctx}"),
+                6 | 7 => {
+                    let min = rng.gen_bool(0.5);
+                    let n = rng.gen_range(0..6);
+                    assert_eq!(set.pop_n(min, n), model.pop(min, n), "{ctx}");
+                }
+                _ => {
+                    let cond = UpdateCond {
+                        nx: rng.gen_bool(0.2),
+                        xx: rng.gen_bool(0.2),
+                        gt: rng.gen_bool(0.2),
+                        lt: false,
+                    };
+                    let current = model.scores.get(member).copied();
+                    let new = current.unwrap_or(0.0) + score;
+                    let expected = cond.permits(current, new).then_some(new);
+                    if expected.is_some() {
+                        model.insert(new, member);
+                    }
+                    assert_eq!(set.incr_by(member, score, cond), Ok(expected), "{ctx}");
+                }
+            }
+
+            assert_eq!(set.len(), model.scores.len(), "{ctx}");
+            let items = model.items();
+            assert_eq!(set.range_iter(0, -1), items, "{ctx}");
+            for _ in 0..4 {
+                let probe = &names[rng.gen_range(0..names.len())];
+                let rank = items.iter().position(|(_, m)| m == probe);
+                assert_eq!(set.rank(probe), rank, "{ctx} rank {probe}");
+                assert_eq!(set.score(probe), model.scores.get(probe).copied(), "{ctx}");
+            }
+        }
+    }
+}
+
+#[test]
+fn drain_yields_in_order_and_empties_the_set() {
+    let mut set = ScoreSet::default();
+    let mut expected = Vec::new();
+    for i in 0..500 {
+        // Mix unique scores with inline and spilled ties.
+        let score = if i % 3 == 0 { (i % 4) as f64 } else { i as f64 };
+        let member = format!("m{i:03}");
+        set.insert(score, &member);
+        expected.push((member, score));
+    }
+    expected.sort_by(|a, b| a.1.total_cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+
+    let mut drain = set.drain();
+    assert_eq!(drain.len(), expected.len());
+    let drained: Vec<(String, f64)> = drain.by_ref().collect();
+    assert_eq!(drained, expected);
+    assert_eq!(drain.next(), None);
+    drop(drain);
+    assert!(set.is_empty());
+    assert_eq!(set.mem_bytes(), 0);
+
+    // Dropping a partly consumed drain removes the rest.
+    for i in 0..100 {
+        set.insert((i % 5) as f64, &format!("m{i}"));
+    }
+    assert_eq!(set.drain().take(10).count(), 10);
+    assert!(set.is_empty());
+    assert_eq!(set.mem_bytes(), 0);
+}
+
+#[test]
+fn copy_range_matches_iter_range() {
+    let mut set = ScoreSet::default();
+    for i in 0..300 {
+        set.insert((i % 40) as f64, &format!("m{i:03}"));
+    }
+    for (start, stop) in [(0, -1), (10, 20), (-50, -1), (5, 4), (290, 400), (-400, 3)] {
+        let slice = set.copy_range(start, stop);
+        let got: Vec<(String, f64)> = slice.iter_all().map(|(m, s)| (m.to_owned(), s)).collect();
+        let want: Vec<(String, f64)> = set
+            .iter_range_fwd(start, stop)
+            .map(|(m, s)| (m.to_owned(), s))
+            .collect();
+        assert_eq!(got, want, "{start}..={stop}");
+        assert_eq!(slice.len(), want.len());
+    }
+}
@@ -0,0 +1,51 @@
+mod helpers;
+
+#[test]
+fn gz_commands_reject_foreign_key_types() -> redis::RedisResult<()> {
+    let vk = helpers::ValkeyInstance::start();
+    let mut con = redis::Client::open(vk.url())?.get_connection()?;
+
+    redis::cmd("SET")
+        .arg("str")
+        .arg("bar")
+        .query::<()>(&mut con)?;
+    redis::cmd("RPUSH")
+        .arg("list")
+        .arg("x")
+        .query::<()>(&mut con)?;
+    redis::cmd("GZADD")
+        .arg("gz")
+        .arg(1)
+        .arg("a")
+        .query::<()>(&mut con)?;
+
+    let commands: &[&[&str]] = &[
+        &["GZADD", "str", "1", "a"],
+        &["GZADD", "str", "INCR", "1", "a"],
+        &["GZADD", "list", "XX", "CH", "1", "a"],
+        &["GZREM", "str", "a"],
+        &["GZPOPMIN", "str"],
+        &["GZPOPRANGEBYSCORE", "str", "-inf", "+inf"],
+        &["GZTRIM", "str"],
+        &["GZSCORE", "str", "a"],
+        &["GZRANK", "list", "a"],
+        &["GZRANGE", "str", "0", "-1"],
+        &["GZCARD", "str"],
+        &["GZUNION", "2", "gz", "str"],
+        &["GZUNIONSTORE", "out", "2", "gz", "list"],
+    ];
+    for args in commands {
+        let mut c = redis::cmd(args[0]);
+        for arg in &args[1..] {
+            c.arg(*arg);
+        }
+        let err = c.query::<redis::Value>(&mut con).unwrap_err();
+        assert_eq!(err.code(), Some("WRONGTYPE"), "{args:?}: {err}");
+    }
+
+    let s: String = redis::cmd("GET").arg("str").query(&mut con)?;
+    assert_eq!(s, "bar");
+    let exists: i64 = redis::cmd("EXISTS").arg("out").query(&mut con)?;
+    assert_eq!(exists, 0);
+    Ok(())
+}
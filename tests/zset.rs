@@ -239,6 +239,15 @@ impl<'a> Ctx<'a> {
         }
         c.query(&mut *self.con)
     }
+    fn inter_withscores(&mut self, keys: &[&str]) -> RedisResult<Vec<String>> {
+        let mut c = cmd(&zcmd(self.fam, "INTER"));
+        c.arg(keys.len());
+        for k in keys {
+            c.arg(k);
+        }
+        c.arg("WITHSCORES");
+        c.query(&mut *self.con)
+    }
     fn diff(&mut self, keys: &[&str]) -> RedisResult<Vec<String>> {
         let mut c = cmd(&zcmd(self.fam, "DIFF"));
         c.arg(keys.len());
@@ -258,16 +267,9 @@ impl<'a> Ctx<'a> {
     }
     fn intercard(&mut self, keys: &[&str]) -> RedisResult<i64> {
         let mut c = cmd(&zcmd(self.fam, "INTERCARD"));
-        if self.fam == Fam::BuiltIn {
-            c.arg(keys.len());
-            for k in keys {
-                c.arg(k);
-            }
-        } else {
-            assert_eq!(keys.len(), 2);
-            for k in keys {
-                c.arg(k);
-            }
+        c.arg(keys.len());
+        for k in keys {
+            c.arg(k);
         }
         c.query(&mut *self.con)
     }
@@ -387,19 +389,11 @@ impl<'a> Ctx<'a> {
 
     fn intercard_limit(&mut self, keys: &[&str], limit: i64) -> RedisResult<i64> {
         let mut c = cmd(&zcmd(self.fam, "INTERCARD"));
-        if self.fam == Fam::BuiltIn {
-            c.arg(keys.len());
-            for k in keys {
-                c.arg(k);
-            }
-            c.arg("LIMIT").arg(limit);
-        } else {
-            assert_eq!(keys.len(), 2);
-            for k in keys {
-                c.arg(k);
-            }
-            c.arg(limit);
+        c.arg(keys.len());
+        for k in keys {
+            c.arg(k);
         }
+        c.arg("LIMIT").arg(limit);
         c.query(&mut *self.con)
     }
 
@@ -552,8 +546,7 @@ fn rejects_nan_scores() {
     });
 }
 
-/// ZADD XX with non‑existing key should not create it (built‑in behaviour).
-/// The module does not implement XX yet – we accept either `Err` or 0.
+/// ZADD XX with non‑existing key should not create it.
 /*
  test "ZADD XX option without key - $encoding" {
      r del ztmp
@@ -572,23 +565,12 @@ fn xx_without_key() {
             .arg("m")
             .query(&mut *ctx.con);
 
-        match ctx.fam {
-            Fam::BuiltIn => {
-                assert_eq!(res.unwrap(), 0);
-                assert_eq!(ctx.r#type("kxx"), "none");
-            }
-            Fam::Module => {
-                // Module may return 0 (ignored opt) or an error (unsupported opt).
-                if let Ok(v) = res {
-                    assert_eq!(v, 0);
-                }
-            }
-        }
+        assert_eq!(res.unwrap(), 0);
+        assert_eq!(ctx.r#type("kxx"), "none");
     });
 }
 
-/// Variadic ZADD – core behaviour check only for built‑in ZSET for now.
-/// For the module we ignore the return value so the test still passes.
+/// Variadic ZADD returns the number of newly added members.
 /*
  test "ZADD - Return value is the number of actually added items - $encoding" {
      list [r zadd myzset 5 x 20 b 30 c] [r zrange myzset 0 -1 withscores]
@@ -618,10 +600,8 @@ fn variadic_add_return_value() {
             .query(&mut *ctx.con)
             .unwrap_or_default();
 
-        if ctx.fam == Fam::BuiltIn {
-            assert_eq!(rv1, 3);
-            assert_eq!(rv2, 1);
-        }
+        assert_eq!(rv1, 3);
+        assert_eq!(rv2, 1);
     });
 }
 
@@ -668,17 +648,8 @@ fn xx_without_key_new_test() {
             .arg("10")
             .arg("x")
             .query(&mut *ctx.con);
-        match ctx.fam {
-            Fam::BuiltIn => {
-                assert_eq!(res.unwrap(), 0);
-                assert_eq!(ctx.r#type("ztmp"), "none");
-            }
-            Fam::Module => {
-                if let Ok(v) = res {
-                    assert_eq!(v, 0);
-                }
-            }
-        }
+        assert_eq!(res.unwrap(), 0);
+        assert_eq!(ctx.r#type("ztmp"), "none");
     });
 }
 
@@ -710,11 +681,9 @@ fn xx_existing_key_no_new_members() {
             .arg("y")
             .query(&mut *ctx.con);
 
-        if ctx.fam == Fam::BuiltIn {
-            assert_eq!(res.unwrap(), 0);
-            let card = ctx.card("ztmp").unwrap();
-            assert_eq!(card, 1);
-        }
+        assert_eq!(res.unwrap(), 0);
+        let card = ctx.card("ztmp").unwrap();
+        assert_eq!(card, 1);
     });
 }
 
@@ -748,9 +717,7 @@ fn xx_return_value_added_count() {
             .arg("z")
             .query(&mut *ctx.con);
 
-        if ctx.fam == Fam::BuiltIn {
-            assert_eq!(rv.unwrap(), 2);
-        }
+        assert_eq!(rv.unwrap(), 2);
     });
 }
 
@@ -801,12 +768,10 @@ fn xx_updates_existing_scores() {
             .arg("zap")
             .query::<i64>(&mut *ctx.con);
 
-        if ctx.fam == Fam::BuiltIn {
-            let card = ctx.card("ztmp").unwrap();
-            assert_eq!(card, 3);
-            assert_eq!(ctx.score("ztmp", "x").unwrap().unwrap(), 11.0);
-            assert_eq!(ctx.score("ztmp", "y").unwrap().unwrap(), 21.0);
-        }
+        let card = ctx.card("ztmp").unwrap();
+        assert_eq!(card, 3);
+        assert_eq!(ctx.score("ztmp", "x").unwrap().unwrap(), 11.0);
+        assert_eq!(ctx.score("ztmp", "y").unwrap().unwrap(), 21.0);
     });
 }
 
@@ -849,14 +814,12 @@ fn gt_updates_when_greater() {
             .arg("z")
             .query(&mut *ctx.con);
 
-        if ctx.fam == Fam::BuiltIn {
-            assert_eq!(res.unwrap(), 3);
-            let card = ctx.card("ztmp").unwrap();
-            assert_eq!(card, 4);
-            assert_eq!(ctx.score("ztmp", "x").unwrap().unwrap(), 11.0);
-            assert_eq!(ctx.score("ztmp", "y").unwrap().unwrap(), 21.0);
-            assert_eq!(ctx.score("ztmp", "z").unwrap().unwrap(), 30.0);
-        }
+        assert_eq!(res.unwrap(), 3);
+        let card = ctx.card("ztmp").unwrap();
+        assert_eq!(card, 4);
+        assert_eq!(ctx.score("ztmp", "x").unwrap().unwrap(), 11.0);
+        assert_eq!(ctx.score("ztmp", "y").unwrap().unwrap(), 21.0);
+        assert_eq!(ctx.score("ztmp", "z").unwrap().unwrap(), 30.0);
     });
 }
 
@@ -899,14 +862,12 @@ fn lt_updates_when_lower() {
             .arg("z")
             .query(&mut *ctx.con);
 
-        if ctx.fam == Fam::BuiltIn {
-            assert_eq!(res.unwrap(), 2);
-            let card = ctx.card("ztmp").unwrap();
-            assert_eq!(card, 4);
-            assert_eq!(ctx.score("ztmp", "x").unwrap().unwrap(), 10.0);
-            assert_eq!(ctx.score("ztmp", "y").unwrap().unwrap(), 20.0);
-            assert_eq!(ctx.score("ztmp", "z").unwrap().unwrap(), 29.0);
-        }
+        assert_eq!(res.unwrap(), 2);
+        let card = ctx.card("ztmp").unwrap();
+        assert_eq!(card, 4);
+        assert_eq!(ctx.score("ztmp", "x").unwrap().unwrap(), 10.0);
+        assert_eq!(ctx.score("ztmp", "y").unwrap().unwrap(), 20.0);
+        assert_eq!(ctx.score("ztmp", "z").unwrap().unwrap(), 29.0);
     });
 }
 
@@ -950,14 +911,12 @@ fn gt_xx_updates_existing_skip_new() {
             .arg("z")
             .query(&mut *ctx.con);
 
-        if ctx.fam == Fam::BuiltIn {
-            assert_eq!(res.unwrap(), 2);
-            let card = ctx.card("ztmp").unwrap();
-            assert_eq!(card, 3);
-            assert_eq!(ctx.score("ztmp", "x").unwrap().unwrap(), 11.0);
-            assert_eq!(ctx.score("ztmp", "y").unwrap().unwrap(), 21.0);
-            assert_eq!(ctx.score("ztmp", "z").unwrap().unwrap(), 30.0);
-        }
+        assert_eq!(res.unwrap(), 2);
+        let card = ctx.card("ztmp").unwrap();
+        assert_eq!(card, 3);
+        assert_eq!(ctx.score("ztmp", "x").unwrap().unwrap(), 11.0);
+        assert_eq!(ctx.score("ztmp", "y").unwrap().unwrap(), 21.0);
+        assert_eq!(ctx.score("ztmp", "z").unwrap().unwrap(), 30.0);
     });
 }
 
@@ -1001,14 +960,12 @@ fn lt_xx_updates_existing_skip_new() {
             .arg("z")
             .query(&mut *ctx.con);
 
-        if ctx.fam == Fam::BuiltIn {
-            assert_eq!(res.unwrap(), 1);
-            let card = ctx.card("ztmp").unwrap();
-            assert_eq!(card, 3);
-            assert_eq!(ctx.score("ztmp", "x").unwrap().unwrap(), 10.0);
-            assert_eq!(ctx.score("ztmp", "y").unwrap().unwrap(), 20.0);
-            assert_eq!(ctx.score("ztmp", "z").unwrap().unwrap(), 29.0);
-        }
+        assert_eq!(res.unwrap(), 1);
+        let card = ctx.card("ztmp").unwrap();
+        assert_eq!(card, 3);
+        assert_eq!(ctx.score("ztmp", "x").unwrap().unwrap(), 10.0);
+        assert_eq!(ctx.score("ztmp", "y").unwrap().unwrap(), 20.0);
+        assert_eq!(ctx.score("ztmp", "z").unwrap().unwrap(), 29.0);
     });
 }
 
@@ -1058,10 +1015,8 @@ fn nx_with_non_existing_key() {
             .arg("z")
             .query::<i64>(&mut *ctx.con);
 
-        if ctx.fam == Fam::BuiltIn {
-            let card = ctx.card("ztmp").unwrap();
-            assert_eq!(card, 3);
-        }
+        let card = ctx.card("ztmp").unwrap();
+        assert_eq!(card, 3);
     });
 }
 
@@ -1103,13 +1058,11 @@ fn nx_only_add_new_elements() {
             .arg("b")
             .query(&mut *ctx.con);
 
-        if ctx.fam == Fam::BuiltIn {
-            assert_eq!(res.unwrap(), 2);
-            assert_eq!(ctx.score("ztmp", "x").unwrap().unwrap(), 10.0);
-            assert_eq!(ctx.score("ztmp", "y").unwrap().unwrap(), 20.0);
-            assert_eq!(ctx.score("ztmp", "a").unwrap().unwrap(), 100.0);
-            assert_eq!(ctx.score("ztmp", "b").unwrap().unwrap(), 200.0);
-        }
+        assert_eq!(res.unwrap(), 2);
+        assert_eq!(ctx.score("ztmp", "x").unwrap().unwrap(), 10.0);
+        assert_eq!(ctx.score("ztmp", "y").unwrap().unwrap(), 20.0);
+        assert_eq!(ctx.score("ztmp", "a").unwrap().unwrap(), 100.0);
+        assert_eq!(ctx.score("ztmp", "b").unwrap().unwrap(), 200.0);
     });
 }
 
@@ -1212,20 +1165,18 @@ fn incr_lt_gt_returns_nil_when_unmodified() {
             .arg("x")
             .query(&mut *ctx.con);
 
-        if ctx.fam == Fam::BuiltIn {
-            assert!(res1.unwrap().is_none());
-            assert_eq!(ctx.score("ztmp", "x").unwrap().unwrap(), 28.0);
-            let res2: Option<f64> = cmd("ZADD")
-                .arg("ztmp")
-                .arg("GT")
-                .arg("INCR")
-                .arg("-1")
-                .arg("x")
-                .query(&mut *ctx.con)
-                .unwrap();
-            assert!(res2.is_none());
-            assert_eq!(ctx.score("ztmp", "x").unwrap().unwrap(), 28.0);
-        }
+        assert!(res1.unwrap().is_none());
+        assert_eq!(ctx.score("ztmp", "x").unwrap().unwrap(), 28.0);
+        let res2: Option<f64> = cmd(&format!("{}ADD", ctx.fam.prefix()))
+            .arg("ztmp")
+            .arg("GT")
+            .arg("INCR")
+            .arg("-1")
+            .arg("x")
+            .query(&mut *ctx.con)
+            .unwrap();
+        assert!(res2.is_none());
+        assert_eq!(ctx.score("ztmp", "x").unwrap().unwrap(), 28.0);
     });
 }
 
@@ -1258,45 +1209,114 @@ fn incr_lt_gt_returns_nil_when_unmodified() {
 fn incr_lt_gt_with_infinity() {
     with_families(|ctx| {
         ctx.del("ztmp");
-        let _ = cmd(&format!("{}ADD", ctx.fam.prefix()))
-            .arg("ztmp")
-            .arg("+inf")
-            .arg("x")
-            .query::<i64>(&mut *ctx.con);
-        let _ = cmd(&format!("{}ADD", ctx.fam.prefix()))
-            .arg("ztmp")
-            .arg("-inf")
-            .arg("y")
-            .query::<i64>(&mut *ctx.con);
+        // The module only stores finite scores; at the extremes of the finite
+        // range a +/-1 increment rounds away just like it does at infinity.
+        let (hi, lo) = match ctx.fam {
+            Fam::BuiltIn => (f64::INFINITY, f64::NEG_INFINITY),
+            Fam::Module => (f64::MAX, f64::MIN),
+        };
+        for (score, member) in [(hi, "x"), (lo, "y")] {
+            let added: i64 = cmd(&format!("{}ADD", ctx.fam.prefix()))
+                .arg("ztmp")
+                .arg(fmt_score(score))
+                .arg(member)
+                .query(&mut *ctx.con)
+                .unwrap();
+            assert_eq!(added, 1);
+        }
 
-        if ctx.fam == Fam::BuiltIn {
-            let cmds = [
-                vec!["LT", "INCR", "1", "x"],
-                vec!["GT", "INCR", "-1", "x"],
-                vec!["LT", "INCR", "-1", "x"],
-                vec!["GT", "INCR", "1", "x"],
-                vec!["LT", "INCR", "1", "y"],
-                vec!["GT", "INCR", "-1", "y"],
-                vec!["LT", "INCR", "-1", "y"],
-                vec!["GT", "INCR", "1", "y"],
-            ];
-            for args in cmds.iter() {
-                let res: Option<f64> = cmd("ZADD")
-                    .arg("ztmp")
-                    .arg(args[0])
-                    .arg(args[1])
-                    .arg(args[2])
-                    .arg(args[3])
-                    .query(&mut *ctx.con)
-                    .unwrap();
-                assert!(res.is_none());
-            }
-            assert_eq!(ctx.score("ztmp", "x").unwrap().unwrap(), f64::INFINITY);
-            assert_eq!(ctx.score("ztmp", "y").unwrap().unwrap(), f64::NEG_INFINITY);
+        let cmds = [
+            ["LT", "INCR", "1", "x"],
+            ["GT", "INCR", "-1", "x"],
+            ["LT", "INCR", "-1", "x"],
+            ["GT", "INCR", "1", "x"],
+            ["LT", "INCR", "1", "y"],
+            ["GT", "INCR", "-1", "y"],
+            ["LT", "INCR", "-1", "y"],
+            ["GT", "INCR", "1", "y"],
+        ];
+        for args in cmds.iter() {
+            let res: Option<f64> = cmd(&format!("{}ADD", ctx.fam.prefix()))
+                .arg("ztmp")
+                .arg(args[0])
+                .arg(args[1])
+                .arg(args[2])
+                .arg(args[3])
+                .query(&mut *ctx.con)
+                .unwrap();
+            assert!(res.is_none(), "{args:?}");
         }
+        assert_eq!(ctx.score("ztmp", "x").unwrap().unwrap(), hi);
+        assert_eq!(ctx.score("ztmp", "y").unwrap().unwrap(), lo);
     });
 }
 
+// ZADD INCR with CH, NX and GT still replies with the score
+/*
+ test "ZADD INCR works with CH, NX and GT - $encoding" {
+     r del ztmp
+     r zadd ztmp 10 x
+     assert {[r zadd ztmp incr ch 5 x] == 15}
+     assert {[r zadd ztmp incr nx 1 x] eq {}}
+     assert {[r zadd ztmp incr nx 3 y] == 3}
+     assert {[r zadd ztmp incr gt 2 x] == 17}
+     assert {[r zadd ztmp incr gt -2 x] eq {}}
+     assert_error "*not compatible*" {r zadd ztmp incr nx gt 1 x}
+     assert_error "*single increment-element*" {r zadd ztmp incr ch 1 x 1 y}
+ }
+*/
+#[test]
+fn incr_with_ch_nx_gt_replies_score() {
+    with_families(|ctx| {
+        ctx.del("ztmp");
+        ctx.add("ztmp", 10.0, "x").unwrap();
+        let cases: [(&[&str], Option<f64>); 5] = [
+            // CH only changes the count reply, which INCR replaces.
+            (&["INCR", "CH", "5", "x"], Some(15.0)),
+            (&["INCR", "NX", "1", "x"], None),
+            (&["INCR", "NX", "3", "y"], Some(3.0)),
+            (&["INCR", "GT", "2", "x"], Some(17.0)),
+            (&["INCR", "GT", "-2", "x"], None),
+        ];
+        for (args, want) in cases {
+            let got: Option<f64> = cmd(&zcmd(ctx.fam, "ADD"))
+                .arg("ztmp")
+                .arg(args)
+                .query(&mut *ctx.con)
+                .unwrap();
+            assert_eq!(got, want, "{:?} {args:?}", ctx.fam);
+        }
+        assert_eq!(ctx.score("ztmp", "x").unwrap(), Some(17.0));
+
+        for (args, msg) in [
+            (&["INCR", "NX", "GT", "1", "x"][..], "not compatible"),
+            (
+                &["INCR", "CH", "1", "x", "1", "y"],
+                "single increment-element",
+            ),
+        ] {
+            let err = cmd(&zcmd(ctx.fam, "ADD"))
+                .arg("ztmp")
+                .arg(args)
+                .query::<Option<f64>>(&mut *ctx.con)
+                .unwrap_err();
+            assert!(
+                err.to_string().contains(msg),
+                "{:?} {args:?}: {err}",
+                ctx.fam
+            );
+        }
+    });
+}
+
+fn fmt_score(score: f64) -> String {
+    if score.is_infinite() {
+        if score > 0.0 { "+inf" } else { "-inf" }.to_string()
+    } else {
+        format!("{score:e}")
+    }
+}
+
 // ZADD INCR works like ZINCRBY
 /*
  test "ZADD INCR works like ZINCRBY - $encoding" {
@@ -1326,10 +1346,8 @@ fn incr_behaves_like_zincrby() {
             .arg("x")
             .query(&mut *ctx.con);
 
-        if ctx.fam == Fam::BuiltIn {
-            assert_eq!(res.unwrap().unwrap(), 25.0);
-            assert_eq!(ctx.score("ztmp", "x").unwrap().unwrap(), 25.0);
-        }
+        assert_eq!(res.unwrap().unwrap(), 25.0);
+        assert_eq!(ctx.score("ztmp", "x").unwrap().unwrap(), 25.0);
     });
 }
 
@@ -1410,10 +1428,8 @@ fn ch_option_changes_return_value() {
             .arg("z")
             .query(&mut *ctx.con);
 
-        if ctx.fam == Fam::BuiltIn {
-            assert_eq!(rv1.unwrap(), 0);
-            assert_eq!(rv2.unwrap(), 2);
-        }
+        assert_eq!(rv1.unwrap(), 0);
+        assert_eq!(rv2.unwrap(), 2);
     });
 }
 
@@ -1427,6 +1443,7 @@ fn ch_option_changes_return_value() {
 #[test]
 fn zincrby_nan_error() {
     with_families(|ctx| {
+        ctx.del("myzset");
         let _: RedisResult<f64> = cmd(&format!("{}INCRBY", ctx.fam.prefix()))
             .arg("myzset")
             .arg("+inf")
@@ -1438,10 +1455,17 @@ fn zincrby_nan_error() {
             .arg("abc")
             .query(&mut *ctx.con);
 
+        let err = res.unwrap_err().to_string();
         if ctx.fam == Fam::BuiltIn {
-            assert!(res.unwrap_err().to_string().to_lowercase().contains("nan"));
+            assert!(
+                err.contains("resulting score is not a number (NaN)"),
+                "{err}"
+            );
         } else {
-            assert!(res.is_err());
+            // gzset keeps every score finite, so the infinite increments are
+            // refused up front and never reach the NaN check.
+            assert!(err.contains("score is not a finite number"), "{err}");
+            assert_eq!(ctx.exists("myzset").unwrap(), 0);
         }
     });
 }
@@ -1497,11 +1521,9 @@ fn variadic_base_case() {
             .query(&mut *ctx.con)
             .unwrap_or_default();
 
-        if ctx.fam == Fam::BuiltIn {
-            assert_eq!(added, 3);
-            let res = ctx.range_ws("myzset", 0, -1).unwrap();
-            assert_eq!(res, ["a", "10", "b", "20", "c", "30"]);
-        }
+        assert_eq!(added, 3);
+        let res = ctx.range_ws("myzset", 0, -1).unwrap();
+        assert_eq!(res, ["a", "10", "b", "20", "c", "30"]);
     });
 }
 
@@ -1537,11 +1559,9 @@ fn variadic_return_value_added() {
             .query(&mut *ctx.con)
             .unwrap_or_default();
 
-        if ctx.fam == Fam::BuiltIn {
-            assert_eq!(added, 1);
-            let vals = ctx.range_ws("myzset", 0, -1).unwrap();
-            assert_eq!(vals, ["x", "5", "a", "10", "b", "20", "c", "30"]);
-        }
+        assert_eq!(added, 1);
+        let vals = ctx.range_ws("myzset", 0, -1).unwrap();
+        assert_eq!(vals, ["x", "5", "a", "10", "b", "20", "c", "30"]);
     });
 }
 
@@ -1570,9 +1590,7 @@ fn variadic_aborts_on_single_error() {
 
         assert!(res.is_err());
         let exists: i32 = cmd("EXISTS").arg("myzset").query(&mut *ctx.con).unwrap();
-        if ctx.fam == Fam::BuiltIn {
-            assert_eq!(exists, 0);
-        }
+        assert_eq!(exists, 0);
     });
 }
 
@@ -1696,21 +1714,14 @@ fn zrem_removes_key_when_last_element_deleted() {
 #[test]
 fn zrem_variadic() {
     with_families(|ctx| {
-        // TODO: implement variadic GZREM for module
-        // TODO: implement variadic GZREM for module
-        // TODO: implement advanced RANGE options for module
-        // TODO: implement GZREVRANGE for module
-        // TODO: implement WITHSCORE options for module
-        if ctx.fam == Fam::BuiltIn {
-            ctx.del("zkey");
-            ctx.add("zkey", 1.0, "a").unwrap();
-            ctx.add("zkey", 2.0, "b").unwrap();
-            ctx.add("zkey", 3.0, "c").unwrap();
-            let removed = ctx.rem_variadic("zkey", &["a", "b", "x"]).unwrap();
-            assert_eq!(removed, 2);
-            let vals = ctx.range("zkey", 0, -1).unwrap();
-            assert_eq!(vals, ["c"]);
-        }
+        ctx.del("zkey");
+        ctx.add("zkey", 1.0, "a").unwrap();
+        ctx.add("zkey", 2.0, "b").unwrap();
+        ctx.add("zkey", 3.0, "c").unwrap();
+        let removed = ctx.rem_variadic("zkey", &["a", "b", "x"]).unwrap();
+        assert_eq!(removed, 2);
+        let vals = ctx.range("zkey", 0, -1).unwrap();
+        assert_eq!(vals, ["c"]);
     });
 }
 
@@ -1725,14 +1736,12 @@ fn zrem_variadic() {
 #[test]
 fn zrem_variadic_removes_key_when_last_element_deleted() {
     with_families(|ctx| {
-        if ctx.fam == Fam::BuiltIn {
-            ctx.del("zkey");
-            ctx.add("zkey", 1.0, "a").unwrap();
-            ctx.add("zkey", 2.0, "b").unwrap();
-            ctx.rem_variadic("zkey", &["a", "b", "c"]).unwrap();
-            let exists = ctx.exists("zkey").unwrap();
-            assert_eq!(exists, 0);
-        }
+        ctx.del("zkey");
+        ctx.add("zkey", 1.0, "a").unwrap();
+        ctx.add("zkey", 2.0, "b").unwrap();
+        ctx.rem_variadic("zkey", &["a", "b", "c"]).unwrap();
+        let exists = ctx.exists("zkey").unwrap();
+        assert_eq!(exists, 0);
     });
 }
 
@@ -1787,6 +1796,36 @@ fn gzrange_negative_index_module() {
     });
 }
 
+#[test]
+fn zrange_out_of_range_indices() {
+    with_families(|ctx| {
+        ctx.del("zkey");
+        ctx.add("zkey", 1.0, "a").unwrap();
+        ctx.add("zkey", 2.0, "b").unwrap();
+        ctx.add("zkey", 3.0, "c").unwrap();
+        let cases: [(isize, isize, &[&str]); 11] = [
+            (2, 1, &[]),
+            (3, 5, &[]),
+            (5, -1, &[]),
+            (1, 10, &["b", "c"]),
+            (-4, 0, &["a"]),
+            (-100, -1, &["a", "b", "c"]),
+            (0, -4, &[]),
+            (-1, -3, &[]),
+            (-3, -3, &["a"]),
+            (isize::MIN, isize::MAX, &["a", "b", "c"]),
+            (isize::MAX, isize::MAX, &[]),
+        ];
+        for (start, stop, expected) in cases {
+            let got = ctx.range("zkey", start, stop).unwrap();
+            assert_eq!(got, expected, "{:?} {start} {stop}", ctx.fam);
+        }
+        assert_eq!(ctx.range_ws("zkey", -4, 0).unwrap(), ["a", "1"]);
+        assert!(ctx.range_ws("zkey", 0, -4).unwrap().is_empty());
+        assert!(ctx.range("nokey", -1, 1).unwrap().is_empty());
+    });
+}
+
 /*
  test {ZREVRANGE basics - $encoding} {
      r del zkey
@@ -1799,18 +1838,20 @@ fn gzrange_negative_index_module() {
 #[test]
 fn zrevrange_basics() {
     with_families(|ctx| {
-        if ctx.fam == Fam::BuiltIn {
-            ctx.del("zkey");
-            ctx.add("zkey", 1.0, "a").unwrap();
-            ctx.add("zkey", 2.0, "b").unwrap();
-            ctx.add("zkey", 3.0, "c").unwrap();
-            let r1 = ctx.revrange("zkey", 0, 1, false).unwrap();
-            assert_eq!(r1, ["c", "b"]);
-            let r2 = ctx.revrange("zkey", 0, -1, false).unwrap();
-            assert_eq!(r2, ["c", "b", "a"]);
-            let r3 = ctx.revrange("zkey", 0, 1, true).unwrap();
-            assert_eq!(r3, ["c", "3", "b", "2"]);
-        }
+        ctx.del("zkey");
+        ctx.add("zkey", 1.0, "a").unwrap();
+        ctx.add("zkey", 2.0, "b").unwrap();
+        ctx.add("zkey", 3.0, "c").unwrap();
+        let r1 = ctx.revrange("zkey", 0, 1, false).unwrap();
+        assert_eq!(r1, ["c", "b"]);
+        let r2 = ctx.revrange("zkey", 0, -1, false).unwrap();
+        assert_eq!(r2, ["c", "b", "a"]);
+        let r3 = ctx.revrange("zkey", 0, 1, true).unwrap();
+        assert_eq!(r3, ["c", "3", "b", "2"]);
+        let r4 = ctx.revrange("zkey", -2, -1, false).unwrap();
+        assert_eq!(r4, ["b", "a"]);
+        assert!(ctx.revrange("zkey", 2, 1, false).unwrap().is_empty());
+        assert!(ctx.revrange("nokey", 0, -1, false).unwrap().is_empty());
     });
 }
 
@@ -1827,25 +1868,32 @@ fn zrevrange_basics() {
 #[test]
 fn zrank_and_zrevrank_basics_withscore() {
     with_families(|ctx| {
+        ctx.del("zkey");
+        ctx.add("zkey", 1.0, "a").unwrap();
+        ctx.add("zkey", 2.0, "b").unwrap();
+        ctx.add("zkey", 3.0, "c").unwrap();
+        let r1 = ctx.rank("zkey", "a").unwrap().unwrap();
+        assert_eq!(r1, 0);
         if ctx.fam == Fam::BuiltIn {
-            ctx.del("zkey");
-            ctx.add("zkey", 1.0, "a").unwrap();
-            ctx.add("zkey", 2.0, "b").unwrap();
-            ctx.add("zkey", 3.0, "c").unwrap();
-            let r1 = ctx.rank("zkey", "a").unwrap().unwrap();
-            assert_eq!(r1, 0);
             let r2 = ctx.revrank("zkey", "a").unwrap().unwrap();
             assert_eq!(r2, 2);
-            let res: (i64, f64) = cmd(&format!("{}RANK", ctx.fam.prefix()))
-                .arg("zkey")
-                .arg("b")
-                .arg("WITHSCORE")
-                .query(&mut *ctx.con)
-                .unwrap();
-            assert_eq!(res, (1, 2.0));
-            let score_a = ctx.score("zkey", "a").unwrap().unwrap();
-            assert_eq!(score_a, 1.0);
         }
+        let res: (i64, f64) = cmd(&format!("{}RANK", ctx.fam.prefix()))
+            .arg("zkey")
+            .arg("b")
+            .arg("WITHSCORE")
+            .query(&mut *ctx.con)
+            .unwrap();
+        assert_eq!(res, (1, 2.0));
+        let missing: Option<(i64, f64)> = cmd(&format!("{}RANK", ctx.fam.prefix()))
+            .arg("zkey")
+            .arg("nope")
+            .arg("WITHSCORE")
+            .query(&mut *ctx.con)
+            .unwrap();
+        assert_eq!(missing, None);
+        let score_a = ctx.score("zkey", "a").unwrap().unwrap();
+        assert_eq!(score_a, 1.0);
     });
 }
 
@@ -1882,12 +1930,10 @@ fn zrank_after_deletion() {
 #[test]
 fn zincrby_can_create_new_set() {
     with_families(|ctx| {
-        if ctx.fam == Fam::BuiltIn {
-            ctx.del("zkey");
-            ctx.incrby("zkey", 5.0, "a").unwrap();
-            let vals = ctx.range_ws("zkey", 0, -1).unwrap();
-            assert_eq!(vals, ["a", "5"]);
-        }
+        ctx.del("zkey");
+        ctx.incrby("zkey", 5.0, "a").unwrap();
+        let vals = ctx.range_ws("zkey", 0, -1).unwrap();
+        assert_eq!(vals, ["a", "5"]);
     });
 }
 
@@ -1903,15 +1949,13 @@ fn zincrby_can_create_new_set() {
 #[test]
 fn zincrby_increment_and_decrement_ordering() {
     with_families(|ctx| {
-        if ctx.fam == Fam::BuiltIn {
-            ctx.del("zkey");
-            ctx.add("zkey", 1.0, "a").unwrap();
-            ctx.add("zkey", 2.0, "b").unwrap();
-            ctx.incrby("zkey", 5.0, "a").unwrap();
-            ctx.incrby("zkey", -3.0, "b").unwrap();
-            let vals = ctx.range("zkey", 0, 1).unwrap();
-            assert_eq!(vals, ["b", "a"]);
-        }
+        ctx.del("zkey");
+        ctx.add("zkey", 1.0, "a").unwrap();
+        ctx.add("zkey", 2.0, "b").unwrap();
+        ctx.incrby("zkey", 5.0, "a").unwrap();
+        ctx.incrby("zkey", -3.0, "b").unwrap();
+        let vals = ctx.range("zkey", 0, 1).unwrap();
+        assert_eq!(vals, ["b", "a"]);
     });
 }
 
@@ -1925,13 +1969,11 @@ fn zincrby_increment_and_decrement_ordering() {
 #[test]
 fn zincrby_return_value() {
     with_families(|ctx| {
-        if ctx.fam == Fam::BuiltIn {
-            ctx.del("zkey");
-            let v1 = ctx.incrby("zkey", 5.0, "a").unwrap();
-            assert!((v1 - 5.0).abs() < f64::EPSILON);
-            let v2 = ctx.incrby("zkey", -2.5, "a").unwrap();
-            assert!((v2 - 2.5).abs() < f64::EPSILON);
-        }
+        ctx.del("zkey");
+        let v1 = ctx.incrby("zkey", 5.0, "a").unwrap();
+        assert!((v1 - 5.0).abs() < f64::EPSILON);
+        let v2 = ctx.incrby("zkey", -2.5, "a").unwrap();
+        assert!((v2 - 2.5).abs() < f64::EPSILON);
     });
 }
 
@@ -1953,10 +1995,10 @@ fn zrangebyscore_and_revrange_and_count_basics() {
         for i in 1..=10 {
             ctx.add("zkey", i as f64, &format!("m{i}")).unwrap();
         }
-        // TODO: implement score-based range commands for module
+        let r1 = ctx.rangebyscore("zkey", "-inf", "3", false, None).unwrap();
+        assert_eq!(r1, ["m1", "m2", "m3"]);
+        // TODO: implement reverse score ranges and counts for module
         if ctx.fam == Fam::BuiltIn {
-            let r1 = ctx.rangebyscore("zkey", "-inf", "3", false, None).unwrap();
-            assert_eq!(r1, ["m1", "m2", "m3"]);
             let r2 = ctx
                 .revrangebyscore("zkey", "+inf", "8", Some((0, 3)))
                 .unwrap();
@@ -1981,10 +2023,8 @@ fn zrangebyscore_withscores() {
         ctx.add("zkey", 1.0, "a").unwrap();
         ctx.add("zkey", 2.0, "b").unwrap();
         ctx.add("zkey", 3.0, "c").unwrap();
-        if ctx.fam == Fam::BuiltIn {
-            let vals = ctx.rangebyscore("zkey", "-inf", "2", true, None).unwrap();
-            assert_eq!(vals, ["a", "1", "b", "2"]);
-        }
+        let vals = ctx.rangebyscore("zkey", "-inf", "2", true, None).unwrap();
+        assert_eq!(vals, ["a", "1", "b", "2"]);
     });
 }
 
@@ -2003,12 +2043,10 @@ fn zrangebyscore_limit() {
         ctx.add("zkey", 2.0, "b").unwrap();
         ctx.add("zkey", 3.0, "c").unwrap();
         ctx.add("zkey", 4.0, "d").unwrap();
-        if ctx.fam == Fam::BuiltIn {
-            let vals = ctx
-                .rangebyscore("zkey", "-inf", "+inf", false, Some((1, 2)))
-                .unwrap();
-            assert_eq!(vals, ["b", "c"]);
-        }
+        let vals = ctx
+            .rangebyscore("zkey", "-inf", "+inf", false, Some((1, 2)))
+            .unwrap();
+        assert_eq!(vals, ["b", "c"]);
     });
 }
 
@@ -2028,12 +2066,10 @@ fn zrangebyscore_limit_withscores() {
         ctx.add("zkey", 2.0, "b").unwrap();
         ctx.add("zkey", 3.0, "c").unwrap();
         ctx.add("zkey", 4.0, "d").unwrap();
-        if ctx.fam == Fam::BuiltIn {
-            let vals = ctx
-                .rangebyscore("zkey", "-inf", "+inf", true, Some((1, 2)))
-                .unwrap();
-            assert_eq!(vals, ["b", "2", "c", "3"]);
-        }
+        let vals = ctx
+            .rangebyscore("zkey", "-inf", "+inf", true, Some((1, 2)))
+            .unwrap();
+        assert_eq!(vals, ["b", "2", "c", "3"]);
     });
 }
 
@@ -2046,11 +2082,11 @@ fn zrangebyscore_limit_withscores() {
 #[test]
 fn zrangebyscore_invalid_min_max_error() {
     with_families(|ctx| {
-        // TODO: validate error handling once module supports ZRANGEBYSCORE
-        if ctx.fam == Fam::BuiltIn {
-            let res = ctx.rangebyscore("zkey", "0", "nan", false, None);
-            assert!(res.is_err());
-        }
+        let res = ctx.rangebyscore("zkey", "0", "nan", false, None);
+        assert!(res
+            .unwrap_err()
+            .to_string()
+            .contains("min or max is not a float"));
     });
 }
 
@@ -2253,19 +2289,17 @@ fn zremrangebylex_basics() {
 #[test]
 fn zunionstore_against_non_existing_key() {
     with_families(|ctx| {
-        if ctx.fam == Fam::BuiltIn {
-            cmd("DEL")
-                .arg("foo")
-                .arg("bar")
-                .arg("dst")
-                .query::<i64>(&mut *ctx.con)
-                .unwrap();
-            ctx.add("foo", 1.0, "a").unwrap();
-            let res = ctx.unionstore("dst", &["foo", "bar"]).unwrap();
-            assert_eq!(res, 1);
-            let vals = ctx.range("dst", 0, -1).unwrap();
-            assert_eq!(vals, ["a"]);
-        }
+        cmd("DEL")
+            .arg("foo")
+            .arg("bar")
+            .arg("dst")
+            .query::<i64>(&mut *ctx.con)
+            .unwrap();
+        ctx.add("foo", 1.0, "a").unwrap();
+        let res = ctx.unionstore("dst", &["foo", "bar"]).unwrap();
+        assert_eq!(res, 1);
+        let vals = ctx.range("dst", 0, -1).unwrap();
+        assert_eq!(vals, ["a"]);
     });
 }
 
@@ -2467,6 +2501,75 @@ fn zpopmin_zpopmax_count_zero_negative() {
     });
 }
 
+#[test]
+fn zpop_count_edges_match_redis() {
+    with_families(|ctx| {
+        for pop in ["POPMIN", "POPMAX"] {
+            // Count 0 replies an empty array before the key is looked at, so
+            // it neither creates, changes nor type-checks the key.
+            ctx.del("pc");
+            let res: Vec<String> = cmd(&zcmd(ctx.fam, pop))
+                .arg("pc")
+                .arg(0)
+                .query(&mut *ctx.con)
+                .unwrap();
+            assert!(res.is_empty());
+            assert_eq!(ctx.exists("pc").unwrap(), 0, "{:?} {pop}", ctx.fam);
+            let _: () = cmd("SET").arg("pc").arg("x").query(&mut *ctx.con).unwrap();
+            let res: Vec<String> = cmd(&zcmd(ctx.fam, pop))
+                .arg("pc")
+                .arg(0)
+                .query(&mut *ctx.con)
+                .unwrap();
+            assert!(res.is_empty(), "{:?} {pop}", ctx.fam);
+
+            // A negative count is rejected before the type check, too.
+            for key_holds_set in [false, true] {
+                if key_holds_set {
+                    ctx.del("pc");
+                    ctx.add("pc", 1.0, "a").unwrap();
+                }
+                let err = cmd(&zcmd(ctx.fam, pop))
+                    .arg("pc")
+                    .arg(-1)
+                    .query::<Vec<String>>(&mut *ctx.con)
+                    .unwrap_err();
+                assert!(
+                    err.to_string()
+                        .contains("value is out of range, must be positive"),
+                    "{:?} {pop}: {err}",
+                    ctx.fam
+                );
+            }
+            assert_eq!(ctx.card("pc").unwrap(), 1);
+
+            // A count past the size pops everything and deletes the key.
+            ctx.add("pc", 2.0, "b").unwrap();
+            let res: Vec<String> = cmd(&zcmd(ctx.fam, pop))
+                .arg("pc")
+                .arg(10)
+                .query(&mut *ctx.con)
+                .unwrap();
+            let want = if pop == "POPMIN" {
+                ["a", "1", "b", "2"]
+            } else {
+                ["b", "2", "a", "1"]
+            };
+            assert_eq!(res, want, "{:?}", ctx.fam);
+            assert_eq!(ctx.exists("pc").unwrap(), 0, "{:?} {pop}", ctx.fam);
+
+            // A missing key replies an empty array and stays missing.
+            let res: Vec<String> = cmd(&zcmd(ctx.fam, pop))
+                .arg("pc")
+                .arg(3)
+                .query(&mut *ctx.con)
+                .unwrap();
+            assert!(res.is_empty());
+            assert_eq!(ctx.exists("pc").unwrap(), 0, "{:?} {pop}", ctx.fam);
+        }
+    });
+}
+
 /*
     foreach {popmin popmax} {ZPOPMIN ZPOPMAX ZMPOP_MIN ZMPOP_MAX} {
         test "Basic $popmin/$popmax with a single key - $encoding" {
@@ -2572,14 +2675,12 @@ fn zunionstore_with_weights() {
     with_families(|ctx| {
         ctx.del("a");
         ctx.del("b");
-        if ctx.fam == Fam::BuiltIn {
-            ctx.add("a", 1.0, "x").unwrap();
-            ctx.add("b", 2.0, "x").unwrap();
-            ctx.add("b", 3.0, "y").unwrap();
-            ctx.unionstore_weights("dst", &["a", "b"], &[2, 3]).unwrap();
-            let vals = ctx.range_ws("dst", 0, -1).unwrap();
-            assert_eq!(vals, ["x", "8", "y", "9"]);
-        }
+        ctx.add("a", 1.0, "x").unwrap();
+        ctx.add("b", 2.0, "x").unwrap();
+        ctx.add("b", 3.0, "y").unwrap();
+        ctx.unionstore_weights("dst", &["a", "b"], &[2, 3]).unwrap();
+        let vals = ctx.range_ws("dst", 0, -1).unwrap();
+        assert_eq!(vals, ["x", "8", "y", "9"]);
     });
 }
 
@@ -2589,14 +2690,12 @@ fn zunionstore_with_aggregate_max() {
     with_families(|ctx| {
         ctx.del("a");
         ctx.del("b");
-        if ctx.fam == Fam::BuiltIn {
-            ctx.add("a", 1.0, "x").unwrap();
-            ctx.add("b", 2.0, "x").unwrap();
-            ctx.add("b", 3.0, "y").unwrap();
-            ctx.unionstore_aggregate_max("dst", &["a", "b"]).unwrap();
-            let vals = ctx.range_ws("dst", 0, -1).unwrap();
-            assert_eq!(vals, ["x", "2", "y", "3"]);
-        }
+        ctx.add("a", 1.0, "x").unwrap();
+        ctx.add("b", 2.0, "x").unwrap();
+        ctx.add("b", 3.0, "y").unwrap();
+        ctx.unionstore_aggregate_max("dst", &["a", "b"]).unwrap();
+        let vals = ctx.range_ws("dst", 0, -1).unwrap();
+        assert_eq!(vals, ["x", "2", "y", "3"]);
     });
 }
 
@@ -2641,14 +2740,37 @@ fn zintercard_with_limit() {
     with_families(|ctx| {
         ctx.del("a");
         ctx.del("b");
-        if ctx.fam == Fam::BuiltIn {
-            ctx.add("a", 1.0, "x").unwrap();
-            ctx.add("a", 2.0, "y").unwrap();
-            ctx.add("b", 3.0, "y").unwrap();
-            ctx.add("b", 4.0, "z").unwrap();
-            let card = ctx.intercard_limit(&["a", "b"], 1).unwrap();
-            assert_eq!(card, 1);
+        ctx.add("a", 1.0, "x").unwrap();
+        ctx.add("a", 2.0, "y").unwrap();
+        ctx.add("b", 3.0, "y").unwrap();
+        ctx.add("b", 4.0, "z").unwrap();
+        let card = ctx.intercard_limit(&["a", "b"], 1).unwrap();
+        assert_eq!(card, 1);
+    });
+}
+
+/* ZINTERCARD across three keys, LIMIT 0 meaning unlimited */
+#[test]
+fn zintercard_three_keys_with_limit() {
+    with_families(|ctx| {
+        for key in ["a", "b", "c"] {
+            ctx.del(key);
+        }
+        for m in ["m1", "m2", "m3", "m4", "m5"] {
+            ctx.add("a", 1.0, m).unwrap();
+        }
+        for m in ["m2", "m3", "m4", "m5", "m6"] {
+            ctx.add("b", 2.0, m).unwrap();
         }
+        for m in ["m3", "m4", "m5", "m7"] {
+            ctx.add("c", 3.0, m).unwrap();
+        }
+        assert_eq!(ctx.intercard(&["a", "b", "c"]).unwrap(), 3);
+        assert_eq!(ctx.intercard_limit(&["a", "b", "c"], 2).unwrap(), 2);
+        assert_eq!(ctx.intercard_limit(&["a", "b", "c"], 0).unwrap(), 3);
+        assert_eq!(ctx.intercard_limit(&["a", "b", "c"], 10).unwrap(), 3);
+        assert_eq!(ctx.intercard(&["a", "b", "missing"]).unwrap(), 0);
+        assert!(ctx.intercard_limit(&["a", "b"], -1).is_err());
     });
 }
 
@@ -2684,18 +2806,107 @@ fn zadd_invalid_option_order() {
     });
 }
 
+/* ZADD option tokens are case-insensitive */
+#[test]
+fn zadd_options_case_insensitive() {
+    with_families(|ctx| {
+        ctx.del("k");
+        ctx.add("k", 1.0, "a").unwrap();
+
+        let added: i64 = cmd(&zcmd(ctx.fam, "ADD"))
+            .arg("k")
+            .arg("nx")
+            .arg(5)
+            .arg("a")
+            .arg(2)
+            .arg("b")
+            .query(&mut *ctx.con)
+            .unwrap();
+        assert_eq!(added, 1);
+        assert_eq!(ctx.score("k", "a").unwrap(), Some(1.0));
+
+        let changed: i64 = cmd(&zcmd(ctx.fam, "ADD"))
+            .arg("k")
+            .arg("Xx")
+            .arg("gT")
+            .arg("cH")
+            .arg(3)
+            .arg("a")
+            .arg(9)
+            .arg("c")
+            .query(&mut *ctx.con)
+            .unwrap();
+        assert_eq!(changed, 1);
+        assert_eq!(ctx.score("k", "c").unwrap(), None);
+
+        let res: RedisResult<i64> = cmd(&zcmd(ctx.fam, "ADD"))
+            .arg("k")
+            .arg("lt")
+            .arg(0)
+            .arg("b")
+            .query(&mut *ctx.con);
+        assert_eq!(res.unwrap(), 0);
+        assert_eq!(ctx.score("k", "b").unwrap(), Some(0.0));
+
+        let score: f64 = cmd(&zcmd(ctx.fam, "ADD"))
+            .arg("k")
+            .arg("Incr")
+            .arg(1.5)
+            .arg("b")
+            .query(&mut *ctx.con)
+            .unwrap();
+        assert_eq!(score, 1.5);
+    });
+}
+
+/* ZADD - repeated members within one call: last write wins, added once */
+#[test]
+fn zadd_repeated_member_in_one_call() {
+    fn zadd(ctx: &mut Ctx<'_>, args: &[&str]) -> i64 {
+        cmd(&zcmd(ctx.fam, "ADD"))
+            .arg("k")
+            .arg(args)
+            .query(&mut *ctx.con)
+            .unwrap()
+    }
+    with_families(|ctx| {
+        ctx.del("k");
+        assert_eq!(zadd(ctx, &["1", "a", "2", "a"]), 1);
+        assert_eq!(ctx.score("k", "a").unwrap(), Some(2.0));
+        assert_eq!(ctx.card("k").unwrap(), 1);
+
+        // CH counts the add and the later rescore separately.
+        ctx.del("k");
+        assert_eq!(zadd(ctx, &["CH", "1", "a", "2", "a", "2", "a"]), 2);
+        assert_eq!(ctx.score("k", "a").unwrap(), Some(2.0));
+
+        // GT/LT compare each pair against the score the previous one left.
+        ctx.del("k");
+        assert_eq!(zadd(ctx, &["GT", "CH", "5", "a", "3", "a", "7", "a"]), 2);
+        assert_eq!(ctx.score("k", "a").unwrap(), Some(7.0));
+        ctx.del("k");
+        assert_eq!(zadd(ctx, &["LT", "CH", "5", "a", "7", "a", "3", "a"]), 2);
+        assert_eq!(ctx.score("k", "a").unwrap(), Some(3.0));
+
+        // XX skips the first pair for a missing member and every repeat too.
+        ctx.del("k");
+        ctx.add("k", 0.0, "b").unwrap();
+        assert_eq!(zadd(ctx, &["XX", "CH", "1", "a", "2", "a", "4", "b"]), 1);
+        assert_eq!(ctx.score("k", "a").unwrap(), None);
+        assert_eq!(ctx.score("k", "b").unwrap(), Some(4.0));
+    });
+}
+
 /* ZUNION – WITHSCORES option */
 #[test]
 fn zunion_with_withscores_option() {
     with_families(|ctx| {
         ctx.del("a");
         ctx.del("b");
-        if ctx.fam == Fam::BuiltIn {
-            ctx.add("a", 1.0, "x").unwrap();
-            ctx.add("b", 2.0, "y").unwrap();
-            let vals = ctx.union_withscores(&["a", "b"]).unwrap();
-            assert_eq!(vals, ["x", "1", "y", "2"]);
-        }
+        ctx.add("a", 1.0, "x").unwrap();
+        ctx.add("b", 2.0, "y").unwrap();
+        let vals = ctx.union_withscores(&["a", "b"]).unwrap();
+        assert_eq!(vals, ["x", "1", "y", "2"]);
     });
 }
 
@@ -2716,25 +2927,155 @@ fn zinter_error_missing_weights_count() {
     });
 }
 
+/* ZUNION/ZINTER/ZUNIONSTORE – malformed WEIGHTS/AGGREGATE errors */
+#[test]
+fn zunion_zinter_option_errors() {
+    let cases: [(&[&str], &str); 7] = [
+        (&["WEIGHTS", "1", "x"], "weight value is not a float"),
+        (&["WEIGHTS", "1", "nan"], "weight value is not a float"),
+        (&["WEIGHTS", "1"], "syntax error"),
+        (&["WEIGHTS", "1", "2", "3"], "syntax error"),
+        (&["AGGREGATE", "AVG"], "syntax error"),
+        (&["AGGREGATE"], "syntax error"),
+        (&["BOGUS"], "syntax error"),
+    ];
+    with_families(|ctx| {
+        ctx.del("a");
+        ctx.del("b");
+        ctx.add("a", 1.0, "x").unwrap();
+        ctx.add("b", 2.0, "x").unwrap();
+        for base in ["UNION", "INTER", "UNIONSTORE"] {
+            for (opts, expected) in cases {
+                let mut c = cmd(&zcmd(ctx.fam, base));
+                if base == "UNIONSTORE" {
+                    c.arg("dst");
+                }
+                c.arg(2).arg("a").arg("b").arg(opts);
+                let err = c.query::<redis::Value>(&mut *ctx.con).unwrap_err();
+                let msg = err.to_string();
+                assert!(
+                    msg.contains(expected),
+                    "{:?} {base} {opts:?}: {msg}",
+                    ctx.fam
+                );
+            }
+        }
+        let err = cmd(&zcmd(ctx.fam, "UNIONSTORE"))
+            .arg("dst")
+            .arg(1)
+            .arg("a")
+            .arg("WITHSCORES")
+            .query::<redis::Value>(&mut *ctx.con)
+            .unwrap_err();
+        assert!(err.to_string().contains("syntax error"), "{:?}", ctx.fam);
+    });
+}
+
+/* Malformed calls: syntax error vs wrong number of arguments */
+#[test]
+fn malformed_calls_match_redis_error_class() {
+    const SYNTAX: &str = "syntax error";
+    const ARITY: &str = "wrong number of arguments";
+    let cases: [(&str, &[&str], &str); 12] = [
+        ("RANGE", &["k", "0", "1", "FOO"], SYNTAX),
+        ("RANGE", &["k", "0"], ARITY),
+        ("REVRANGE", &["k", "0", "1", "WITHSCORES", "FOO"], SYNTAX),
+        ("POPMIN", &["k", "1", "2"], SYNTAX),
+        ("POPMAX", &[], ARITY),
+        ("RANDMEMBER", &["k", "1", "FOO"], SYNTAX),
+        ("RANDMEMBER", &["k", "1", "WITHSCORES", "FOO"], SYNTAX),
+        ("RANK", &["k", "a", "FOO"], SYNTAX),
+        ("RANK", &["k", "a", "WITHSCORE", "FOO"], ARITY),
+        ("UNION", &["3", "k", "k"], SYNTAX),
+        ("SCAN", &["k", "0", "COUNT"], SYNTAX),
+        ("SCAN", &["k"], ARITY),
+    ];
+    with_families(|ctx| {
+        ctx.del("k");
+        ctx.add("k", 1.0, "a").unwrap();
+        for (base, args, expected) in cases {
+            let err = cmd(&zcmd(ctx.fam, base))
+                .arg(args)
+                .query::<redis::Value>(&mut *ctx.con)
+                .unwrap_err();
+            let msg = err.to_string();
+            assert!(
+                msg.contains(expected),
+                "{:?} {base} {args:?}: {msg}",
+                ctx.fam
+            );
+        }
+
+        // A repeated WITHSCORES is accepted, and a lone one is a bad count
+        // rather than an arity mismatch.
+        let res: Vec<String> = cmd(&zcmd(ctx.fam, "RANGE"))
+            .arg(&["k", "0", "-1", "WITHSCORES", "withscores"])
+            .query(&mut *ctx.con)
+            .unwrap();
+        assert_eq!(res, ["a", "1"], "{:?}", ctx.fam);
+        let err = cmd(&zcmd(ctx.fam, "RANDMEMBER"))
+            .arg(&["k", "WITHSCORES"])
+            .query::<redis::Value>(&mut *ctx.con)
+            .unwrap_err();
+        assert!(!err.to_string().contains(ARITY), "{:?}: {err}", ctx.fam);
+    });
+}
+
+/* ZUNION/ZINTER – WEIGHTS and AGGREGATE */
+#[test]
+fn zunion_zinter_weights_and_aggregate() {
+    with_families(|ctx| {
+        ctx.del("a");
+        ctx.del("b");
+        ctx.add("a", 1.0, "x").unwrap();
+        ctx.add("a", 2.0, "y").unwrap();
+        ctx.add("b", 3.0, "x").unwrap();
+        ctx.add("b", 4.0, "z").unwrap();
+        let query = |ctx: &mut Ctx, base: &str, opts: &[&str]| -> Vec<String> {
+            cmd(&zcmd(ctx.fam, base))
+                .arg(2)
+                .arg("a")
+                .arg("b")
+                .arg(opts)
+                .arg("WITHSCORES")
+                .query(&mut *ctx.con)
+                .unwrap()
+        };
+        assert_eq!(
+            query(ctx, "UNION", &["WEIGHTS", "2", "3"]),
+            ["y", "4", "x", "11", "z", "12"]
+        );
+        assert_eq!(
+            query(ctx, "UNION", &["AGGREGATE", "MIN"]),
+            ["x", "1", "y", "2", "z", "4"]
+        );
+        assert_eq!(
+            query(ctx, "INTER", &["WEIGHTS", "2", "3", "AGGREGATE", "MAX"]),
+            ["x", "9"]
+        );
+        assert_eq!(
+            query(ctx, "INTER", &["WEIGHTS", "0", "1", "AGGREGATE", "SUM"]),
+            ["x", "3"]
+        );
+    });
+}
+
 /* ZDIFF – WITHSCORES basics */
 #[test]
 fn zdiff_withscores_basics() {
     with_families(|ctx| {
         ctx.del("a");
         ctx.del("b");
-        if ctx.fam == Fam::BuiltIn {
-            ctx.add("a", 1.0, "x").unwrap();
-            ctx.add("a", 2.0, "y").unwrap();
-            ctx.add("b", 3.0, "y").unwrap();
-            let res = ctx.diff_withscores(&["a", "b"]).unwrap();
-            assert_eq!(res, ["x", "1"]);
-        }
+        ctx.add("a", 1.0, "x").unwrap();
+        ctx.add("a", 2.0, "y").unwrap();
+        ctx.add("b", 3.0, "y").unwrap();
+        let res = ctx.diff_withscores(&["a", "b"]).unwrap();
+        assert_eq!(res, ["x", "1"]);
     });
 }
 
 #[test]
 fn gzunion_inter_diff_withscores() {
-    use redis::cmd;
     with_families(|ctx| {
         ctx.del("ua");
         ctx.del("ub");
@@ -2743,32 +3084,25 @@ fn gzunion_inter_diff_withscores() {
         ctx.add("ub", 2.0, "y").unwrap();
         ctx.add("ub", 3.0, "z").unwrap();
 
-        let union_vals = if ctx.fam == Fam::BuiltIn {
-            ctx.union_withscores(&["ua", "ub"]).unwrap()
-        } else {
-            ctx.union(&["ua", "ub"]).unwrap()
-        };
+        let union_vals = ctx.union_withscores(&["ua", "ub"]).unwrap();
         assert_eq!(union_vals, ["x", "1", "z", "3", "y", "4"]);
+        assert_eq!(ctx.union(&["ua", "ub"]).unwrap(), ["x", "z", "y"]);
 
-        let inter_vals: Vec<String> = if ctx.fam == Fam::BuiltIn {
-            cmd("ZINTER")
-                .arg(2)
-                .arg("ua")
-                .arg("ub")
-                .arg("WITHSCORES")
-                .query(&mut *ctx.con)
-                .unwrap()
-        } else {
-            ctx.inter(&["ua", "ub"]).unwrap()
-        };
+        let inter_vals = ctx.inter_withscores(&["ua", "ub"]).unwrap();
         assert_eq!(inter_vals, ["y", "4"]);
+        assert_eq!(ctx.inter(&["ua", "ub"]).unwrap(), ["y"]);
 
-        let diff_vals = if ctx.fam == Fam::BuiltIn {
-            ctx.diff_withscores(&["ua", "ub"]).unwrap()
-        } else {
-            ctx.diff(&["ua", "ub"]).unwrap()
-        };
+        let diff_vals = ctx.diff_withscores(&["ua", "ub"]).unwrap();
         assert_eq!(diff_vals, ["x", "1"]);
+        assert_eq!(ctx.diff(&["ua", "ub"]).unwrap(), ["x"]);
+
+        let res: RedisResult<Vec<String>> = cmd(&zcmd(ctx.fam, "UNION"))
+            .arg(2)
+            .arg("ua")
+            .arg("ub")
+            .arg("BOGUS")
+            .query(&mut *ctx.con);
+        assert!(res.is_err());
     });
 }
 
@@ -2781,11 +3115,7 @@ fn gzunion_tiebreak_order() {
         ctx.add("ta", 1.0, "a").unwrap();
         ctx.add("tb", 1.0, "c").unwrap();
 
-        let vals = if ctx.fam == Fam::BuiltIn {
-            ctx.union_withscores(&["ta", "tb"]).unwrap()
-        } else {
-            ctx.union(&["ta", "tb"]).unwrap()
-        };
+        let vals = ctx.union_withscores(&["ta", "tb"]).unwrap();
         assert_eq!(vals, ["a", "1", "b", "1", "c", "1"]);
     });
 }
@@ -2850,10 +3180,15 @@ fn bzpopmax_timeout() {
 fn zpop_against_empty_key_returns_nil() {
     with_families(|ctx| {
         ctx.del("empty");
-        if ctx.fam == Fam::BuiltIn {
-            let res: Option<Vec<String>> =
-                cmd("ZPOPMIN").arg("empty").query(&mut *ctx.con).unwrap();
-            assert!(res.is_none() || res.as_ref().unwrap().is_empty());
+        let (min, max) = match ctx.fam {
+            Fam::BuiltIn => ("ZPOPMIN", "ZPOPMAX"),
+            Fam::Module => ("GZPOPMIN", "GZPOPMAX"),
+        };
+        for name in [min, max] {
+            let res: redis::Value = cmd(name).arg("empty").query(&mut *ctx.con).unwrap();
+            assert_eq!(res, redis::Value::Bulk(Vec::new()), "{name}");
+            let res: redis::Value = cmd(name).arg("empty").arg(3).query(&mut *ctx.con).unwrap();
+            assert_eq!(res, redis::Value::Bulk(Vec::new()), "{name} count");
         }
     });
 }
@@ -2864,12 +3199,10 @@ fn zunionstore_duplicate_keys_once() {
     with_families(|ctx| {
         ctx.del("foo");
         ctx.add("foo", 1.0, "a").unwrap();
-        if ctx.fam == Fam::BuiltIn {
-            let res = ctx.unionstore("dstdup", &["foo", "foo"]).unwrap();
-            assert_eq!(res, 1);
-            let vals = ctx.range("dstdup", 0, -1).unwrap();
-            assert_eq!(vals, ["a"]);
-        }
+        let res = ctx.unionstore("dstdup", &["foo", "foo"]).unwrap();
+        assert_eq!(res, 1);
+        let vals = ctx.range("dstdup", 0, -1).unwrap();
+        assert_eq!(vals, ["a"]);
     });
 }
 
@@ -2893,19 +3226,18 @@ fn zadd_incr_gt_nx_incompatibility() {
 #[test]
 fn zset_write_wrong_type() {
     with_families(|ctx| {
-        if ctx.fam == Fam::BuiltIn {
-            cmd("SET")
-                .arg("foo")
-                .arg("bar")
-                .query::<()>(&mut *ctx.con)
-                .unwrap();
-            let res: RedisResult<i64> = cmd("ZADD")
-                .arg("foo")
-                .arg("1")
-                .arg("a")
-                .query(&mut *ctx.con);
-            assert!(res.is_err());
-        }
+        cmd("SET")
+            .arg("foo")
+            .arg("bar")
+            .query::<()>(&mut *ctx.con)
+            .unwrap();
+        let err = cmd(&zcmd(ctx.fam, "ADD"))
+            .arg("foo")
+            .arg("1")
+            .arg("a")
+            .query::<i64>(&mut *ctx.con)
+            .unwrap_err();
+        assert_eq!(err.code(), Some("WRONGTYPE"));
     });
 }
 
@@ -3680,6 +4012,63 @@ fn zunion_interdiff_with_sets() {
             assert_eq!(diff, ["4", "5"]);
             let res = ctx.diffstore("dest", &["set_big", "zset_small"]).unwrap();
             assert_eq!(res, 2);
+        } else {
+            cmd("SADD")
+                .arg("set_small")
+                .arg("1")
+                .arg("2")
+                .arg("3")
+                .query::<i64>(&mut *ctx.con)
+                .unwrap();
+            cmd("SADD")
+                .arg("set_big")
+                .arg("1")
+                .arg("2")
+                .arg("3")
+                .arg("4")
+                .arg("5")
+                .query::<i64>(&mut *ctx.con)
+                .unwrap();
+            for (s, m) in &[(1.0, "1"), (2.0, "2"), (3.0, "3")] {
+                ctx.add("zset_small", *s, m).unwrap();
+            }
+            for (s, m) in &[(1.0, "1"), (2.0, "2"), (3.0, "3"), (4.0, "4"), (5.0, "5")] {
+                ctx.add("zset_big", *s, m).unwrap();
+            }
+
+            // Plain set members score 1.
+            let pairs = |flat: Vec<String>| -> Vec<(String, f64)> {
+                flat.chunks(2)
+                    .map(|c| (c[0].clone(), c[1].parse().unwrap()))
+                    .collect()
+            };
+            let owned = |items: &[(&str, f64)]| -> Vec<(String, f64)> {
+                items.iter().map(|(m, s)| (m.to_string(), *s)).collect()
+            };
+
+            let union = pairs(ctx.union_withscores(&["set_small", "zset_big"]).unwrap());
+            assert_eq!(
+                union,
+                owned(&[("1", 2.0), ("2", 3.0), ("3", 4.0), ("4", 4.0), ("5", 5.0)])
+            );
+            let inter = pairs(ctx.inter_withscores(&["set_small", "zset_big"]).unwrap());
+            assert_eq!(inter, owned(&[("1", 2.0), ("2", 3.0), ("3", 4.0)]));
+            let diff = ctx.diff(&["set_small", "zset_big"]).unwrap();
+            assert!(diff.is_empty());
+            assert_eq!(ctx.intercard(&["set_small", "zset_big"]).unwrap(), 3);
+
+            let union = pairs(ctx.union_withscores(&["set_big", "zset_small"]).unwrap());
+            assert_eq!(
+                union,
+                owned(&[("4", 1.0), ("5", 1.0), ("1", 2.0), ("2", 3.0), ("3", 4.0)])
+            );
+            let inter = pairs(ctx.inter_withscores(&["set_big", "zset_small"]).unwrap());
+            assert_eq!(inter, owned(&[("1", 2.0), ("2", 3.0), ("3", 4.0)]));
+            let diff = pairs(ctx.diff_withscores(&["set_big", "zset_small"]).unwrap());
+            assert_eq!(diff, owned(&[("4", 1.0), ("5", 1.0)]));
+
+            let inter = pairs(ctx.inter_withscores(&["set_small", "set_big"]).unwrap());
+            assert_eq!(inter, owned(&[("1", 2.0), ("2", 2.0), ("3", 2.0)]));
         }
     });
 }
@@ -3767,3 +4156,67 @@ fn zadd_overflows_listpack_limit() {
         }
     });
 }
+
+/* ZRANGEBYSCORE – LIMIT offset -1 returns everything after offset */
+#[test]
+fn zrangebyscore_limit_negative_count_returns_rest() {
+    with_families(|ctx| {
+        ctx.del("zkey");
+        for i in 0..8 {
+            ctx.add("zkey", i as f64, &format!("m{i}")).unwrap();
+        }
+        let vals = ctx
+            .rangebyscore("zkey", "-inf", "+inf", false, Some((5, -1)))
+            .unwrap();
+        assert_eq!(vals, ["m5", "m6", "m7"]);
+        let vals = ctx
+            .rangebyscore("zkey", "(1", "6", true, Some((3, -7)))
+            .unwrap();
+        assert_eq!(vals, ["m5", "5", "m6", "6"]);
+        let vals = ctx
+            .rangebyscore("zkey", "-inf", "+inf", false, Some((0, -1)))
+            .unwrap();
+        assert_eq!(vals.len(), 8);
+        for offset in [8, 20] {
+            let vals = ctx
+                .rangebyscore("zkey", "-inf", "+inf", false, Some((offset, -1)))
+                .unwrap();
+            assert!(vals.is_empty(), "offset {offset} is past the range");
+        }
+        let vals = ctx
+            .rangebyscore("zkey", "6", "+inf", false, Some((2, 5)))
+            .unwrap();
+        assert!(vals.is_empty());
+    });
+}
+
+/* ZRANGEBYSCORE – exclusive bounds, negative LIMIT count and offset */
+#[test]
+fn zrangebyscore_exclusive_bounds_and_limit_edges() {
+    with_families(|ctx| {
+        ctx.del("zkey");
+        for (score, member) in [(1.0, "a"), (2.0, "b"), (2.0, "c"), (3.0, "d"), (4.0, "e")] {
+            ctx.add("zkey", score, member).unwrap();
+        }
+        let vals = ctx.rangebyscore("zkey", "(1", "(4", false, None).unwrap();
+        assert_eq!(vals, ["b", "c", "d"]);
+        let vals = ctx.rangebyscore("zkey", "(2", "+inf", false, None).unwrap();
+        assert_eq!(vals, ["d", "e"]);
+        let vals = ctx.rangebyscore("zkey", "(2", "(2", false, None).unwrap();
+        assert!(vals.is_empty());
+        let vals = ctx.rangebyscore("zkey", "3", "1", false, None).unwrap();
+        assert!(vals.is_empty());
+        let vals = ctx
+            .rangebyscore("zkey", "-inf", "+inf", false, Some((2, -1)))
+            .unwrap();
+        assert_eq!(vals, ["c", "d", "e"]);
+        let vals = ctx
+            .rangebyscore("zkey", "-inf", "+inf", false, Some((-1, 2)))
+            .unwrap();
+        assert!(vals.is_empty());
+        let vals = ctx
+            .rangebyscore("zkey", "-inf", "+inf", false, Some((10, 2)))
+            .unwrap();
+        assert!(vals.is_empty());
+    });
+}